@@ -0,0 +1,26 @@
+import { MigrationInterface, QueryRunner } from 'typeorm';
+
+/**
+ * Add the user's ML-KEM-768 keypair to vaults, for the hybrid post-quantum
+ * key wrapping format. The private key is stored ECIES-wrapped.
+ *
+ * Both columns are nullable: existing vaults get a keypair from the first
+ * client that needs one (POST /vault/mlkem-key).
+ */
+export class AddVaultMlkemKey1740400000000 implements MigrationInterface {
+  name = 'AddVaultMlkemKey1740400000000';
+
+  public async up(queryRunner: QueryRunner): Promise<void> {
+    await queryRunner.query(
+      `ALTER TABLE "vaults" ADD COLUMN IF NOT EXISTS "encrypted_mlkem_private_key" bytea`
+    );
+    await queryRunner.query(`ALTER TABLE "vaults" ADD COLUMN IF NOT EXISTS "mlkem_public_key" bytea`);
+  }
+
+  public async down(queryRunner: QueryRunner): Promise<void> {
+    await queryRunner.query(`ALTER TABLE "vaults" DROP COLUMN IF EXISTS "mlkem_public_key"`);
+    await queryRunner.query(
+      `ALTER TABLE "vaults" DROP COLUMN IF EXISTS "encrypted_mlkem_private_key"`
+    );
+  }
+}
@@ -1,2 +1,2 @@
-export { InitVaultDto, VaultResponseDto } from './init-vault.dto';
+export { InitVaultDto, SetMlkemKeyDto, VaultResponseDto } from './init-vault.dto';
 export { QuotaResponseDto } from './quota.dto';
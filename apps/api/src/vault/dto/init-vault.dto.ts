@@ -1,5 +1,5 @@
-import { ApiProperty } from '@nestjs/swagger';
-import { IsString, IsNotEmpty, Matches } from 'class-validator';
+import { ApiProperty, ApiPropertyOptional } from '@nestjs/swagger';
+import { IsString, IsNotEmpty, IsOptional, Matches } from 'class-validator';
 import { TeeKeysDto } from '../../tee/dto/tee-keys.dto';
 
 /**
@@ -45,6 +45,55 @@ export class InitVaultDto {
   @IsString()
   @IsNotEmpty()
   rootIpnsName!: string;
+
+  @ApiPropertyOptional({
+    description: 'ML-KEM-768 private key sealed under the vault secret, then ECIES-wrapped (hex-encoded)',
+    example: 'a1b2c3d4e5f6...',
+  })
+  @IsOptional()
+  @IsString()
+  @Matches(/^[0-9a-fA-F]+$/, {
+    message: 'encryptedMlkemPrivateKey must be hex-encoded',
+  })
+  encryptedMlkemPrivateKey?: string;
+
+  @ApiPropertyOptional({
+    description: 'ML-KEM-768 public key (1184 bytes, hex-encoded)',
+    example: 'a1b2c3d4e5f6...',
+  })
+  @IsOptional()
+  @IsString()
+  @Matches(/^[0-9a-fA-F]{2368}$/, {
+    message: 'mlkemPublicKey must be 1184 hex-encoded bytes',
+  })
+  mlkemPublicKey?: string;
+}
+
+/**
+ * Request DTO for adding an ML-KEM-768 key to a vault created without one
+ * All byte fields are hex-encoded strings
+ */
+export class SetMlkemKeyDto {
+  @ApiProperty({
+    description: 'ML-KEM-768 private key sealed under the vault secret, then ECIES-wrapped (hex-encoded)',
+    example: 'a1b2c3d4e5f6...',
+  })
+  @IsString()
+  @IsNotEmpty()
+  @Matches(/^[0-9a-fA-F]+$/, {
+    message: 'encryptedMlkemPrivateKey must be hex-encoded',
+  })
+  encryptedMlkemPrivateKey!: string;
+
+  @ApiProperty({
+    description: 'ML-KEM-768 public key (1184 bytes, hex-encoded)',
+    example: 'a1b2c3d4e5f6...',
+  })
+  @IsString()
+  @Matches(/^[0-9a-fA-F]{2368}$/, {
+    message: 'mlkemPublicKey must be 1184 hex-encoded bytes',
+  })
+  mlkemPublicKey!: string;
 }
 
 /**
@@ -81,6 +130,22 @@ export class VaultResponseDto {
   })
   rootIpnsName!: string;
 
+  @ApiProperty({
+    description: 'Sealed, ECIES-wrapped ML-KEM-768 private key (hex-encoded), null if not set',
+    example: 'a1b2c3d4e5f6...',
+    nullable: true,
+    type: String,
+  })
+  encryptedMlkemPrivateKey!: string | null;
+
+  @ApiProperty({
+    description: 'ML-KEM-768 public key (hex-encoded), null if not set',
+    example: 'a1b2c3d4e5f6...',
+    nullable: true,
+    type: String,
+  })
+  mlkemPublicKey!: string | null;
+
   @ApiProperty({
     description: 'Vault creation timestamp',
     example: '2026-01-20T12:00:00.000Z',
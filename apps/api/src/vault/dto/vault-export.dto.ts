@@ -42,6 +42,14 @@ export class VaultExportDto {
   })
   encryptedRootIpnsPrivateKey!: string;
 
+  @ApiPropertyOptional({
+    description:
+      'Sealed, ECIES-wrapped ML-KEM-768 private key (hex-encoded), for hybrid-wrapped folder keys. Omitted if the vault has none.',
+    example: 'a1b2c3d4e5f6...',
+    type: String,
+  })
+  encryptedMlkemPrivateKey?: string;
+
   @ApiPropertyOptional({
     description:
       'Key derivation method used. Always "web3auth" for Core Kit users. Null if user record not found.',
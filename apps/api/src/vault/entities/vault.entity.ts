@@ -51,6 +51,21 @@ export class Vault {
   @Column({ type: 'varchar', length: 255, name: 'root_ipns_name' })
   rootIpnsName!: string;
 
+  /**
+   * ML-KEM-768 private key (random, independent of the secp256k1 key) for the
+   * hybrid post-quantum key wrapping format, sealed under a vault secret the
+   * server never sees and then ECIES-wrapped.
+   * Null for vaults created before it; set once via POST /vault/mlkem-key
+   */
+  @Column({ type: 'bytea', nullable: true, name: 'encrypted_mlkem_private_key' })
+  encryptedMlkemPrivateKey!: Buffer | null;
+
+  /**
+   * ML-KEM-768 public key (1184 bytes), for wrapping keys to this user
+   */
+  @Column({ type: 'bytea', nullable: true, name: 'mlkem_public_key' })
+  mlkemPublicKey!: Buffer | null;
+
   @CreateDateColumn({ name: 'created_at' })
   createdAt!: Date;
 
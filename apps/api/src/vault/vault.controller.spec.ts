@@ -19,6 +19,8 @@ describe('VaultController', () => {
     encryptedRootFolderKey: 'encrypted-folder-key-hex',
     encryptedRootIpnsPrivateKey: 'encrypted-ipns-key-hex',
     rootIpnsName: 'k51qzi5uqu5test',
+    encryptedMlkemPrivateKey: null,
+    mlkemPublicKey: null,
     createdAt: new Date('2026-01-20T00:00:00Z'),
     initializedAt: null,
     teeKeys: null,
@@ -2,7 +2,7 @@ import { Controller, Post, Get, Body, UseGuards, Request, NotFoundException } fr
 import { ApiTags, ApiOperation, ApiResponse, ApiBearerAuth } from '@nestjs/swagger';
 import { JwtAuthGuard } from '../auth/guards/jwt-auth.guard';
 import { VaultService } from './vault.service';
-import { InitVaultDto, VaultResponseDto } from './dto/init-vault.dto';
+import { InitVaultDto, SetMlkemKeyDto, VaultResponseDto } from './dto/init-vault.dto';
 import { VaultExportDto } from './dto/vault-export.dto';
 import { QuotaResponseDto } from './dto/quota.dto';
 
@@ -45,6 +45,36 @@ export class VaultController {
     return this.vaultService.initializeVault(req.user.id, dto);
   }
 
+  @Post('mlkem-key')
+  @ApiOperation({
+    summary: 'Add ML-KEM key to vault',
+    description:
+      'Store an ML-KEM-768 keypair (private key ECIES-wrapped) on a vault created without one. Returns 409 Conflict if the vault already has one.',
+  })
+  @ApiResponse({
+    status: 201,
+    description: 'ML-KEM key stored',
+    type: VaultResponseDto,
+  })
+  @ApiResponse({
+    status: 401,
+    description: 'Unauthorized - JWT token required',
+  })
+  @ApiResponse({
+    status: 404,
+    description: 'Not Found - Vault does not exist',
+  })
+  @ApiResponse({
+    status: 409,
+    description: 'Conflict - Vault already has an ML-KEM key',
+  })
+  async setMlkemKey(
+    @Request() req: RequestWithUser,
+    @Body() dto: SetMlkemKeyDto
+  ): Promise<VaultResponseDto> {
+    return this.vaultService.setMlkemKey(req.user.id, dto);
+  }
+
   @Get('export')
   @ApiOperation({
     summary: 'Export vault for independent recovery',
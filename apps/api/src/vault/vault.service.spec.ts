@@ -49,6 +49,8 @@ describe('VaultService', () => {
   const testEncryptedRootFolderKey = 'b'.repeat(64);
   const testEncryptedRootIpnsPrivateKey = 'c'.repeat(128);
   const testRootIpnsName = 'k51qzi5uqu5dg12345';
+  const testEncryptedMlkemPrivateKey = 'd'.repeat(96);
+  const testMlkemPublicKey = 'e'.repeat(2368); // 1184 bytes
   const mockVaultEntity: Vault = {
     id: testVaultId,
     ownerId: testUserId,
@@ -56,6 +58,8 @@ describe('VaultService', () => {
     encryptedRootFolderKey: Buffer.from(testEncryptedRootFolderKey, 'hex'),
     encryptedRootIpnsPrivateKey: Buffer.from(testEncryptedRootIpnsPrivateKey, 'hex'),
     rootIpnsName: testRootIpnsName,
+    encryptedMlkemPrivateKey: null,
+    mlkemPublicKey: null,
     createdAt: new Date('2026-01-20T12:00:00.000Z'),
     initializedAt: null,
     updatedAt: new Date('2026-01-20T12:00:00.000Z'),
@@ -164,6 +168,8 @@ describe('VaultService', () => {
         encryptedRootFolderKey: Buffer.from(testEncryptedRootFolderKey, 'hex'),
         encryptedRootIpnsPrivateKey: Buffer.from(testEncryptedRootIpnsPrivateKey, 'hex'),
         rootIpnsName: testRootIpnsName,
+        encryptedMlkemPrivateKey: null,
+        mlkemPublicKey: null,
         initializedAt: null,
       });
       expect(mockVaultRepo.save).toHaveBeenCalled();
@@ -195,6 +201,24 @@ describe('VaultService', () => {
       expect(Buffer.isBuffer(createCall.encryptedRootIpnsPrivateKey)).toBe(true);
     });
 
+    it('should store the ML-KEM keypair when provided', async () => {
+      mockVaultRepo.findOne.mockResolvedValue(null);
+      mockVaultRepo.create.mockReturnValue(mockVaultEntity);
+      mockVaultRepo.save.mockResolvedValue(mockVaultEntity);
+
+      await service.initializeVault(testUserId, {
+        ...testInitVaultDto,
+        encryptedMlkemPrivateKey: testEncryptedMlkemPrivateKey,
+        mlkemPublicKey: testMlkemPublicKey,
+      });
+
+      const createCall = mockVaultRepo.create.mock.calls[0][0];
+      expect(createCall.encryptedMlkemPrivateKey).toEqual(
+        Buffer.from(testEncryptedMlkemPrivateKey, 'hex')
+      );
+      expect(createCall.mlkemPublicKey).toEqual(Buffer.from(testMlkemPublicKey, 'hex'));
+    });
+
     it('should throw ConflictException if vault already exists', async () => {
       mockVaultRepo.findOne.mockResolvedValue(mockVaultEntity);
 
@@ -252,6 +276,8 @@ describe('VaultService', () => {
         encryptedRootFolderKey: testEncryptedRootFolderKey,
         encryptedRootIpnsPrivateKey: testEncryptedRootIpnsPrivateKey,
         rootIpnsName: testRootIpnsName,
+        encryptedMlkemPrivateKey: null,
+        mlkemPublicKey: null,
         createdAt: mockVaultEntity.createdAt,
         initializedAt: null,
         teeKeys: null,
@@ -266,6 +292,49 @@ describe('VaultService', () => {
     });
   });
 
+  describe('setMlkemKey', () => {
+    const setMlkemKeyDto = {
+      encryptedMlkemPrivateKey: testEncryptedMlkemPrivateKey,
+      mlkemPublicKey: testMlkemPublicKey,
+    };
+
+    it('should store the ML-KEM keypair on a vault without one', async () => {
+      mockVaultRepo.findOne.mockResolvedValue({ ...mockVaultEntity });
+      mockVaultRepo.save.mockImplementation((vault) => Promise.resolve(vault));
+
+      const result = await service.setMlkemKey(testUserId, setMlkemKeyDto);
+
+      const saved = mockVaultRepo.save.mock.calls[0][0];
+      expect(saved.encryptedMlkemPrivateKey).toEqual(
+        Buffer.from(testEncryptedMlkemPrivateKey, 'hex')
+      );
+      expect(saved.mlkemPublicKey).toEqual(Buffer.from(testMlkemPublicKey, 'hex'));
+      expect(result.encryptedMlkemPrivateKey).toBe(testEncryptedMlkemPrivateKey);
+      expect(result.mlkemPublicKey).toBe(testMlkemPublicKey);
+    });
+
+    it('should throw ConflictException if the vault already has an ML-KEM key', async () => {
+      mockVaultRepo.findOne.mockResolvedValue({
+        ...mockVaultEntity,
+        encryptedMlkemPrivateKey: Buffer.from('aa', 'hex'),
+        mlkemPublicKey: Buffer.from('bb', 'hex'),
+      });
+
+      await expect(service.setMlkemKey(testUserId, setMlkemKeyDto)).rejects.toThrow(
+        ConflictException
+      );
+      expect(mockVaultRepo.save).not.toHaveBeenCalled();
+    });
+
+    it('should throw NotFoundException if vault does not exist', async () => {
+      mockVaultRepo.findOne.mockResolvedValue(null);
+
+      await expect(service.setMlkemKey(testUserId, setMlkemKeyDto)).rejects.toThrow(
+        NotFoundException
+      );
+    });
+  });
+
   describe('findVault', () => {
     it('should return vault response DTO if found', async () => {
       mockVaultRepo.findOne.mockResolvedValue(mockVaultEntity);
@@ -587,6 +656,19 @@ describe('VaultService', () => {
       expect(result.encryptedRootIpnsPrivateKey).toBe(testEncryptedRootIpnsPrivateKey);
       expect(result.exportedAt).toBeDefined();
       expect(result.derivationMethod).toBe('web3auth');
+      expect(result.encryptedMlkemPrivateKey).toBeUndefined();
+    });
+
+    it('should include the wrapped ML-KEM private key when the vault has one', async () => {
+      mockVaultRepo.findOne.mockResolvedValue({
+        ...mockVaultEntity,
+        encryptedMlkemPrivateKey: Buffer.from(testEncryptedMlkemPrivateKey, 'hex'),
+      });
+      mockUserRepo.findOne.mockResolvedValue({ id: testUserId });
+
+      const result = await service.getExportData(testUserId);
+
+      expect(result.encryptedMlkemPrivateKey).toBe(testEncryptedMlkemPrivateKey);
     });
 
     it('should return web3auth derivation method for all users (Core Kit)', async () => {
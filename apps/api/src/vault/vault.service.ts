@@ -5,7 +5,7 @@ import { Vault } from './entities/vault.entity';
 import { PinnedCid } from './entities/pinned-cid.entity';
 import { FolderIpns } from '../ipns/entities/folder-ipns.entity';
 import { User } from '../auth/entities/user.entity';
-import { InitVaultDto, VaultResponseDto } from './dto/init-vault.dto';
+import { InitVaultDto, SetMlkemKeyDto, VaultResponseDto } from './dto/init-vault.dto';
 import { VaultExportDto } from './dto/vault-export.dto';
 import { QuotaResponseDto } from './dto/quota.dto';
 import { TeeKeyStateService } from '../tee/tee-key-state.service';
@@ -53,6 +53,10 @@ export class VaultService {
       encryptedRootFolderKey: Buffer.from(dto.encryptedRootFolderKey, 'hex'),
       encryptedRootIpnsPrivateKey: Buffer.from(dto.encryptedRootIpnsPrivateKey, 'hex'),
       rootIpnsName: dto.rootIpnsName,
+      encryptedMlkemPrivateKey: dto.encryptedMlkemPrivateKey
+        ? Buffer.from(dto.encryptedMlkemPrivateKey, 'hex')
+        : null,
+      mlkemPublicKey: dto.mlkemPublicKey ? Buffer.from(dto.mlkemPublicKey, 'hex') : null,
       initializedAt: null,
     });
 
@@ -93,6 +97,33 @@ export class VaultService {
     return this.toVaultResponse(vault, teeKeys);
   }
 
+  /**
+   * Add an ML-KEM-768 keypair to a vault created without one
+   * The keypair is set once: keys already wrapped to it must stay unwrappable
+   *
+   * @throws NotFoundException if vault does not exist
+   * @throws ConflictException if the vault already has an ML-KEM key
+   */
+  async setMlkemKey(userId: string, dto: SetMlkemKeyDto): Promise<VaultResponseDto> {
+    const vault = await this.vaultRepository.findOne({
+      where: { ownerId: userId },
+    });
+
+    if (!vault) {
+      throw new NotFoundException('Vault not found');
+    }
+    if (vault.encryptedMlkemPrivateKey) {
+      throw new ConflictException('Vault already has an ML-KEM key');
+    }
+
+    vault.encryptedMlkemPrivateKey = Buffer.from(dto.encryptedMlkemPrivateKey, 'hex');
+    vault.mlkemPublicKey = Buffer.from(dto.mlkemPublicKey, 'hex');
+    const savedVault = await this.vaultRepository.save(vault);
+
+    const teeKeys = await this.teeKeyStateService.getTeeKeysDto();
+    return this.toVaultResponse(savedVault, teeKeys);
+  }
+
   /**
    * Check if vault exists for user (returns null if not)
    */
@@ -198,6 +229,7 @@ export class VaultService {
       rootIpnsName: vault.rootIpnsName,
       encryptedRootFolderKey: vault.encryptedRootFolderKey.toString('hex'),
       encryptedRootIpnsPrivateKey: vault.encryptedRootIpnsPrivateKey.toString('hex'),
+      encryptedMlkemPrivateKey: vault.encryptedMlkemPrivateKey?.toString('hex'),
       derivationMethod: user ? 'web3auth' : null,
     };
   }
@@ -212,6 +244,8 @@ export class VaultService {
       encryptedRootFolderKey: vault.encryptedRootFolderKey.toString('hex'),
       encryptedRootIpnsPrivateKey: vault.encryptedRootIpnsPrivateKey.toString('hex'),
       rootIpnsName: vault.rootIpnsName,
+      encryptedMlkemPrivateKey: vault.encryptedMlkemPrivateKey?.toString('hex') ?? null,
+      mlkemPublicKey: vault.mlkemPublicKey?.toString('hex') ?? null,
       createdAt: vault.createdAt,
       initializedAt: vault.initializedAt,
       teeKeys,
@@ -15,6 +15,7 @@ import {
   unsealAesGcm,
   wrapKey,
   unwrapKey,
+  reWrapKey,
   signEd25519,
   verifyEd25519,
   createIpnsRecord,
@@ -115,6 +116,22 @@ async function main() {
   console.log(`ECIES_ROUNDTRIP: ${toHex(unwrapped) === toHex(eciesPlaintext)}`);
   console.log();
 
+  // 4b. ECIES re-wrap from the ECIES keypair above to a fixed recipient keypair.
+  // Rust re-wraps ECIES_WRAPPED for the same recipient and checks the recipient
+  // recovers ECIES_PLAINTEXT.
+  console.log('--- ECIES Re-Wrap Test Vector ---');
+  const rewrapRecipientPrivateKey = hexToBytes(
+    'e4c2a0d9a8b8d6a54f7f3c3b1c0f4e5d6a7b8c9d0e1f2a3b4c5d6e7f8091a2b3'
+  );
+  const rewrapRecipientPublicKey = getPublicKey(rewrapRecipientPrivateKey, false);
+  console.log(`ECIES_REWRAP_RECIPIENT_PRIVATE_KEY: "${toHex(rewrapRecipientPrivateKey)}"`);
+  console.log(`ECIES_REWRAP_RECIPIENT_PUBLIC_KEY: "${toHex(rewrapRecipientPublicKey)}"`);
+  const rewrapped = await reWrapKey(wrapped, eciesPrivateKey, rewrapRecipientPublicKey);
+  console.log(`ECIES_REWRAPPED: "${toHex(rewrapped)}"`);
+  const rewrapUnwrapped = await unwrapKey(rewrapped, rewrapRecipientPrivateKey);
+  console.log(`ECIES_REWRAP_ROUNDTRIP: ${toHex(rewrapUnwrapped) === toHex(eciesPlaintext)}`);
+  console.log();
+
   // 5. IPNS Record with fixed Ed25519 keypair and fixed timestamp
   console.log('--- IPNS Record Test Vector ---');
   const ipnsPrivateKey = hexToBytes(
@@ -1,10 +1,15 @@
 //! Keychain operations for secure refresh token storage.
 //!
 //! Uses the `keyring` crate with apple-native feature for macOS Keychain integration.
-//! Refresh tokens are stored in the system Keychain, never on disk.
+//! Refresh tokens are stored in the system Keychain, never on disk. So is the
+//! user's vault secret (see `crypto::keys::VaultSecret`), which unlike the
+//! refresh token survives logout.
 
 use keyring::Entry;
 use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::crypto::keys::{SecretKey, VaultSecret};
 
 /// Keychain service name matching the Tauri app identifier.
 const SERVICE_NAME: &str = "com.cipherbox.desktop";
@@ -12,6 +17,12 @@ const SERVICE_NAME: &str = "com.cipherbox.desktop";
 /// Special username for storing the last logged-in user ID.
 const LAST_USER_ID_KEY: &str = "last_user_id";
 
+/// Username prefix of the vault secret entries (followed by the user ID).
+const VAULT_SECRET_KEY_PREFIX: &str = "vault_secret:";
+
+/// Username prefix of the entries marking a vault secret export as confirmed.
+const VAULT_SECRET_CONFIRMED_KEY_PREFIX: &str = "vault_secret_confirmed:";
+
 #[derive(Debug, Error)]
 pub enum KeychainError {
     #[error("Keychain operation failed: {0}")]
@@ -81,3 +92,56 @@ pub fn get_last_user_id() -> Result<Option<String>, KeychainError> {
         Err(e) => Err(KeychainError::from(e)),
     }
 }
+
+/// Store the vault secret of the given user ID in the Keychain (hex-encoded).
+///
+/// Deletes any existing entry first, like `store_refresh_token`, and with it
+/// the export confirmation: a replaced secret has not been exported yet.
+pub fn store_vault_secret(user_id: &str, secret: &VaultSecret) -> Result<(), KeychainError> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}{}", VAULT_SECRET_KEY_PREFIX, user_id))?;
+    let _ = entry.delete_credential(); // ignore NotFound
+    entry.set_password(&Zeroizing::new(hex::encode(secret.as_bytes())))?;
+    let confirmed = Entry::new(SERVICE_NAME, &format!("{}{}", VAULT_SECRET_CONFIRMED_KEY_PREFIX, user_id))?;
+    match confirmed.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(KeychainError::from(e)),
+    }
+}
+
+/// Record that the user exported the vault secret of the given user ID and
+/// confirmed it, so data may be hybrid-wrapped under it.
+pub fn store_vault_secret_confirmed(user_id: &str) -> Result<(), KeychainError> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}{}", VAULT_SECRET_CONFIRMED_KEY_PREFIX, user_id))?;
+    let _ = entry.delete_credential(); // ignore NotFound
+    entry.set_password("1")?;
+    Ok(())
+}
+
+/// Whether the vault secret of the given user ID was exported and confirmed.
+pub fn is_vault_secret_confirmed(user_id: &str) -> Result<bool, KeychainError> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}{}", VAULT_SECRET_CONFIRMED_KEY_PREFIX, user_id))?;
+    match entry.get_password() {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(KeychainError::from(e)),
+    }
+}
+
+/// Retrieve the vault secret of the given user ID from the Keychain.
+///
+/// Returns `None` if this device has never stored one for the user.
+pub fn get_vault_secret(user_id: &str) -> Result<Option<VaultSecret>, KeychainError> {
+    let entry = Entry::new(SERVICE_NAME, &format!("{}{}", VAULT_SECRET_KEY_PREFIX, user_id))?;
+    let encoded = match entry.get_password() {
+        Ok(encoded) => Zeroizing::new(encoded),
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => return Err(KeychainError::from(e)),
+    };
+    let bytes = Zeroizing::new(
+        hex::decode(encoded.as_str())
+            .map_err(|_| KeychainError::OperationFailed("Invalid vault secret entry".to_string()))?,
+    );
+    VaultSecret::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| KeychainError::OperationFailed(e.to_string()))
+}
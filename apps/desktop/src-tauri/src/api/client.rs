@@ -16,6 +16,9 @@ pub struct ApiClient {
     client: Client,
     base_url: String,
     access_token: Arc<RwLock<Option<String>>>,
+    ipns_routing_url: Option<String>,
+    ipns_sequences: super::routing::SequenceFloor,
+    envelope_history: crate::crypto::folder::EnvelopeHistory,
 }
 
 impl ApiClient {
@@ -30,9 +33,36 @@ impl ApiClient {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             access_token: Arc::new(RwLock::new(None)),
+            ipns_routing_url: None,
+            ipns_sequences: Default::default(),
+            envelope_history: Default::default(),
         }
     }
 
+    /// Resolve IPNS names directly via a delegated routing endpoint
+    /// (`{url}/routing/v1/ipns/{name}`) instead of trusting the backend.
+    pub fn with_ipns_routing_url(mut self, url: Option<String>) -> Self {
+        self.ipns_routing_url = url.map(|u| u.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Delegated routing base URL, if direct IPNS resolution is enabled.
+    pub fn ipns_routing_url(&self) -> Option<&str> {
+        self.ipns_routing_url.as_deref()
+    }
+
+    /// Highest IPNS sequence verified per name by direct resolution.
+    pub fn ipns_sequences(&self) -> &super::routing::SequenceFloor {
+        &self.ipns_sequences
+    }
+
+    /// IPNS names whose metadata was read in a v2 envelope. Holds the
+    /// signed-in vault's names only: seeded from its snapshot on mount,
+    /// saved back by the crawler and cleared on logout.
+    pub fn envelope_history(&self) -> &crate::crypto::folder::EnvelopeHistory {
+        &self.envelope_history
+    }
+
     /// Store the access token for authenticated requests.
     pub async fn set_access_token(&self, token: String) {
         let mut guard = self.access_token.write().await;
@@ -67,6 +97,29 @@ impl ApiClient {
         builder.send().await
     }
 
+    /// Send an authenticated GET request for the inclusive byte range `start..=end`.
+    pub async fn authenticated_get_range(
+        &self,
+        path: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Response, reqwest::Error> {
+        let url = format!("{}{}", self.base_url, path);
+        let token = self.access_token.read().await;
+
+        let mut builder = self
+            .client
+            .get(&url)
+            .header("X-Client-Type", "desktop")
+            .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+
+        if let Some(ref t) = *token {
+            builder = builder.bearer_auth(t);
+        }
+
+        builder.send().await
+    }
+
     /// Send an authenticated POST request with a JSON body to a relative API path.
     pub async fn authenticated_post<T: Serialize>(
         &self,
@@ -112,6 +165,16 @@ impl ApiClient {
         Ok(bytes.to_vec())
     }
 
+    /// Send an unauthenticated GET request to an absolute URL with an Accept header.
+    /// Used for delegated routing requests, which go to a separate service.
+    pub async fn get_with_accept(&self, url: &str, accept: &str) -> Result<Response, reqwest::Error> {
+        self.client
+            .get(url)
+            .header(reqwest::header::ACCEPT, accept)
+            .send()
+            .await
+    }
+
     /// Send an authenticated multipart POST request (used for IPFS file uploads).
     pub async fn authenticated_multipart_post(
         &self,
@@ -27,14 +27,73 @@ pub async fn fetch_content(client: &ApiClient, cid: &str) -> Result<Vec<u8>, Str
     Ok(bytes.to_vec())
 }
 
+/// Fetch the inclusive byte range `start..=end` of encrypted content.
+///
+/// GET /ipfs/{cid} with a `Range` header. A server that ignores the header
+/// and returns the whole object (200) is handled by slicing; a range that
+/// starts past the end (416) yields no bytes.
+pub async fn fetch_content_range(
+    client: &ApiClient,
+    cid: &str,
+    start: u64,
+    end: u64,
+) -> Result<Vec<u8>, String> {
+    let resp = client
+        .authenticated_get_range(&format!("/ipfs/{}", cid), start, end)
+        .await
+        .map_err(|e| format!("IPFS range fetch failed: {}", e))?;
+
+    let status = resp.status().as_u16();
+    if status == 416 {
+        return Ok(Vec::new());
+    }
+    if !resp.status().is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("IPFS range fetch failed ({}): {}", status, body));
+    }
+
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read IPFS response: {}", e))?;
+
+    if status == 206 {
+        return Ok(bytes.to_vec());
+    }
+
+    log::debug!("IPFS range request for {} ignored, slicing full response", cid);
+    let len = bytes.len() as u64;
+    if start >= len {
+        return Ok(Vec::new());
+    }
+    Ok(bytes[start as usize..=end.min(len - 1) as usize].to_vec())
+}
+
 /// Upload encrypted file content to IPFS via the backend.
 ///
 /// POST /ipfs/upload with multipart form data. Returns CID string.
 /// Used by write operations in plan 09-06.
 pub async fn upload_content(client: &ApiClient, data: &[u8]) -> Result<String, String> {
+    upload_part(client, reqwest::multipart::Part::bytes(data.to_vec())).await
+}
+
+/// Upload encrypted content from a streaming body of exactly `len` bytes.
+///
+/// Same request as `upload_content`, but the content is produced while it is
+/// sent and never held in memory as a whole.
+pub async fn upload_content_stream(
+    client: &ApiClient,
+    body: reqwest::Body,
+    len: u64,
+) -> Result<String, String> {
+    upload_part(client, reqwest::multipart::Part::stream_with_length(body, len)).await
+}
+
+/// POST one multipart `file` part to /ipfs/upload. Returns the CID.
+async fn upload_part(client: &ApiClient, part: reqwest::multipart::Part) -> Result<String, String> {
     use reqwest::multipart;
 
-    let part = multipart::Part::bytes(data.to_vec())
+    let part = part
         .file_name("encrypted")
         .mime_str("application/octet-stream")
         .map_err(|e| format!("Failed to create multipart part: {}", e))?;
@@ -92,3 +151,38 @@ pub async fn unpin_content(client: &ApiClient, cid: &str) -> Result<(), String>
         Err(format!("IPFS unpin failed ({}): {}", status, body))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::serve_once;
+
+    #[tokio::test]
+    async fn range_fetch_sends_range_header() {
+        let (url, request) = serve_once(206, b"cdef".to_vec()).await;
+        let client = ApiClient::new(&url);
+
+        let bytes = fetch_content_range(&client, "bafytest", 2, 5).await.unwrap();
+        assert_eq!(bytes, b"cdef");
+        let request = request.await.unwrap().to_lowercase();
+        assert!(request.contains("range: bytes=2-5"), "request was: {}", request);
+    }
+
+    #[tokio::test]
+    async fn range_fetch_slices_full_response() {
+        let (url, _) = serve_once(200, b"abcdefgh".to_vec()).await;
+        let client = ApiClient::new(&url);
+
+        let bytes = fetch_content_range(&client, "bafytest", 2, 5).await.unwrap();
+        assert_eq!(bytes, b"cdef");
+    }
+
+    #[tokio::test]
+    async fn range_fetch_past_end_is_empty() {
+        let (url, _) = serve_once(416, Vec::new()).await;
+        let client = ApiClient::new(&url);
+
+        let bytes = fetch_content_range(&client, "bafytest", 100, 200).await.unwrap();
+        assert!(bytes.is_empty());
+    }
+}
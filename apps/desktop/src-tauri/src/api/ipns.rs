@@ -1,6 +1,7 @@
 //! IPNS resolution via the CipherBox backend API.
 //!
-//! Resolves IPNS names to their current CID and sequence number.
+//! Resolves IPNS names to their current CID and sequence number, either via
+//! the backend or (when configured) via verified delegated routing.
 
 use serde::Deserialize;
 
@@ -18,34 +19,86 @@ pub struct IpnsResolveResponse {
     pub sequence_number: String,
 }
 
-/// Resolve an IPNS name to its current CID via the backend.
+/// Why an IPNS name could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// The name has no record (404), and none was verified for it before.
+    NotFound,
+    /// Any other failure, including a record gone missing after one was
+    /// verified for the name.
+    Failed(String),
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::NotFound => f.write_str("IPNS name not found"),
+            ResolveError::Failed(e) => f.write_str(e),
+        }
+    }
+}
+
+impl From<ResolveError> for String {
+    fn from(err: ResolveError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Resolve an IPNS name to its current CID.
 ///
-/// GET /ipns/resolve?ipnsName={name}
+/// When a delegated routing URL is configured on the client, the signed record
+/// is fetched and verified locally (see `api::routing`). Otherwise falls back to
+/// GET /ipns/resolve?ipnsName={name} on the backend.
 /// Returns the CID and sequence number of the current IPNS record.
+///
+/// A missing record is only `ResolveError::NotFound` for a name without a
+/// verified sequence (`ApiClient::ipns_sequences`): a record seen before
+/// cannot have become unpublished, so its absence is an error.
 pub async fn resolve_ipns(
     client: &ApiClient,
     ipns_name: &str,
-) -> Result<IpnsResolveResponse, String> {
+) -> Result<IpnsResolveResponse, ResolveError> {
+    let result = match client.ipns_routing_url() {
+        Some(routing_url) => super::routing::resolve_ipns_verified(client, routing_url, ipns_name).await,
+        None => resolve_ipns_via_backend(client, ipns_name).await,
+    };
+    match result {
+        Err(ResolveError::NotFound) => match client.ipns_sequences().get(ipns_name) {
+            Some(sequence) => Err(ResolveError::Failed(format!(
+                "IPNS record of {} not found, though sequence {} was verified for it",
+                ipns_name, sequence
+            ))),
+            None => Err(ResolveError::NotFound),
+        },
+        result => result,
+    }
+}
+
+/// GET /ipns/resolve?ipnsName={name} on the backend.
+async fn resolve_ipns_via_backend(
+    client: &ApiClient,
+    ipns_name: &str,
+) -> Result<IpnsResolveResponse, ResolveError> {
     let path = format!("/ipns/resolve?ipnsName={}", urlencoding::encode(ipns_name));
     let resp = client
         .authenticated_get(&path)
         .await
-        .map_err(|e| format!("IPNS resolve failed: {}", e))?;
+        .map_err(|e| ResolveError::Failed(format!("IPNS resolve failed: {}", e)))?;
 
     if resp.status().as_u16() == 404 {
-        return Err("IPNS name not found".to_string());
+        return Err(ResolveError::NotFound);
     }
 
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
-        return Err(format!("IPNS resolve failed ({}): {}", status, body));
+        return Err(ResolveError::Failed(format!("IPNS resolve failed ({}): {}", status, body)));
     }
 
     let resolve_resp: IpnsResolveResponse = resp
         .json()
         .await
-        .map_err(|e| format!("Failed to parse IPNS resolve response: {}", e))?;
+        .map_err(|e| ResolveError::Failed(format!("Failed to parse IPNS resolve response: {}", e)))?;
 
     Ok(resolve_resp)
 }
@@ -7,4 +7,7 @@ pub mod auth;
 pub mod client;
 pub mod ipfs;
 pub mod ipns;
+pub mod routing;
+#[cfg(test)]
+pub mod test_server;
 pub mod types;
@@ -0,0 +1,242 @@
+//! Direct IPNS resolution via a delegated routing endpoint.
+//!
+//! Fetches raw IPNS records from `GET /routing/v1/ipns/{name}` (IPFS delegated
+//! routing API) and only accepts the value after the record has been
+//! unmarshaled and verified against the IPNS name: the name's Ed25519 public
+//! key, the V2 signature over the CBOR data, and the EOL validity. A record
+//! whose sequence number is below the highest one verified for the name
+//! (`SequenceFloor`) is rejected as a rollback, so the router cannot serve an
+//! older, validly signed record again.
+//!
+//! Unlike `api::ipns::resolve_ipns`, the CID returned here does not depend on
+//! trusting the CipherBox backend.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::client::ApiClient;
+use super::ipns::{IpnsResolveResponse, ResolveError};
+
+/// Content type for raw IPNS records (IPIP-351).
+pub const IPNS_RECORD_CONTENT_TYPE: &str = "application/vnd.ipfs.ipns-record";
+
+/// Highest IPNS sequence number verified per name.
+///
+/// Seeded from the vault snapshot on mount, so rollbacks are caught across
+/// restarts too, and cleared on logout.
+#[derive(Default)]
+pub struct SequenceFloor {
+    highest: Mutex<HashMap<String, u64>>,
+}
+
+impl SequenceFloor {
+    /// Accept `sequence` for `ipns_name` and raise the floor to it, unless a
+    /// higher sequence was verified for the name before.
+    pub fn check(&self, ipns_name: &str, sequence: u64) -> Result<(), String> {
+        let mut highest = self.highest.lock().unwrap();
+        let floor = highest.entry(ipns_name.to_string()).or_insert(0);
+        if sequence < *floor {
+            return Err(format!(
+                "IPNS record rolled back for {}: sequence {} is below {}",
+                ipns_name, sequence, floor
+            ));
+        }
+        *floor = sequence;
+        Ok(())
+    }
+
+    /// Highest sequence verified for `ipns_name`, if a record of it was.
+    pub fn get(&self, ipns_name: &str) -> Option<u64> {
+        self.highest.lock().unwrap().get(ipns_name).copied()
+    }
+
+    /// Raise the floor of `ipns_name` to `sequence`, e.g. from a vault snapshot.
+    pub fn raise(&self, ipns_name: &str, sequence: u64) {
+        let mut highest = self.highest.lock().unwrap();
+        let floor = highest.entry(ipns_name.to_string()).or_insert(0);
+        *floor = (*floor).max(sequence);
+    }
+
+    /// Forget every name (logout).
+    pub fn clear(&self) {
+        self.highest.lock().unwrap().clear();
+    }
+}
+
+/// Fetch the raw marshaled IPNS record for `ipns_name` from a delegated router.
+pub async fn fetch_ipns_record(
+    client: &ApiClient,
+    routing_url: &str,
+    ipns_name: &str,
+) -> Result<Vec<u8>, ResolveError> {
+    let url = format!(
+        "{}/routing/v1/ipns/{}",
+        routing_url.trim_end_matches('/'),
+        urlencoding::encode(ipns_name)
+    );
+    let resp = client
+        .get_with_accept(&url, IPNS_RECORD_CONTENT_TYPE)
+        .await
+        .map_err(|e| ResolveError::Failed(format!("IPNS routing request failed: {}", e)))?;
+
+    if resp.status().as_u16() == 404 {
+        return Err(ResolveError::NotFound);
+    }
+
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(ResolveError::Failed(format!("IPNS routing request failed ({}): {}", status, body)));
+    }
+
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| ResolveError::Failed(format!("Failed to read IPNS record: {}", e)))?;
+
+    if bytes.len() > crate::crypto::ipns::MAX_IPNS_RECORD_SIZE {
+        return Err(ResolveError::Failed(format!("IPNS record too large ({} bytes)", bytes.len())));
+    }
+
+    Ok(bytes.to_vec())
+}
+
+/// Resolve an IPNS name via delegated routing, verifying the record locally.
+///
+/// Records older than the highest sequence verified for the name before are
+/// rejected. Returns the same shape as the backend resolver so callers can
+/// use either.
+pub async fn resolve_ipns_verified(
+    client: &ApiClient,
+    routing_url: &str,
+    ipns_name: &str,
+) -> Result<IpnsResolveResponse, ResolveError> {
+    let bytes = fetch_ipns_record(client, routing_url, ipns_name).await?;
+
+    let record = crate::crypto::ipns::validate_ipns_record(&bytes, ipns_name)
+        .map_err(|e| ResolveError::Failed(format!("IPNS record rejected for {}: {}", ipns_name, e)))?;
+
+    let cid = record
+        .value
+        .strip_prefix("/ipfs/")
+        .filter(|cid| !cid.is_empty() && !cid.contains('/'))
+        .ok_or_else(|| ResolveError::Failed(format!("Unsupported IPNS value for {}: {}", ipns_name, record.value)))?;
+
+    client
+        .ipns_sequences()
+        .check(ipns_name, record.sequence)
+        .map_err(ResolveError::Failed)?;
+
+    Ok(IpnsResolveResponse {
+        success: true,
+        cid: cid.to_string(),
+        sequence_number: record.sequence.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_server::serve_once;
+
+    /// Ed25519 private key from the crypto cross-language vectors.
+    const TEST_IPNS_PRIVATE_KEY: &str =
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    fn signed_record(value: &str, sequence: u64) -> (Vec<u8>, String) {
+        let key = crate::crypto::keys::IpnsSigningKey::from_bytes(
+            hex::decode(TEST_IPNS_PRIVATE_KEY).unwrap().try_into().unwrap(),
+        );
+        let record = crate::crypto::ipns::create_ipns_record(&key, value, sequence, 86_400_000).unwrap();
+        let public_key: [u8; 32] = record.public_key.clone().try_into().unwrap();
+        let name = crate::crypto::ipns::derive_ipns_name(&public_key).unwrap();
+        (crate::crypto::ipns::marshal_ipns_record(&record).unwrap(), name)
+    }
+
+    #[tokio::test]
+    async fn resolves_verified_record() {
+        let (record, name) = signed_record("/ipfs/bafytestcid", 9);
+        let (url, _) = serve_once(200, record).await;
+        let client = ApiClient::new("http://unused.invalid");
+
+        let resp = resolve_ipns_verified(&client, &url, &name).await.unwrap();
+        assert!(resp.success);
+        assert_eq!(resp.cid, "bafytestcid");
+        assert_eq!(resp.sequence_number, "9");
+    }
+
+    #[tokio::test]
+    async fn rejects_record_signed_for_another_name() {
+        let (record, _) = signed_record("/ipfs/bafytestcid", 9);
+        let (other_public, _) = crate::crypto::ed25519::generate_ed25519_keypair();
+        let other_name =
+            crate::crypto::ipns::derive_ipns_name(&other_public.try_into().unwrap()).unwrap();
+        let (url, _) = serve_once(200, record).await;
+        let client = ApiClient::new("http://unused.invalid");
+
+        let result = resolve_ipns_verified(&client, &url, &other_name).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_record() {
+        let (mut record, name) = signed_record("/ipfs/bafytestcid", 9);
+        let last = record.len() - 1;
+        record[last] ^= 0x01;
+        let (url, _) = serve_once(200, record).await;
+        let client = ApiClient::new("http://unused.invalid");
+
+        assert!(resolve_ipns_verified(&client, &url, &name).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_rolled_back_record() {
+        let client = ApiClient::new("http://unused.invalid");
+        let (newer, name) = signed_record("/ipfs/bafynewcid", 9);
+        let (url, _) = serve_once(200, newer).await;
+        resolve_ipns_verified(&client, &url, &name).await.unwrap();
+
+        // Validly signed, but older than what was already verified
+        let (older, _) = signed_record("/ipfs/bafyoldcid", 8);
+        let (url, _) = serve_once(200, older.clone()).await;
+        let err = resolve_ipns_verified(&client, &url, &name).await.unwrap_err();
+        assert!(err.to_string().contains("rolled back"), "{}", err);
+
+        // After logout the floor is gone
+        client.ipns_sequences().clear();
+        let (url, _) = serve_once(200, older).await;
+        assert!(resolve_ipns_verified(&client, &url, &name).await.is_ok());
+    }
+
+    #[test]
+    fn sequence_floor_is_raised_from_snapshot() {
+        let floor = SequenceFloor::default();
+        floor.raise("k51name", 5);
+        floor.raise("k51name", 3);
+        assert!(floor.check("k51name", 4).is_err());
+        assert!(floor.check("k51name", 5).is_ok());
+        assert!(floor.check("k51other", 0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn missing_record_is_not_found() {
+        let (_, name) = signed_record("/ipfs/bafytestcid", 9);
+        let (url, _) = serve_once(404, b"{\"error\":\"record not found\"}".to_vec()).await;
+        let client = ApiClient::new("http://unused.invalid");
+
+        let err = resolve_ipns_verified(&client, &url, &name).await.unwrap_err();
+        assert_eq!(err, ResolveError::NotFound);
+    }
+
+    #[tokio::test]
+    async fn missing_record_of_verified_name_is_an_error() {
+        let (_, name) = signed_record("/ipfs/bafytestcid", 9);
+
+        // The router answering 404 cannot make a verified name look unpublished
+        let (url, _) = serve_once(404, b"{\"error\":\"record not found\"}".to_vec()).await;
+        let client = ApiClient::new("http://unused.invalid").with_ipns_routing_url(Some(url));
+        client.ipns_sequences().raise(&name, 9);
+        let err = crate::api::ipns::resolve_ipns(&client, &name).await.unwrap_err();
+        assert!(matches!(err, ResolveError::Failed(_)), "{}", err);
+    }
+}
@@ -0,0 +1,134 @@
+//! Local HTTP server standing in for the backend (or a delegated router) in
+//! tests of code that goes through `ApiClient`.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// A request received by the server.
+pub struct Request {
+    /// Request line and headers, as sent.
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn method(&self) -> &str {
+        self.head.split_whitespace().next().unwrap_or_default()
+    }
+
+    /// Path and query of the request line.
+    pub fn path(&self) -> &str {
+        self.head.split_whitespace().nth(1).unwrap_or_default()
+    }
+
+    /// Value of the header `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// The content of the single file part of a multipart upload.
+    pub fn multipart_file(&self) -> Vec<u8> {
+        let boundary = self
+            .header("content-type")
+            .and_then(|value| value.split("boundary=").nth(1))
+            .unwrap()
+            .trim();
+        let body = &self.body;
+        let start = body.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let close = format!("\r\n--{}--", boundary);
+        let end = body.windows(close.len()).rposition(|w| w == close.as_bytes()).unwrap();
+        body[start..end].to_vec()
+    }
+}
+
+/// Read one request: its head and its body.
+async fn read_request(socket: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut request = Request { head: String::new(), body: Vec::new() };
+    loop {
+        let mut line = String::new();
+        if socket.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        if line == "\r\n" {
+            break;
+        }
+        request.head.push_str(&line);
+    }
+
+    if let Some(len) = request.header("content-length") {
+        request.body.resize(len.parse().ok()?, 0);
+        socket.read_exact(&mut request.body).await.ok()?;
+    } else if request
+        .header("transfer-encoding")
+        .is_some_and(|te| te.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            let mut size = String::new();
+            socket.read_line(&mut size).await.ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            // The chunk and its trailing CRLF
+            let mut chunk = vec![0u8; size + 2];
+            socket.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            request.body.extend_from_slice(&chunk[..size]);
+        }
+    }
+    Some(request)
+}
+
+/// Serve from a local listener, one request per connection, answering each
+/// request with the status and body `handle` returns. Returns the base URL.
+pub async fn serve<F>(handle: F) -> String
+where
+    F: Fn(Request) -> (u16, Vec<u8>) + Send + Sync + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = Arc::new(handle);
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                let mut socket = BufReader::new(socket);
+                let Some(request) = read_request(&mut socket).await else {
+                    return;
+                };
+                let (status, body) = handle(request);
+                let head = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let socket = socket.get_mut();
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// Answer every request with `status` and `body`. Returns the base URL and
+/// the head of the first request received.
+pub async fn serve_once(
+    status: u16,
+    body: Vec<u8>,
+) -> (String, tokio::sync::oneshot::Receiver<String>) {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    let url = serve(move |request| {
+        if let Some(tx) = tx.lock().unwrap().take() {
+            let _ = tx.send(request.head);
+        }
+        (status, body.clone())
+    })
+    .await;
+    (url, rx)
+}
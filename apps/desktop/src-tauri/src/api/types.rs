@@ -95,6 +95,19 @@ pub struct InitVaultRequest {
     pub encrypted_root_folder_key: String,
     pub encrypted_root_ipns_private_key: String,
     pub root_ipns_name: String,
+    /// ML-KEM-768 private key sealed under the vault secret (None without a Keychain).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_mlkem_private_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mlkem_public_key: Option<String>,
+}
+
+/// Request body for POST /vault/mlkem-key (vaults created before ML-KEM keys).
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetMlkemKeyRequest {
+    pub encrypted_mlkem_private_key: String,
+    pub mlkem_public_key: String,
 }
 
 /// Vault response from GET /vault.
@@ -105,4 +118,8 @@ pub struct VaultResponse {
     pub root_ipns_name: String,
     pub encrypted_root_ipns_private_key: String,
     pub tee_keys: Option<TeeKeysResponse>,
+    /// ML-KEM-768 private key sealed under the vault secret, or plain
+    /// ECIES-wrapped by older clients (None for older vaults).
+    #[serde(default)]
+    pub encrypted_mlkem_private_key: Option<String>,
 }
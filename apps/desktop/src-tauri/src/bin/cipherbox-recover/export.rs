@@ -0,0 +1,222 @@
+//! Vault export parsing (`docs/VAULT_EXPORT_FORMAT.md`, section 2).
+
+use base64::Engine;
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+use crate::crypto::keys::{FolderKey, MlKemSecretKey, Secp256k1SecretKey, SecretKey, VaultSecret};
+
+/// Value of the export `format` field.
+pub const EXPORT_FORMAT: &str = "cipherbox-vault-export";
+
+/// Supported export `version`.
+pub const EXPORT_VERSION: &str = "1.0";
+
+/// Vault export JSON.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultExport {
+    pub format: String,
+    pub version: String,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    /// IPNS name of the root folder.
+    pub root_ipns_name: String,
+    /// Hex hybrid- or ECIES-wrapped 32-byte root folder key.
+    pub encrypted_root_folder_key: String,
+    /// Hex ECIES-wrapped 64-byte root IPNS private key (seed || public key).
+    pub encrypted_root_ipns_private_key: String,
+    /// Hex ML-KEM-768 private key sealed under the vault secret (or plain
+    /// ECIES-wrapped), for hybrid-wrapped folder keys.
+    #[serde(default)]
+    pub encrypted_mlkem_private_key: Option<String>,
+}
+
+impl VaultExport {
+    /// Parse and validate an export file (recovery step 1).
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let export: VaultExport =
+            serde_json::from_slice(bytes).map_err(|e| format!("Invalid export JSON: {}", e))?;
+        if export.format != EXPORT_FORMAT {
+            return Err(format!("Not a vault export (format {:?})", export.format));
+        }
+        if export.version != EXPORT_VERSION {
+            return Err(format!("Unsupported export version {:?}", export.version));
+        }
+        if export.root_ipns_name.is_empty()
+            || export.encrypted_root_folder_key.is_empty()
+            || export.encrypted_root_ipns_private_key.is_empty()
+        {
+            return Err("Export is missing required fields".to_string());
+        }
+        Ok(export)
+    }
+
+    /// Decrypt the root folder key (recovery step 3).
+    ///
+    /// Also unwraps the root IPNS private key and checks that it belongs to
+    /// `rootIpnsName`; a mismatch is only logged, since the IPNS key is not
+    /// needed to read the vault.
+    pub fn root_folder_key(
+        &self,
+        private_key: &Secp256k1SecretKey,
+        mlkem_private_key: Option<&MlKemSecretKey>,
+    ) -> Result<FolderKey, String> {
+        let wrapped = hex::decode(&self.encrypted_root_folder_key)
+            .map_err(|_| "Invalid encryptedRootFolderKey hex".to_string())?;
+        let folder_key: FolderKey =
+            crate::crypto::ecies::unwrap_secret_key_hybrid(&wrapped, private_key, mlkem_private_key)
+                .map_err(|_| "Cannot decrypt the root folder key: wrong private key?".to_string())?;
+
+        let wrapped = hex::decode(&self.encrypted_root_ipns_private_key)
+            .map_err(|_| "Invalid encryptedRootIpnsPrivateKey hex".to_string())?;
+        let ipns_key = Zeroizing::new(
+            crate::crypto::ecies::unwrap_key(&wrapped, private_key)
+                .map_err(|_| "Cannot decrypt the root IPNS key".to_string())?,
+        );
+        let name_key = crate::crypto::ipns::decode_ipns_name(&self.root_ipns_name)
+            .map_err(|e| format!("Invalid rootIpnsName: {}", e))?;
+        if ipns_key.len() != 64 || ipns_key[32..] != name_key {
+            log::warn!("Root IPNS private key does not match rootIpnsName");
+        }
+
+        Ok(folder_key)
+    }
+
+    /// Decrypt the ML-KEM-768 private key, if the export has one.
+    ///
+    /// A key sealed under the vault secret needs `vault_secret` too.
+    pub fn mlkem_private_key(
+        &self,
+        private_key: &Secp256k1SecretKey,
+        vault_secret: Option<&VaultSecret>,
+    ) -> Result<Option<MlKemSecretKey>, String> {
+        let Some(encrypted) = &self.encrypted_mlkem_private_key else {
+            return Ok(None);
+        };
+        let wrapped =
+            hex::decode(encrypted).map_err(|_| "Invalid encryptedMlkemPrivateKey hex".to_string())?;
+        match crate::crypto::ecies::open_mlkem_private_key(&wrapped, private_key, vault_secret) {
+            Ok(key) => Ok(Some(key)),
+            Err(crate::crypto::ecies::EciesError::VaultSecretMissing) => Err(
+                "The ML-KEM private key is sealed under the vault secret: pass --vault-secret-file"
+                    .to_string(),
+            ),
+            Err(_) => Err("Cannot decrypt the ML-KEM private key: wrong vault secret?".to_string()),
+        }
+    }
+}
+
+/// Parse the vault secret exported from the desktop app (64 hex characters).
+pub fn parse_vault_secret(input: &str) -> Result<VaultSecret, String> {
+    let bytes = Zeroizing::new(
+        hex::decode(input.trim()).map_err(|_| "Vault secret must be 64 hex characters".to_string())?,
+    );
+    VaultSecret::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+/// Parse the user's secp256k1 private key (recovery step 2).
+///
+/// Accepts 64 hex characters (optionally `0x`-prefixed) or base64.
+pub fn parse_private_key(input: &str) -> Result<Secp256k1SecretKey, String> {
+    let input = input.trim();
+    let hex_input = input.strip_prefix("0x").unwrap_or(input);
+    let bytes = Zeroizing::new(if hex_input.len() == 64 {
+        hex::decode(hex_input).map_err(|_| "Invalid hex private key".to_string())?
+    } else {
+        base64::engine::general_purpose::STANDARD
+            .decode(input)
+            .map_err(|_| "Private key must be 64 hex characters or base64".to_string())?
+    });
+    Secp256k1SecretKey::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PRIVATE_KEY: &str =
+        "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+    const TEST_PUBLIC_KEY: &str =
+        "042c8c31fc9f990c6b55e3865a184a4ce50e09481f2eaeb3e60ec1cea13a6ae64564b95e4fdb6948c0386e189b006a29f686769b011704275e4459822dc3328085";
+
+    #[test]
+    fn parses_hex_and_base64_keys() {
+        let bytes = hex::decode(TEST_PRIVATE_KEY).unwrap();
+        let base64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
+
+        for input in [
+            TEST_PRIVATE_KEY.to_string(),
+            format!("0x{}\n", TEST_PRIVATE_KEY),
+            base64,
+        ] {
+            let key = parse_private_key(&input).unwrap();
+            assert_eq!(key.as_bytes().as_slice(), bytes.as_slice());
+        }
+    }
+
+    #[test]
+    fn sealed_mlkem_key_needs_vault_secret() {
+        let private_key = parse_private_key(TEST_PRIVATE_KEY).unwrap();
+        let public_key = hex::decode(TEST_PUBLIC_KEY).unwrap();
+        let (mlkem_private_key, mlkem_public_key) = crate::crypto::ecies::generate_mlkem_keypair();
+        let vault_secret = VaultSecret::generate();
+        let sealed =
+            crate::crypto::ecies::seal_mlkem_private_key(&mlkem_private_key, &vault_secret, &public_key)
+                .unwrap();
+        let root_folder_key = FolderKey::generate();
+        let (ipns_key, ipns_public_key) = crate::crypto::keys::IpnsSigningKey::generate();
+        let ipns_key_libp2p = [ipns_key.as_bytes().as_slice(), &ipns_public_key].concat();
+        let export = VaultExport {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION.to_string(),
+            exported_at: None,
+            root_ipns_name: crate::crypto::ipns::derive_ipns_name(
+                &ipns_public_key.clone().try_into().unwrap(),
+            )
+            .unwrap(),
+            encrypted_root_folder_key: hex::encode(
+                crate::crypto::ecies::wrap_key_hybrid(
+                    root_folder_key.as_bytes(),
+                    &public_key,
+                    &mlkem_public_key,
+                )
+                .unwrap(),
+            ),
+            encrypted_root_ipns_private_key: hex::encode(
+                crate::crypto::ecies::wrap_key(&ipns_key_libp2p, &public_key).unwrap(),
+            ),
+            encrypted_mlkem_private_key: Some(hex::encode(&sealed)),
+        };
+
+        let err = export.mlkem_private_key(&private_key, None).unwrap_err();
+        assert!(err.contains("--vault-secret-file"), "{}", err);
+        assert!(export.root_folder_key(&private_key, None).is_err());
+
+        let vault_secret = parse_vault_secret(&hex::encode(vault_secret.as_bytes())).unwrap();
+        let mlkem_private_key = export.mlkem_private_key(&private_key, Some(&vault_secret)).unwrap();
+        let unwrapped = export
+            .root_folder_key(&private_key, mlkem_private_key.as_ref())
+            .unwrap();
+        assert_eq!(unwrapped.as_bytes(), root_folder_key.as_bytes());
+    }
+
+    #[test]
+    fn rejects_short_key() {
+        assert!(parse_private_key("abcd").is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_format() {
+        let json = br#"{"format":"something-else","version":"1.0","rootIpnsName":"k51",
+            "encryptedRootFolderKey":"04","encryptedRootIpnsPrivateKey":"04"}"#;
+        assert!(VaultExport::parse(json).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        let json = br#"{"format":"cipherbox-vault-export","version":"1.0","rootIpnsName":"",
+            "encryptedRootFolderKey":"04","encryptedRootIpnsPrivateKey":"04"}"#;
+        assert!(VaultExport::parse(json).is_err());
+    }
+}
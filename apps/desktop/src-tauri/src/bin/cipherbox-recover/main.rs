@@ -0,0 +1,181 @@
+//! cipherbox-recover: standalone vault recovery.
+//!
+//! Implements the recovery procedure of `docs/VAULT_EXPORT_FORMAT.md` without
+//! any CipherBox server: takes a vault export and the user's secp256k1
+//! private key, walks the folder hierarchy, and writes every decrypted file to
+//! an output directory, followed by a per-file report.
+//!
+//! ```text
+//! cipherbox-recover vault-export.json --key-file key.txt --output ./recovered
+//! cipherbox-recover vault-export.json --blocks ./blocks --offline -o ./recovered
+//! ```
+//!
+//! Vaults whose ML-KEM private key is sealed under the vault secret also need
+//! `--vault-secret-file`, with the secret exported from the desktop app.
+
+#[allow(dead_code, unused_imports)]
+#[path = "../../crypto/mod.rs"]
+mod crypto;
+
+mod export;
+mod source;
+mod walk;
+
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use zeroize::Zeroizing;
+
+use export::VaultExport;
+use source::BlockSource;
+use walk::Recovery;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "cipherbox-recover",
+    about = "Recover a CipherBox vault from a vault export"
+)]
+struct Args {
+    /// Vault export JSON file
+    export: PathBuf,
+
+    /// Directory to write the recovered files to (created if missing)
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// File containing the secp256k1 private key (hex or base64); read from stdin if omitted
+    #[arg(long)]
+    key_file: Option<PathBuf>,
+
+    /// File containing the vault secret (hex) exported from the desktop app,
+    /// for vaults with a sealed ML-KEM private key
+    #[arg(long)]
+    vault_secret_file: Option<PathBuf>,
+
+    /// IPFS gateway used to fetch content
+    #[arg(long, default_value = "https://ipfs.io")]
+    gateway: String,
+
+    /// Delegated routing endpoint used to resolve IPNS names
+    #[arg(long, default_value = "https://delegated-ipfs.dev")]
+    routing: String,
+
+    /// Local directory of blocks (files named by CID, IPNS records under `ipns/`)
+    #[arg(long)]
+    blocks: Option<PathBuf>,
+
+    /// Never use the network; everything must be in --blocks
+    #[arg(long, requires = "blocks")]
+    offline: bool,
+
+    /// Accept IPNS records past their validity (signatures are still checked)
+    #[arg(long)]
+    allow_expired: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args = Args::parse();
+
+    match run(args).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Run a recovery. Returns whether every entry was recovered.
+async fn run(args: Args) -> Result<bool, String> {
+    let bytes = std::fs::read(&args.export)
+        .map_err(|e| format!("Failed to read {}: {}", args.export.display(), e))?;
+    let export = VaultExport::parse(&bytes)?;
+    if let Some(exported_at) = &export.exported_at {
+        eprintln!("Vault export from {}", exported_at);
+    }
+
+    let private_key = export::parse_private_key(&read_key_input(args.key_file.as_ref())?)?;
+    let vault_secret = match &args.vault_secret_file {
+        Some(path) => {
+            let input = Zeroizing::new(
+                std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+            );
+            Some(export::parse_vault_secret(&input)?)
+        }
+        None => None,
+    };
+    let mlkem_private_key = export.mlkem_private_key(&private_key, vault_secret.as_ref())?;
+    let root_folder_key = export.root_folder_key(&private_key, mlkem_private_key.as_ref())?;
+
+    let source = if args.offline {
+        BlockSource::new(None, None, args.blocks, args.allow_expired)
+    } else {
+        BlockSource::new(
+            Some(args.gateway),
+            Some(args.routing),
+            args.blocks,
+            args.allow_expired,
+        )
+    };
+    let mut recovery = Recovery::new(&source, &private_key, args.output.clone())
+        .with_mlkem_private_key(mlkem_private_key);
+    recovery
+        .run(&export.root_ipns_name, root_folder_key)
+        .await?;
+
+    let mut recovered = 0usize;
+    let mut failed = 0usize;
+    let mut total_bytes = 0u64;
+    for report in &recovery.reports {
+        let kind = if report.is_folder { "folder " } else { "" };
+        match &report.result {
+            Ok(size) => {
+                recovered += 1;
+                total_bytes += size;
+                println!("  OK      {:>12}  {}", size, report.path.display());
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "  FAILED  {:>12}  {}{}: {}",
+                    "",
+                    kind,
+                    report.path.display(),
+                    e
+                );
+            }
+        }
+    }
+    println!(
+        "Recovered {} files ({} bytes) into {}, {} failed",
+        recovered,
+        total_bytes,
+        args.output.display(),
+        failed
+    );
+    Ok(failed == 0)
+}
+
+/// Read the private key from `key_file`, or prompt for it on stdin.
+fn read_key_input(key_file: Option<&PathBuf>) -> Result<Zeroizing<String>, String> {
+    if let Some(path) = key_file {
+        return std::fs::read_to_string(path)
+            .map(Zeroizing::new)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    }
+
+    eprint!("secp256k1 private key (hex or base64): ");
+    let _ = std::io::stderr().flush();
+    let mut line = Zeroizing::new(String::new());
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read private key: {}", e))?;
+    Ok(line)
+}
@@ -0,0 +1,160 @@
+//! Where recovery reads IPFS content and IPNS records from.
+//!
+//! Content comes from a local directory of blocks (one file per CID, named by
+//! the CID) or from an IPFS gateway (`{gateway}/ipfs/{cid}`). IPNS names are
+//! resolved from raw records in `{blocks}/ipns/{name}` when present, otherwise
+//! via a delegated routing endpoint (`{routing}/routing/v1/ipns/{name}`).
+//! Records are always signature-verified against the name before use, so
+//! neither the gateway nor the router has to be trusted for IPNS values.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crypto::ipns::{self, IpnsError};
+
+/// Content type for raw IPNS records (IPIP-351).
+const IPNS_RECORD_CONTENT_TYPE: &str = "application/vnd.ipfs.ipns-record";
+
+/// Reads blocks and IPNS records for the recovery walk.
+pub struct BlockSource {
+    client: reqwest::Client,
+    /// IPFS gateway base URL (None when running offline).
+    gateway_url: Option<String>,
+    /// Delegated routing base URL (None when running offline).
+    routing_url: Option<String>,
+    /// Local directory of blocks, preferred over the gateway.
+    blocks_dir: Option<PathBuf>,
+    /// Accept validly signed IPNS records whose EOL has passed.
+    allow_expired: bool,
+}
+
+impl BlockSource {
+    pub fn new(
+        gateway_url: Option<String>,
+        routing_url: Option<String>,
+        blocks_dir: Option<PathBuf>,
+        allow_expired: bool,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            gateway_url: gateway_url.map(|u| u.trim_end_matches('/').to_string()),
+            routing_url: routing_url.map(|u| u.trim_end_matches('/').to_string()),
+            blocks_dir,
+            allow_expired,
+        }
+    }
+
+    /// Fetch the content stored under `cid`.
+    pub async fn fetch(&self, cid: &str) -> Result<Vec<u8>, String> {
+        check_path_segment(cid, "CID")?;
+
+        if let Some(dir) = &self.blocks_dir {
+            let path = dir.join(cid);
+            if path.is_file() || self.gateway_url.is_none() {
+                return std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read block {}: {}", path.display(), e));
+            }
+        }
+
+        let gateway = self
+            .gateway_url
+            .as_deref()
+            .ok_or_else(|| format!("Block {} not available offline", cid))?;
+        let url = format!("{}/ipfs/{}", gateway, cid);
+        let resp = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Gateway request failed for {}: {}", cid, e))?;
+        if !resp.status().is_success() {
+            return Err(format!("Gateway returned {} for {}", resp.status(), cid));
+        }
+        resp.bytes()
+            .await
+            .map(|b| b.to_vec())
+            .map_err(|e| format!("Failed to read {} from gateway: {}", cid, e))
+    }
+
+    /// Resolve `ipns_name` to the CID its verified record points at.
+    pub async fn resolve_ipns(&self, ipns_name: &str) -> Result<String, String> {
+        check_path_segment(ipns_name, "IPNS name")?;
+        let bytes = self.fetch_ipns_record(ipns_name).await?;
+
+        let record = ipns::unmarshal_ipns_record(&bytes)
+            .map_err(|e| format!("IPNS record rejected for {}: {}", ipns_name, e))?;
+        match ipns::verify_ipns_record(&record, ipns_name, SystemTime::now()) {
+            Ok(()) => {}
+            Err(IpnsError::RecordExpired) if self.allow_expired => {
+                ipns::verify_ipns_record(&record, ipns_name, UNIX_EPOCH)
+                    .map_err(|e| format!("IPNS record rejected for {}: {}", ipns_name, e))?;
+                log::warn!("Using expired IPNS record for {}", ipns_name);
+            }
+            Err(e) => return Err(format!("IPNS record rejected for {}: {}", ipns_name, e)),
+        }
+
+        record
+            .value
+            .strip_prefix("/ipfs/")
+            .filter(|cid| !cid.is_empty() && !cid.contains('/'))
+            .map(str::to_string)
+            .ok_or_else(|| format!("Unsupported IPNS value for {}: {}", ipns_name, record.value))
+    }
+
+    /// Raw marshaled IPNS record, from the blocks directory or delegated routing.
+    async fn fetch_ipns_record(&self, ipns_name: &str) -> Result<Vec<u8>, String> {
+        if let Some(dir) = &self.blocks_dir {
+            let path = dir.join("ipns").join(ipns_name);
+            if path.is_file() {
+                return std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read IPNS record {}: {}", path.display(), e));
+            }
+        }
+
+        let routing = self
+            .routing_url
+            .as_deref()
+            .ok_or_else(|| format!("IPNS record for {} not available offline", ipns_name))?;
+        let url = format!(
+            "{}/routing/v1/ipns/{}",
+            routing,
+            urlencoding::encode(ipns_name)
+        );
+        let resp = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, IPNS_RECORD_CONTENT_TYPE)
+            .send()
+            .await
+            .map_err(|e| format!("IPNS routing request failed: {}", e))?;
+
+        if resp.status().as_u16() == 404 {
+            return Err(format!("IPNS name not found: {}", ipns_name));
+        }
+        if !resp.status().is_success() {
+            return Err(format!(
+                "IPNS routing request failed ({}) for {}",
+                resp.status(),
+                ipns_name
+            ));
+        }
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read IPNS record: {}", e))?;
+        if bytes.len() > ipns::MAX_IPNS_RECORD_SIZE {
+            return Err(format!("IPNS record too large ({} bytes)", bytes.len()));
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+/// CIDs and IPNS names come from decrypted metadata and end up in file paths
+/// and URLs, so only plain alphanumeric identifiers are accepted.
+fn check_path_segment(value: &str, what: &str) -> Result<(), String> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid {}: {:?}", what, value));
+    }
+    Ok(())
+}
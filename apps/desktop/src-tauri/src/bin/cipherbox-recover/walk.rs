@@ -0,0 +1,521 @@
+//! Folder hierarchy walk (recovery steps 4-7).
+//!
+//! Resolves each folder's IPNS name, decrypts its metadata with the folder
+//! key, then recovers every FilePointer through the file's own IPNS record.
+//! A file or subfolder that cannot be recovered is reported and skipped; only
+//! a failure on the root folder aborts the walk.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use zeroize::Zeroizing;
+
+use crate::crypto::chunked::CHUNKED_MODE;
+use crate::crypto::folder::{FilePointer, FolderChild, FolderEntry};
+use crate::crypto::keys::{FileKey, FolderKey, MlKemSecretKey, Secp256k1SecretKey};
+use crate::source::BlockSource;
+
+/// Outcome for one file or folder of the vault.
+pub struct EntryReport {
+    /// Path relative to the output directory.
+    pub path: PathBuf,
+    /// Whether the entry is a folder (folders are only reported on failure).
+    pub is_folder: bool,
+    /// Plaintext bytes written, or why the entry was skipped.
+    pub result: Result<u64, String>,
+}
+
+/// Recovers a vault into `output_dir`.
+pub struct Recovery<'a> {
+    source: &'a BlockSource,
+    private_key: &'a Secp256k1SecretKey,
+    mlkem_private_key: Option<MlKemSecretKey>,
+    output_dir: PathBuf,
+    pub reports: Vec<EntryReport>,
+}
+
+impl<'a> Recovery<'a> {
+    pub fn new(
+        source: &'a BlockSource,
+        private_key: &'a Secp256k1SecretKey,
+        output_dir: PathBuf,
+    ) -> Self {
+        Self {
+            source,
+            private_key,
+            mlkem_private_key: None,
+            output_dir,
+            reports: Vec::new(),
+        }
+    }
+
+    /// Unwrap hybrid-wrapped folder keys with the user's ML-KEM key.
+    pub fn with_mlkem_private_key(mut self, key: Option<MlKemSecretKey>) -> Self {
+        self.mlkem_private_key = key;
+        self
+    }
+
+    /// Walk the vault starting at the root folder.
+    ///
+    /// Fails only if the root folder itself cannot be read.
+    pub async fn run(
+        &mut self,
+        root_ipns_name: &str,
+        root_folder_key: FolderKey,
+    ) -> Result<(), String> {
+        fs::create_dir_all(&self.output_dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.output_dir.display(), e))?;
+
+        let mut visited = HashSet::new();
+        let mut pending = vec![(root_ipns_name.to_string(), root_folder_key, PathBuf::new())];
+        while let Some((ipns_name, folder_key, rel_path)) = pending.pop() {
+            if !visited.insert(ipns_name.clone()) {
+                self.report(
+                    rel_path,
+                    true,
+                    Err("Folder already visited (cycle)".to_string()),
+                );
+                continue;
+            }
+
+            let children = match self.read_folder(&ipns_name, &folder_key).await {
+                Ok(children) => children,
+                Err(e) if rel_path.as_os_str().is_empty() => return Err(e),
+                Err(e) => {
+                    self.report(rel_path, true, Err(e));
+                    continue;
+                }
+            };
+
+            for child in children {
+                match child {
+                    FolderChild::Folder(entry) => {
+                        let path = rel_path.join(&entry.name);
+                        match self.open_subfolder(&entry, &path) {
+                            Ok(key) => pending.push((entry.ipns_name, key, path)),
+                            Err(e) => self.report(path, true, Err(e)),
+                        }
+                    }
+                    FolderChild::File(pointer) => {
+                        let path = rel_path.join(&pointer.name);
+                        let result = self.recover_file(&pointer, &folder_key, &path).await;
+                        self.report(path, false, result);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, path: PathBuf, is_folder: bool, result: Result<u64, String>) {
+        match &result {
+            Ok(size) => log::debug!("Recovered {} ({} bytes)", path.display(), size),
+            Err(e) => log::warn!("Failed to recover {}: {}", path.display(), e),
+        }
+        self.reports.push(EntryReport {
+            path,
+            is_folder,
+            result,
+        });
+    }
+
+    /// Resolve, fetch and decrypt a folder's metadata.
+    async fn read_folder(
+        &self,
+        ipns_name: &str,
+        folder_key: &FolderKey,
+    ) -> Result<Vec<FolderChild>, String> {
+        let cid = self.source.resolve_ipns(ipns_name).await?;
+        let envelope = self.source.fetch(&cid).await?;
+        let metadata = crate::crypto::folder::decrypt_folder_metadata_envelope(
+            &envelope, folder_key, ipns_name,
+        )
+        .map_err(|e| format!("Folder metadata decryption failed: {}", e))?;
+        Ok(metadata.children)
+    }
+
+    /// Unwrap a subfolder's key and create its output directory.
+    fn open_subfolder(&self, entry: &FolderEntry, rel_path: &Path) -> Result<FolderKey, String> {
+        check_name(&entry.name)?;
+        let wrapped = hex::decode(&entry.folder_key_encrypted)
+            .map_err(|_| "Invalid folder key hex".to_string())?;
+        let key = crate::crypto::ecies::unwrap_secret_key_hybrid(
+            &wrapped,
+            self.private_key,
+            self.mlkem_private_key.as_ref(),
+        )
+        .map_err(|e| format!("Folder key unwrap failed: {}", e))?;
+        let dir = self.output_dir.join(rel_path);
+        fs::create_dir(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(key)
+    }
+
+    /// Recover one file through its per-file IPNS record. Returns its size.
+    async fn recover_file(
+        &self,
+        pointer: &FilePointer,
+        folder_key: &FolderKey,
+        rel_path: &Path,
+    ) -> Result<u64, String> {
+        check_name(&pointer.name)?;
+        let meta_cid = self
+            .source
+            .resolve_ipns(&pointer.file_meta_ipns_name)
+            .await?;
+        let envelope = self.source.fetch(&meta_cid).await?;
+        let metadata = crate::crypto::folder::decrypt_file_metadata_envelope(
+            &envelope,
+            folder_key,
+            &pointer.file_meta_ipns_name,
+        )
+        .map_err(|e| format!("File metadata decryption failed: {}", e))?;
+
+        if let Some(scheme) = metadata.padding.as_deref() {
+            crate::crypto::padding::PaddingScheme::from_name(scheme).map_err(|e| e.to_string())?;
+        }
+
+        let wrapped = hex::decode(&metadata.file_key_encrypted)
+            .map_err(|_| "Invalid file key hex".to_string())?;
+        let file_key: FileKey = crate::crypto::ecies::unwrap_secret_key(&wrapped, self.private_key)
+            .map_err(|e| format!("File key unwrap failed: {}", e))?;
+
+        let mut plaintext = Zeroizing::new(if metadata.encryption_mode == CHUNKED_MODE {
+            self.fetch_chunked(&metadata.cid, &file_key).await?
+        } else {
+            let encrypted = self.source.fetch(&metadata.cid).await?;
+            if let Some(hashes) = &metadata.chunk_hashes {
+                if crate::crypto::aes_ctr::ctr_chunk_hashes(&encrypted) != *hashes {
+                    return Err("Content does not match its chunk hashes".to_string());
+                }
+            }
+            decrypt_content(
+                &encrypted,
+                &file_key,
+                &metadata.file_iv,
+                &metadata.encryption_mode,
+            )?
+        });
+        if metadata.padding.is_some() {
+            crate::crypto::padding::unpad_content(&mut plaintext)
+                .map_err(|e| format!("Content unpadding failed: {}", e))?;
+        }
+
+        let path = self.output_dir.join(rel_path);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        file.write_all(&plaintext)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(plaintext.len() as u64)
+    }
+
+    /// Download and reassemble a chunked file from its manifest.
+    async fn fetch_chunked(
+        &self,
+        manifest_cid: &str,
+        file_key: &FileKey,
+    ) -> Result<Vec<u8>, String> {
+        let sealed = self.source.fetch(manifest_cid).await?;
+        let manifest = crate::crypto::chunked::decrypt_manifest(&sealed, file_key)
+            .map_err(|e| format!("Chunk manifest decryption failed: {}", e))?;
+        let mut plaintext = Vec::with_capacity(manifest.size as usize);
+        for chunk in &manifest.chunks {
+            let sealed = self.source.fetch(&chunk.cid).await?;
+            let part = Zeroizing::new(
+                crate::crypto::chunked::decrypt_chunk(&sealed, file_key, chunk)
+                    .map_err(|e| format!("Chunk {} decryption failed: {}", chunk.cid, e))?,
+            );
+            plaintext.extend_from_slice(&part);
+        }
+        Ok(plaintext)
+    }
+}
+
+/// Decrypt single-object file content according to its encryption mode.
+///
+/// Same dispatch as the FUSE read path: "CTR", "GCM-SEG", otherwise AES-GCM.
+fn decrypt_content(
+    encrypted: &[u8],
+    file_key: &FileKey,
+    iv_hex: &str,
+    encryption_mode: &str,
+) -> Result<Vec<u8>, String> {
+    let iv = hex::decode(iv_hex).map_err(|_| "Invalid file IV hex".to_string())?;
+    match encryption_mode {
+        "CTR" => {
+            let iv: [u8; 16] = iv
+                .try_into()
+                .map_err(|_| "Invalid CTR IV length (expected 16)".to_string())?;
+            crate::crypto::aes_ctr::decrypt_aes_ctr(encrypted, file_key.as_bytes(), &iv)
+                .map_err(|e| format!("CTR decryption failed: {}", e))
+        }
+        crate::crypto::aes_gcm_seg::GCM_SEG_MODE => {
+            let prefix: [u8; crate::crypto::aes_gcm_seg::GCM_SEG_NONCE_PREFIX_SIZE] = iv
+                .try_into()
+                .map_err(|_| "Invalid GCM-SEG nonce prefix length (expected 7)".to_string())?;
+            crate::crypto::aes_gcm_seg::decrypt_aes_gcm_seg(encrypted, file_key.as_bytes(), &prefix)
+                .map_err(|e| format!("GCM-SEG decryption failed: {}", e))
+        }
+        _ => {
+            let iv: [u8; 12] = iv
+                .try_into()
+                .map_err(|_| "Invalid GCM IV length (expected 12)".to_string())?;
+            crate::crypto::aes::decrypt_aes_gcm(encrypted, file_key.as_bytes(), &iv)
+                .map_err(|e| format!("GCM decryption failed: {}", e))
+        }
+    }
+}
+
+/// Names come from (possibly hostile) metadata: refuse anything that is not a
+/// single path component.
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(format!("Unsafe file name {:?}", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::folder::{FileMetadata, FolderMetadata, MetadataFormat};
+    use crate::crypto::keys::{IpnsSigningKey, SecretKey};
+    use crate::crypto::padding::PaddingScheme;
+
+    /// secp256k1 keypair from the crypto cross-language vectors.
+    const TEST_PRIVATE_KEY: &str =
+        "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+    const TEST_PUBLIC_KEY: &str =
+        "042c8c31fc9f990c6b55e3865a184a4ce50e09481f2eaeb3e60ec1cea13a6ae64564b95e4fdb6948c0386e189b006a29f686769b011704275e4459822dc3328085";
+
+    /// Writes a vault into a local blocks directory.
+    struct VaultBuilder {
+        blocks: PathBuf,
+        public_key: Vec<u8>,
+        next_cid: usize,
+    }
+
+    impl VaultBuilder {
+        fn new(blocks: PathBuf) -> Self {
+            fs::create_dir_all(blocks.join("ipns")).unwrap();
+            Self {
+                blocks,
+                public_key: hex::decode(TEST_PUBLIC_KEY).unwrap(),
+                next_cid: 0,
+            }
+        }
+
+        fn put(&mut self, bytes: &[u8]) -> String {
+            self.next_cid += 1;
+            let cid = format!("bafytest{}", self.next_cid);
+            fs::write(self.blocks.join(&cid), bytes).unwrap();
+            cid
+        }
+
+        /// Create an IPNS name and publish a record for `cid` under it.
+        fn publish(&self, key: &IpnsSigningKey, cid: &str) -> String {
+            let public_key: [u8; 32] = key.public_key().try_into().unwrap();
+            let name = crate::crypto::ipns::derive_ipns_name(&public_key).unwrap();
+            let record = crate::crypto::ipns::create_ipns_record(
+                key,
+                &format!("/ipfs/{}", cid),
+                1,
+                86_400_000,
+            )
+            .unwrap();
+            let bytes = crate::crypto::ipns::marshal_ipns_record(&record).unwrap();
+            fs::write(self.blocks.join("ipns").join(&name), bytes).unwrap();
+            name
+        }
+
+        fn ipns_name(key: &IpnsSigningKey) -> String {
+            let public_key: [u8; 32] = key.public_key().try_into().unwrap();
+            crate::crypto::ipns::derive_ipns_name(&public_key).unwrap()
+        }
+
+        /// Store a GCM file and return its pointer.
+        fn file(&mut self, name: &str, content: &[u8], folder_key: &FolderKey) -> FilePointer {
+            let file_key = FileKey::generate();
+            let iv = crate::crypto::utils::generate_iv();
+            let encrypted =
+                crate::crypto::aes::encrypt_aes_gcm(content, file_key.as_bytes(), &iv).unwrap();
+            let cid = self.put(&encrypted);
+            let metadata = FileMetadata {
+                version: "v1".to_string(),
+                cid,
+                file_key_encrypted: hex::encode(
+                    crate::crypto::ecies::wrap_key(file_key.as_bytes(), &self.public_key).unwrap(),
+                ),
+                file_iv: hex::encode(iv),
+                size: content.len() as u64,
+                mime_type: "application/octet-stream".to_string(),
+                encryption_mode: "GCM".to_string(),
+                padding: None,
+                chunk_hashes: None,
+                created_at: 0,
+                modified_at: 0,
+                versions: None,
+            };
+            self.file_pointer(name, &metadata, folder_key)
+        }
+
+        fn file_pointer(
+            &mut self,
+            name: &str,
+            metadata: &FileMetadata,
+            folder_key: &FolderKey,
+        ) -> FilePointer {
+            let (ipns_key, _) = IpnsSigningKey::generate();
+            let ipns_name = Self::ipns_name(&ipns_key);
+            let envelope = crate::crypto::folder::encrypt_file_metadata_envelope(
+                metadata,
+                folder_key,
+                &ipns_name,
+                MetadataFormat::V2 {
+                    padding: PaddingScheme::None,
+                },
+            )
+            .unwrap();
+            let cid = self.put(&envelope);
+            self.publish(&ipns_key, &cid);
+            FilePointer {
+                id: name.to_string(),
+                name: name.to_string(),
+                file_meta_ipns_name: ipns_name,
+                ipns_private_key_encrypted: None,
+                created_at: 0,
+                modified_at: 0,
+            }
+        }
+
+        /// Store a folder's metadata and return its IPNS name.
+        fn folder(
+            &mut self,
+            key: &IpnsSigningKey,
+            children: Vec<FolderChild>,
+            folder_key: &FolderKey,
+        ) -> String {
+            let ipns_name = Self::ipns_name(key);
+            let metadata = FolderMetadata {
+                version: "v2".to_string(),
+                children,
+            };
+            let envelope = crate::crypto::folder::encrypt_folder_metadata_envelope(
+                &metadata,
+                folder_key,
+                &ipns_name,
+                MetadataFormat::V2 {
+                    padding: PaddingScheme::None,
+                },
+            )
+            .unwrap();
+            let cid = self.put(&envelope);
+            self.publish(key, &cid)
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn recovers_tree_from_local_blocks() {
+        let dir = test_dir("cipherbox-test-recover-tree");
+        let mut vault = VaultBuilder::new(dir.join("blocks"));
+        let private_key =
+            Secp256k1SecretKey::from_slice(&hex::decode(TEST_PRIVATE_KEY).unwrap()).unwrap();
+        let (mlkem_private_key, mlkem_public_key) = crate::crypto::ecies::generate_mlkem_keypair();
+
+        // docs/ holds a file and uses a hybrid-wrapped folder key
+        let docs_key = FolderKey::generate();
+        let (docs_ipns_key, _) = IpnsSigningKey::generate();
+        let note = vault.file("note.txt", b"hello from docs", &docs_key);
+        let docs_name = vault.folder(&docs_ipns_key, vec![FolderChild::File(note)], &docs_key);
+
+        let root_key = FolderKey::generate();
+        let (root_ipns_key, _) = IpnsSigningKey::generate();
+        let readme = vault.file("readme.md", b"# Root", &root_key);
+        let docs = FolderEntry {
+            id: "docs".to_string(),
+            name: "docs".to_string(),
+            ipns_name: docs_name,
+            folder_key_encrypted: hex::encode(
+                crate::crypto::ecies::wrap_key_hybrid(
+                    docs_key.as_bytes(),
+                    &vault.public_key,
+                    &mlkem_public_key,
+                )
+                .unwrap(),
+            ),
+            ipns_private_key_encrypted: String::new(),
+            created_at: 0,
+            modified_at: 0,
+        };
+        let root_name = vault.folder(
+            &root_ipns_key,
+            vec![FolderChild::File(readme), FolderChild::Folder(docs)],
+            &root_key,
+        );
+
+        let source = BlockSource::new(None, None, Some(dir.join("blocks")), false);
+        let mut recovery = Recovery::new(&source, &private_key, dir.join("out"))
+            .with_mlkem_private_key(Some(mlkem_private_key));
+        recovery.run(&root_name, root_key).await.unwrap();
+
+        assert!(recovery.reports.iter().all(|r| r.result.is_ok()));
+        assert_eq!(recovery.reports.len(), 2);
+        assert_eq!(fs::read(dir.join("out/readme.md")).unwrap(), b"# Root");
+        assert_eq!(
+            fs::read(dir.join("out/docs/note.txt")).unwrap(),
+            b"hello from docs"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn missing_file_record_is_reported_and_skipped() {
+        let dir = test_dir("cipherbox-test-recover-missing");
+        let mut vault = VaultBuilder::new(dir.join("blocks"));
+        let private_key =
+            Secp256k1SecretKey::from_slice(&hex::decode(TEST_PRIVATE_KEY).unwrap()).unwrap();
+
+        let root_key = FolderKey::generate();
+        let (root_ipns_key, _) = IpnsSigningKey::generate();
+        let good = vault.file("good.txt", b"ok", &root_key);
+        let lost = vault.file("lost.txt", b"gone", &root_key);
+        fs::remove_file(dir.join("blocks/ipns").join(&lost.file_meta_ipns_name)).unwrap();
+        let root_name = vault.folder(
+            &root_ipns_key,
+            vec![FolderChild::File(good), FolderChild::File(lost)],
+            &root_key,
+        );
+
+        let source = BlockSource::new(None, None, Some(dir.join("blocks")), false);
+        let mut recovery = Recovery::new(&source, &private_key, dir.join("out"));
+        recovery.run(&root_name, root_key).await.unwrap();
+
+        let failed: Vec<_> = recovery
+            .reports
+            .iter()
+            .filter(|r| r.result.is_err())
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].path, PathBuf::from("lost.txt"));
+        assert_eq!(fs::read(dir.join("out/good.txt")).unwrap(), b"ok");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_unsafe_names() {
+        for name in ["", ".", "..", "a/b", "..\\x", "nul\0"] {
+            assert!(check_name(name).is_err(), "{:?} must be rejected", name);
+        }
+        assert!(check_name("report (final).pdf").is_ok());
+    }
+}
@@ -11,6 +11,9 @@ use zeroize::Zeroizing;
 
 use crate::api::{auth, types};
 use crate::crypto;
+use crate::crypto::keys::{
+    FolderKey, IpnsSigningKey, MlKemSecretKey, Secp256k1SecretKey, SecretKey, VaultSecret,
+};
 use crate::state::AppState;
 
 /// Handle completed Web3Auth authentication from the webview.
@@ -40,15 +43,15 @@ pub async fn handle_auth_complete(
     } else {
         &private_key
     };
-    let private_key_bytes =
-        hex::decode(private_key_hex).map_err(|_| "Invalid private key hex".to_string())?;
-    if private_key_bytes.len() != 32 {
-        return Err("Private key must be 32 bytes".to_string());
-    }
+    let private_key_bytes = Zeroizing::new(
+        hex::decode(private_key_hex).map_err(|_| "Invalid private key hex".to_string())?,
+    );
+    let secret_key = Secp256k1SecretKey::from_slice(&private_key_bytes)
+        .map_err(|_| "Private key must be 32 bytes".to_string())?;
 
     // Derive uncompressed public key from private key (65 bytes, 0x04 prefix)
     // Used for both ECIES operations and backend auth (backend expects uncompressed)
-    let public_key_bytes = derive_public_key(&private_key_bytes)?;
+    let public_key_bytes = derive_public_key(secret_key.as_bytes())?;
     let public_key_hex = hex::encode(&public_key_bytes); // 130 hex chars
 
     // 2. Login with backend (requires uncompressed publicKey, 130 hex chars)
@@ -81,7 +84,7 @@ pub async fn handle_auth_complete(
         &state,
         login_resp.access_token,
         login_resp.refresh_token,
-        Zeroizing::new(private_key_bytes),
+        secret_key,
         public_key_bytes,
         login_resp.is_new_user,
         false,
@@ -98,7 +101,7 @@ async fn complete_auth_setup(
     state: &AppState,
     access_token: String,
     refresh_token: String,
-    private_key_bytes: Zeroizing<Vec<u8>>,
+    private_key: Secp256k1SecretKey,
     public_key_bytes: Vec<u8>,
     is_new_user: bool,
     skip_keychain: bool,
@@ -120,9 +123,17 @@ async fn complete_auth_setup(
         log::info!("Skipping Keychain storage (test-login mode)");
     }
 
-    // 4. Store keys in AppState
-    *state.private_key.write().await = Some(private_key_bytes.to_vec());
+    // 4. Store keys in AppState, and whether the vault secret export was
+    //    confirmed (hybrid wrapping waits for it)
+    *state.private_key.write().await = Some(private_key.clone());
     *state.public_key.write().await = Some(public_key_bytes.clone());
+    if !skip_keychain {
+        let confirmed = auth::is_vault_secret_confirmed(&user_id).unwrap_or_else(|e| {
+            log::warn!("Keychain read vault secret confirmation failed: {}", e);
+            false
+        });
+        *state.vault_secret_confirmed.write().await = confirmed;
+    }
 
     // 5. Initialize vault for new users, or fetch existing vault
     //    Also handle the edge case where user exists but vault was deleted.
@@ -152,95 +163,29 @@ async fn complete_auth_setup(
     }
     #[cfg(feature = "fuse")]
     {
-        *state.mount_status.write().await = crate::state::MountStatus::Mounting;
-        let private_key = state
-            .private_key
-            .read()
-            .await
-            .as_ref()
-            .ok_or("Private key not available for FUSE mount")?
-            .clone();
-        let public_key = state
-            .public_key
-            .read()
-            .await
-            .as_ref()
-            .ok_or("Public key not available for FUSE mount")?
-            .clone();
-        let root_folder_key = state
-            .root_folder_key
-            .read()
-            .await
-            .as_ref()
-            .ok_or("Root folder key not available for FUSE mount")?
-            .clone();
-        let root_ipns_name = state
-            .root_ipns_name
-            .read()
-            .await
-            .as_ref()
-            .ok_or("Root IPNS name not available for FUSE mount")?
-            .clone();
-        let root_ipns_private_key = state.root_ipns_private_key.read().await.clone();
-
-        // Extract TEE keys for new folder creation
-        let tee_keys = state.tee_keys.read().await;
-        let tee_public_key = tee_keys.as_ref().and_then(|tk| {
-            hex::decode(&tk.current_public_key).ok()
-        });
-        let tee_key_epoch = tee_keys.as_ref().map(|tk| tk.current_epoch);
-        drop(tee_keys);
-
-        let rt = tokio::runtime::Handle::current();
-        match crate::fuse::mount_filesystem(
-            state,
-            rt,
-            private_key,
-            public_key,
-            root_folder_key,
-            root_ipns_name,
-            root_ipns_private_key,
-            tee_public_key,
-            tee_key_epoch,
-        ).await {
-            Ok(_handle) => {
-                *state.mount_status.write().await = crate::state::MountStatus::Mounted;
-                let _ = crate::tray::update_tray_status(app, &crate::tray::TrayStatus::Synced);
-                log::info!("FUSE filesystem mounted at ~/CipherBox");
-            }
-            Err(e) => {
-                let err_msg = format!("FUSE mount failed: {}", e);
-                *state.mount_status.write().await =
-                    crate::state::MountStatus::Error(err_msg.clone());
-                let _ = crate::tray::update_tray_status(
-                    app,
-                    &crate::tray::TrayStatus::Error(err_msg.clone()),
-                );
-                log::error!("{}", err_msg);
-                // Don't fail auth -- user is authenticated but mount failed
-            }
+        // A failed mount doesn't fail auth: the supervisor shows it in the
+        // tray and keeps retrying
+        for spec in state.mount.mounts() {
+            let mount = crate::fuse::supervisor::mount(app, state, &spec).await;
+            crate::fuse::supervisor::supervise(app, spec, mount);
         }
     }
 
-    // 8. Register device in encrypted registry (non-blocking, after mount)
-    {
+    // 8. Register device in encrypted registry (non-blocking, after mount).
+    //    A read-only instance publishes nothing, the registry included.
+    if !state.mount.read_only {
         let reg_api = state.api.clone();
-        let reg_private_key = Zeroizing::new(private_key_bytes.to_vec());
+        let reg_private_key = private_key;
         let reg_public_key = public_key_bytes.clone();
         let reg_user_id = user_id.clone();
+        let reg_padding = state.padding;
         tokio::spawn(async move {
-            let pk_arr: [u8; 32] = match reg_private_key.as_slice().try_into() {
-                Ok(arr) => arr,
-                Err(_) => {
-                    log::warn!("Device registry: invalid private key length");
-                    return;
-                }
-            };
             match crate::registry::register_device(
                 &reg_api,
-                &pk_arr,
+                &reg_private_key,
                 &reg_public_key,
                 &reg_user_id,
+                reg_padding,
             )
             .await
             {
@@ -285,13 +230,13 @@ pub async fn handle_session_restore(
     } else {
         &private_key
     };
-    let private_key_bytes =
-        hex::decode(private_key_hex).map_err(|_| "Invalid private key hex".to_string())?;
-    if private_key_bytes.len() != 32 {
-        return Err("Private key must be 32 bytes".to_string());
-    }
+    let private_key_bytes = Zeroizing::new(
+        hex::decode(private_key_hex).map_err(|_| "Invalid private key hex".to_string())?,
+    );
+    let secret_key = Secp256k1SecretKey::from_slice(&private_key_bytes)
+        .map_err(|_| "Private key must be 32 bytes".to_string())?;
 
-    let public_key_bytes = derive_public_key(&private_key_bytes)?;
+    let public_key_bytes = derive_public_key(secret_key.as_bytes())?;
 
     // Get the existing access token (set by try_silent_refresh)
     let access_token = state
@@ -311,7 +256,7 @@ pub async fn handle_session_restore(
         &state,
         access_token,
         refresh_token,
-        Zeroizing::new(private_key_bytes),
+        secret_key,
         public_key_bytes,
         false, // not a new user
         true,  // skip Keychain writes (already stored)
@@ -504,14 +449,14 @@ pub async fn handle_test_login_complete(
     } else {
         &private_key_hex
     };
-    let private_key_bytes =
-        hex::decode(pk_hex).map_err(|_| "Invalid private key hex from test-login".to_string())?;
-    if private_key_bytes.len() != 32 {
-        return Err("Private key must be 32 bytes".to_string());
-    }
+    let private_key_bytes = Zeroizing::new(
+        hex::decode(pk_hex).map_err(|_| "Invalid private key hex from test-login".to_string())?,
+    );
+    let secret_key = Secp256k1SecretKey::from_slice(&private_key_bytes)
+        .map_err(|_| "Private key must be 32 bytes".to_string())?;
 
     // Derive public key from the test-login private key
-    let public_key_bytes = derive_public_key(&private_key_bytes)?;
+    let public_key_bytes = derive_public_key(secret_key.as_bytes())?;
 
     // Delegate to shared post-auth setup (skips /auth/login POST and Keychain)
     complete_auth_setup(
@@ -519,7 +464,7 @@ pub async fn handle_test_login_complete(
         &state,
         access_token,
         refresh_token,
-        Zeroizing::new(private_key_bytes),
+        secret_key,
         public_key_bytes,
         is_new_user,
         true, // skip Keychain — test-login re-authenticates each time
@@ -535,7 +480,7 @@ pub async fn logout(app: tauri::AppHandle, state: State<'_, AppState>) -> Result
     // Unmount FUSE filesystem before clearing keys
     #[cfg(feature = "fuse")]
     {
-        if let Err(e) = crate::fuse::unmount_filesystem() {
+        if let Err(e) = crate::fuse::supervisor::unmount_all(&state) {
             log::warn!("FUSE unmount failed (will continue logout): {}", e);
         }
         *state.mount_status.write().await = crate::state::MountStatus::Unmounted;
@@ -564,12 +509,19 @@ pub async fn logout(app: tauri::AppHandle, state: State<'_, AppState>) -> Result
 
 /// Initialize a new vault for a first-time user.
 ///
-/// Generates a root folder AES-256 key and derives a deterministic Ed25519 IPNS
-/// keypair via HKDF from the user's private key. ECIES-wraps them with the
-/// user's secp256k1 public key, and POSTs everything to `/vault/init`.
+/// Generates a root folder AES-256 key, and derives a deterministic Ed25519
+/// IPNS keypair via HKDF from the user's private key. ECIES-wraps both with the
+/// user's secp256k1 public key and POSTs everything to `/vault/init`. When
+/// hybrid wrapping is enabled and the vault secret export confirmed, also
+/// generates an ML-KEM-768 keypair, seals its private key under the vault
+/// secret and hybrid-wraps the root folder key.
 async fn initialize_vault(state: &AppState, public_key: &[u8]) -> Result<(), String> {
+    if state.mount.read_only {
+        return Err("No vault to mount, and a read-only instance cannot create one".to_string());
+    }
+
     // Generate root folder AES-256 key (32 random bytes)
-    let root_folder_key = crypto::utils::generate_random_bytes(32);
+    let root_folder_key = FolderKey::generate();
 
     // Derive IPNS keypair deterministically via HKDF from user's private key
     let private_key = state
@@ -579,26 +531,52 @@ async fn initialize_vault(state: &AppState, public_key: &[u8]) -> Result<(), Str
         .as_ref()
         .ok_or("Private key not available for vault IPNS derivation")?
         .clone();
-    let private_key_arr: [u8; 32] = private_key
-        .as_slice()
-        .try_into()
-        .map_err(|_| "Invalid private key length")?;
     let (ipns_private_key, _ipns_public_key, root_ipns_name) =
-        crypto::hkdf::derive_vault_ipns_keypair(&private_key_arr)
+        crypto::hkdf::derive_vault_ipns_keypair(&private_key)
             .map_err(|e| format!("Vault IPNS derivation failed: {:?}", e))?;
 
-    // ECIES-wrap keys with user's uncompressed secp256k1 public key
-    let encrypted_root_folder_key = crypto::ecies::wrap_key(&root_folder_key, public_key)
-        .map_err(|e| format!("Failed to wrap root folder key: {}", e))?;
-    let encrypted_ipns_private_key = crypto::ecies::wrap_key(&ipns_private_key, public_key)
+    let vault_secret = if state.hybrid_wrap_ready().await {
+        get_vault_secret(state).await.unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            None
+        })
+    } else {
+        None
+    };
+    let mlkem_key = match vault_secret {
+        Some(vault_secret) => {
+            let (mlkem_private_key, mlkem_public_key) = crypto::ecies::generate_mlkem_keypair();
+            let encrypted_mlkem_private_key =
+                crypto::ecies::seal_mlkem_private_key(&mlkem_private_key, &vault_secret, public_key)
+                    .map_err(|e| format!("Failed to seal ML-KEM private key: {}", e))?;
+            Some((encrypted_mlkem_private_key, mlkem_public_key))
+        }
+        None => None,
+    };
+
+    // Hybrid-wrap the root folder key when there is an ML-KEM key, ECIES-wrap
+    // the IPNS key with the user's uncompressed secp256k1 public key
+    let encrypted_root_folder_key = crypto::ecies::wrap_key_hybrid_if_available(
+        root_folder_key.as_bytes(),
+        public_key,
+        mlkem_key.as_ref().map(|(_, mlkem_public_key)| mlkem_public_key.as_slice()),
+    )
+    .map_err(|e| format!("Failed to wrap root folder key: {}", e))?;
+    let encrypted_ipns_private_key = crypto::ecies::wrap_key(ipns_private_key.as_bytes(), public_key)
         .map_err(|e| format!("Failed to wrap IPNS private key: {}", e))?;
 
     // 1. Register vault with backend
+    let (encrypted_mlkem_private_key, mlkem_public_key) = match mlkem_key {
+        Some((encrypted, public)) => (Some(hex::encode(encrypted)), Some(hex::encode(public))),
+        None => (None, None),
+    };
     let init_req = types::InitVaultRequest {
         owner_public_key: hex::encode(public_key),
         encrypted_root_folder_key: hex::encode(&encrypted_root_folder_key),
         encrypted_root_ipns_private_key: hex::encode(&encrypted_ipns_private_key),
         root_ipns_name: root_ipns_name.clone(),
+        encrypted_mlkem_private_key,
+        mlkem_public_key,
     };
 
     let resp = state
@@ -622,36 +600,25 @@ async fn initialize_vault(state: &AppState, public_key: &[u8]) -> Result<(), Str
         children: vec![],
     };
 
-    // Encrypt metadata with root folder key
-    let folder_key_arr: [u8; 32] = root_folder_key
-        .try_into()
-        .map_err(|_| "Invalid root folder key length".to_string())?;
-    let sealed = crypto::folder::encrypt_folder_metadata(&empty_metadata, &folder_key_arr)
-        .map_err(|e| format!("Metadata encryption failed: {}", e))?;
-
-    // Format as JSON { "iv": "<hex>", "data": "<base64>" }
-    let iv_hex = hex::encode(&sealed[..12]);
-    use base64::Engine;
-    let data_base64 = base64::engine::general_purpose::STANDARD.encode(&sealed[12..]);
-    let json_metadata = serde_json::json!({
-        "iv": iv_hex,
-        "data": data_base64,
-    });
-    let json_bytes = serde_json::to_vec(&json_metadata)
-        .map_err(|e| format!("JSON serialization failed: {}", e))?;
+    // Encrypt metadata with root folder key, bound to the root IPNS name
+    let json_bytes = crypto::folder::encrypt_folder_metadata_envelope(
+        &empty_metadata,
+        &root_folder_key,
+        &root_ipns_name,
+        state.metadata_format,
+    )
+    .map_err(|e| format!("Metadata encryption failed: {}", e))?;
 
     // Upload encrypted metadata to IPFS
     let initial_cid = crate::api::ipfs::upload_content(&state.api, &json_bytes).await?;
 
     // Create and sign IPNS record (sequence 0, 24h lifetime)
-    let ipns_key_arr: [u8; 32] = ipns_private_key.as_slice()
-        .try_into()
-        .map_err(|_| "Invalid IPNS private key length".to_string())?;
     let value = format!("/ipfs/{}", initial_cid);
-    let record = crypto::ipns::create_ipns_record(&ipns_key_arr, &value, 0, 86_400_000)
+    let record = crypto::ipns::create_ipns_record(&ipns_private_key, &value, 0, 86_400_000)
         .map_err(|e| format!("IPNS record creation failed: {}", e))?;
     let marshaled = crypto::ipns::marshal_ipns_record(&record)
         .map_err(|e| format!("IPNS record marshaling failed: {}", e))?;
+    use base64::Engine;
     let record_base64 = base64::engine::general_purpose::STANDARD.encode(&marshaled);
 
     // Publish IPNS record via backend
@@ -671,7 +638,10 @@ async fn initialize_vault(state: &AppState, public_key: &[u8]) -> Result<(), Str
 /// Fetch vault keys from backend and decrypt them using the user's private key.
 ///
 /// Decrypts:
-/// - Root folder AES-256 key (32 bytes) from ECIES-wrapped hex
+/// - ML-KEM-768 private key (2400 bytes) with the vault secret from the
+///   Keychain, first adding one to a vault without it if hybrid wrapping is
+///   enabled and the vault secret export confirmed
+/// - Root folder AES-256 key (32 bytes) from hybrid- or ECIES-wrapped hex
 /// - Root IPNS Ed25519 private key (32 bytes) from ECIES-wrapped hex
 ///
 /// The IPNS public key is derivable from the private key if ever needed.
@@ -706,33 +676,68 @@ async fn fetch_and_decrypt_vault(state: &AppState) -> Result<(), String> {
         .ok_or("Private key not available for vault decryption")?
         .clone();
 
+    // Open the ML-KEM private key first: the root folder key may be hybrid-wrapped.
+    // A vault without one gets one only once hybrid wrapping may start.
+    let enroll_mlkem = !state.mount.read_only && state.hybrid_wrap_ready().await;
+    let mlkem_private_key = match vault.encrypted_mlkem_private_key {
+        Some(encrypted) => {
+            let encrypted = hex::decode(&encrypted).map_err(|_| "Invalid encryptedMlkemPrivateKey hex")?;
+            let vault_secret = get_vault_secret(state).await.unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                None
+            });
+            match crypto::ecies::open_mlkem_private_key(&encrypted, &private_key, vault_secret.as_ref()) {
+                Ok(key) => Some(key),
+                Err(crypto::ecies::EciesError::VaultSecretMissing) => {
+                    // Hybrid-wrapped folders stay unreadable until it is imported
+                    log::warn!("This device has no vault secret: import it to open the ML-KEM key");
+                    None
+                }
+                Err(e) => return Err(format!("Failed to decrypt ML-KEM private key: {}", e)),
+            }
+        }
+        None if enroll_mlkem => match add_mlkem_key(state).await {
+            Ok(key) => Some(key),
+            Err(e) => {
+                // Nothing is hybrid-wrapped to this user yet, so carry on without
+                log::warn!("Could not add an ML-KEM key to the vault: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
     // Decrypt root folder key
     let encrypted_root_folder_key = hex::decode(&vault.encrypted_root_folder_key)
         .map_err(|_| "Invalid encryptedRootFolderKey hex")?;
-    let root_folder_key = crypto::ecies::unwrap_key(&encrypted_root_folder_key, &private_key)
-        .map_err(|e| format!("Failed to decrypt root folder key: {}", e))?;
+    let root_folder_key: FolderKey = crypto::ecies::unwrap_secret_key_hybrid(
+        &encrypted_root_folder_key,
+        &private_key,
+        mlkem_private_key.as_ref(),
+    )
+    .map_err(|e| format!("Failed to decrypt root folder key: {}", e))?;
     *state.root_folder_key.write().await = Some(root_folder_key);
 
-    // Decrypt root IPNS private key
-    let encrypted_root_ipns_private_key = hex::decode(&vault.encrypted_root_ipns_private_key)
-        .map_err(|_| "Invalid encryptedRootIpnsPrivateKey hex")?;
-    let root_ipns_private_key =
-        crypto::ecies::unwrap_key(&encrypted_root_ipns_private_key, &private_key)
-            .map_err(|e| format!("Failed to decrypt root IPNS private key: {}", e))?;
-
-    // Verify stored IPNS key matches HKDF derivation (consistency check)
-    let private_key_arr: [u8; 32] = private_key
-        .as_slice()
-        .try_into()
-        .map_err(|_| "Invalid private key length for HKDF check")?;
-    if let Ok((expected_ipns_key, _, _)) = crypto::hkdf::derive_vault_ipns_keypair(&private_key_arr) {
-        if root_ipns_private_key != *expected_ipns_key {
-            log::warn!("Vault IPNS key mismatch: stored key differs from HKDF derivation");
-            // Don't block - proceed with stored key for backward compatibility
+    // Decrypt root IPNS private key (never in read-only mode: nothing is published)
+    if !state.mount.read_only {
+        let encrypted_root_ipns_private_key = hex::decode(&vault.encrypted_root_ipns_private_key)
+            .map_err(|_| "Invalid encryptedRootIpnsPrivateKey hex")?;
+        let root_ipns_private_key: IpnsSigningKey =
+            crypto::ecies::unwrap_secret_key(&encrypted_root_ipns_private_key, &private_key)
+                .map_err(|e| format!("Failed to decrypt root IPNS private key: {}", e))?;
+
+        // Verify stored IPNS key matches HKDF derivation (consistency check)
+        if let Ok((expected_ipns_key, _, _)) = crypto::hkdf::derive_vault_ipns_keypair(&private_key) {
+            if root_ipns_private_key.as_bytes() != expected_ipns_key.as_bytes() {
+                log::warn!("Vault IPNS key mismatch: stored key differs from HKDF derivation");
+                // Don't block - proceed with stored key for backward compatibility
+            }
         }
+
+        *state.root_ipns_private_key.write().await = Some(root_ipns_private_key);
     }
 
-    *state.root_ipns_private_key.write().await = Some(root_ipns_private_key);
+    *state.mlkem_private_key.write().await = mlkem_private_key;
 
     // Store IPNS name and TEE keys
     *state.root_ipns_name.write().await = Some(vault.root_ipns_name);
@@ -742,6 +747,110 @@ async fn fetch_and_decrypt_vault(state: &AppState) -> Result<(), String> {
     Ok(())
 }
 
+/// Generate an ML-KEM-768 keypair for a vault created without one, and store it
+/// on the vault (private key sealed under the vault secret) via `/vault/mlkem-key`.
+///
+/// Only once the vault secret export is confirmed (`AppState::hybrid_wrap_ready`):
+/// the sealed key, and every key hybrid-wrapped with it, need that secret.
+async fn add_mlkem_key(state: &AppState) -> Result<MlKemSecretKey, String> {
+    let public_key = state
+        .public_key
+        .read()
+        .await
+        .clone()
+        .ok_or("Public key not available for ML-KEM key wrapping")?;
+    let vault_secret = get_vault_secret(state).await?.ok_or("This device has no vault secret")?;
+    let (mlkem_private_key, mlkem_public_key) = crypto::ecies::generate_mlkem_keypair();
+    let encrypted_mlkem_private_key =
+        crypto::ecies::seal_mlkem_private_key(&mlkem_private_key, &vault_secret, &public_key)
+            .map_err(|e| format!("Failed to seal ML-KEM private key: {}", e))?;
+
+    let req = types::SetMlkemKeyRequest {
+        encrypted_mlkem_private_key: hex::encode(&encrypted_mlkem_private_key),
+        mlkem_public_key: hex::encode(&mlkem_public_key),
+    };
+    let resp = state
+        .api
+        .authenticated_post("/vault/mlkem-key", &req)
+        .await
+        .map_err(|e| format!("ML-KEM key request failed: {}", e))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("ML-KEM key upload failed ({}): {}", status, body));
+    }
+
+    log::info!("Added an ML-KEM-768 key to the vault");
+    Ok(mlkem_private_key)
+}
+
+/// The logged-in user's vault secret from the Keychain, if this device has it.
+async fn get_vault_secret(state: &AppState) -> Result<Option<VaultSecret>, String> {
+    let user_id = state.user_id.read().await.clone().ok_or("Not logged in")?;
+    auth::get_vault_secret(&user_id).map_err(|e| format!("Keychain read vault secret failed: {}", e))
+}
+
+/// The logged-in user's vault secret, generating one and storing it in the
+/// Keychain first if this device has none. Only for exporting it before an
+/// ML-KEM key is sealed: a vault whose key is already sealed needs the secret
+/// it was sealed under.
+async fn get_or_create_vault_secret(state: &AppState) -> Result<VaultSecret, String> {
+    if let Some(vault_secret) = get_vault_secret(state).await? {
+        return Ok(vault_secret);
+    }
+    let user_id = state.user_id.read().await.clone().ok_or("Not logged in")?;
+    let vault_secret = VaultSecret::generate();
+    auth::store_vault_secret(&user_id, &vault_secret)
+        .map_err(|e| format!("Keychain store vault secret failed: {}", e))?;
+    Ok(vault_secret)
+}
+
+/// Export the vault secret (hex) so it can be kept offline and imported on the
+/// user's other devices, generating it first if this device has none.
+///
+/// Nothing is hybrid-wrapped under it until `confirm_vault_secret` is called
+/// with the exported value.
+#[tauri::command]
+pub async fn export_vault_secret(state: State<'_, AppState>) -> Result<String, String> {
+    let vault_secret = get_or_create_vault_secret(&state).await?;
+    Ok(hex::encode(vault_secret.as_bytes()))
+}
+
+/// Confirm the user kept the exported vault secret by entering it back.
+///
+/// Allows hybrid wrapping (if enabled) from the next login on, when the vault
+/// gets its ML-KEM key.
+#[tauri::command]
+pub async fn confirm_vault_secret(state: State<'_, AppState>, vault_secret: String) -> Result<(), String> {
+    let user_id = state.user_id.read().await.clone().ok_or("Not logged in")?;
+    let bytes = Zeroizing::new(hex::decode(vault_secret.trim()).map_err(|_| "Invalid vault secret hex")?);
+    let stored = get_vault_secret(&state).await?.ok_or("This device has no vault secret to confirm")?;
+    if bytes.as_slice() != stored.as_bytes() {
+        return Err("The vault secret does not match the exported one".to_string());
+    }
+    auth::store_vault_secret_confirmed(&user_id)
+        .map_err(|e| format!("Keychain store vault secret confirmation failed: {}", e))?;
+    *state.vault_secret_confirmed.write().await = true;
+    Ok(())
+}
+
+/// Import a vault secret exported on another device into the Keychain, which
+/// also confirms it: the user evidently kept it.
+///
+/// Takes effect on the next login, when the ML-KEM key is opened with it.
+#[tauri::command]
+pub async fn import_vault_secret(state: State<'_, AppState>, vault_secret: String) -> Result<(), String> {
+    let user_id = state.user_id.read().await.clone().ok_or("Not logged in")?;
+    let bytes = Zeroizing::new(hex::decode(vault_secret.trim()).map_err(|_| "Invalid vault secret hex")?);
+    let vault_secret = VaultSecret::from_slice(&bytes).map_err(|e| e.to_string())?;
+    auth::store_vault_secret(&user_id, &vault_secret)
+        .map_err(|e| format!("Keychain store vault secret failed: {}", e))?;
+    auth::store_vault_secret_confirmed(&user_id)
+        .map_err(|e| format!("Keychain store vault secret confirmation failed: {}", e))?;
+    *state.vault_secret_confirmed.write().await = true;
+    Ok(())
+}
+
 /// Extract the user ID (`sub` claim) from a JWT access token.
 ///
 /// Decodes the JWT payload (base64url) without verification -- the server
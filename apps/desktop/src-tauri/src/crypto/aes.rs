@@ -4,7 +4,7 @@
 //! This matches the TypeScript `sealAesGcm` output exactly.
 
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use thiserror::Error;
@@ -97,3 +97,43 @@ pub fn unseal_aes_gcm(sealed: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, AesError
 
     decrypt_aes_gcm(ciphertext, key, &iv)
 }
+
+/// Seal data using AES-256-GCM with associated data.
+///
+/// Same sealed format as `seal_aes_gcm`. The `aad` is authenticated but not
+/// stored; the exact same bytes must be supplied to `unseal_aes_gcm_with_aad`.
+pub fn seal_aes_gcm_with_aad(
+    plaintext: &[u8],
+    key: &[u8; 32],
+    aad: &[u8],
+) -> Result<Vec<u8>, AesError> {
+    let iv = generate_iv();
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| AesError::EncryptionFailed)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&iv), Payload { msg: plaintext, aad })
+        .map_err(|_| AesError::EncryptionFailed)?;
+
+    let mut sealed = Vec::with_capacity(AES_IV_SIZE + ciphertext.len());
+    sealed.extend_from_slice(&iv);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Unseal data encrypted with `seal_aes_gcm_with_aad`.
+///
+/// Fails with `DecryptionFailed` if the ciphertext or the associated data differ.
+pub fn unseal_aes_gcm_with_aad(
+    sealed: &[u8],
+    key: &[u8; 32],
+    aad: &[u8],
+) -> Result<Vec<u8>, AesError> {
+    if sealed.len() < MIN_SEALED_SIZE {
+        return Err(AesError::DecryptionFailed);
+    }
+
+    let (iv, ciphertext) = sealed.split_at(AES_IV_SIZE);
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| AesError::DecryptionFailed)?;
+    cipher
+        .decrypt(Nonce::from_slice(iv), Payload { msg: ciphertext, aad })
+        .map_err(|_| AesError::DecryptionFailed)
+}
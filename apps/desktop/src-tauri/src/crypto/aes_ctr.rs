@@ -8,15 +8,24 @@
 //! `AES-CTR` with `length: 64`.
 //!
 //! SECURITY NOTE: AES-CTR does NOT provide authentication (unlike GCM).
-//! Integrity is provided by IPFS content addressing.
+//! Integrity is provided by IPFS content addressing and, for partial reads,
+//! by per-chunk SHA-256 hashes of the ciphertext recorded in file metadata.
 
 use aes::Aes256;
 use ctr::cipher::{KeyIvInit, StreamCipher};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 /// AES-CTR IV size in bytes (128-bit counter block).
 pub const AES_CTR_IV_SIZE: usize = 16;
 
+/// Nonce size in bytes (first half of the IV; the second half is the counter).
+pub const AES_CTR_NONCE_SIZE: usize = 8;
+
+/// Ciphertext chunk size covered by each integrity hash (1 MiB, the web
+/// app's CTR encryption chunk size).
+pub const CTR_CHUNK_SIZE: usize = 1024 * 1024;
+
 /// AES block size in bytes.
 const AES_BLOCK_SIZE: usize = 16;
 
@@ -36,6 +45,8 @@ pub enum AesCtrError {
     DecryptionFailed,
     #[error("Invalid range")]
     InvalidRange,
+    #[error("Chunk integrity check failed")]
+    IntegrityCheckFailed,
 }
 
 /// Encrypt data using AES-256-CTR.
@@ -109,16 +120,8 @@ pub fn decrypt_aes_ctr_range(
     let block_aligned_start = start_block * AES_BLOCK_SIZE;
     let block_aligned_end = ((end_block + 1) * AES_BLOCK_SIZE).min(ciphertext.len());
 
-    // Build counter for starting block:
-    // Copy nonce (first 8 bytes of IV), compute counter = baseCounter + startBlock
-    let mut counter = [0u8; 16];
-    counter[..8].copy_from_slice(&iv[..8]);
-
-    let base_counter = u64::from_be_bytes(iv[8..16].try_into().unwrap());
-    let new_counter = base_counter.wrapping_add(start_block as u64);
-    counter[8..16].copy_from_slice(&new_counter.to_be_bytes());
-
-    // Create cipher with adjusted counter
+    // Create cipher with counter adjusted to the starting block
+    let counter = counter_for_block(iv, start_block as u64);
     let mut cipher = Aes256Ctr64BE::new(key.into(), &counter.into());
 
     // Decrypt the block-aligned range
@@ -132,3 +135,106 @@ pub fn decrypt_aes_ctr_range(
 
     Ok(result)
 }
+
+/// Decrypt a window of AES-256-CTR ciphertext that starts at `offset` in the stream.
+///
+/// Used for HTTP range reads, where only the ciphertext bytes of the window
+/// are available. `offset` must be a multiple of the AES block size (16).
+pub fn decrypt_aes_ctr_at(
+    ciphertext: &[u8],
+    key: &[u8; 32],
+    iv: &[u8; 16],
+    offset: u64,
+) -> Result<Vec<u8>, AesCtrError> {
+    if !offset.is_multiple_of(AES_BLOCK_SIZE as u64) {
+        return Err(AesCtrError::InvalidRange);
+    }
+
+    let counter = counter_for_block(iv, offset / AES_BLOCK_SIZE as u64);
+    let mut cipher = Aes256Ctr64BE::new(key.into(), &counter.into());
+
+    let mut output = ciphertext.to_vec();
+    cipher.apply_keystream(&mut output);
+
+    Ok(output)
+}
+
+/// Counter block for the given block index: copy the nonce (first 8 bytes of
+/// the IV) and compute counter = baseCounter + block.
+fn counter_for_block(iv: &[u8; 16], block: u64) -> [u8; 16] {
+    let mut counter = [0u8; 16];
+    counter[..8].copy_from_slice(&iv[..8]);
+
+    let base_counter = u64::from_be_bytes(iv[8..16].try_into().unwrap());
+    let new_counter = base_counter.wrapping_add(block);
+    counter[8..16].copy_from_slice(&new_counter.to_be_bytes());
+    counter
+}
+
+/// Incremental AES-256-CTR encryption in `CTR_CHUNK_SIZE` chunks.
+///
+/// Produces the same ciphertext and chunk hashes as `encrypt_aes_ctr` plus
+/// `ctr_chunk_hashes`, without holding the whole file in memory.
+pub struct CtrChunkEncryptor {
+    cipher: Aes256Ctr64BE,
+    chunk_hashes: Vec<String>,
+    finished: bool,
+}
+
+impl CtrChunkEncryptor {
+    pub fn new(key: &[u8; 32], iv: &[u8; 16]) -> Self {
+        Self {
+            cipher: Aes256Ctr64BE::new(key.into(), iv.into()),
+            chunk_hashes: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Encrypt the next chunk in place and record its hash.
+    ///
+    /// Every chunk except the last must be exactly `CTR_CHUNK_SIZE` bytes; a
+    /// shorter chunk ends the stream.
+    pub fn encrypt_chunk(&mut self, chunk: &mut [u8]) -> Result<(), AesCtrError> {
+        if self.finished || chunk.len() > CTR_CHUNK_SIZE {
+            return Err(AesCtrError::InvalidRange);
+        }
+        self.finished = chunk.len() < CTR_CHUNK_SIZE;
+        self.cipher.apply_keystream(chunk);
+        self.chunk_hashes.push(hex::encode(Sha256::digest(&*chunk)));
+        Ok(())
+    }
+
+    /// Chunk hashes of everything encrypted so far.
+    pub fn finish(self) -> Vec<String> {
+        self.chunk_hashes
+    }
+}
+
+/// Hex-encoded SHA-256 of each `CTR_CHUNK_SIZE` chunk of `ciphertext`.
+///
+/// Recorded as `chunkHashes` in file metadata so a reader that fetches only
+/// part of a CTR file can still detect tampered or corrupted chunks.
+pub fn ctr_chunk_hashes(ciphertext: &[u8]) -> Vec<String> {
+    ciphertext
+        .chunks(CTR_CHUNK_SIZE)
+        .map(|chunk| hex::encode(Sha256::digest(chunk)))
+        .collect()
+}
+
+/// Check ciphertext chunk `index` against its recorded hash.
+///
+/// The chunk must be complete: `CTR_CHUNK_SIZE` bytes, or the remainder of
+/// the file for the last chunk.
+pub fn verify_ctr_chunk(
+    chunk: &[u8],
+    index: usize,
+    chunk_hashes: &[String],
+) -> Result<(), AesCtrError> {
+    let expected = chunk_hashes
+        .get(index)
+        .ok_or(AesCtrError::IntegrityCheckFailed)?;
+    if hex::encode(Sha256::digest(chunk)) != expected.to_ascii_lowercase() {
+        return Err(AesCtrError::IntegrityCheckFailed);
+    }
+    Ok(())
+}
@@ -0,0 +1,362 @@
+//! Segmented AES-256-GCM ("GCM-SEG") for large file content.
+//!
+//! The plaintext is split into fixed-size segments which are sealed
+//! independently with AES-256-GCM (STREAM construction). This bounds memory
+//! use to a single segment during encryption and decryption, and allows any
+//! byte range to be decrypted AND authenticated without touching the rest of
+//! the file.
+//!
+//! Ciphertext layout: `segment_0 || segment_1 || ... || segment_n`, where each
+//! segment is `ciphertext || auth tag (16 bytes)`. Every segment except the
+//! last carries exactly `GCM_SEG_SEGMENT_SIZE` plaintext bytes; the last one
+//! carries 0..=`GCM_SEG_SEGMENT_SIZE` bytes (an empty file is a single empty
+//! final segment).
+//!
+//! Per-segment nonce (12 bytes): `nonce_prefix (7) || segment_index (u32 BE) || last_flag (1)`.
+//! The index prevents segment reordering and the final-segment flag prevents
+//! truncation or extension at a segment boundary. The random 7-byte nonce
+//! prefix is stored hex-encoded in `FileMetadata.file_iv`.
+
+use std::io::{Read, Write};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use rand::RngCore;
+use thiserror::Error;
+
+use super::aes::{AES_IV_SIZE, AES_TAG_SIZE};
+
+/// Encryption mode string recorded in `FileMetadata.encryption_mode`.
+pub const GCM_SEG_MODE: &str = "GCM-SEG";
+
+/// Plaintext bytes per segment (64 KiB).
+pub const GCM_SEG_SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Ciphertext bytes per full segment (plaintext + auth tag).
+pub const GCM_SEG_ENCRYPTED_SEGMENT_SIZE: usize = GCM_SEG_SEGMENT_SIZE + AES_TAG_SIZE;
+
+/// Random per-file nonce prefix size in bytes.
+pub const GCM_SEG_NONCE_PREFIX_SIZE: usize = 7;
+
+/// Nonce flag byte for every segment except the last.
+const FLAG_NOT_LAST: u8 = 0x00;
+
+/// Nonce flag byte for the final segment.
+const FLAG_LAST: u8 = 0x01;
+
+#[derive(Debug, Error)]
+pub enum AesGcmSegError {
+    #[error("Encryption failed")]
+    EncryptionFailed,
+    #[error("Decryption failed")]
+    DecryptionFailed,
+    #[error("Invalid ciphertext size")]
+    InvalidCiphertextSize,
+    #[error("Invalid range")]
+    InvalidRange,
+    #[error("Too many segments")]
+    TooManySegments,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Generate a random 7-byte nonce prefix for a new GCM-SEG file.
+pub fn generate_nonce_prefix() -> [u8; GCM_SEG_NONCE_PREFIX_SIZE] {
+    let mut prefix = [0u8; GCM_SEG_NONCE_PREFIX_SIZE];
+    rand::rngs::OsRng.fill_bytes(&mut prefix);
+    prefix
+}
+
+/// Build the 12-byte nonce for a given segment.
+fn segment_nonce(
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+    index: u64,
+    is_last: bool,
+) -> Result<[u8; AES_IV_SIZE], AesGcmSegError> {
+    let index: u32 = index
+        .try_into()
+        .map_err(|_| AesGcmSegError::TooManySegments)?;
+
+    let mut nonce = [0u8; AES_IV_SIZE];
+    nonce[..GCM_SEG_NONCE_PREFIX_SIZE].copy_from_slice(nonce_prefix);
+    nonce[GCM_SEG_NONCE_PREFIX_SIZE..AES_IV_SIZE - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[AES_IV_SIZE - 1] = if is_last { FLAG_LAST } else { FLAG_NOT_LAST };
+    Ok(nonce)
+}
+
+/// Number of segments a plaintext of `plaintext_len` bytes is split into.
+pub fn segment_count(plaintext_len: u64) -> u64 {
+    if plaintext_len == 0 {
+        1
+    } else {
+        plaintext_len.div_ceil(GCM_SEG_SEGMENT_SIZE as u64)
+    }
+}
+
+/// Total ciphertext size for a plaintext of `plaintext_len` bytes.
+pub fn encrypted_size(plaintext_len: u64) -> u64 {
+    plaintext_len + segment_count(plaintext_len) * AES_TAG_SIZE as u64
+}
+
+/// Recover the plaintext size from a ciphertext size.
+///
+/// Fails if no plaintext length produces exactly `ciphertext_len` bytes.
+pub fn plaintext_size(ciphertext_len: u64) -> Result<u64, AesGcmSegError> {
+    let full = GCM_SEG_ENCRYPTED_SEGMENT_SIZE as u64;
+    let tag = AES_TAG_SIZE as u64;
+    if ciphertext_len < tag {
+        return Err(AesGcmSegError::InvalidCiphertextSize);
+    }
+
+    let full_segments = ciphertext_len / full;
+    let remainder = ciphertext_len % full;
+    let plaintext_len = if remainder == 0 {
+        full_segments * GCM_SEG_SEGMENT_SIZE as u64
+    } else if remainder >= tag {
+        full_segments * GCM_SEG_SEGMENT_SIZE as u64 + (remainder - tag)
+    } else {
+        return Err(AesGcmSegError::InvalidCiphertextSize);
+    };
+
+    if encrypted_size(plaintext_len) != ciphertext_len {
+        return Err(AesGcmSegError::InvalidCiphertextSize);
+    }
+    Ok(plaintext_len)
+}
+
+/// Encrypt a single segment.
+///
+/// `index` is the zero-based segment index; `is_last` MUST be true for the
+/// final segment of the file and false for all others.
+pub fn encrypt_segment(
+    plaintext: &[u8],
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+    index: u64,
+    is_last: bool,
+) -> Result<Vec<u8>, AesGcmSegError> {
+    if plaintext.len() > GCM_SEG_SEGMENT_SIZE || (!is_last && plaintext.len() != GCM_SEG_SEGMENT_SIZE) {
+        return Err(AesGcmSegError::EncryptionFailed);
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| AesGcmSegError::EncryptionFailed)?;
+    let nonce = segment_nonce(nonce_prefix, index, is_last)?;
+
+    cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| AesGcmSegError::EncryptionFailed)
+}
+
+/// Decrypt and authenticate a single segment.
+///
+/// Fails if the segment was tampered with, is at the wrong index, or its
+/// final-segment flag does not match `is_last`.
+pub fn decrypt_segment(
+    segment: &[u8],
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+    index: u64,
+    is_last: bool,
+) -> Result<Vec<u8>, AesGcmSegError> {
+    if segment.len() < AES_TAG_SIZE || segment.len() > GCM_SEG_ENCRYPTED_SEGMENT_SIZE {
+        return Err(AesGcmSegError::InvalidCiphertextSize);
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| AesGcmSegError::DecryptionFailed)?;
+    let nonce = segment_nonce(nonce_prefix, index, is_last)?;
+
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), segment)
+        .map_err(|_| AesGcmSegError::DecryptionFailed)
+}
+
+/// Decrypt an in-memory GCM-SEG ciphertext.
+pub fn decrypt_aes_gcm_seg(
+    ciphertext: &[u8],
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+) -> Result<Vec<u8>, AesGcmSegError> {
+    let plaintext_len = plaintext_size(ciphertext.len() as u64)?;
+    let mut output = Vec::with_capacity(plaintext_len as usize);
+    decrypt_aes_gcm_seg_stream(ciphertext, &mut output, key, nonce_prefix)?;
+    Ok(output)
+}
+
+/// Read until `buf` is full or EOF. Returns the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Stream-encrypt everything from `reader` into `writer`.
+///
+/// Holds at most two plaintext segments in memory (one lookahead segment is
+/// needed to know which segment is the last). Returns the plaintext length.
+pub fn encrypt_aes_gcm_seg_stream<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+) -> Result<u64, AesGcmSegError> {
+    let mut current = zeroize::Zeroizing::new(vec![0u8; GCM_SEG_SEGMENT_SIZE]);
+    let mut next = zeroize::Zeroizing::new(vec![0u8; GCM_SEG_SEGMENT_SIZE]);
+
+    let mut current_len = read_full(&mut reader, &mut current)?;
+    let mut index: u64 = 0;
+    let mut total: u64 = 0;
+
+    loop {
+        // A short segment is always the last one; a full one is the last
+        // only if nothing follows it.
+        let next_len = if current_len == GCM_SEG_SEGMENT_SIZE {
+            read_full(&mut reader, &mut next)?
+        } else {
+            0
+        };
+        let is_last = next_len == 0;
+
+        let sealed = encrypt_segment(&current[..current_len], key, nonce_prefix, index, is_last)?;
+        writer.write_all(&sealed)?;
+        total += current_len as u64;
+
+        if is_last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        index += 1;
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
+
+/// Stream-decrypt everything from `reader` into `writer`.
+///
+/// Each segment is authenticated before its plaintext is written. Truncation
+/// is only detected at the end of the stream, so on error the caller MUST
+/// discard everything already written. Returns the plaintext length.
+pub fn decrypt_aes_gcm_seg_stream<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+) -> Result<u64, AesGcmSegError> {
+    let mut current = vec![0u8; GCM_SEG_ENCRYPTED_SEGMENT_SIZE];
+    let mut next = vec![0u8; GCM_SEG_ENCRYPTED_SEGMENT_SIZE];
+
+    let mut current_len = read_full(&mut reader, &mut current)?;
+    let mut index: u64 = 0;
+    let mut total: u64 = 0;
+
+    loop {
+        let next_len = if current_len == GCM_SEG_ENCRYPTED_SEGMENT_SIZE {
+            read_full(&mut reader, &mut next)?
+        } else {
+            0
+        };
+        let is_last = next_len == 0;
+
+        let plaintext = zeroize::Zeroizing::new(decrypt_segment(
+            &current[..current_len],
+            key,
+            nonce_prefix,
+            index,
+            is_last,
+        )?);
+        writer.write_all(&plaintext)?;
+        total += plaintext.len() as u64;
+
+        if is_last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        index += 1;
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
+
+/// Byte range of segment `index` within a ciphertext of `ciphertext_len` bytes.
+///
+/// Used to fetch only the segments covering a plaintext range (e.g. via HTTP
+/// range requests) before passing them to `decrypt_segment`.
+pub fn segment_ciphertext_range(
+    index: u64,
+    ciphertext_len: u64,
+) -> Result<std::ops::Range<u64>, AesGcmSegError> {
+    let plaintext_len = plaintext_size(ciphertext_len)?;
+    if index >= segment_count(plaintext_len) {
+        return Err(AesGcmSegError::InvalidRange);
+    }
+    let start = index * GCM_SEG_ENCRYPTED_SEGMENT_SIZE as u64;
+    let end = (start + GCM_SEG_ENCRYPTED_SEGMENT_SIZE as u64).min(ciphertext_len);
+    Ok(start..end)
+}
+
+/// Segments covering plaintext bytes `[start_byte, end_byte]` (inclusive) of
+/// a file with `ciphertext_len` bytes of GCM-SEG ciphertext.
+///
+/// The range is clamped to the plaintext length, matching
+/// `decrypt_aes_ctr_range`; it is empty if `start_byte` is past the end.
+pub fn segments_for_range(
+    ciphertext_len: u64,
+    start_byte: u64,
+    end_byte: u64,
+) -> Result<std::ops::Range<u64>, AesGcmSegError> {
+    if start_byte > end_byte {
+        return Err(AesGcmSegError::InvalidRange);
+    }
+    let plaintext_len = plaintext_size(ciphertext_len)?;
+    if start_byte >= plaintext_len {
+        return Ok(0..0);
+    }
+    let clamped_end = end_byte.min(plaintext_len - 1);
+    let segment_size = GCM_SEG_SEGMENT_SIZE as u64;
+    Ok(start_byte / segment_size..clamped_end / segment_size + 1)
+}
+
+/// Decrypt and authenticate consecutive segments, starting with segment
+/// `first`, of a file with `ciphertext_len` bytes of GCM-SEG ciphertext.
+///
+/// `window` holds the ciphertext of whole segments only, e.g. as fetched with
+/// an HTTP range request covering `segment_ciphertext_range` of each. Returns
+/// the plaintext of every segment in `window`, in order.
+pub fn decrypt_segments(
+    window: &[u8],
+    ciphertext_len: u64,
+    first: u64,
+    key: &[u8; 32],
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+) -> Result<Vec<zeroize::Zeroizing<Vec<u8>>>, AesGcmSegError> {
+    let final_index = segment_count(plaintext_size(ciphertext_len)?) - 1;
+
+    let mut segments = Vec::new();
+    for (i, segment) in window.chunks(GCM_SEG_ENCRYPTED_SEGMENT_SIZE).enumerate() {
+        let index = first + i as u64;
+        let range = segment_ciphertext_range(index, ciphertext_len)?;
+        if segment.len() as u64 != range.end - range.start {
+            return Err(AesGcmSegError::InvalidCiphertextSize);
+        }
+        segments.push(zeroize::Zeroizing::new(decrypt_segment(
+            segment,
+            key,
+            nonce_prefix,
+            index,
+            index == final_index,
+        )?));
+    }
+    Ok(segments)
+}
@@ -0,0 +1,209 @@
+//! Chunked file representation ("CHUNKED" encryption mode).
+//!
+//! Large files are split with FastCDC. Each chunk is sealed with AES-256-GCM
+//! under its own key and uploaded as a separate IPFS object. An encrypted chunk
+//! manifest lists the chunks in order; `FileMetadata.cid` points at the
+//! manifest and `FileMetadata.file_key_encrypted` wraps the file key.
+//!
+//! Chunk ids and chunk keys are derived from the file key (HMAC and HKDF), so a
+//! chunk that is unchanged between two versions of the same file gets the same
+//! id and key, and its uploaded ciphertext can be reused. File keys are random
+//! per file and only ever wrapped to the owner, so chunk keys are never shared
+//! across users and equal content in different files is not linkable.
+//!
+//! Accepted leak: because the file key is kept across versions, the server
+//! sees which chunks (by id, CID and size) are unchanged between two versions
+//! of a file, and so roughly where and how much each save edited. That is what
+//! makes the reuse possible; a per-version salt in the chunk id would hide it,
+//! but every version would then upload every chunk again. Content stays
+//! confidential. Files that must not reveal their edit pattern should not be
+//! stored chunked (chunked uploads are opt-in, see `CIPHERBOX_CHUNKED`).
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::aes::{self, AesError};
+use super::fastcdc;
+use super::keys::FileKey;
+
+/// `FileMetadata.encryption_mode` value for chunked files.
+pub const CHUNKED_MODE: &str = "CHUNKED";
+
+/// Current chunk manifest schema version.
+pub const CHUNK_MANIFEST_VERSION: &str = "v1";
+
+/// HKDF info for the HMAC key that computes chunk ids.
+const CHUNK_ID_INFO: &[u8] = b"cipherbox-chunk-id-v1";
+
+/// HKDF info prefix for per-chunk encryption keys (followed by the chunk id).
+const CHUNK_KEY_INFO: &[u8] = b"cipherbox-chunk-key-v1:";
+
+/// HKDF info for the manifest encryption key.
+const MANIFEST_KEY_INFO: &[u8] = b"cipherbox-chunk-manifest-v1";
+
+#[derive(Debug, Error)]
+pub enum ChunkError {
+    #[error("Encryption failed")]
+    EncryptionFailed(#[from] AesError),
+    #[error("Key derivation failed")]
+    KeyDerivationFailed,
+    #[error("Serialization failed")]
+    SerializationFailed,
+    #[error("Invalid chunk manifest")]
+    InvalidManifest,
+    #[error("Unsupported chunk manifest version: {0}")]
+    UnsupportedManifestVersion(String),
+    #[error("Chunk content does not match its manifest entry")]
+    ChunkMismatch,
+}
+
+/// One chunk of a chunked file, in file order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkRef {
+    /// Hex HMAC-SHA256 of the chunk plaintext, keyed from the file key.
+    pub id: String,
+    /// IPFS CID of the sealed chunk (IV || ciphertext || tag).
+    pub cid: String,
+    /// Plaintext size in bytes.
+    pub size: u64,
+}
+
+/// Ordered list of a file's chunks. Stored sealed with a key derived from the
+/// file key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkManifest {
+    /// Schema version ("v1").
+    pub version: String,
+    /// Total plaintext size in bytes.
+    pub size: u64,
+    /// Chunks in file order.
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl ChunkManifest {
+    /// Build a manifest from encrypted chunks and the CIDs they are stored under.
+    pub fn new(chunks: &[EncryptedChunk], cids: Vec<String>) -> Self {
+        Self::from_refs(
+            chunks
+                .iter()
+                .zip(cids)
+                .map(|(chunk, cid)| ChunkRef {
+                    id: chunk.id.clone(),
+                    cid,
+                    size: chunk.size,
+                })
+                .collect(),
+        )
+    }
+
+    /// Build a manifest from chunk references in file order.
+    pub fn from_refs(chunks: Vec<ChunkRef>) -> Self {
+        Self {
+            version: CHUNK_MANIFEST_VERSION.to_string(),
+            size: chunks.iter().map(|c| c.size).sum(),
+            chunks,
+        }
+    }
+}
+
+/// A sealed chunk ready for upload.
+#[derive(Debug, Clone)]
+pub struct EncryptedChunk {
+    /// Chunk id (see `ChunkRef::id`).
+    pub id: String,
+    /// Plaintext size in bytes.
+    pub size: u64,
+    /// IV (12) || ciphertext || tag (16).
+    pub ciphertext: Vec<u8>,
+}
+
+/// Expand a 32-byte subkey from the file key.
+fn derive_subkey(file_key: &FileKey, info: &[u8]) -> Result<Zeroizing<[u8; 32]>, ChunkError> {
+    let hk = Hkdf::<Sha256>::new(None, file_key.as_bytes());
+    let mut okm = Zeroizing::new([0u8; 32]);
+    hk.expand(info, okm.as_mut())
+        .map_err(|_| ChunkError::KeyDerivationFailed)?;
+    Ok(okm)
+}
+
+/// Chunk id: hex HMAC-SHA256 of the plaintext under a key derived from the file key.
+pub fn chunk_id(file_key: &FileKey, plaintext: &[u8]) -> Result<String, ChunkError> {
+    let id_key = derive_subkey(file_key, CHUNK_ID_INFO)?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(id_key.as_ref())
+        .map_err(|_| ChunkError::KeyDerivationFailed)?;
+    mac.update(plaintext);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Per-chunk AES-256 key, bound to the chunk id.
+fn chunk_key(file_key: &FileKey, id: &str) -> Result<Zeroizing<[u8; 32]>, ChunkError> {
+    let mut info = Zeroizing::new(CHUNK_KEY_INFO.to_vec());
+    info.extend_from_slice(id.as_bytes());
+    derive_subkey(file_key, &info)
+}
+
+/// Split `plaintext` with FastCDC and seal every chunk.
+pub fn encrypt_chunks(plaintext: &[u8], file_key: &FileKey) -> Result<Vec<EncryptedChunk>, ChunkError> {
+    fastcdc::chunk_boundaries(plaintext)
+        .into_iter()
+        .map(|range| encrypt_chunk(&plaintext[range], file_key))
+        .collect()
+}
+
+/// Seal one chunk produced by FastCDC.
+pub fn encrypt_chunk(chunk: &[u8], file_key: &FileKey) -> Result<EncryptedChunk, ChunkError> {
+    let id = chunk_id(file_key, chunk)?;
+    let key = chunk_key(file_key, &id)?;
+    let ciphertext = aes::seal_aes_gcm(chunk, &key)?;
+    Ok(EncryptedChunk {
+        id,
+        size: chunk.len() as u64,
+        ciphertext,
+    })
+}
+
+/// Open a sealed chunk and check it against its manifest entry.
+pub fn decrypt_chunk(
+    sealed: &[u8],
+    file_key: &FileKey,
+    chunk: &ChunkRef,
+) -> Result<Vec<u8>, ChunkError> {
+    let key = chunk_key(file_key, &chunk.id)?;
+    let mut plaintext = aes::unseal_aes_gcm(sealed, &key)?;
+    if plaintext.len() as u64 != chunk.size || chunk_id(file_key, &plaintext)? != chunk.id {
+        plaintext.zeroize();
+        return Err(ChunkError::ChunkMismatch);
+    }
+    Ok(plaintext)
+}
+
+/// Serialize and seal a chunk manifest.
+pub fn encrypt_manifest(manifest: &ChunkManifest, file_key: &FileKey) -> Result<Vec<u8>, ChunkError> {
+    let key = derive_subkey(file_key, MANIFEST_KEY_INFO)?;
+    let json = Zeroizing::new(
+        serde_json::to_vec(manifest).map_err(|_| ChunkError::SerializationFailed)?,
+    );
+    Ok(aes::seal_aes_gcm(&json, &key)?)
+}
+
+/// Open a sealed chunk manifest and validate it.
+pub fn decrypt_manifest(sealed: &[u8], file_key: &FileKey) -> Result<ChunkManifest, ChunkError> {
+    let key = derive_subkey(file_key, MANIFEST_KEY_INFO)?;
+    let json = Zeroizing::new(aes::unseal_aes_gcm(sealed, &key)?);
+    let manifest: ChunkManifest =
+        serde_json::from_slice(&json).map_err(|_| ChunkError::InvalidManifest)?;
+
+    if manifest.version != CHUNK_MANIFEST_VERSION {
+        return Err(ChunkError::UnsupportedManifestVersion(manifest.version));
+    }
+    if manifest.chunks.iter().map(|c| c.size).sum::<u64>() != manifest.size {
+        return Err(ChunkError::InvalidManifest);
+    }
+    Ok(manifest)
+}
@@ -2,8 +2,39 @@
 //!
 //! Uses the `ecies` Rust crate which is cross-compatible with the `eciesjs` npm package
 //! (same author: ecies/rs and ecies/js). Format: ephemeral_pubkey(65) || nonce(16) || tag(16) || ciphertext.
+//!
+//! Long-lived keys can instead use the hybrid format (`wrap_key_hybrid`), which
+//! combines ECIES with ML-KEM-768 so wrapped keys stay confidential unless both
+//! secp256k1 and ML-KEM are broken:
+//!
+//! ```text
+//! 0x01 || mlkem_ct(1088) || ecies(classical share)(129) || IV(12) || ciphertext || tag(16)
+//! ```
+//!
+//! The data is sealed with AES-256-GCM under a key derived (HKDF-SHA256) from
+//! both shared secrets and both ciphertexts. The user's ML-KEM-768 keypair is
+//! generated at random (`generate_mlkem_keypair`) and stored on the vault with
+//! its private key sealed under a `VaultSecret` kept on the user's devices and
+//! then ECIES-wrapped (`seal_mlkem_private_key`):
+//!
+//! ```text
+//! 0x02 || ecies(IV(12) || ciphertext || tag(16))
+//! ```
+//!
+//! so breaking secp256k1 alone does not expose it.
+//! `unwrap_key_hybrid` detects the format from the first byte: legacy ECIES
+//! output always starts with 0x04 (uncompressed ephemeral public key).
 
+use hkdf::Hkdf;
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768};
+use rand::RngCore;
+use sha2::Sha256;
 use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::aes;
+use super::keys::{MlKemSecretKey, Secp256k1SecretKey, SecretKey, VaultSecret};
 
 /// secp256k1 uncompressed public key size in bytes (04 prefix + x + y coordinates).
 pub const SECP256K1_PUBLIC_KEY_SIZE: usize = 65;
@@ -14,6 +45,37 @@ pub const SECP256K1_PRIVATE_KEY_SIZE: usize = 32;
 /// ECIES minimum ciphertext size: ephemeral pubkey (65) + auth tag (16).
 pub const ECIES_MIN_CIPHERTEXT_SIZE: usize = SECP256K1_PUBLIC_KEY_SIZE + 16;
 
+/// Header byte of the hybrid ECIES + ML-KEM-768 wrap format (version 1).
+pub const HYBRID_WRAP_VERSION: u8 = 0x01;
+
+/// Header byte of an ML-KEM-768 private key sealed under the vault secret.
+pub const SEALED_MLKEM_KEY_VERSION: u8 = 0x02;
+
+/// ML-KEM-768 encapsulation (public) key size in bytes.
+pub const MLKEM768_PUBLIC_KEY_SIZE: usize = 1184;
+
+/// ML-KEM-768 decapsulation (private) key size in bytes.
+pub const MLKEM768_PRIVATE_KEY_SIZE: usize = 2400;
+
+/// ML-KEM-768 ciphertext size in bytes.
+pub const MLKEM768_CIPHERTEXT_SIZE: usize = 1088;
+
+/// ECIES ciphertext of the 32-byte classical share:
+/// ephemeral pubkey (65) + nonce (16) + tag (16) + share (32).
+const HYBRID_ECIES_SHARE_SIZE: usize = SECP256K1_PUBLIC_KEY_SIZE + 16 + 16 + 32;
+
+/// HKDF salt, shared with the IPNS key derivations.
+const HKDF_SALT: &[u8] = b"CipherBox-v1";
+
+/// HKDF info prefix for the hybrid key-encryption key (followed by both ciphertexts).
+const HYBRID_KEK_INFO: &[u8] = b"cipherbox-hybrid-wrap-v1";
+
+/// HKDF info for the key sealing the ML-KEM-768 private key under the vault secret.
+const MLKEM_SEAL_INFO: &[u8] = b"cipherbox-mlkem-seal-v1";
+
+type MlKemDecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type MlKemEncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
 #[derive(Debug, Error)]
 pub enum EciesError {
     #[error("Key wrapping failed")]
@@ -26,6 +88,14 @@ pub enum EciesError {
     InvalidPublicKeyFormat,
     #[error("Invalid private key size")]
     InvalidPrivateKeySize,
+    #[error("Key re-wrapping failed")]
+    RewrapFailed,
+    #[error("Unwrapped key has invalid size")]
+    InvalidUnwrappedKeySize,
+    #[error("Key derivation failed")]
+    KeyDerivationFailed,
+    #[error("Vault secret not available")]
+    VaultSecretMissing,
 }
 
 /// Wrap (encrypt) data using ECIES with secp256k1.
@@ -45,17 +115,264 @@ pub fn wrap_key(data: &[u8], recipient_public_key: &[u8]) -> Result<Vec<u8>, Eci
     ecies::encrypt(recipient_public_key, data).map_err(|_| EciesError::WrappingFailed)
 }
 
-/// Unwrap (decrypt) data using ECIES with secp256k1.
-pub fn unwrap_key(wrapped: &[u8], private_key: &[u8]) -> Result<Vec<u8>, EciesError> {
-    // Validate private key size
-    if private_key.len() != SECP256K1_PRIVATE_KEY_SIZE {
-        return Err(EciesError::InvalidPrivateKeySize);
-    }
-
+/// Unwrap (decrypt) data wrapped with `wrap_key`.
+///
+/// Hybrid-wrapped data needs the ML-KEM key too, see `unwrap_key_hybrid`.
+pub fn unwrap_key(wrapped: &[u8], private_key: &Secp256k1SecretKey) -> Result<Vec<u8>, EciesError> {
     // Validate minimum ciphertext size
     if wrapped.len() < ECIES_MIN_CIPHERTEXT_SIZE {
         return Err(EciesError::UnwrappingFailed);
     }
 
-    ecies::decrypt(private_key, wrapped).map_err(|_| EciesError::UnwrappingFailed)
+    ecies::decrypt(private_key.as_bytes(), wrapped).map_err(|_| EciesError::UnwrappingFailed)
+}
+
+/// Unwrap an ECIES-wrapped secret key straight into its typed form.
+///
+/// The intermediate plaintext is zeroized; a length mismatch fails with
+/// `InvalidUnwrappedKeySize`.
+pub fn unwrap_secret_key<K: SecretKey>(
+    wrapped: &[u8],
+    private_key: &Secp256k1SecretKey,
+) -> Result<K, EciesError> {
+    let plain = Zeroizing::new(unwrap_key(wrapped, private_key)?);
+    K::from_slice(&plain).map_err(|_| EciesError::InvalidUnwrappedKeySize)
+}
+
+/// Re-wrap a key from one recipient to another.
+///
+/// Unwraps `owner_wrapped_key` with the owner's private key, then wraps the
+/// plaintext key for `recipient_public_key`. The plaintext key is zeroized
+/// before returning, on success and on failure.
+///
+/// Mirrors TypeScript `reWrapKey`: every failure maps to the generic
+/// `RewrapFailed` to avoid leaking which step failed.
+pub fn rewrap_key(
+    owner_wrapped_key: &[u8],
+    owner_private_key: &Secp256k1SecretKey,
+    recipient_public_key: &[u8],
+) -> Result<Vec<u8>, EciesError> {
+    let plain_key = Zeroizing::new(
+        unwrap_key(owner_wrapped_key, owner_private_key).map_err(|_| EciesError::RewrapFailed)?,
+    );
+
+    wrap_key(&plain_key, recipient_public_key).map_err(|_| EciesError::RewrapFailed)
+}
+
+/// Generate a random ML-KEM-768 keypair.
+///
+/// Returns (private key, 1184-byte public key). The private key is stored on
+/// the vault with `seal_mlkem_private_key`.
+pub fn generate_mlkem_keypair() -> (MlKemSecretKey, Vec<u8>) {
+    let (dk, ek) = MlKem768::generate(&mut rand::rngs::OsRng);
+    let mut dk_bytes = dk.as_bytes();
+    let private_key =
+        MlKemSecretKey::from_slice(&dk_bytes).expect("ML-KEM-768 private key is 2400 bytes");
+    dk_bytes.as_mut_slice().zeroize();
+    (private_key, ek.as_bytes().to_vec())
+}
+
+fn mlkem_decapsulation_key(private_key: &MlKemSecretKey) -> Result<MlKemDecapsulationKey, EciesError> {
+    let dk_bytes: &Encoded<MlKemDecapsulationKey> = private_key
+        .as_slice()
+        .try_into()
+        .map_err(|_| EciesError::InvalidPrivateKeySize)?;
+    Ok(MlKemDecapsulationKey::from_bytes(dk_bytes))
+}
+
+/// The ML-KEM-768 public key (1184 bytes) of `private_key`, for `wrap_key_hybrid`.
+pub fn mlkem_public_key(private_key: &MlKemSecretKey) -> Result<Vec<u8>, EciesError> {
+    let dk = mlkem_decapsulation_key(private_key)?;
+    Ok(dk.encapsulation_key().as_bytes().to_vec())
+}
+
+/// Key sealing the ML-KEM-768 private key, derived from the vault secret.
+fn mlkem_seal_key(vault_secret: &VaultSecret) -> Result<Zeroizing<[u8; 32]>, EciesError> {
+    let hk = Hkdf::<Sha256>::new(Some(HKDF_SALT), vault_secret.as_bytes());
+    let mut key = Zeroizing::new([0u8; 32]);
+    hk.expand(MLKEM_SEAL_INFO, key.as_mut())
+        .map_err(|_| EciesError::KeyDerivationFailed)?;
+    Ok(key)
+}
+
+/// Seal an ML-KEM-768 private key for storage on the vault.
+///
+/// AES-256-GCM under a key derived from `vault_secret`, then ECIES-wrapped to
+/// the user's secp256k1 public key: opening it takes both secrets.
+pub fn seal_mlkem_private_key(
+    mlkem_private_key: &MlKemSecretKey,
+    vault_secret: &VaultSecret,
+    recipient_public_key: &[u8],
+) -> Result<Vec<u8>, EciesError> {
+    let key = mlkem_seal_key(vault_secret)?;
+    let sealed = Zeroizing::new(
+        aes::seal_aes_gcm_with_aad(mlkem_private_key.as_slice(), &key, &[SEALED_MLKEM_KEY_VERSION])
+            .map_err(|_| EciesError::WrappingFailed)?,
+    );
+
+    let wrapped = wrap_key(&sealed, recipient_public_key)?;
+    let mut out = Vec::with_capacity(1 + wrapped.len());
+    out.push(SEALED_MLKEM_KEY_VERSION);
+    out.extend_from_slice(&wrapped);
+    Ok(out)
+}
+
+/// Open an ML-KEM-768 private key stored on the vault.
+///
+/// Keys sealed with `seal_mlkem_private_key` fail with `VaultSecretMissing`
+/// without `vault_secret`; keys stored plain ECIES-wrapped by older clients
+/// only need the secp256k1 key.
+pub fn open_mlkem_private_key(
+    stored: &[u8],
+    private_key: &Secp256k1SecretKey,
+    vault_secret: Option<&VaultSecret>,
+) -> Result<MlKemSecretKey, EciesError> {
+    let Some((&SEALED_MLKEM_KEY_VERSION, wrapped)) = stored.split_first() else {
+        return unwrap_secret_key(stored, private_key);
+    };
+    let vault_secret = vault_secret.ok_or(EciesError::VaultSecretMissing)?;
+
+    let sealed = Zeroizing::new(unwrap_key(wrapped, private_key)?);
+    let key = mlkem_seal_key(vault_secret)?;
+    let plain = Zeroizing::new(
+        aes::unseal_aes_gcm_with_aad(&sealed, &key, &[SEALED_MLKEM_KEY_VERSION])
+            .map_err(|_| EciesError::UnwrappingFailed)?,
+    );
+    MlKemSecretKey::from_slice(&plain).map_err(|_| EciesError::InvalidUnwrappedKeySize)
+}
+
+/// Key-encryption key binding both shared secrets to both ciphertexts.
+fn hybrid_kek(
+    ecies_share: &[u8],
+    mlkem_share: &[u8],
+    mlkem_ct: &[u8],
+    ecies_ct: &[u8],
+) -> Result<Zeroizing<[u8; 32]>, EciesError> {
+    let mut ikm = Zeroizing::new(Vec::with_capacity(ecies_share.len() + mlkem_share.len()));
+    ikm.extend_from_slice(ecies_share);
+    ikm.extend_from_slice(mlkem_share);
+
+    let mut info = Vec::with_capacity(HYBRID_KEK_INFO.len() + mlkem_ct.len() + ecies_ct.len());
+    info.extend_from_slice(HYBRID_KEK_INFO);
+    info.extend_from_slice(mlkem_ct);
+    info.extend_from_slice(ecies_ct);
+
+    let hk = Hkdf::<Sha256>::new(Some(HKDF_SALT), &ikm);
+    let mut kek = Zeroizing::new([0u8; 32]);
+    hk.expand(&info, kek.as_mut())
+        .map_err(|_| EciesError::KeyDerivationFailed)?;
+    Ok(kek)
+}
+
+/// Wrap (encrypt) data with the hybrid ECIES + ML-KEM-768 format.
+///
+/// `recipient_mlkem_public_key` is the recipient's ML-KEM-768 public key
+/// (see `mlkem_public_key`). Use for long-lived keys such as folder keys.
+pub fn wrap_key_hybrid(
+    data: &[u8],
+    recipient_public_key: &[u8],
+    recipient_mlkem_public_key: &[u8],
+) -> Result<Vec<u8>, EciesError> {
+    let ek_bytes: &Encoded<MlKemEncapsulationKey> = recipient_mlkem_public_key
+        .try_into()
+        .map_err(|_| EciesError::InvalidPublicKeySize)?;
+    let ek = MlKemEncapsulationKey::from_bytes(ek_bytes);
+
+    // Classical share, ECIES-wrapped to the recipient
+    let mut ecies_share = Zeroizing::new([0u8; 32]);
+    rand::rngs::OsRng.fill_bytes(ecies_share.as_mut());
+    let ecies_ct = wrap_key(ecies_share.as_ref(), recipient_public_key)?;
+
+    // Post-quantum share
+    let (mlkem_ct, mut mlkem_share) = ek
+        .encapsulate(&mut rand::rngs::OsRng)
+        .map_err(|_| EciesError::WrappingFailed)?;
+
+    let kek = hybrid_kek(ecies_share.as_ref(), &mlkem_share, &mlkem_ct, &ecies_ct);
+    mlkem_share.as_mut_slice().zeroize();
+    let kek = kek?;
+    let sealed = aes::seal_aes_gcm_with_aad(data, &kek, &[HYBRID_WRAP_VERSION])
+        .map_err(|_| EciesError::WrappingFailed)?;
+
+    let mut out = Vec::with_capacity(1 + mlkem_ct.len() + ecies_ct.len() + sealed.len());
+    out.push(HYBRID_WRAP_VERSION);
+    out.extend_from_slice(&mlkem_ct);
+    out.extend_from_slice(&ecies_ct);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Wrap data with `wrap_key_hybrid` when the recipient has an ML-KEM-768
+/// public key, and with plain `wrap_key` otherwise.
+///
+/// Used for folder keys, so they get post-quantum protection from every
+/// vault that has an ML-KEM key.
+pub fn wrap_key_hybrid_if_available(
+    data: &[u8],
+    recipient_public_key: &[u8],
+    recipient_mlkem_public_key: Option<&[u8]>,
+) -> Result<Vec<u8>, EciesError> {
+    match recipient_mlkem_public_key {
+        Some(mlkem_public_key) => wrap_key_hybrid(data, recipient_public_key, mlkem_public_key),
+        None => wrap_key(data, recipient_public_key),
+    }
+}
+
+/// Unwrap (decrypt) data wrapped with `wrap_key` or `wrap_key_hybrid`.
+///
+/// The format is detected from the first byte. Hybrid data fails to unwrap
+/// without `mlkem_private_key`.
+pub fn unwrap_key_hybrid(
+    wrapped: &[u8],
+    private_key: &Secp256k1SecretKey,
+    mlkem_private_key: Option<&MlKemSecretKey>,
+) -> Result<Vec<u8>, EciesError> {
+    match (wrapped.split_first(), mlkem_private_key) {
+        (Some((&HYBRID_WRAP_VERSION, body)), Some(mlkem_private_key)) => {
+            unwrap_hybrid_body(body, private_key, mlkem_private_key)
+        }
+        (Some((&HYBRID_WRAP_VERSION, _)), None) => Err(EciesError::UnwrappingFailed),
+        _ => unwrap_key(wrapped, private_key),
+    }
+}
+
+/// `unwrap_secret_key` for data that may be hybrid-wrapped.
+pub fn unwrap_secret_key_hybrid<K: SecretKey>(
+    wrapped: &[u8],
+    private_key: &Secp256k1SecretKey,
+    mlkem_private_key: Option<&MlKemSecretKey>,
+) -> Result<K, EciesError> {
+    let plain = Zeroizing::new(unwrap_key_hybrid(wrapped, private_key, mlkem_private_key)?);
+    K::from_slice(&plain).map_err(|_| EciesError::InvalidUnwrappedKeySize)
+}
+
+/// Unwrap the body (after the header byte) of a hybrid-wrapped key.
+fn unwrap_hybrid_body(
+    body: &[u8],
+    private_key: &Secp256k1SecretKey,
+    mlkem_private_key: &MlKemSecretKey,
+) -> Result<Vec<u8>, EciesError> {
+    let min_len = MLKEM768_CIPHERTEXT_SIZE
+        + HYBRID_ECIES_SHARE_SIZE
+        + aes::AES_IV_SIZE
+        + aes::AES_TAG_SIZE;
+    if body.len() < min_len {
+        return Err(EciesError::UnwrappingFailed);
+    }
+    let (mlkem_ct, rest) = body.split_at(MLKEM768_CIPHERTEXT_SIZE);
+    let (ecies_ct, sealed) = rest.split_at(HYBRID_ECIES_SHARE_SIZE);
+
+    let ecies_share = Zeroizing::new(
+        ecies::decrypt(private_key.as_bytes(), ecies_ct).map_err(|_| EciesError::UnwrappingFailed)?,
+    );
+
+    let dk = mlkem_decapsulation_key(mlkem_private_key)?;
+    let ct = Ciphertext::<MlKem768>::try_from(mlkem_ct).map_err(|_| EciesError::UnwrappingFailed)?;
+    let mut mlkem_share = dk.decapsulate(&ct).map_err(|_| EciesError::UnwrappingFailed)?;
+
+    let kek = hybrid_kek(&ecies_share, &mlkem_share, mlkem_ct, ecies_ct);
+    mlkem_share.as_mut_slice().zeroize();
+    let kek = kek?;
+    aes::unseal_aes_gcm_with_aad(sealed, &kek, &[HYBRID_WRAP_VERSION])
+        .map_err(|_| EciesError::UnwrappingFailed)
 }
@@ -0,0 +1,152 @@
+//! FastCDC content-defined chunking.
+//!
+//! Splits a byte stream into variable-size chunks whose boundaries depend on
+//! the content (a rolling Gear hash), not on offsets. Inserting or deleting
+//! bytes only moves the boundaries near the edit, so most chunks of an edited
+//! file are identical to the previous version's.
+//!
+//! Uses normalized chunking (FastCDC, Xia et al. 2016): a stricter mask before
+//! the average size and a looser one after it keeps chunk sizes close to
+//! `AVG_CHUNK_SIZE`.
+
+use std::io::{self, Read};
+use std::ops::Range;
+
+use zeroize::Zeroizing;
+
+/// Chunks are never smaller than this (except the last one).
+pub const MIN_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Target average chunk size (1 MiB).
+pub const AVG_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Chunks are cut at this size if no boundary was found.
+pub const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// log2(AVG_CHUNK_SIZE).
+const AVG_BITS: u32 = AVG_CHUNK_SIZE.trailing_zeros();
+
+/// Mask used before the average size: two extra bits make a cut less likely.
+const MASK_S: u64 = top_bits(AVG_BITS + 2);
+
+/// Mask used after the average size: two fewer bits make a cut more likely.
+const MASK_L: u64 = top_bits(AVG_BITS - 2);
+
+/// Gear table: 256 fixed pseudo-random 64-bit values.
+///
+/// Part of the chunk format: changing it moves every chunk boundary and
+/// defeats reuse of chunks uploaded by earlier versions.
+const GEAR: [u64; 256] = gear_table();
+
+/// Mask with the `n` most significant bits set.
+///
+/// The Gear hash shifts left once per byte, so the high bits depend on the
+/// last 64 bytes while the low bits only see the last few.
+const fn top_bits(n: u32) -> u64 {
+    !0u64 << (64 - n)
+}
+
+/// SplitMix64 sequence with a fixed seed.
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x4369_7068_6572_426f; // "CipherBo"
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Length of the first chunk of `data`.
+fn cut_point(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK_SIZE {
+        return data.len();
+    }
+    let end = data.len().min(MAX_CHUNK_SIZE);
+    let normal = end.min(AVG_CHUNK_SIZE);
+
+    let mut hash: u64 = 0;
+    let mut i = MIN_CHUNK_SIZE;
+    while i < normal {
+        hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+        if hash & MASK_S == 0 {
+            return i + 1;
+        }
+        i += 1;
+    }
+    while i < end {
+        hash = (hash << 1).wrapping_add(GEAR[data[i] as usize]);
+        if hash & MASK_L == 0 {
+            return i + 1;
+        }
+        i += 1;
+    }
+    end
+}
+
+/// Split `data` into content-defined chunks.
+///
+/// Returns contiguous byte ranges covering all of `data`. Empty input yields
+/// no chunks.
+pub fn chunk_boundaries(data: &[u8]) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let len = cut_point(&data[offset..]);
+        chunks.push(offset..offset + len);
+        offset += len;
+    }
+    chunks
+}
+
+/// FastCDC over a reader.
+///
+/// Yields the same chunks as `chunk_boundaries` on the whole input, but only
+/// buffers up to `MAX_CHUNK_SIZE` bytes: a cut point never looks further
+/// ahead than that.
+pub struct StreamChunker<R> {
+    reader: R,
+    buf: Zeroizing<Vec<u8>>,
+    eof: bool,
+}
+
+impl<R: Read> StreamChunker<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Zeroizing::new(Vec::with_capacity(MAX_CHUNK_SIZE)),
+            eof: false,
+        }
+    }
+
+    /// The next chunk, or `None` once the input is exhausted.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Zeroizing<Vec<u8>>>> {
+        while !self.eof && self.buf.len() < MAX_CHUNK_SIZE {
+            let filled = self.buf.len();
+            self.buf.resize(MAX_CHUNK_SIZE, 0);
+            match self.reader.read(&mut self.buf[filled..]) {
+                Ok(n) => {
+                    self.buf.truncate(filled + n);
+                    self.eof = n == 0;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => self.buf.truncate(filled),
+                Err(e) => {
+                    self.buf.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+        let len = cut_point(&self.buf);
+        let chunk = Zeroizing::new(self.buf[..len].to_vec());
+        self.buf.drain(..len);
+        Ok(Some(chunk))
+    }
+}
@@ -6,12 +6,33 @@
 //!
 //! Only v2 schema (per-file IPNS pointers via FilePointer) is supported.
 //! v1 (inline file data) has been removed.
+//!
+//! On IPFS, encrypted metadata is wrapped in a JSON envelope. The legacy
+//! envelope is `{ "iv", "data" }` with no associated data. Envelope v2 adds
+//! `envelope`, `type` and `schema` fields and binds them, together with the
+//! IPNS name the metadata is published under, as AES-GCM associated data so
+//! the server cannot swap one folder's (or file's) metadata CID into another
+//! IPNS record encrypted under the same key.
+//!
+//! Both envelopes can pad the metadata JSON (see `padding`); v2 records the
+//! scheme in the envelope's `padding` field.
+//!
+//! The web app only reads legacy envelopes so far, so they are still written
+//! by default (`MetadataFormat`). Readers accept both; a strict reader
+//! (`EnvelopeHistory`) rejects a legacy envelope for an IPNS name it has
+//! already seen in v2, and writers keep such names in v2.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
 
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
 
 use super::aes::{self, AesError};
+use super::keys::FolderKey;
+use super::padding::{self, PaddingScheme};
 
 #[derive(Debug, Error)]
 pub enum FolderError {
@@ -21,6 +42,16 @@ pub enum FolderError {
     SerializationFailed,
     #[error("Deserialization failed")]
     DeserializationFailed,
+    #[error("Invalid metadata envelope")]
+    InvalidEnvelope,
+    #[error("Unsupported metadata envelope version: {0}")]
+    UnsupportedEnvelopeVersion(u32),
+    #[error("Metadata object type mismatch")]
+    ObjectTypeMismatch,
+    #[error("Metadata schema version mismatch")]
+    SchemaVersionMismatch,
+    #[error("Legacy metadata envelope for a record already seen in v2")]
+    LegacyEnvelopeRejected,
 }
 
 /// Subfolder entry within folder metadata.
@@ -95,10 +126,10 @@ pub struct FolderMetadata {
 /// Returns the sealed bytes: IV (12) || ciphertext || tag (16).
 pub fn encrypt_folder_metadata(
     metadata: &FolderMetadata,
-    folder_key: &[u8; 32],
+    folder_key: &FolderKey,
 ) -> Result<Vec<u8>, FolderError> {
     let mut json = serde_json::to_vec(metadata).map_err(|_| FolderError::SerializationFailed)?;
-    let result = aes::seal_aes_gcm(&json, folder_key).map_err(FolderError::EncryptionFailed);
+    let result = aes::seal_aes_gcm(&json, folder_key.as_bytes()).map_err(FolderError::EncryptionFailed);
     json.zeroize();
     result
 }
@@ -109,17 +140,21 @@ pub fn encrypt_folder_metadata(
 /// Rejects metadata with version other than "v2".
 pub fn decrypt_folder_metadata(
     sealed: &[u8],
-    folder_key: &[u8; 32],
+    folder_key: &FolderKey,
 ) -> Result<FolderMetadata, FolderError> {
-    let mut json = aes::unseal_aes_gcm(sealed, folder_key).map_err(FolderError::EncryptionFailed)?;
+    let json = Zeroizing::new(
+        aes::unseal_aes_gcm(sealed, folder_key.as_bytes()).map_err(FolderError::EncryptionFailed)?,
+    );
+    parse_folder_metadata(&json)
+}
 
+/// Deserialize decrypted folder metadata JSON, rejecting versions other than "v2".
+fn parse_folder_metadata(json: &[u8]) -> Result<FolderMetadata, FolderError> {
     // Parse as generic JSON to check version field
-    let value: serde_json::Value =
-        serde_json::from_slice(&json).map_err(|e| {
-            log::error!("JSON parse failed: {}", e);
-            json.zeroize();
-            FolderError::DeserializationFailed
-        })?;
+    let value: serde_json::Value = serde_json::from_slice(json).map_err(|e| {
+        log::error!("JSON parse failed: {}", e);
+        FolderError::DeserializationFailed
+    })?;
 
     let version = value.get("version").and_then(|v| v.as_str());
     if version != Some("v2") {
@@ -127,17 +162,13 @@ pub fn decrypt_folder_metadata(
             "Unsupported folder metadata version: {:?} (only v2 is supported)",
             version
         );
-        json.zeroize();
         return Err(FolderError::DeserializationFailed);
     }
 
-    let result: Result<FolderMetadata, _> = serde_json::from_value(value).map_err(|e| {
+    serde_json::from_value(value).map_err(|e| {
         log::error!("V2 metadata deserialization failed: {}", e);
         FolderError::DeserializationFailed
-    });
-
-    json.zeroize();
-    result
+    })
 }
 
 /// Default encryption mode for FileMetadata: "GCM".
@@ -163,6 +194,10 @@ pub struct VersionEntry {
     pub timestamp: u64,
     /// Encryption mode used for this version.
     pub encryption_mode: String,
+    /// Content padding scheme used for this version (None if unpadded).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub padding: Option<String>,
 }
 
 /// Decrypted per-file metadata structure.
@@ -184,9 +219,17 @@ pub struct FileMetadata {
     pub size: u64,
     /// MIME type of the original file.
     pub mime_type: String,
-    /// Encryption mode (optional for backward compat; defaults to "GCM").
+    /// Encryption mode: "GCM", "CTR", "GCM-SEG" or "CHUNKED" (optional for backward compat; defaults to "GCM").
     #[serde(default = "default_encryption_mode")]
     pub encryption_mode: String,
+    /// Content padding scheme ("padme" or "pow2"). None if the content is unpadded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub padding: Option<String>,
+    /// CTR only: hex SHA-256 of each 1 MiB ciphertext chunk, for verifying range reads.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub chunk_hashes: Option<Vec<String>>,
     /// Creation timestamp (Unix ms).
     pub created_at: u64,
     /// Last modification timestamp (Unix ms).
@@ -204,10 +247,10 @@ pub struct FileMetadata {
 /// Returns the sealed bytes: IV (12) || ciphertext || tag (16).
 pub fn encrypt_file_metadata(
     metadata: &FileMetadata,
-    folder_key: &[u8; 32],
+    folder_key: &FolderKey,
 ) -> Result<Vec<u8>, FolderError> {
     let mut json = serde_json::to_vec(metadata).map_err(|_| FolderError::SerializationFailed)?;
-    let result = aes::seal_aes_gcm(&json, folder_key).map_err(FolderError::EncryptionFailed);
+    let result = aes::seal_aes_gcm(&json, folder_key.as_bytes()).map_err(FolderError::EncryptionFailed);
     json.zeroize();
     result
 }
@@ -218,10 +261,389 @@ pub fn encrypt_file_metadata(
 /// Unseals, then JSON deserializes to FileMetadata.
 pub fn decrypt_file_metadata(
     sealed: &[u8],
-    folder_key: &[u8; 32],
+    folder_key: &FolderKey,
 ) -> Result<FileMetadata, FolderError> {
-    let mut json = aes::unseal_aes_gcm(sealed, folder_key).map_err(FolderError::EncryptionFailed)?;
+    let mut json = aes::unseal_aes_gcm(sealed, folder_key.as_bytes()).map_err(FolderError::EncryptionFailed)?;
     let result = serde_json::from_slice(&json).map_err(|_| FolderError::DeserializationFailed);
     json.zeroize();
     result
 }
+
+/// Current metadata envelope version (the legacy `{ iv, data }` envelope is v1).
+pub const METADATA_ENVELOPE_VERSION: u32 = 2;
+
+/// Domain separation prefix for metadata envelope associated data.
+const METADATA_AAD_PREFIX: &str = "cipherbox-metadata";
+
+/// Kind of object an encrypted metadata envelope holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataObjectType {
+    /// Folder metadata, published under the folder's IPNS name.
+    Folder,
+    /// Per-file metadata, published under the file's own IPNS name.
+    File,
+}
+
+impl MetadataObjectType {
+    /// Value of the envelope `type` field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetadataObjectType::Folder => "folder",
+            MetadataObjectType::File => "file",
+        }
+    }
+}
+
+/// How metadata envelopes are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataFormat {
+    /// Legacy `{ iv, data }` envelope, without associated data, JSON padded
+    /// with `padding` (trailing whitespace, which v1 readers parse unchanged).
+    /// The default while the web app reads nothing else.
+    Legacy { padding: PaddingScheme },
+    /// v2 envelope bound to its IPNS name, JSON padded with `padding`.
+    V2 { padding: PaddingScheme },
+}
+
+impl Default for MetadataFormat {
+    fn default() -> Self {
+        MetadataFormat::Legacy {
+            padding: PaddingScheme::None,
+        }
+    }
+}
+
+impl MetadataFormat {
+    /// Parse an envelope version as used in configuration ("v1" or "v2").
+    pub fn from_name(name: &str, padding: PaddingScheme) -> Result<Self, String> {
+        match name {
+            "" | "v1" => Ok(MetadataFormat::Legacy { padding }),
+            "v2" => Ok(MetadataFormat::V2 { padding }),
+            other => Err(format!("Unknown metadata envelope version: {}", other)),
+        }
+    }
+}
+
+/// IPNS names whose metadata was read in a v2 envelope, for strict readers.
+///
+/// Whoever writes a record in v2 keeps writing it in v2, so a legacy envelope
+/// for such a name can only be old, unbound metadata served again. The names
+/// outlive a restart in the vault's snapshot (`v2_names` / `extend`).
+pub struct EnvelopeHistory {
+    v2: Mutex<BTreeSet<String>>,
+}
+
+impl EnvelopeHistory {
+    /// Empty history.
+    pub const fn new() -> Self {
+        Self {
+            v2: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Whether metadata of `ipns_name` was read in a v2 envelope.
+    pub fn is_v2(&self, ipns_name: &str) -> bool {
+        self.v2.lock().unwrap().contains(ipns_name)
+    }
+
+    /// Names read in v2 so far.
+    pub fn v2_names(&self) -> BTreeSet<String> {
+        self.v2.lock().unwrap().clone()
+    }
+
+    /// Add names read in v2 before, e.g. as persisted in a vault snapshot.
+    pub fn extend(&self, names: impl IntoIterator<Item = String>) {
+        self.v2.lock().unwrap().extend(names);
+    }
+
+    /// Forget every name, e.g. on logout, before another vault is mounted.
+    pub fn clear(&self) {
+        self.v2.lock().unwrap().clear();
+    }
+
+    /// `format`, upgraded to v2 if `ipns_name` was read in v2: writing it
+    /// back in a legacy envelope would make strict readers reject it.
+    pub fn format_for(&self, ipns_name: &str, format: MetadataFormat) -> MetadataFormat {
+        match format {
+            MetadataFormat::Legacy { padding } if self.is_v2(ipns_name) => MetadataFormat::V2 { padding },
+            format => format,
+        }
+    }
+
+    /// Record that metadata of `ipns_name` was read in a v2 envelope (`v2`)
+    /// or a legacy one, which is rejected once the name was seen in v2.
+    fn record(&self, ipns_name: &str, v2: bool) -> Result<(), FolderError> {
+        let mut seen = self.v2.lock().unwrap();
+        if v2 {
+            seen.insert(ipns_name.to_string());
+            Ok(())
+        } else if seen.contains(ipns_name) {
+            Err(FolderError::LegacyEnvelopeRejected)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for EnvelopeHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encrypted metadata envelope as stored on IPFS.
+///
+/// `envelope`, `type` and `schema` are absent in legacy (v1) envelopes.
+#[derive(Serialize, Deserialize)]
+struct MetadataEnvelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    envelope: Option<u32>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    object_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema: Option<String>,
+    /// Padding scheme applied to the JSON before sealing (absent if unpadded).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    padding: Option<String>,
+    /// Hex-encoded 12-byte IV for AES-GCM.
+    iv: String,
+    /// Base64-encoded AES-GCM ciphertext (includes 16-byte auth tag).
+    data: String,
+}
+
+/// Build the associated data binding an envelope to its IPNS name.
+///
+/// Format: `cipherbox-metadata:<envelope>:<type>:<schema>:<ipns name>`.
+/// None of the components can contain `:` (IPNS names are base36).
+fn metadata_aad(object_type: MetadataObjectType, schema: &str, ipns_name: &str) -> Vec<u8> {
+    format!(
+        "{}:{}:{}:{}:{}",
+        METADATA_AAD_PREFIX,
+        METADATA_ENVELOPE_VERSION,
+        object_type.as_str(),
+        schema,
+        ipns_name
+    )
+    .into_bytes()
+}
+
+/// Seal serialized metadata into an envelope of `format` and return the
+/// envelope JSON bytes.
+///
+/// The JSON is padded with trailing whitespace according to the format's
+/// padding; only v2 records the scheme in the envelope.
+fn seal_metadata_envelope(
+    json: &[u8],
+    folder_key: &FolderKey,
+    object_type: MetadataObjectType,
+    schema: &str,
+    ipns_name: &str,
+    format: MetadataFormat,
+) -> Result<Vec<u8>, FolderError> {
+    let (sealed, mut envelope) = match format {
+        MetadataFormat::Legacy { padding } => (
+            aes::seal_aes_gcm(&padding::pad_json(json, padding), folder_key.as_bytes())?,
+            MetadataEnvelope {
+                envelope: None,
+                object_type: None,
+                schema: None,
+                padding: None,
+                iv: String::new(),
+                data: String::new(),
+            },
+        ),
+        MetadataFormat::V2 { padding } => {
+            let aad = metadata_aad(object_type, schema, ipns_name);
+            let padded = padding::pad_json(json, padding);
+            (
+                aes::seal_aes_gcm_with_aad(&padded, folder_key.as_bytes(), &aad)?,
+                MetadataEnvelope {
+                    envelope: Some(METADATA_ENVELOPE_VERSION),
+                    object_type: Some(object_type.as_str().to_string()),
+                    schema: Some(schema.to_string()),
+                    padding: padding.metadata_value(),
+                    iv: String::new(),
+                    data: String::new(),
+                },
+            )
+        }
+    };
+    envelope.iv = hex::encode(&sealed[..aes::AES_IV_SIZE]);
+    envelope.data = base64::engine::general_purpose::STANDARD.encode(&sealed[aes::AES_IV_SIZE..]);
+    serde_json::to_vec(&envelope).map_err(|_| FolderError::SerializationFailed)
+}
+
+/// Open an envelope (v2 or legacy) and return the decrypted metadata JSON.
+///
+/// Also returns the schema version bound by a v2 envelope, so callers can check
+/// it against the decrypted `version` field. Legacy envelopes return `None`.
+fn open_metadata_envelope(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    object_type: MetadataObjectType,
+    ipns_name: &str,
+) -> Result<(Zeroizing<Vec<u8>>, Option<String>), FolderError> {
+    let envelope: MetadataEnvelope =
+        serde_json::from_slice(envelope_bytes).map_err(|_| FolderError::InvalidEnvelope)?;
+
+    let iv = hex::decode(&envelope.iv).map_err(|_| FolderError::InvalidEnvelope)?;
+    if iv.len() != aes::AES_IV_SIZE {
+        return Err(FolderError::InvalidEnvelope);
+    }
+    let ciphertext = base64::engine::general_purpose::STANDARD
+        .decode(&envelope.data)
+        .map_err(|_| FolderError::InvalidEnvelope)?;
+
+    // Reconstruct sealed format: IV || ciphertext (includes tag)
+    let mut sealed = Vec::with_capacity(iv.len() + ciphertext.len());
+    sealed.extend_from_slice(&iv);
+    sealed.extend_from_slice(&ciphertext);
+
+    match envelope.envelope {
+        // Legacy envelope: no associated data.
+        None => {
+            let json = aes::unseal_aes_gcm(&sealed, folder_key.as_bytes())?;
+            Ok((Zeroizing::new(json), None))
+        }
+        Some(METADATA_ENVELOPE_VERSION) => {
+            if envelope.object_type.as_deref() != Some(object_type.as_str()) {
+                return Err(FolderError::ObjectTypeMismatch);
+            }
+            let schema = envelope.schema.ok_or(FolderError::InvalidEnvelope)?;
+            let aad = metadata_aad(object_type, &schema, ipns_name);
+            let json = aes::unseal_aes_gcm_with_aad(&sealed, folder_key.as_bytes(), &aad)?;
+            Ok((Zeroizing::new(json), Some(schema)))
+        }
+        Some(other) => Err(FolderError::UnsupportedEnvelopeVersion(other)),
+    }
+}
+
+/// Encrypt folder metadata into an envelope of `format`, which in v2 is bound
+/// to `ipns_name`.
+///
+/// v2 pads the JSON according to the format's padding, so the child count is
+/// not revealed exactly. Returns the envelope JSON bytes ready for IPFS upload.
+pub fn encrypt_folder_metadata_envelope(
+    metadata: &FolderMetadata,
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    format: MetadataFormat,
+) -> Result<Vec<u8>, FolderError> {
+    let json = Zeroizing::new(
+        serde_json::to_vec(metadata).map_err(|_| FolderError::SerializationFailed)?,
+    );
+    seal_metadata_envelope(
+        &json,
+        folder_key,
+        MetadataObjectType::Folder,
+        &metadata.version,
+        ipns_name,
+        format,
+    )
+}
+
+/// Decrypt a folder metadata envelope fetched from `ipns_name`.
+///
+/// v2 envelopes only decrypt if they were sealed for this IPNS name as folder
+/// metadata. Legacy `{ iv, data }` envelopes are still accepted.
+/// Rejects metadata with version other than "v2".
+pub fn decrypt_folder_metadata_envelope(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+) -> Result<FolderMetadata, FolderError> {
+    open_folder_metadata_envelope(envelope_bytes, folder_key, ipns_name, None)
+}
+
+/// Decrypt a folder metadata envelope fetched from `ipns_name`, rejecting a
+/// legacy envelope if `history` has seen the folder in v2.
+pub fn decrypt_folder_metadata_envelope_strict(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    history: &EnvelopeHistory,
+) -> Result<FolderMetadata, FolderError> {
+    open_folder_metadata_envelope(envelope_bytes, folder_key, ipns_name, Some(history))
+}
+
+fn open_folder_metadata_envelope(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    history: Option<&EnvelopeHistory>,
+) -> Result<FolderMetadata, FolderError> {
+    let (json, schema) =
+        open_metadata_envelope(envelope_bytes, folder_key, MetadataObjectType::Folder, ipns_name)?;
+    if let Some(history) = history {
+        history.record(ipns_name, schema.is_some())?;
+    }
+    let metadata = parse_folder_metadata(&json)?;
+    if schema.is_some_and(|schema| schema != metadata.version) {
+        return Err(FolderError::SchemaVersionMismatch);
+    }
+    Ok(metadata)
+}
+
+/// Encrypt file metadata into an envelope of `format`, which in v2 is bound
+/// to the file's `ipns_name`.
+///
+/// Uses the parent folder's folderKey. Returns the envelope JSON bytes.
+pub fn encrypt_file_metadata_envelope(
+    metadata: &FileMetadata,
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    format: MetadataFormat,
+) -> Result<Vec<u8>, FolderError> {
+    let json = Zeroizing::new(
+        serde_json::to_vec(metadata).map_err(|_| FolderError::SerializationFailed)?,
+    );
+    seal_metadata_envelope(
+        &json,
+        folder_key,
+        MetadataObjectType::File,
+        &metadata.version,
+        ipns_name,
+        format,
+    )
+}
+
+/// Decrypt a file metadata envelope fetched from the file's `ipns_name`.
+///
+/// Uses the parent folder's folderKey. Legacy envelopes are still accepted.
+pub fn decrypt_file_metadata_envelope(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+) -> Result<FileMetadata, FolderError> {
+    open_file_metadata_envelope(envelope_bytes, folder_key, ipns_name, None)
+}
+
+/// Decrypt a file metadata envelope fetched from the file's `ipns_name`,
+/// rejecting a legacy envelope if `history` has seen the file in v2.
+pub fn decrypt_file_metadata_envelope_strict(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    history: &EnvelopeHistory,
+) -> Result<FileMetadata, FolderError> {
+    open_file_metadata_envelope(envelope_bytes, folder_key, ipns_name, Some(history))
+}
+
+fn open_file_metadata_envelope(
+    envelope_bytes: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    history: Option<&EnvelopeHistory>,
+) -> Result<FileMetadata, FolderError> {
+    let (json, schema) =
+        open_metadata_envelope(envelope_bytes, folder_key, MetadataObjectType::File, ipns_name)?;
+    if let Some(history) = history {
+        history.record(ipns_name, schema.is_some())?;
+    }
+    let metadata: FileMetadata =
+        serde_json::from_slice(&json).map_err(|_| FolderError::DeserializationFailed)?;
+    if schema.is_some_and(|schema| schema != metadata.version) {
+        return Err(FolderError::SchemaVersionMismatch);
+    }
+    Ok(metadata)
+}
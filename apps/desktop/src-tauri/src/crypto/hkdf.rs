@@ -16,9 +16,10 @@ use ed25519_dalek::SigningKey;
 use hkdf::Hkdf;
 use sha2::Sha256;
 use thiserror::Error;
-use zeroize::{Zeroize, Zeroizing};
+use zeroize::Zeroize;
 
 use super::ipns;
+use super::keys::{IpnsSigningKey, LocalStateKey, Secp256k1SecretKey};
 
 /// Common HKDF salt for all CipherBox derivations.
 const HKDF_SALT: &[u8] = b"CipherBox-v1";
@@ -29,6 +30,9 @@ const VAULT_HKDF_INFO: &[u8] = b"cipherbox-vault-ipns-v1";
 /// HKDF info for device registry IPNS keypair derivation.
 const REGISTRY_HKDF_INFO: &[u8] = b"cipherbox-device-registry-ipns-v1";
 
+/// HKDF info for the key sealing device-local state.
+const LOCAL_STATE_HKDF_INFO: &[u8] = b"cipherbox-desktop-local-state-v1";
+
 /// HKDF info prefix for per-file IPNS keypair derivation.
 const FILE_HKDF_INFO_PREFIX: &str = "cipherbox-file-ipns-v1:";
 
@@ -51,11 +55,11 @@ pub enum HkdfError {
 ///
 /// All three public functions follow the same pattern and delegate here.
 fn derive_ipns_keypair(
-    user_private_key: &[u8; 32],
+    user_private_key: &Secp256k1SecretKey,
     info: &[u8],
-) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>, String), HkdfError> {
+) -> Result<(IpnsSigningKey, Vec<u8>, String), HkdfError> {
     // 1. HKDF-SHA256: extract + expand
-    let hk = Hkdf::<Sha256>::new(Some(HKDF_SALT), user_private_key);
+    let hk = Hkdf::<Sha256>::new(Some(HKDF_SALT), user_private_key.as_bytes());
     let mut okm = [0u8; 32];
     hk.expand(info, &mut okm)
         .map_err(|_| HkdfError::DerivationFailed)?;
@@ -65,7 +69,7 @@ fn derive_ipns_keypair(
     okm.zeroize();
     let verifying_key = signing_key.verifying_key();
 
-    let private_key = Zeroizing::new(signing_key.to_bytes().to_vec());
+    let private_key = IpnsSigningKey::from_bytes(signing_key.to_bytes());
     let public_key = verifying_key.to_bytes().to_vec();
 
     // 3. Derive IPNS name from public key
@@ -84,8 +88,8 @@ fn derive_ipns_keypair(
 ///
 /// Returns (ed25519_private_key, ed25519_public_key, ipns_name).
 pub fn derive_vault_ipns_keypair(
-    user_private_key: &[u8; 32],
-) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>, String), HkdfError> {
+    user_private_key: &Secp256k1SecretKey,
+) -> Result<(IpnsSigningKey, Vec<u8>, String), HkdfError> {
     derive_ipns_keypair(user_private_key, VAULT_HKDF_INFO)
 }
 
@@ -95,9 +99,9 @@ pub fn derive_vault_ipns_keypair(
 ///
 /// Returns (ed25519_private_key, ed25519_public_key, ipns_name).
 pub fn derive_file_ipns_keypair(
-    user_private_key: &[u8; 32],
+    user_private_key: &Secp256k1SecretKey,
     file_id: &str,
-) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>, String), HkdfError> {
+) -> Result<(IpnsSigningKey, Vec<u8>, String), HkdfError> {
     if file_id.len() < MIN_FILE_ID_LENGTH {
         return Err(HkdfError::InvalidFileId);
     }
@@ -112,7 +116,21 @@ pub fn derive_file_ipns_keypair(
 ///
 /// Returns (ed25519_private_key, ed25519_public_key, ipns_name).
 pub fn derive_registry_ipns_keypair(
-    user_private_key: &[u8; 32],
-) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>, String), HkdfError> {
+    user_private_key: &Secp256k1SecretKey,
+) -> Result<(IpnsSigningKey, Vec<u8>, String), HkdfError> {
     derive_ipns_keypair(user_private_key, REGISTRY_HKDF_INFO)
 }
+
+/// Derive the AES-256 key sealing device-local state (desktop only).
+///
+/// Uses HKDF info "cipherbox-desktop-local-state-v1". Any device with the
+/// user's key derives the same key, but the sealed state never leaves the device.
+pub fn derive_local_state_key(
+    user_private_key: &Secp256k1SecretKey,
+) -> Result<LocalStateKey, HkdfError> {
+    let hk = Hkdf::<Sha256>::new(Some(HKDF_SALT), user_private_key.as_bytes());
+    let mut okm = [0u8; 32];
+    hk.expand(LOCAL_STATE_HKDF_INFO, &mut okm)
+        .map_err(|_| HkdfError::DerivationFailed)?;
+    Ok(LocalStateKey::from_bytes(okm))
+}
@@ -4,13 +4,15 @@
 //! - CBOR-encoded data field with V2 signature
 //! - Protobuf-encoded IpnsEntry for marshaling
 //! - CIDv1 base36 IPNS name derivation
+//! - Unmarshaling and V2 signature/validity verification of fetched records
 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use ciborium::Value as CborValue;
 use thiserror::Error;
 
-use super::ed25519::{get_public_key, sign_ed25519};
+use super::ed25519::{get_public_key, sign_ed25519, verify_ed25519};
+use super::keys::IpnsSigningKey;
 
 /// IPNS signature prefix per IPFS spec: "ipns-signature:".
 const IPNS_SIGNATURE_PREFIX: &[u8] = b"ipns-signature:";
@@ -19,6 +21,15 @@ const IPNS_SIGNATURE_PREFIX: &[u8] = b"ipns-signature:";
 /// This matches the ipns npm package default.
 const DEFAULT_TTL_NS: u64 = 300_000_000_000;
 
+/// Maximum accepted size of a marshaled IPNS record (10 KiB per IPNS spec).
+pub const MAX_IPNS_RECORD_SIZE: usize = 10 * 1024;
+
+/// CID multicodec for libp2p public keys.
+const LIBP2P_KEY_CODEC: u64 = 0x72;
+
+/// libp2p KeyType enum value for Ed25519.
+const LIBP2P_KEY_TYPE_ED25519: u64 = 1;
+
 #[derive(Debug, Error)]
 pub enum IpnsError {
     #[error("IPNS record creation failed")]
@@ -35,6 +46,14 @@ pub enum IpnsError {
     CborEncodingFailed,
     #[error("Signing failed")]
     SigningFailed,
+    #[error("IPNS record unmarshaling failed")]
+    UnmarshalingFailed,
+    #[error("Invalid IPNS name")]
+    InvalidName,
+    #[error("IPNS record signature verification failed")]
+    SignatureVerificationFailed,
+    #[error("IPNS record expired")]
+    RecordExpired,
 }
 
 /// IPNS record structure matching the TypeScript IPNSRecord type.
@@ -153,7 +172,7 @@ fn civil_from_days(days: i64) -> (i64, u32, u32) {
 ///
 /// Per IPNS spec, V1 signature is over: value_bytes + validity_bytes + varint(validityType)
 fn compute_v1_signature(
-    ed25519_private_key: &[u8; 32],
+    ed25519_private_key: &IpnsSigningKey,
     value: &str,
     validity: &str,
 ) -> Result<Vec<u8>, IpnsError> {
@@ -163,34 +182,34 @@ fn compute_v1_signature(
     // ValidityType 0 as varint = single byte 0x00
     data_to_sign.push(0x00);
 
-    sign_ed25519(&data_to_sign, ed25519_private_key).map_err(|_| IpnsError::SigningFailed)
+    sign_ed25519(&data_to_sign, ed25519_private_key.as_bytes()).map_err(|_| IpnsError::SigningFailed)
 }
 
 /// Compute the V2 signature.
 ///
 /// Per IPNS spec, V2 signature is over: "ipns-signature:" + cbor_data
 fn compute_v2_signature(
-    ed25519_private_key: &[u8; 32],
+    ed25519_private_key: &IpnsSigningKey,
     cbor_data: &[u8],
 ) -> Result<Vec<u8>, IpnsError> {
     let mut data_to_sign = Vec::with_capacity(IPNS_SIGNATURE_PREFIX.len() + cbor_data.len());
     data_to_sign.extend_from_slice(IPNS_SIGNATURE_PREFIX);
     data_to_sign.extend_from_slice(cbor_data);
 
-    sign_ed25519(&data_to_sign, ed25519_private_key).map_err(|_| IpnsError::SigningFailed)
+    sign_ed25519(&data_to_sign, ed25519_private_key.as_bytes()).map_err(|_| IpnsError::SigningFailed)
 }
 
 /// Create an IPNS record signed with the given Ed25519 private key.
 ///
 /// Matches the TypeScript `createIpnsRecord` with `v1Compatible: true`.
 pub fn create_ipns_record(
-    ed25519_private_key: &[u8; 32],
+    ed25519_private_key: &IpnsSigningKey,
     value: &str,
     sequence_number: u64,
     lifetime_ms: u64,
 ) -> Result<IpnsRecord, IpnsError> {
     // Derive public key
-    let public_key = get_public_key(ed25519_private_key).map_err(|_| IpnsError::InvalidPrivateKey)?;
+    let public_key = get_public_key(ed25519_private_key.as_bytes()).map_err(|_| IpnsError::InvalidPrivateKey)?;
 
     // Compute validity timestamp
     let now = SystemTime::now();
@@ -406,3 +425,327 @@ fn encode_base36(data: &[u8]) -> String {
     result.reverse();
     String::from_utf8(result).unwrap_or_default()
 }
+
+/// Decode a lowercase base36 string into bytes (inverse of `encode_base36`).
+fn decode_base36(encoded: &str) -> Result<Vec<u8>, IpnsError> {
+    let leading_zeros = encoded.bytes().take_while(|&b| b == b'0').count();
+
+    // Big-endian base-256 accumulator, multiplied by 36 per input digit
+    let mut num: Vec<u8> = Vec::new();
+    for c in encoded.bytes().skip(leading_zeros) {
+        let digit = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'z' => c - b'a' + 10,
+            _ => return Err(IpnsError::InvalidName),
+        } as u32;
+
+        let mut carry = digit;
+        for byte in num.iter_mut().rev() {
+            let acc = (*byte as u32) * 36 + carry;
+            *byte = (acc & 0xff) as u8;
+            carry = acc >> 8;
+        }
+        while carry > 0 {
+            num.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let mut result = vec![0u8; leading_zeros];
+    result.extend_from_slice(&num);
+    Ok(result)
+}
+
+/// Decode a varint (protobuf LEB128) at `*pos`, advancing `*pos`.
+fn decode_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// A single decoded protobuf field value.
+enum ProtoField<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Decode a protobuf message into `(field_number, value)` pairs.
+///
+/// Only varint (0) and length-delimited (2) wire types are used by IpnsEntry
+/// and libp2p PublicKey; anything else is rejected.
+fn decode_proto_fields(buf: &[u8]) -> Option<Vec<(u64, ProtoField<'_>)>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let tag = decode_varint(buf, &mut pos)?;
+        let field_number = tag >> 3;
+        match tag & 0x07 {
+            0 => fields.push((field_number, ProtoField::Varint(decode_varint(buf, &mut pos)?))),
+            2 => {
+                let len = decode_varint(buf, &mut pos)? as usize;
+                let end = pos.checked_add(len).filter(|&end| end <= buf.len())?;
+                fields.push((field_number, ProtoField::Bytes(&buf[pos..end])));
+                pos = end;
+            }
+            _ => return None,
+        }
+    }
+    Some(fields)
+}
+
+/// Extract the 32-byte Ed25519 key from a libp2p PublicKey protobuf.
+fn decode_libp2p_public_key(buf: &[u8]) -> Result<[u8; 32], IpnsError> {
+    let fields = decode_proto_fields(buf).ok_or(IpnsError::InvalidPublicKey)?;
+
+    let mut key_type = None;
+    let mut data = None;
+    for (field_number, value) in fields {
+        match (field_number, value) {
+            (1, ProtoField::Varint(v)) => key_type = Some(v),
+            (2, ProtoField::Bytes(b)) => data = Some(b),
+            _ => {}
+        }
+    }
+
+    if key_type != Some(LIBP2P_KEY_TYPE_ED25519) {
+        return Err(IpnsError::InvalidPublicKey);
+    }
+    data.and_then(|d| d.try_into().ok())
+        .ok_or(IpnsError::InvalidPublicKey)
+}
+
+/// Decode an IPNS name (k51... CIDv1 base36) back to its Ed25519 public key.
+///
+/// Inverse of `derive_ipns_name`: base36 -> CIDv1 (libp2p-key codec) ->
+/// identity multihash -> libp2p PublicKey protobuf -> 32-byte key.
+pub fn decode_ipns_name(ipns_name: &str) -> Result<[u8; 32], IpnsError> {
+    let encoded = ipns_name.strip_prefix('k').ok_or(IpnsError::InvalidName)?;
+    let cid_bytes = decode_base36(encoded)?;
+
+    let mut pos = 0;
+    let version = decode_varint(&cid_bytes, &mut pos).ok_or(IpnsError::InvalidName)?;
+    let codec = decode_varint(&cid_bytes, &mut pos).ok_or(IpnsError::InvalidName)?;
+    if version != 1 || codec != LIBP2P_KEY_CODEC {
+        return Err(IpnsError::InvalidName);
+    }
+
+    // Identity multihash: code 0x00 + varint(len) + data
+    let hash_code = decode_varint(&cid_bytes, &mut pos).ok_or(IpnsError::InvalidName)?;
+    let len = decode_varint(&cid_bytes, &mut pos).ok_or(IpnsError::InvalidName)? as usize;
+    if hash_code != 0x00 || pos + len != cid_bytes.len() {
+        return Err(IpnsError::InvalidName);
+    }
+
+    decode_libp2p_public_key(&cid_bytes[pos..]).map_err(|_| IpnsError::InvalidName)
+}
+
+/// Fields carried in the signed CBOR `data` of a V2 record.
+struct CborRecordData {
+    value: Vec<u8>,
+    validity: Vec<u8>,
+    validity_type: u64,
+    sequence: u64,
+    ttl: u64,
+}
+
+/// Parse the CBOR `data` field of an IPNS record.
+fn parse_cbor_data(data: &[u8]) -> Result<CborRecordData, IpnsError> {
+    let value: CborValue =
+        ciborium::from_reader(data).map_err(|_| IpnsError::UnmarshalingFailed)?;
+    let entries = match value {
+        CborValue::Map(entries) => entries,
+        _ => return Err(IpnsError::UnmarshalingFailed),
+    };
+
+    let get = |key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| matches!(k, CborValue::Text(t) if t == key))
+            .map(|(_, v)| v)
+    };
+    let get_bytes = |key: &str| match get(key) {
+        Some(CborValue::Bytes(b)) => Ok(b.clone()),
+        _ => Err(IpnsError::UnmarshalingFailed),
+    };
+    let get_u64 = |key: &str| match get(key) {
+        Some(CborValue::Integer(i)) => {
+            u64::try_from(*i).map_err(|_| IpnsError::UnmarshalingFailed)
+        }
+        _ => Err(IpnsError::UnmarshalingFailed),
+    };
+
+    Ok(CborRecordData {
+        value: get_bytes("Value")?,
+        validity: get_bytes("Validity")?,
+        validity_type: get_u64("ValidityType")?,
+        sequence: get_u64("Sequence")?,
+        ttl: get_u64("TTL")?,
+    })
+}
+
+/// Unmarshal a protobuf IpnsEntry into an `IpnsRecord`.
+///
+/// Record fields are taken from the signed CBOR `data` (V2). Legacy V1
+/// protobuf fields, when present, must agree with it. Records without
+/// `data` or a V2 signature are rejected. `public_key` is empty when the
+/// record does not embed one (Ed25519 keys are inlined in the name).
+pub fn unmarshal_ipns_record(bytes: &[u8]) -> Result<IpnsRecord, IpnsError> {
+    if bytes.len() > MAX_IPNS_RECORD_SIZE {
+        return Err(IpnsError::UnmarshalingFailed);
+    }
+    let fields = decode_proto_fields(bytes).ok_or(IpnsError::UnmarshalingFailed)?;
+
+    let mut pb_value = None;
+    let mut pb_validity = None;
+    let mut pb_validity_type = None;
+    let mut pb_sequence = None;
+    let mut pb_ttl = None;
+    let mut signature_v1 = Vec::new();
+    let mut signature_v2 = Vec::new();
+    let mut public_key = Vec::new();
+    let mut data = Vec::new();
+
+    for (field_number, value) in fields {
+        match (field_number, value) {
+            (1, ProtoField::Bytes(b)) => pb_value = Some(b),
+            (2, ProtoField::Bytes(b)) => signature_v1 = b.to_vec(),
+            (3, ProtoField::Varint(v)) => pb_validity_type = Some(v),
+            (4, ProtoField::Bytes(b)) => pb_validity = Some(b),
+            (5, ProtoField::Varint(v)) => pb_sequence = Some(v),
+            (6, ProtoField::Varint(v)) => pb_ttl = Some(v),
+            (7, ProtoField::Bytes(b)) => public_key = decode_libp2p_public_key(b)?.to_vec(),
+            (8, ProtoField::Bytes(b)) => signature_v2 = b.to_vec(),
+            (9, ProtoField::Bytes(b)) => data = b.to_vec(),
+            // Unknown fields are ignored; known fields with the wrong wire type are not
+            (1..=9, _) => return Err(IpnsError::UnmarshalingFailed),
+            _ => {}
+        }
+    }
+
+    if data.is_empty() || signature_v2.is_empty() {
+        return Err(IpnsError::UnmarshalingFailed);
+    }
+    let cbor = parse_cbor_data(&data)?;
+
+    let mismatch = pb_value.is_some_and(|v| v != cbor.value.as_slice())
+        || pb_validity.is_some_and(|v| v != cbor.validity.as_slice())
+        || pb_validity_type.is_some_and(|v| v != cbor.validity_type)
+        || pb_sequence.is_some_and(|v| v != cbor.sequence)
+        || pb_ttl.is_some_and(|v| v != cbor.ttl);
+    if mismatch {
+        return Err(IpnsError::UnmarshalingFailed);
+    }
+
+    Ok(IpnsRecord {
+        value: String::from_utf8(cbor.value).map_err(|_| IpnsError::UnmarshalingFailed)?,
+        validity: String::from_utf8(cbor.validity).map_err(|_| IpnsError::UnmarshalingFailed)?,
+        validity_type: u32::try_from(cbor.validity_type).map_err(|_| IpnsError::UnmarshalingFailed)?,
+        sequence: cbor.sequence,
+        ttl: cbor.ttl,
+        signature_v1,
+        signature_v2,
+        data,
+        public_key,
+    })
+}
+
+/// Convert (year, month, day) to days since Unix epoch.
+/// Algorithm from Howard Hinnant's days_from_civil (inverse of `civil_from_days`).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as u64; // [0, 399]
+    let mp = if month > 2 { month - 3 } else { month + 9 } as u64; // [0, 11]
+    let doy = (153 * mp + 2) / 5 + day as u64 - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+    era * 146097 + doe as i64 - 719468
+}
+
+/// Parse an RFC3339 UTC validity timestamp ("2026-02-08T23:31:12.138000000Z").
+///
+/// Accepts any fractional-second precision (including none); only the `Z`
+/// offset is supported, matching what IPNS implementations emit.
+fn parse_validity_timestamp(validity: &str) -> Result<SystemTime, IpnsError> {
+    let invalid = || IpnsError::UnmarshalingFailed;
+    let body = validity.strip_suffix('Z').ok_or_else(invalid)?;
+    let (date, time) = body.split_once('T').ok_or_else(invalid)?;
+    let (time, fraction) = match time.split_once('.') {
+        Some((t, f)) => (t, f),
+        None => (time, ""),
+    };
+
+    let num = |s: &str| s.parse::<u64>().map_err(|_| invalid());
+    let date_parts: Vec<&str> = date.split('-').collect();
+    let time_parts: Vec<&str> = time.split(':').collect();
+    if date_parts.len() != 3 || time_parts.len() != 3 || fraction.len() > 9 {
+        return Err(invalid());
+    }
+
+    let (year, month, day) = (num(date_parts[0])?, num(date_parts[1])?, num(date_parts[2])?);
+    let (hours, minutes, seconds) = (num(time_parts[0])?, num(time_parts[1])?, num(time_parts[2])?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return Err(invalid());
+    }
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        num(fraction)? * 10u64.pow(9 - fraction.len() as u32)
+    };
+
+    let days = days_from_civil(year as i64, month as u32, day as u32);
+    if days < 0 {
+        return Err(invalid());
+    }
+    let secs = days as u64 * 86400 + hours * 3600 + minutes * 60 + seconds;
+    Ok(UNIX_EPOCH + Duration::new(secs, nanos as u32))
+}
+
+/// Verify an unmarshaled IPNS record against the name it was fetched for.
+///
+/// Checks, per the IPNS spec:
+/// 1. The Ed25519 public key decoded from `ipns_name` matches the embedded key (if any)
+/// 2. The V2 signature over "ipns-signature:" + CBOR data is valid for that key
+/// 3. The record is an EOL record whose validity is later than `now`
+pub fn verify_ipns_record(
+    record: &IpnsRecord,
+    ipns_name: &str,
+    now: SystemTime,
+) -> Result<(), IpnsError> {
+    let public_key = decode_ipns_name(ipns_name)?;
+    if !record.public_key.is_empty() && record.public_key != public_key {
+        return Err(IpnsError::InvalidPublicKey);
+    }
+
+    let mut signed = Vec::with_capacity(IPNS_SIGNATURE_PREFIX.len() + record.data.len());
+    signed.extend_from_slice(IPNS_SIGNATURE_PREFIX);
+    signed.extend_from_slice(&record.data);
+    if !verify_ed25519(&signed, &record.signature_v2, &public_key) {
+        return Err(IpnsError::SignatureVerificationFailed);
+    }
+
+    if record.validity_type != 0 {
+        return Err(IpnsError::UnmarshalingFailed);
+    }
+    if parse_validity_timestamp(&record.validity)? <= now {
+        return Err(IpnsError::RecordExpired);
+    }
+
+    Ok(())
+}
+
+/// Unmarshal and fully verify a marshaled IPNS record fetched for `ipns_name`.
+///
+/// Convenience wrapper around `unmarshal_ipns_record` + `verify_ipns_record`
+/// using the current time. Only a record returned from here may be trusted.
+pub fn validate_ipns_record(bytes: &[u8], ipns_name: &str) -> Result<IpnsRecord, IpnsError> {
+    let record = unmarshal_ipns_record(bytes)?;
+    verify_ipns_record(&record, ipns_name, SystemTime::now())?;
+    Ok(record)
+}
@@ -0,0 +1,197 @@
+//! Typed secret keys.
+//!
+//! Each kind of key is its own fixed-size type, so passing a file key where a
+//! folder key is expected is a compile error rather than a runtime
+//! "Invalid key length". All key types zeroize on drop and redact their bytes
+//! in `Debug` output. Key bytes live in a `SecureBuffer`, so they are also
+//! locked in RAM where the platform and `RLIMIT_MEMLOCK` allow.
+
+use std::fmt;
+
+use rand::RngCore;
+use thiserror::Error;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use super::secure_mem::SecureBuffer;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    #[error("Invalid {kind} length: {actual} (expected {expected})")]
+    InvalidLength {
+        kind: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// Common interface of the typed secret keys.
+pub trait SecretKey: Sized {
+    /// Key length in bytes.
+    const LEN: usize;
+
+    /// Build the key from a slice, checking its length.
+    fn from_slice(bytes: &[u8]) -> Result<Self, KeyError>;
+
+    /// Borrow the raw key bytes.
+    fn as_slice(&self) -> &[u8];
+}
+
+macro_rules! secret_key {
+    ($(#[$meta:meta])* $name:ident, $len:expr, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        pub struct $name(SecureBuffer);
+
+        impl $name {
+            /// Wrap raw key bytes, zeroizing the caller's copy.
+            pub fn from_bytes(mut bytes: [u8; $len]) -> Self {
+                let key = Self(SecureBuffer::from_slice(&bytes));
+                bytes.zeroize();
+                key
+            }
+
+            /// Borrow the raw key bytes.
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                self.0
+                    .as_slice()
+                    .try_into()
+                    .expect("key length is checked at construction")
+            }
+        }
+
+        // SecureBuffer zeroizes on drop.
+        impl ZeroizeOnDrop for $name {}
+
+        impl SecretKey for $name {
+            const LEN: usize = $len;
+
+            fn from_slice(bytes: &[u8]) -> Result<Self, KeyError> {
+                if bytes.len() != $len {
+                    return Err(KeyError::InvalidLength {
+                        kind: $kind,
+                        expected: $len,
+                        actual: bytes.len(),
+                    });
+                }
+                Ok(Self(SecureBuffer::from_slice(bytes)))
+            }
+
+            fn as_slice(&self) -> &[u8] {
+                self.0.as_slice()
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = KeyError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, KeyError> {
+                <Self as SecretKey>::from_slice(bytes)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(concat!(stringify!($name), "([REDACTED])"))
+            }
+        }
+    };
+}
+
+secret_key!(
+    /// User's 32-byte secp256k1 private key (ECIES unwrap, HKDF root).
+    Secp256k1SecretKey,
+    32,
+    "secp256k1 private key"
+);
+
+secret_key!(
+    /// 32-byte AES-256 key encrypting a folder's metadata (and its files' metadata).
+    FolderKey,
+    32,
+    "folder key"
+);
+
+secret_key!(
+    /// 32-byte AES-256 key encrypting a single file's content.
+    FileKey,
+    32,
+    "file key"
+);
+
+secret_key!(
+    /// 32-byte Ed25519 seed used to sign IPNS records.
+    IpnsSigningKey,
+    32,
+    "IPNS signing key"
+);
+
+secret_key!(
+    /// 32-byte AES-256 key sealing state kept on this device (e.g. the vault
+    /// snapshot). Derived from the user's private key, never stored.
+    LocalStateKey,
+    32,
+    "local state key"
+);
+
+secret_key!(
+    /// 2400-byte ML-KEM-768 decapsulation key for the hybrid key wrapping
+    /// format. Random, stored on the vault sealed under the `VaultSecret` and
+    /// ECIES-wrapped.
+    MlKemSecretKey,
+    2400,
+    "ML-KEM-768 private key"
+);
+
+secret_key!(
+    /// Random 32-byte secret sealing the ML-KEM-768 private key, so that
+    /// breaking secp256k1 alone does not expose it. Kept in the OS keychain,
+    /// never sent to the server.
+    VaultSecret,
+    32,
+    "vault secret"
+);
+
+/// Fill a fresh 32-byte buffer from the OS RNG.
+fn random_32() -> SecureBuffer {
+    let mut bytes = SecureBuffer::new(vec![0u8; 32]);
+    rand::rngs::OsRng.fill_bytes(bytes.as_mut_slice());
+    bytes
+}
+
+impl FolderKey {
+    /// Generate a random folder key.
+    pub fn generate() -> Self {
+        Self(random_32())
+    }
+}
+
+impl FileKey {
+    /// Generate a random file key.
+    pub fn generate() -> Self {
+        Self(random_32())
+    }
+}
+
+impl VaultSecret {
+    /// Generate a random vault secret.
+    pub fn generate() -> Self {
+        Self(random_32())
+    }
+}
+
+impl IpnsSigningKey {
+    /// Generate a random Ed25519 IPNS keypair.
+    ///
+    /// Returns (signing_key, 32-byte public key).
+    pub fn generate() -> (Self, Vec<u8>) {
+        let (public_key, private_key) = super::ed25519::generate_ed25519_keypair();
+        let private_key = Zeroizing::new(private_key);
+        let key = Self::from_slice(&private_key).expect("Ed25519 private key is 32 bytes");
+        (key, public_key)
+    }
+
+    /// Derive the 32-byte Ed25519 public key.
+    pub fn public_key(&self) -> Vec<u8> {
+        super::ed25519::get_public_key(self.as_bytes()).expect("Ed25519 private key is 32 bytes")
+    }
+}
@@ -5,11 +5,17 @@
 
 pub mod aes;
 pub mod aes_ctr;
+pub mod aes_gcm_seg;
+pub mod chunked;
 pub mod ecies;
 pub mod ed25519;
+pub mod fastcdc;
 pub mod folder;
 pub mod hkdf;
 pub mod ipns;
+pub mod keys;
+pub mod padding;
+pub mod secure_mem;
 pub mod utils;
 
 #[cfg(test)]
@@ -17,8 +23,15 @@ mod tests;
 
 // Re-export primary functions for convenience
 pub use aes::{decrypt_aes_gcm, encrypt_aes_gcm, seal_aes_gcm, unseal_aes_gcm};
-pub use ecies::{unwrap_key, wrap_key};
+pub use ecies::{rewrap_key, unwrap_key, unwrap_secret_key, wrap_key};
 pub use ed25519::{generate_ed25519_keypair, get_public_key, sign_ed25519, verify_ed25519};
-pub use folder::{decrypt_folder_metadata, encrypt_folder_metadata, FolderMetadata};
-pub use ipns::{create_ipns_record, derive_ipns_name, marshal_ipns_record, IpnsRecord};
+pub use folder::{
+    decrypt_folder_metadata, decrypt_folder_metadata_envelope, encrypt_folder_metadata,
+    encrypt_folder_metadata_envelope, FolderMetadata,
+};
+pub use ipns::{
+    create_ipns_record, decode_ipns_name, derive_ipns_name, marshal_ipns_record,
+    unmarshal_ipns_record, validate_ipns_record, IpnsRecord,
+};
+pub use keys::{FileKey, FolderKey, IpnsSigningKey, Secp256k1SecretKey, SecretKey};
 pub use utils::{clear_bytes, generate_file_key, generate_iv, generate_random_bytes};
@@ -0,0 +1,130 @@
+//! Length padding to hide plaintext sizes from the storage provider.
+//!
+//! AES-GCM ciphertext is exactly as long as the plaintext plus a constant, so
+//! without padding the provider learns every file's exact size and every
+//! folder's child count, which is often enough to guess file types. Padding
+//! is opt-in and rounds lengths up to a bucket:
+//!
+//! - Padmé ("padme"): at most ~12% overhead, leaks O(log log n) bits of the size.
+//! - Power of two ("pow2"): at most 100% overhead, leaks O(log n) bits.
+//!
+//! File content is padded ISO/IEC 7816-4 style (`0x80` then zero bytes) and
+//! the scheme is recorded in `FileMetadata.padding`. Metadata JSON is padded
+//! with trailing spaces, which is still valid JSON, so readers need no
+//! unpadding step; v2 envelopes record the scheme in their `padding` field.
+
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+#[derive(Debug, Error)]
+pub enum PaddingError {
+    #[error("Unknown padding scheme: {0}")]
+    UnknownScheme(String),
+    #[error("Invalid padding")]
+    InvalidPadding,
+}
+
+/// Length padding scheme.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PaddingScheme {
+    /// No padding (ciphertext length reveals plaintext length).
+    #[default]
+    None,
+    /// Padmé buckets.
+    Padme,
+    /// Next power of two.
+    PowerOfTwo,
+}
+
+impl PaddingScheme {
+    /// Parse a scheme name as used in metadata and configuration.
+    ///
+    /// Accepts "none" (or an empty string), "padme" and "pow2".
+    pub fn from_name(name: &str) -> Result<Self, PaddingError> {
+        match name {
+            "" | "none" => Ok(PaddingScheme::None),
+            "padme" => Ok(PaddingScheme::Padme),
+            "pow2" => Ok(PaddingScheme::PowerOfTwo),
+            other => Err(PaddingError::UnknownScheme(other.to_string())),
+        }
+    }
+
+    /// Scheme name as used in metadata and configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaddingScheme::None => "none",
+            PaddingScheme::Padme => "padme",
+            PaddingScheme::PowerOfTwo => "pow2",
+        }
+    }
+
+    /// Value of the metadata `padding` field (`None` when unpadded).
+    pub fn metadata_value(&self) -> Option<String> {
+        match self {
+            PaddingScheme::None => None,
+            scheme => Some(scheme.as_str().to_string()),
+        }
+    }
+
+    /// Length `len` is rounded up to under this scheme.
+    pub fn padded_len(&self, len: usize) -> usize {
+        match self {
+            PaddingScheme::None => len,
+            PaddingScheme::Padme => padme_len(len),
+            PaddingScheme::PowerOfTwo => len.next_power_of_two(),
+        }
+    }
+}
+
+/// Padmé: keep the top `floor(log2(E)) + 1` bits of the length, where
+/// `E = floor(log2(len))`, and round the remaining low bits up.
+fn padme_len(len: usize) -> usize {
+    if len < 2 {
+        return len;
+    }
+    let e = usize::BITS - 1 - len.leading_zeros();
+    let s = u32::BITS - e.leading_zeros();
+    let mask = (1usize << (e - s)) - 1;
+    (len + mask) & !mask
+}
+
+/// Pad serialized JSON with trailing spaces up to the scheme's bucket.
+pub fn pad_json(json: &[u8], scheme: PaddingScheme) -> Zeroizing<Vec<u8>> {
+    let mut padded = Zeroizing::new(Vec::with_capacity(scheme.padded_len(json.len())));
+    padded.extend_from_slice(json);
+    padded.resize(scheme.padded_len(json.len()), b' ');
+    padded
+}
+
+/// Pad file content as `data || 0x80 || 0x00...` up to the scheme's bucket.
+///
+/// Always adds at least the `0x80` marker, so `unpad_content` can strip it.
+pub fn pad_content(data: &[u8], scheme: PaddingScheme) -> Zeroizing<Vec<u8>> {
+    let tail = content_padding_tail(data.len(), scheme);
+    let mut padded = Zeroizing::new(Vec::with_capacity(data.len() + tail.len()));
+    padded.extend_from_slice(data);
+    padded.extend_from_slice(&tail);
+    padded
+}
+
+/// The bytes `pad_content` appends to `len` bytes of content.
+///
+/// Lets content that is streamed rather than held in memory be padded.
+pub fn content_padding_tail(len: usize, scheme: PaddingScheme) -> Vec<u8> {
+    let mut tail = vec![0u8; scheme.padded_len(len + 1) - len];
+    tail[0] = 0x80;
+    tail
+}
+
+/// Strip padding added by `pad_content`, in place.
+pub fn unpad_content(data: &mut Vec<u8>) -> Result<(), PaddingError> {
+    let marker = data
+        .iter()
+        .rposition(|&b| b != 0)
+        .ok_or(PaddingError::InvalidPadding)?;
+    if data[marker] != 0x80 {
+        return Err(PaddingError::InvalidPadding);
+    }
+    data.truncate(marker);
+    Ok(())
+}
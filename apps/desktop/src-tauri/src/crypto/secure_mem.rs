@@ -0,0 +1,208 @@
+//! Locked (non-swappable) memory for decrypted key material and plaintext.
+//!
+//! On Linux, `SecureBuffer` pins its pages in RAM with `mlock` and excludes
+//! them from core dumps with `madvise(MADV_DONTDUMP)`. Locks are reference
+//! counted per page, because small allocations share pages and `munlock` is
+//! not nested: a page is only unlocked once no live buffer still covers it.
+//!
+//! Locking degrades gracefully. When `RLIMIT_MEMLOCK` is exhausted the buffer
+//! is still usable and still zeroized on drop, just swappable; the first
+//! failure is logged as a warning. On other platforms buffers are never locked.
+
+use std::collections::BTreeMap;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use zeroize::Zeroize;
+
+/// Live lock count per page start address.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// Set once a lock attempt has failed (memory is no longer fully protected).
+#[cfg(target_os = "linux")]
+static DEGRADED: AtomicBool = AtomicBool::new(false);
+
+/// Heap buffer that is zeroized on drop and, where possible, locked in RAM.
+///
+/// Takes ownership of an existing `Vec<u8>` without copying it, so plaintext
+/// never exists in an unlocked allocation created by this type.
+pub struct SecureBuffer {
+    data: Vec<u8>,
+    locked: bool,
+}
+
+impl SecureBuffer {
+    /// Lock `data`'s allocation in place.
+    pub fn new(data: Vec<u8>) -> Self {
+        let locked = lock_region(data.as_ptr(), data.capacity());
+        Self { data, locked }
+    }
+
+    /// Copy `bytes` into a new locked buffer.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Whether the buffer's pages are currently locked in RAM.
+    #[allow(dead_code)]
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl Clone for SecureBuffer {
+    fn clone(&self) -> Self {
+        Self::from_slice(&self.data)
+    }
+}
+
+impl Drop for SecureBuffer {
+    fn drop(&mut self) {
+        let ptr = self.data.as_ptr();
+        let capacity = self.data.capacity();
+        // Zeroizes the full capacity, not just `len`
+        self.data.zeroize();
+        if self.locked {
+            unlock_region(ptr, capacity);
+        }
+    }
+}
+
+/// Log the process memory-lock limit at startup.
+///
+/// Warns when the limit is below `budget` (e.g. the content cache size), since
+/// buffers beyond the limit will stay swappable.
+pub fn log_memlock_limit(budget: usize) {
+    match memlock_limit() {
+        Some(limit) if limit < budget as u64 => log::warn!(
+            "RLIMIT_MEMLOCK is {} bytes (< {} bytes wanted); decrypted data beyond the limit \
+             will not be locked in RAM and may be written to swap",
+            limit,
+            budget
+        ),
+        Some(limit) if limit == u64::MAX => log::info!("RLIMIT_MEMLOCK: unlimited"),
+        Some(limit) => log::info!("RLIMIT_MEMLOCK: {} bytes", limit),
+        None => log::debug!("Memory locking not supported on this platform"),
+    }
+}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        // SAFETY: sysconf has no memory-safety preconditions.
+        let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if size > 0 {
+            return size as usize;
+        }
+    }
+    4096
+}
+
+/// Page-aligned `[start, end)` covering `len` bytes at `ptr`.
+fn page_bounds(ptr: *const u8, len: usize) -> (usize, usize) {
+    let page = page_size();
+    let start = (ptr as usize) & !(page - 1);
+    let end = (ptr as usize + len).div_ceil(page) * page;
+    (start, end)
+}
+
+#[cfg(target_os = "linux")]
+fn memlock_limit() -> Option<u64> {
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit writes into the provided struct only.
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut rlim) } != 0 {
+        return None;
+    }
+    if rlim.rlim_cur == libc::RLIM_INFINITY {
+        Some(u64::MAX)
+    } else {
+        Some(rlim.rlim_cur)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn memlock_limit() -> Option<u64> {
+    None
+}
+
+/// Lock the pages covering `len` bytes at `ptr`. Returns whether they were locked.
+#[cfg(target_os = "linux")]
+fn lock_region(ptr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+    let (start, end) = page_bounds(ptr, len);
+
+    // SAFETY: the range covers pages of a live allocation owned by the caller.
+    // MADV_DONTDUMP only affects core dumps and is not limited by RLIMIT_MEMLOCK.
+    if unsafe { libc::madvise(start as *mut libc::c_void, end - start, libc::MADV_DONTDUMP) } != 0 {
+        log::debug!(
+            "madvise(MADV_DONTDUMP) failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: as above; mlock on already-locked pages is a no-op.
+    if unsafe { libc::mlock(start as *const libc::c_void, end - start) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if !DEGRADED.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "mlock of {} bytes failed ({}); RLIMIT_MEMLOCK={:?}, {} bytes already locked. \
+                 Continuing without memory locking: decrypted data may be written to swap",
+                end - start,
+                err,
+                memlock_limit(),
+                pages.len() * page_size()
+            );
+        } else {
+            log::debug!("mlock of {} bytes failed: {}", end - start, err);
+        }
+        return false;
+    }
+
+    for page in (start..end).step_by(page_size()) {
+        *pages.entry(page).or_insert(0) += 1;
+    }
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lock_region(_ptr: *const u8, _len: usize) -> bool {
+    false
+}
+
+/// Release one lock reference on the pages covering `len` bytes at `ptr`,
+/// unlocking pages no other buffer still uses.
+fn unlock_region(ptr: *const u8, len: usize) {
+    let (start, end) = page_bounds(ptr, len);
+    let page = page_size();
+    let mut pages = LOCKED_PAGES.lock().unwrap_or_else(|e| e.into_inner());
+    for addr in (start..end).step_by(page) {
+        let Some(count) = pages.get_mut(&addr) else {
+            continue;
+        };
+        *count -= 1;
+        if *count == 0 {
+            pages.remove(&addr);
+            #[cfg(target_os = "linux")]
+            // SAFETY: the page was locked by lock_region and is still mapped
+            // (the owning allocation has not been freed yet).
+            unsafe {
+                libc::munlock(addr as *const libc::c_void, page);
+            }
+        }
+    }
+}
@@ -7,15 +7,25 @@
 
 use super::aes;
 use super::aes_ctr;
+use super::aes_gcm_seg;
+use super::chunked::{self, ChunkError, ChunkManifest};
 use super::ecies;
 use super::ed25519;
+use super::fastcdc;
 use super::folder::{
-    decrypt_file_metadata, decrypt_folder_metadata,
-    encrypt_file_metadata, encrypt_folder_metadata, FileMetadata,
-    FilePointer, FolderChild, FolderEntry, FolderMetadata,
+    decrypt_file_metadata, decrypt_file_metadata_envelope, decrypt_folder_metadata,
+    decrypt_folder_metadata_envelope, encrypt_file_metadata, encrypt_file_metadata_envelope,
+    encrypt_folder_metadata, encrypt_folder_metadata_envelope, EnvelopeHistory, FileMetadata,
+    FilePointer, FolderChild, FolderEntry, FolderError, FolderMetadata, MetadataFormat,
 };
 use super::hkdf;
 use super::ipns;
+use super::keys::{
+    FileKey, FolderKey, IpnsSigningKey, KeyError, MlKemSecretKey, Secp256k1SecretKey, SecretKey,
+    VaultSecret,
+};
+use super::padding::{self, PaddingScheme};
+use super::secure_mem::SecureBuffer;
 use super::utils;
 
 // ============================================================
@@ -201,6 +211,18 @@ fn seal_empty_plaintext() {
     assert!(unsealed.is_empty());
 }
 
+#[test]
+fn seal_with_aad_requires_same_aad() {
+    let key = utils::generate_file_key();
+    let sealed = aes::seal_aes_gcm_with_aad(b"bound", &key, b"context-a").unwrap();
+
+    let opened = aes::unseal_aes_gcm_with_aad(&sealed, &key, b"context-a").unwrap();
+    assert_eq!(opened, b"bound");
+
+    assert!(aes::unseal_aes_gcm_with_aad(&sealed, &key, b"context-b").is_err());
+    assert!(aes::unseal_aes_gcm(&sealed, &key).is_err(), "AAD must not be optional on open");
+}
+
 // ============================================================
 // Ed25519 Cross-Language Test Vectors
 // ============================================================
@@ -332,9 +354,14 @@ const ECIES_TEST_PLAINTEXT: &str =
 const ECIES_TS_WRAPPED: &str =
     "04d8fffb3faadd4a9977c520a6627f3ff4fa0f8350032398bb1bfa7c0bdff2661212743ee8fec655eb145cefc7fb1a6d52f6062f7ce8f16666e080af74685294e1218732428593e85e92dc42580fcc6e0dbae6237813e9961f8fdd8409e0592d7442ac41d85539dac237cc344f1766e41532b7ea90274a83da7e40f4733e21a022";
 
+/// Parse a hex-encoded secp256k1 private key into its typed form.
+fn secp256k1_key(hex_key: &str) -> Secp256k1SecretKey {
+    Secp256k1SecretKey::from_slice(&hex::decode(hex_key).unwrap()).unwrap()
+}
+
 #[test]
 fn ecies_unwrap_typescript_wrapped() {
-    let private_key = hex::decode(ECIES_TEST_PRIVATE_KEY).unwrap();
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
     let wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
     let expected_plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
 
@@ -347,7 +374,7 @@ fn ecies_unwrap_typescript_wrapped() {
 
 #[test]
 fn ecies_roundtrip() {
-    let private_key = hex::decode(ECIES_TEST_PRIVATE_KEY).unwrap();
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
     let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
     let plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
 
@@ -359,7 +386,7 @@ fn ecies_roundtrip() {
 #[test]
 fn ecies_ephemeral_key_produces_different_output() {
     let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
-    let private_key = hex::decode(ECIES_TEST_PRIVATE_KEY).unwrap();
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
     let plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
 
     let wrapped1 = ecies::wrap_key(&plaintext, &public_key).unwrap();
@@ -384,7 +411,7 @@ fn ecies_wrong_private_key_fails() {
     let wrapped = ecies::wrap_key(plaintext, &public_key).unwrap();
 
     // Use a different private key
-    let wrong_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap(); // 32 bytes but wrong key
+    let wrong_key = secp256k1_key(ED25519_TEST_PRIVATE_KEY); // 32 bytes but wrong key
     let result = ecies::unwrap_key(&wrapped, &wrong_key);
     assert!(result.is_err());
 }
@@ -407,14 +434,16 @@ fn ecies_invalid_public_key_prefix_fails() {
 #[test]
 fn ecies_invalid_private_key_size_fails() {
     let short_key = vec![0u8; 16]; // too short
-    let wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
-    let result = ecies::unwrap_key(&wrapped, &short_key);
-    assert!(result.is_err());
+    let result = Secp256k1SecretKey::from_slice(&short_key);
+    assert!(matches!(
+        result,
+        Err(KeyError::InvalidLength { expected: 32, actual: 16, .. })
+    ));
 }
 
 #[test]
 fn ecies_tampered_ciphertext_fails() {
-    let private_key = hex::decode(ECIES_TEST_PRIVATE_KEY).unwrap();
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
     let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
 
     let mut wrapped = ecies::wrap_key(b"test data", &public_key).unwrap();
@@ -425,13 +454,375 @@ fn ecies_tampered_ciphertext_fails() {
     assert!(result.is_err());
 }
 
+// ============================================================
+// Hybrid Key Wrapping Tests
+// ============================================================
+
+/// Random ML-KEM-768 keypair of the ECIES test user (generated once per process).
+fn test_mlkem_keypair() -> &'static (MlKemSecretKey, Vec<u8>) {
+    static KEYPAIR: std::sync::OnceLock<(MlKemSecretKey, Vec<u8>)> = std::sync::OnceLock::new();
+    KEYPAIR.get_or_init(ecies::generate_mlkem_keypair)
+}
+
+/// Wrap `data` in the hybrid format to the ECIES test keypair.
+fn hybrid_wrap_to_test_key(data: &[u8]) -> Vec<u8> {
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    ecies::wrap_key_hybrid(data, &public_key, &test_mlkem_keypair().1).unwrap()
+}
+
+/// Unwrap `wrapped` as the ECIES test user, with their ML-KEM key.
+fn hybrid_unwrap_as_test_key(wrapped: &[u8]) -> Result<Vec<u8>, ecies::EciesError> {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    ecies::unwrap_key_hybrid(wrapped, &private_key, Some(&test_mlkem_keypair().0))
+}
+
+#[test]
+fn hybrid_wrap_roundtrip() {
+    let plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
+
+    let wrapped = hybrid_wrap_to_test_key(&plaintext);
+    assert_eq!(wrapped[0], ecies::HYBRID_WRAP_VERSION);
+
+    let unwrapped = hybrid_unwrap_as_test_key(&wrapped).unwrap();
+    assert_eq!(unwrapped, plaintext);
+}
+
+#[test]
+fn hybrid_unwrap_secret_key_typed() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let folder_key = FolderKey::generate();
+
+    let wrapped = hybrid_wrap_to_test_key(folder_key.as_bytes());
+    let unwrapped: FolderKey =
+        ecies::unwrap_secret_key_hybrid(&wrapped, &private_key, Some(&test_mlkem_keypair().0))
+            .unwrap();
+    assert_eq!(unwrapped.as_bytes(), folder_key.as_bytes());
+}
+
+#[test]
+fn hybrid_and_legacy_formats_both_unwrap() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+
+    let legacy = ecies::wrap_key(b"legacy", &public_key).unwrap();
+    let hybrid = hybrid_wrap_to_test_key(b"hybrid");
+    assert_ne!(legacy[0], ecies::HYBRID_WRAP_VERSION);
+
+    assert_eq!(hybrid_unwrap_as_test_key(&legacy).unwrap(), b"legacy");
+    assert_eq!(hybrid_unwrap_as_test_key(&hybrid).unwrap(), b"hybrid");
+    // Legacy keys also unwrap for users without an ML-KEM key
+    assert_eq!(ecies::unwrap_key_hybrid(&legacy, &private_key, None).unwrap(), b"legacy");
+}
+
+#[test]
+fn hybrid_needs_mlkem_private_key() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let wrapped = hybrid_wrap_to_test_key(b"test data");
+    assert!(ecies::unwrap_key(&wrapped, &private_key).is_err());
+    assert!(ecies::unwrap_key_hybrid(&wrapped, &private_key, None).is_err());
+
+    let (other_mlkem_key, _) = ecies::generate_mlkem_keypair();
+    assert!(ecies::unwrap_key_hybrid(&wrapped, &private_key, Some(&other_mlkem_key)).is_err());
+}
+
+#[test]
+fn hybrid_wrong_private_key_fails() {
+    let wrapped = hybrid_wrap_to_test_key(b"test data");
+    let wrong_key = secp256k1_key(ED25519_TEST_PRIVATE_KEY);
+    assert!(ecies::unwrap_key_hybrid(&wrapped, &wrong_key, Some(&test_mlkem_keypair().0)).is_err());
+}
+
+#[test]
+fn hybrid_tampered_parts_fail() {
+    let wrapped = hybrid_wrap_to_test_key(b"test data");
+
+    // ML-KEM ciphertext, ECIES share, sealed data
+    let ecies_start = 1 + ecies::MLKEM768_CIPHERTEXT_SIZE;
+    for offset in [10, ecies_start + 70, wrapped.len() - 1] {
+        let mut tampered = wrapped.clone();
+        tampered[offset] ^= 0xff;
+        assert!(
+            hybrid_unwrap_as_test_key(&tampered).is_err(),
+            "tampering at offset {} must be detected",
+            offset
+        );
+    }
+}
+
+#[test]
+fn hybrid_truncated_fails() {
+    let wrapped = hybrid_wrap_to_test_key(b"test data");
+    assert!(hybrid_unwrap_as_test_key(&wrapped[..wrapped.len() / 2]).is_err());
+    assert!(hybrid_unwrap_as_test_key(&[ecies::HYBRID_WRAP_VERSION]).is_err());
+}
+
+#[test]
+fn mlkem_keypairs_are_random() {
+    let (private_key, public_key) = ecies::generate_mlkem_keypair();
+    assert_eq!(public_key.len(), ecies::MLKEM768_PUBLIC_KEY_SIZE);
+    assert_eq!(private_key.as_slice().len(), ecies::MLKEM768_PRIVATE_KEY_SIZE);
+    assert_eq!(ecies::mlkem_public_key(&private_key).unwrap(), public_key);
+
+    let (_, other) = ecies::generate_mlkem_keypair();
+    assert_ne!(public_key, other);
+}
+
+#[test]
+fn sealed_mlkem_private_key_roundtrip() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let (mlkem_private_key, mlkem_public_key) = test_mlkem_keypair();
+    let vault_secret = VaultSecret::generate();
+
+    let stored = ecies::seal_mlkem_private_key(mlkem_private_key, &vault_secret, &public_key).unwrap();
+    assert_eq!(stored[0], ecies::SEALED_MLKEM_KEY_VERSION);
+
+    let opened = ecies::open_mlkem_private_key(&stored, &private_key, Some(&vault_secret)).unwrap();
+    assert_eq!(ecies::mlkem_public_key(&opened).unwrap(), *mlkem_public_key);
+}
+
+#[test]
+fn sealed_mlkem_private_key_needs_vault_secret() {
+    // The secp256k1 key alone must not open it
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let stored =
+        ecies::seal_mlkem_private_key(&test_mlkem_keypair().0, &VaultSecret::generate(), &public_key)
+            .unwrap();
+
+    assert!(matches!(
+        ecies::open_mlkem_private_key(&stored, &private_key, None),
+        Err(ecies::EciesError::VaultSecretMissing)
+    ));
+    assert!(ecies::open_mlkem_private_key(&stored, &private_key, Some(&VaultSecret::generate())).is_err());
+    assert!(ecies::unwrap_key(&stored[1..], &private_key).is_ok());
+    assert!(ecies::unwrap_secret_key::<MlKemSecretKey>(&stored[1..], &private_key).is_err());
+}
+
+#[test]
+fn legacy_mlkem_private_key_opens_without_vault_secret() {
+    // Vaults written before the vault secret store it plain ECIES-wrapped
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let (mlkem_private_key, mlkem_public_key) = test_mlkem_keypair();
+
+    let stored = ecies::wrap_key(mlkem_private_key.as_slice(), &public_key).unwrap();
+    let opened = ecies::open_mlkem_private_key(&stored, &private_key, None).unwrap();
+    assert_eq!(ecies::mlkem_public_key(&opened).unwrap(), *mlkem_public_key);
+}
+
+#[test]
+fn hybrid_invalid_mlkem_public_key_size_fails() {
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let result = ecies::wrap_key_hybrid(b"test", &public_key, &[0u8; 800]);
+    assert!(matches!(result, Err(ecies::EciesError::InvalidPublicKeySize)));
+}
+
+// ============================================================
+// Typed Secret Key Tests
+// ============================================================
+
+#[test]
+fn secret_key_debug_is_redacted() {
+    let key = FolderKey::from_bytes([0xab; 32]);
+    let debug = format!("{:?}", key);
+    assert_eq!(debug, "FolderKey([REDACTED])");
+    assert!(!debug.contains("ab"));
+
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    assert_eq!(format!("{:?}", private_key), "Secp256k1SecretKey([REDACTED])");
+}
+
+#[test]
+fn secret_key_from_slice_checks_length() {
+    assert!(FolderKey::from_slice(&[0u8; 32]).is_ok());
+    assert!(matches!(
+        FileKey::from_slice(&[0u8; 31]),
+        Err(KeyError::InvalidLength { expected: 32, actual: 31, .. })
+    ));
+    assert!(IpnsSigningKey::try_from(&[0u8; 33][..]).is_err());
+}
+
+#[test]
+fn generated_keys_are_distinct() {
+    let key1 = FolderKey::generate();
+    let key2 = FolderKey::generate();
+    assert_ne!(key1.as_bytes(), key2.as_bytes());
+}
+
+#[test]
+fn ipns_signing_key_generate_matches_public_key() {
+    let (key, public_key) = IpnsSigningKey::generate();
+    assert_eq!(public_key.len(), 32);
+    assert_eq!(key.public_key(), public_key);
+}
+
+#[test]
+fn ecies_unwrap_secret_key_roundtrip() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
+    let expected = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
+
+    let file_key: FileKey = ecies::unwrap_secret_key(&wrapped, &private_key).unwrap();
+    assert_eq!(file_key.as_bytes().as_slice(), expected.as_slice());
+}
+
+#[test]
+fn ecies_unwrap_secret_key_rejects_wrong_size() {
+    let private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let wrapped = ecies::wrap_key(&[0x42u8; 16], &public_key).unwrap();
+
+    let result: Result<FolderKey, _> = ecies::unwrap_secret_key(&wrapped, &private_key);
+    assert!(matches!(result, Err(ecies::EciesError::InvalidUnwrappedKeySize)));
+}
+
+// ============================================================
+// Secure Memory Tests
+// ============================================================
+
+#[test]
+fn secure_buffer_holds_data_and_clones_independently() {
+    let buf = SecureBuffer::new(vec![7u8; 100]);
+    let copy = buf.clone();
+    drop(buf);
+    assert_eq!(copy.as_slice(), &[7u8; 100][..]);
+}
+
+#[test]
+fn secure_buffer_shared_page_stays_usable_after_neighbour_drop() {
+    // Small buffers usually share a page; dropping one must not disturb the other.
+    let a = SecureBuffer::from_slice(&[1u8; 32]);
+    let b = SecureBuffer::from_slice(&[2u8; 32]);
+    drop(a);
+    assert_eq!(b.as_slice(), &[2u8; 32][..]);
+    if !cfg!(target_os = "linux") {
+        assert!(!b.is_locked(), "Locking is Linux-only");
+    }
+}
+
+#[test]
+fn secret_key_survives_clone_and_drop() {
+    let key = FolderKey::from_bytes([0x5a; 32]);
+    let copy = key.clone();
+    drop(key);
+    assert_eq!(copy.as_bytes(), &[0x5a; 32]);
+}
+
+// ============================================================
+// ECIES Re-Wrap Tests
+// ============================================================
+
+/// Fixed secp256k1 private key of the re-wrap recipient.
+const ECIES_REWRAP_RECIPIENT_PRIVATE_KEY: &str =
+    "e4c2a0d9a8b8d6a54f7f3c3b1c0f4e5d6a7b8c9d0e1f2a3b4c5d6e7f8091a2b3";
+/// Corresponding uncompressed public key (65 bytes, 0x04 prefix).
+const ECIES_REWRAP_RECIPIENT_PUBLIC_KEY: &str =
+    "04072f7fca47aff99a3690d7ea11dd2dcb145d82293451a7a60107e1b0d91e0b3a3683c41830096349972d07dc7b292ebe609c1909a64c4c31fd742218b4cffd9f";
+/// ECIES_TS_WRAPPED re-wrapped for the recipient (`ECIES_REWRAPPED`), for Rust to unwrap.
+const ECIES_TS_REWRAPPED: &str =
+    "048dc7e0a113e05b35cd8c06b71352f0920da3f643a97f42956369f21524b66e23ffe5de987577e8b5557499c0a7e2d116aad83ccddb75cc3e90d6f9a64910f838791188738721d9cbdf0ab7ca1b1a3ff3cf03267b12fa603aa11a9c46fdbeb4ffaee78d9d421177e83badc48f4f97b33a6ebc088b3a8a10b9b9c635785ddefa73";
+
+#[test]
+fn ecies_rewrap_recipient_public_key_matches_private_key() {
+    let private_key = hex::decode(ECIES_REWRAP_RECIPIENT_PRIVATE_KEY).unwrap();
+    let secret = ::ecies::SecretKey::parse_slice(&private_key).unwrap();
+    let public_key = ::ecies::PublicKey::from_secret_key(&secret);
+    assert_eq!(
+        hex::encode(public_key.serialize()),
+        ECIES_REWRAP_RECIPIENT_PUBLIC_KEY
+    );
+}
+
+#[test]
+fn ecies_unwrap_typescript_rewrapped() {
+    let recipient_private_key = secp256k1_key(ECIES_REWRAP_RECIPIENT_PRIVATE_KEY);
+    let rewrapped = hex::decode(ECIES_TS_REWRAPPED).unwrap();
+    let expected_plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
+
+    let unwrapped = ecies::unwrap_key(&rewrapped, &recipient_private_key).unwrap();
+    assert_eq!(
+        unwrapped, expected_plaintext,
+        "Rust must decrypt a key re-wrapped by TypeScript"
+    );
+
+    // Re-wrapped for the recipient only
+    let owner_private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    assert!(ecies::unwrap_key(&rewrapped, &owner_private_key).is_err());
+}
+
+#[test]
+fn ecies_rewrap_typescript_wrapped_for_recipient() {
+    let owner_private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let recipient_private_key = secp256k1_key(ECIES_REWRAP_RECIPIENT_PRIVATE_KEY);
+    let recipient_public_key = hex::decode(ECIES_REWRAP_RECIPIENT_PUBLIC_KEY).unwrap();
+    let owner_wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
+    let expected_plaintext = hex::decode(ECIES_TEST_PLAINTEXT).unwrap();
+
+    let rewrapped =
+        ecies::rewrap_key(&owner_wrapped, &owner_private_key, &recipient_public_key).unwrap();
+    assert_ne!(rewrapped, owner_wrapped);
+
+    let unwrapped = ecies::unwrap_key(&rewrapped, &recipient_private_key).unwrap();
+    assert_eq!(
+        unwrapped, expected_plaintext,
+        "Recipient must recover the key originally wrapped by TypeScript"
+    );
+
+    // Owner can no longer open the re-wrapped key
+    assert!(ecies::unwrap_key(&rewrapped, &owner_private_key).is_err());
+}
+
+#[test]
+fn ecies_rewrap_roundtrip_back_to_owner() {
+    let owner_private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let owner_public_key = hex::decode(ECIES_TEST_PUBLIC_KEY).unwrap();
+    let recipient_private_key = secp256k1_key(ECIES_REWRAP_RECIPIENT_PRIVATE_KEY);
+    let recipient_public_key = hex::decode(ECIES_REWRAP_RECIPIENT_PUBLIC_KEY).unwrap();
+    let key = utils::generate_file_key();
+
+    let owner_wrapped = ecies::wrap_key(&key, &owner_public_key).unwrap();
+    let for_recipient =
+        ecies::rewrap_key(&owner_wrapped, &owner_private_key, &recipient_public_key).unwrap();
+    let back_to_owner =
+        ecies::rewrap_key(&for_recipient, &recipient_private_key, &owner_public_key).unwrap();
+
+    let unwrapped = ecies::unwrap_key(&back_to_owner, &owner_private_key).unwrap();
+    assert_eq!(unwrapped, key);
+}
+
+#[test]
+fn ecies_rewrap_wrong_owner_key_fails() {
+    let recipient_private_key = secp256k1_key(ECIES_REWRAP_RECIPIENT_PRIVATE_KEY);
+    let recipient_public_key = hex::decode(ECIES_REWRAP_RECIPIENT_PUBLIC_KEY).unwrap();
+    let owner_wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
+
+    let result = ecies::rewrap_key(&owner_wrapped, &recipient_private_key, &recipient_public_key);
+    assert!(matches!(result, Err(ecies::EciesError::RewrapFailed)));
+}
+
+#[test]
+fn ecies_rewrap_invalid_recipient_key_fails() {
+    let owner_private_key = secp256k1_key(ECIES_TEST_PRIVATE_KEY);
+    let owner_wrapped = hex::decode(ECIES_TS_WRAPPED).unwrap();
+
+    let short_key = vec![0x04u8; 33];
+    let result = ecies::rewrap_key(&owner_wrapped, &owner_private_key, &short_key);
+    assert!(matches!(result, Err(ecies::EciesError::RewrapFailed)));
+
+    let mut bad_prefix = hex::decode(ECIES_REWRAP_RECIPIENT_PUBLIC_KEY).unwrap();
+    bad_prefix[0] = 0x02;
+    let result = ecies::rewrap_key(&owner_wrapped, &owner_private_key, &bad_prefix);
+    assert!(matches!(result, Err(ecies::EciesError::RewrapFailed)));
+}
+
 // ============================================================
 // Folder Metadata Tests
 // ============================================================
 
 #[test]
 fn folder_metadata_encrypt_decrypt_roundtrip() {
-    let key = utils::generate_file_key();
+    let key = FolderKey::generate();
     let metadata = FolderMetadata {
         version: "v2".to_string(),
         children: vec![
@@ -610,8 +1001,8 @@ fn folder_entry_camel_case_serialization() {
 
 #[test]
 fn folder_metadata_wrong_key_fails() {
-    let key1 = utils::generate_file_key();
-    let key2 = utils::generate_file_key();
+    let key1 = FolderKey::generate();
+    let key2 = FolderKey::generate();
     let metadata = FolderMetadata {
         version: "v2".to_string(),
         children: vec![],
@@ -624,7 +1015,7 @@ fn folder_metadata_wrong_key_fails() {
 
 #[test]
 fn folder_metadata_rejects_v1_version() {
-    let key = utils::generate_file_key();
+    let key = FolderKey::generate();
 
     // Manually create a v1-tagged JSON and encrypt it
     let v1_json = serde_json::json!({
@@ -632,7 +1023,7 @@ fn folder_metadata_rejects_v1_version() {
         "children": []
     });
     let mut json_bytes = serde_json::to_vec(&v1_json).unwrap();
-    let sealed = aes::seal_aes_gcm(&json_bytes, &key).unwrap();
+    let sealed = aes::seal_aes_gcm(&json_bytes, key.as_bytes()).unwrap();
     json_bytes.fill(0);
 
     let result = decrypt_folder_metadata(&sealed, &key);
@@ -646,7 +1037,7 @@ fn folder_metadata_rejects_v1_version() {
 #[test]
 fn ipns_record_creation() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
 
     let record = ipns::create_ipns_record(
         &pk,
@@ -672,7 +1063,7 @@ fn ipns_record_creation() {
 fn ipns_record_has_correct_public_key() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
     let expected_public = hex::decode(ED25519_TEST_PUBLIC_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
 
     let record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 0, 86400000).unwrap();
     assert_eq!(record.public_key, expected_public);
@@ -681,7 +1072,7 @@ fn ipns_record_has_correct_public_key() {
 #[test]
 fn ipns_record_validity_is_future() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
 
     let record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 0, 86400000).unwrap();
 
@@ -705,7 +1096,7 @@ fn ipns_record_signature_v2_is_deterministic_for_same_cbor() {
     // Since the CBOR data includes a timestamp, the signature will differ
     // between calls. But for the SAME CBOR data, the signature must be deterministic.
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.clone().try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.clone().try_into().unwrap());
 
     let record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 1, 86400000).unwrap();
 
@@ -725,7 +1116,7 @@ fn ipns_record_signature_v2_is_deterministic_for_same_cbor() {
 #[test]
 fn ipns_record_cbor_data_contains_expected_fields() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
     let value = "/ipfs/bafybeicklkqcnlvtiscr2hzkubjwnwjinvskffn4xorqeduft3wq7vm5u4";
 
     let record = ipns::create_ipns_record(&pk, value, 42, 86400000).unwrap();
@@ -766,7 +1157,7 @@ fn ipns_record_cbor_data_contains_expected_fields() {
 #[test]
 fn ipns_marshal_produces_protobuf() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
 
     let record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 0, 86400000).unwrap();
     let marshaled = ipns::marshal_ipns_record(&record).unwrap();
@@ -783,7 +1174,7 @@ fn ipns_marshal_produces_protobuf() {
 #[test]
 fn ipns_marshal_contains_all_fields() {
     let private_key = hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap();
-    let pk: [u8; 32] = private_key.try_into().unwrap();
+    let pk = IpnsSigningKey::from_bytes(private_key.try_into().unwrap());
 
     let record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 7, 86400000).unwrap();
     let marshaled = ipns::marshal_ipns_record(&record).unwrap();
@@ -853,6 +1244,141 @@ fn ipns_name_different_keys_produce_different_names() {
     assert_ne!(name1, name2, "Different keys should produce different names");
 }
 
+// ============================================================
+// IPNS Record Unmarshal & Verification Tests
+// ============================================================
+
+/// Create a marshaled record signed with the Ed25519 test key, plus its name.
+fn signed_test_record(value: &str, sequence: u64, lifetime_ms: u64) -> (Vec<u8>, String) {
+    let pk = IpnsSigningKey::from_bytes(hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap().try_into().unwrap());
+    let record = ipns::create_ipns_record(&pk, value, sequence, lifetime_ms).unwrap();
+    let marshaled = ipns::marshal_ipns_record(&record).unwrap();
+    (marshaled, IPNS_EXPECTED_NAME.to_string())
+}
+
+#[test]
+fn ipns_decode_name_cross_language() {
+    let public_key = ipns::decode_ipns_name(IPNS_EXPECTED_NAME).unwrap();
+    assert_eq!(
+        hex::encode(public_key),
+        ED25519_TEST_PUBLIC_KEY,
+        "Decoding the TypeScript-derived name must yield the original public key"
+    );
+}
+
+#[test]
+fn ipns_decode_name_roundtrip_random_keys() {
+    for _ in 0..10 {
+        let (public_key, _) = ed25519::generate_ed25519_keypair();
+        let pk: [u8; 32] = public_key.clone().try_into().unwrap();
+        let name = ipns::derive_ipns_name(&pk).unwrap();
+        assert_eq!(ipns::decode_ipns_name(&name).unwrap().to_vec(), public_key);
+    }
+}
+
+#[test]
+fn ipns_decode_name_rejects_invalid_names() {
+    assert!(ipns::decode_ipns_name("").is_err());
+    assert!(ipns::decode_ipns_name("bafzaajaiaejca").is_err(), "Missing k prefix");
+    assert!(ipns::decode_ipns_name("k51-not-base36").is_err());
+    assert!(ipns::decode_ipns_name("k51qzi5uqu5d").is_err(), "Truncated name");
+
+    let mut truncated = IPNS_EXPECTED_NAME.to_string();
+    truncated.pop();
+    assert!(ipns::decode_ipns_name(&truncated).is_err());
+}
+
+#[test]
+fn ipns_unmarshal_roundtrip() {
+    let value = "/ipfs/bafybeicklkqcnlvtiscr2hzkubjwnwjinvskffn4xorqeduft3wq7vm5u4";
+    let pk = IpnsSigningKey::from_bytes(hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap().try_into().unwrap());
+    let record = ipns::create_ipns_record(&pk, value, 42, 86400000).unwrap();
+    let marshaled = ipns::marshal_ipns_record(&record).unwrap();
+
+    let parsed = ipns::unmarshal_ipns_record(&marshaled).unwrap();
+    assert_eq!(parsed.value, value);
+    assert_eq!(parsed.sequence, 42);
+    assert_eq!(parsed.validity, record.validity);
+    assert_eq!(parsed.validity_type, 0);
+    assert_eq!(parsed.ttl, record.ttl);
+    assert_eq!(parsed.signature_v1, record.signature_v1);
+    assert_eq!(parsed.signature_v2, record.signature_v2);
+    assert_eq!(parsed.data, record.data);
+    assert_eq!(parsed.public_key, record.public_key);
+}
+
+#[test]
+fn ipns_validate_accepts_valid_record() {
+    let (marshaled, name) = signed_test_record("/ipfs/bafy123", 7, 86400000);
+    let record = ipns::validate_ipns_record(&marshaled, &name).unwrap();
+    assert_eq!(record.value, "/ipfs/bafy123");
+    assert_eq!(record.sequence, 7);
+}
+
+#[test]
+fn ipns_validate_rejects_record_for_other_name() {
+    let (marshaled, _) = signed_test_record("/ipfs/bafy123", 7, 86400000);
+    let (other_public, _) = ed25519::generate_ed25519_keypair();
+    let other_name = ipns::derive_ipns_name(&other_public.try_into().unwrap()).unwrap();
+
+    let result = ipns::validate_ipns_record(&marshaled, &other_name);
+    assert!(result.is_err(), "Record signed by another key must be rejected");
+}
+
+#[test]
+fn ipns_validate_rejects_forged_signature() {
+    let pk = IpnsSigningKey::from_bytes(hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap().try_into().unwrap());
+    let mut record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 7, 86400000).unwrap();
+    record.signature_v2[0] ^= 0x01;
+    let marshaled = ipns::marshal_ipns_record(&record).unwrap();
+
+    let result = ipns::validate_ipns_record(&marshaled, IPNS_EXPECTED_NAME);
+    assert!(matches!(result, Err(ipns::IpnsError::SignatureVerificationFailed)));
+}
+
+#[test]
+fn ipns_validate_rejects_tampered_cbor_data() {
+    let (mut marshaled, name) = signed_test_record("/ipfs/bafy123", 7, 86400000);
+    // Data (field 9) is the last field, so the final byte belongs to the CBOR map
+    let last = marshaled.len() - 1;
+    marshaled[last] ^= 0x01;
+
+    assert!(ipns::validate_ipns_record(&marshaled, &name).is_err());
+}
+
+#[test]
+fn ipns_unmarshal_rejects_v1_fields_disagreeing_with_cbor() {
+    let pk = IpnsSigningKey::from_bytes(hex::decode(ED25519_TEST_PRIVATE_KEY).unwrap().try_into().unwrap());
+    let mut record = ipns::create_ipns_record(&pk, "/ipfs/bafy123", 7, 86400000).unwrap();
+    // Protobuf value says one thing, signed CBOR says another
+    record.value = "/ipfs/bafyevil".to_string();
+    let marshaled = ipns::marshal_ipns_record(&record).unwrap();
+
+    assert!(ipns::unmarshal_ipns_record(&marshaled).is_err());
+}
+
+#[test]
+fn ipns_verify_rejects_expired_record() {
+    let (marshaled, name) = signed_test_record("/ipfs/bafy123", 7, 60_000);
+    let record = ipns::unmarshal_ipns_record(&marshaled).unwrap();
+
+    let now = std::time::SystemTime::now();
+    assert!(ipns::verify_ipns_record(&record, &name, now).is_ok());
+
+    let later = now + std::time::Duration::from_secs(120);
+    let result = ipns::verify_ipns_record(&record, &name, later);
+    assert!(matches!(result, Err(ipns::IpnsError::RecordExpired)));
+}
+
+#[test]
+fn ipns_unmarshal_rejects_garbage_and_oversized_input() {
+    assert!(ipns::unmarshal_ipns_record(&[]).is_err());
+    assert!(ipns::unmarshal_ipns_record(&[0xff, 0xff, 0xff]).is_err());
+
+    let oversized = vec![0u8; ipns::MAX_IPNS_RECORD_SIZE + 1];
+    assert!(ipns::unmarshal_ipns_record(&oversized).is_err());
+}
+
 // ============================================================
 // Utility Tests
 // ============================================================
@@ -903,11 +1429,15 @@ const HKDF_TEST_KEY: [u8; 32] = [
     0x1f, 0x20,
 ];
 
+fn hkdf_test_key() -> Secp256k1SecretKey {
+    Secp256k1SecretKey::from_bytes(HKDF_TEST_KEY)
+}
+
 #[test]
 fn hkdf_vault_derivation_produces_k51_name() {
-    let (priv_key, pub_key, ipns_name) = hkdf::derive_vault_ipns_keypair(&HKDF_TEST_KEY).unwrap();
+    let (priv_key, pub_key, ipns_name) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
 
-    assert_eq!(priv_key.len(), 32, "Ed25519 private key should be 32 bytes");
+    assert_eq!(priv_key.as_slice().len(), 32, "Ed25519 private key should be 32 bytes");
     assert_eq!(pub_key.len(), 32, "Ed25519 public key should be 32 bytes");
     assert!(
         ipns_name.starts_with("k51"),
@@ -918,17 +1448,17 @@ fn hkdf_vault_derivation_produces_k51_name() {
 
 #[test]
 fn hkdf_vault_derivation_is_deterministic() {
-    let (_, _, name1) = hkdf::derive_vault_ipns_keypair(&HKDF_TEST_KEY).unwrap();
-    let (_, _, name2) = hkdf::derive_vault_ipns_keypair(&HKDF_TEST_KEY).unwrap();
+    let (_, _, name1) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
+    let (_, _, name2) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
     assert_eq!(name1, name2, "Same key should produce same IPNS name");
 }
 
 #[test]
 fn hkdf_file_derivation_different_file_ids_produce_different_names() {
     let (_, _, name1) =
-        hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, "file-id-001-abcdef").unwrap();
+        hkdf::derive_file_ipns_keypair(&hkdf_test_key(), "file-id-001-abcdef").unwrap();
     let (_, _, name2) =
-        hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, "file-id-002-ghijkl").unwrap();
+        hkdf::derive_file_ipns_keypair(&hkdf_test_key(), "file-id-002-ghijkl").unwrap();
 
     assert_ne!(name1, name2, "Different file IDs should produce different IPNS names");
     assert!(name1.starts_with("k51"));
@@ -938,21 +1468,21 @@ fn hkdf_file_derivation_different_file_ids_produce_different_names() {
 #[test]
 fn hkdf_file_derivation_same_file_id_is_deterministic() {
     let file_id = "test-file-id-1234567890";
-    let (_, _, name1) = hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, file_id).unwrap();
-    let (_, _, name2) = hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, file_id).unwrap();
+    let (_, _, name1) = hkdf::derive_file_ipns_keypair(&hkdf_test_key(), file_id).unwrap();
+    let (_, _, name2) = hkdf::derive_file_ipns_keypair(&hkdf_test_key(), file_id).unwrap();
     assert_eq!(name1, name2, "Same key + fileId should produce same IPNS name");
 }
 
 #[test]
 fn hkdf_file_derivation_rejects_short_file_id() {
-    let result = hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, "short");
+    let result = hkdf::derive_file_ipns_keypair(&hkdf_test_key(), "short");
     assert!(result.is_err(), "File ID < 10 chars should fail");
 }
 
 #[test]
 fn hkdf_registry_derivation_differs_from_vault() {
-    let (_, _, vault_name) = hkdf::derive_vault_ipns_keypair(&HKDF_TEST_KEY).unwrap();
-    let (_, _, registry_name) = hkdf::derive_registry_ipns_keypair(&HKDF_TEST_KEY).unwrap();
+    let (_, _, vault_name) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
+    let (_, _, registry_name) = hkdf::derive_registry_ipns_keypair(&hkdf_test_key()).unwrap();
 
     assert_ne!(
         vault_name, registry_name,
@@ -960,12 +1490,22 @@ fn hkdf_registry_derivation_differs_from_vault() {
     );
 }
 
+#[test]
+fn hkdf_local_state_key_is_deterministic_and_separated() {
+    let key1 = hkdf::derive_local_state_key(&hkdf_test_key()).unwrap();
+    let key2 = hkdf::derive_local_state_key(&hkdf_test_key()).unwrap();
+    assert_eq!(key1.as_bytes(), key2.as_bytes());
+
+    let (vault_seed, _, _) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
+    assert_ne!(key1.as_bytes(), vault_seed.as_bytes(), "Local state key must use its own HKDF info");
+}
+
 #[test]
 fn hkdf_all_three_domains_produce_different_names() {
-    let (_, _, vault_name) = hkdf::derive_vault_ipns_keypair(&HKDF_TEST_KEY).unwrap();
+    let (_, _, vault_name) = hkdf::derive_vault_ipns_keypair(&hkdf_test_key()).unwrap();
     let (_, _, file_name) =
-        hkdf::derive_file_ipns_keypair(&HKDF_TEST_KEY, "file-id-001-abcdef").unwrap();
-    let (_, _, registry_name) = hkdf::derive_registry_ipns_keypair(&HKDF_TEST_KEY).unwrap();
+        hkdf::derive_file_ipns_keypair(&hkdf_test_key(), "file-id-001-abcdef").unwrap();
+    let (_, _, registry_name) = hkdf::derive_registry_ipns_keypair(&hkdf_test_key()).unwrap();
 
     assert_ne!(vault_name, file_name);
     assert_ne!(vault_name, registry_name);
@@ -1070,6 +1610,40 @@ fn aes_ctr_range_decrypt_invalid_range_returns_error() {
     assert!(result.is_err());
 }
 
+#[test]
+fn aes_ctr_decrypt_at_offset_matches_full_decrypt() {
+    let key: [u8; 32] = utils::generate_file_key();
+    let iv: [u8; 16] = utils::generate_random_bytes(16).try_into().unwrap();
+    let plaintext: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+    let ciphertext = aes_ctr::encrypt_aes_ctr(&plaintext, &key, &iv).unwrap();
+
+    // Only the window's ciphertext is available, as with an HTTP range read
+    let window = &ciphertext[1024..2048 + 7];
+    let decrypted = aes_ctr::decrypt_aes_ctr_at(window, &key, &iv, 1024).unwrap();
+    assert_eq!(decrypted, &plaintext[1024..2048 + 7]);
+}
+
+#[test]
+fn aes_ctr_decrypt_at_unaligned_offset_fails() {
+    let key: [u8; 32] = utils::generate_file_key();
+    let iv: [u8; 16] = utils::generate_random_bytes(16).try_into().unwrap();
+    let result = aes_ctr::decrypt_aes_ctr_at(&[0u8; 32], &key, &iv, 17);
+    assert!(result.is_err());
+}
+
+#[test]
+fn aes_ctr_decrypt_at_wraps_counter() {
+    // Base counter close to u64::MAX: the 64-bit counter wraps like Web Crypto's
+    let key: [u8; 32] = utils::generate_file_key();
+    let mut iv = [0u8; 16];
+    iv[8..].copy_from_slice(&(u64::MAX - 1).to_be_bytes());
+    let plaintext = vec![0x5au8; 128];
+    let ciphertext = aes_ctr::encrypt_aes_ctr(&plaintext, &key, &iv).unwrap();
+
+    let decrypted = aes_ctr::decrypt_aes_ctr_at(&ciphertext[64..], &key, &iv, 64).unwrap();
+    assert_eq!(decrypted, &plaintext[64..]);
+}
+
 #[test]
 fn aes_ctr_empty_data() {
     let key: [u8; 32] = utils::generate_file_key();
@@ -1082,17 +1656,323 @@ fn aes_ctr_empty_data() {
     assert!(decrypted.is_empty());
 }
 
-// ============================================================
-// FileMetadata Tests (per-file IPNS, separate schema)
-// ============================================================
+#[test]
+fn ctr_iv_has_zero_counter() {
+    let iv1 = utils::generate_ctr_iv();
+    let iv2 = utils::generate_ctr_iv();
+    assert_eq!(&iv1[8..], &[0u8; 8], "Counter half must start at zero");
+    assert_ne!(iv1[..8], iv2[..8], "Nonces should be random");
+}
 
 #[test]
-fn file_metadata_deserialization_with_encryption_mode() {
-    let json = r#"{
-        "version": "v1",
-        "cid": "bafybeicklkqcnlvtiscr2hzkubjwnwjinvskffn4xorqeduft3wq7vm5u4",
-        "fileKeyEncrypted": "deadbeef",
-        "fileIv": "aabbccdd",
+fn ctr_chunk_hashes_cover_each_chunk() {
+    let ciphertext = utils::generate_random_bytes(2 * aes_ctr::CTR_CHUNK_SIZE + 100);
+    let hashes = aes_ctr::ctr_chunk_hashes(&ciphertext);
+    assert_eq!(hashes.len(), 3);
+    assert!(hashes.iter().all(|h| h.len() == 64));
+
+    for (i, chunk) in ciphertext.chunks(aes_ctr::CTR_CHUNK_SIZE).enumerate() {
+        aes_ctr::verify_ctr_chunk(chunk, i, &hashes).unwrap();
+    }
+    assert!(aes_ctr::ctr_chunk_hashes(b"").is_empty());
+}
+
+#[test]
+fn ctr_chunk_encryptor_matches_one_shot() {
+    let key: [u8; 32] = utils::generate_file_key();
+    let iv = utils::generate_ctr_iv();
+
+    for len in [2 * aes_ctr::CTR_CHUNK_SIZE, 2 * aes_ctr::CTR_CHUNK_SIZE + 33] {
+        let plaintext = utils::generate_random_bytes(len);
+        let mut encryptor = aes_ctr::CtrChunkEncryptor::new(&key, &iv);
+        let mut streamed = Vec::new();
+        for chunk in plaintext.chunks(aes_ctr::CTR_CHUNK_SIZE) {
+            let mut chunk = chunk.to_vec();
+            encryptor.encrypt_chunk(&mut chunk).unwrap();
+            streamed.extend_from_slice(&chunk);
+        }
+
+        let expected = aes_ctr::encrypt_aes_ctr(&plaintext, &key, &iv).unwrap();
+        assert_eq!(streamed, expected);
+        assert_eq!(encryptor.finish(), aes_ctr::ctr_chunk_hashes(&expected));
+    }
+}
+
+#[test]
+fn ctr_chunk_encryptor_rejects_data_after_short_chunk() {
+    let key: [u8; 32] = utils::generate_file_key();
+    let mut encryptor = aes_ctr::CtrChunkEncryptor::new(&key, &utils::generate_ctr_iv());
+    encryptor.encrypt_chunk(&mut [0u8; 100]).unwrap();
+    assert!(encryptor.encrypt_chunk(&mut [0u8; 100]).is_err());
+}
+
+#[test]
+fn ctr_chunk_verification_detects_tampering() {
+    let mut ciphertext = utils::generate_random_bytes(aes_ctr::CTR_CHUNK_SIZE + 16);
+    let hashes = aes_ctr::ctr_chunk_hashes(&ciphertext);
+
+    ciphertext[aes_ctr::CTR_CHUNK_SIZE + 3] ^= 0x01;
+    let last = &ciphertext[aes_ctr::CTR_CHUNK_SIZE..];
+    assert!(matches!(
+        aes_ctr::verify_ctr_chunk(last, 1, &hashes),
+        Err(aes_ctr::AesCtrError::IntegrityCheckFailed)
+    ));
+    // Truncated chunk and unknown index
+    assert!(aes_ctr::verify_ctr_chunk(&ciphertext[..16], 0, &hashes).is_err());
+    assert!(aes_ctr::verify_ctr_chunk(last, 2, &hashes).is_err());
+}
+
+// ============================================================
+// Segmented AES-256-GCM (GCM-SEG) Tests
+// ============================================================
+
+/// Plaintext spanning 2.5 segments.
+fn gcm_seg_test_plaintext() -> Vec<u8> {
+    let len = aes_gcm_seg::GCM_SEG_SEGMENT_SIZE * 2 + aes_gcm_seg::GCM_SEG_SEGMENT_SIZE / 2;
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// GCM-SEG ciphertext of `plaintext`, as the spool writes it.
+fn seal_gcm_seg(
+    plaintext: &[u8],
+    key: &[u8; 32],
+    prefix: &[u8; aes_gcm_seg::GCM_SEG_NONCE_PREFIX_SIZE],
+) -> Vec<u8> {
+    let mut ciphertext = Vec::new();
+    aes_gcm_seg::encrypt_aes_gcm_seg_stream(plaintext, &mut ciphertext, key, prefix).unwrap();
+    ciphertext
+}
+
+#[test]
+fn aes_gcm_seg_roundtrip() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+    assert_eq!(
+        ciphertext.len() as u64,
+        aes_gcm_seg::encrypted_size(plaintext.len() as u64)
+    );
+    assert_eq!(ciphertext.len(), plaintext.len() + 3 * aes::AES_TAG_SIZE);
+
+    let decrypted = aes_gcm_seg::decrypt_aes_gcm_seg(&ciphertext, &key, &prefix).unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn aes_gcm_seg_empty_plaintext_is_single_segment() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+
+    let ciphertext = seal_gcm_seg(b"", &key, &prefix);
+    assert_eq!(ciphertext.len(), aes::AES_TAG_SIZE, "Empty input should be one tag-only segment");
+
+    let decrypted = aes_gcm_seg::decrypt_aes_gcm_seg(&ciphertext, &key, &prefix).unwrap();
+    assert!(decrypted.is_empty());
+}
+
+#[test]
+fn aes_gcm_seg_exact_segment_multiple_roundtrip() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = vec![0x5au8; aes_gcm_seg::GCM_SEG_SEGMENT_SIZE * 2];
+
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+    assert_eq!(ciphertext.len(), 2 * aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE);
+
+    let decrypted = aes_gcm_seg::decrypt_aes_gcm_seg(&ciphertext, &key, &prefix).unwrap();
+    assert_eq!(decrypted, plaintext);
+}
+
+#[test]
+fn aes_gcm_seg_stream_matches_buffer() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+
+    let mut streamed = Vec::new();
+    let written =
+        aes_gcm_seg::encrypt_aes_gcm_seg_stream(&plaintext[..], &mut streamed, &key, &prefix)
+            .unwrap();
+    assert_eq!(written, plaintext.len() as u64);
+
+    let mut decrypted = Vec::new();
+    aes_gcm_seg::decrypt_aes_gcm_seg_stream(&streamed[..], &mut decrypted, &key, &prefix)
+        .unwrap();
+    assert_eq!(decrypted, plaintext);
+
+    let buffered = aes_gcm_seg::decrypt_aes_gcm_seg(&streamed, &key, &prefix).unwrap();
+    assert_eq!(buffered, decrypted, "Streaming and buffered decryption must agree");
+}
+
+/// Plaintext bytes `[start, end]` of a GCM-SEG `ciphertext`, decrypted from
+/// only the segments covering them, as a FUSE range read fetches them.
+fn read_gcm_seg_range(
+    ciphertext: &[u8],
+    key: &[u8; 32],
+    prefix: &[u8; aes_gcm_seg::GCM_SEG_NONCE_PREFIX_SIZE],
+    start: u64,
+    end: u64,
+) -> Result<Vec<u8>, aes_gcm_seg::AesGcmSegError> {
+    let len = ciphertext.len() as u64;
+    let segments = aes_gcm_seg::segments_for_range(len, start, end)?;
+    if segments.is_empty() {
+        return Ok(Vec::new());
+    }
+    let from = aes_gcm_seg::segment_ciphertext_range(segments.start, len)?.start;
+    let to = aes_gcm_seg::segment_ciphertext_range(segments.end - 1, len)?.end;
+    let window = &ciphertext[from as usize..to as usize];
+    let plaintext: Vec<u8> = aes_gcm_seg::decrypt_segments(window, len, segments.start, key, prefix)?
+        .iter()
+        .flat_map(|segment| segment.iter().copied())
+        .collect();
+
+    let offset = segments.start * aes_gcm_seg::GCM_SEG_SEGMENT_SIZE as u64;
+    let end = end.saturating_add(1).min(offset + plaintext.len() as u64);
+    Ok(plaintext[(start - offset) as usize..(end - offset) as usize].to_vec())
+}
+
+#[test]
+fn aes_gcm_seg_range_across_segment_boundary() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+
+    let seg = aes_gcm_seg::GCM_SEG_SEGMENT_SIZE as u64;
+    let start = seg - 10;
+    let end = seg + 9;
+    let range = read_gcm_seg_range(&ciphertext, &key, &prefix, start, end).unwrap();
+    assert_eq!(range, &plaintext[start as usize..=end as usize]);
+}
+
+#[test]
+fn aes_gcm_seg_range_clamps_to_plaintext_end() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+
+    let start = plaintext.len() as u64 - 5;
+    let range = read_gcm_seg_range(&ciphertext, &key, &prefix, start, u64::MAX).unwrap();
+    assert_eq!(range, &plaintext[start as usize..]);
+
+    let beyond = read_gcm_seg_range(
+        &ciphertext, &key, &prefix, plaintext.len() as u64, plaintext.len() as u64 + 10,
+    )
+    .unwrap();
+    assert!(beyond.is_empty());
+
+    let result = aes_gcm_seg::segments_for_range(ciphertext.len() as u64, 10, 5);
+    assert!(result.is_err(), "start > end should fail");
+}
+
+#[test]
+fn aes_gcm_seg_range_rejects_tampered_segment() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+    let mut ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+
+    // Flip a byte in the second segment
+    ciphertext[aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE + 3] ^= 0x01;
+
+    // Ranges within the first segment are still readable
+    assert!(read_gcm_seg_range(&ciphertext, &key, &prefix, 0, 99).is_ok());
+
+    let seg = aes_gcm_seg::GCM_SEG_SEGMENT_SIZE as u64;
+    let result = read_gcm_seg_range(&ciphertext, &key, &prefix, seg, seg + 1);
+    assert!(result.is_err(), "Tampered segment must fail authentication");
+}
+
+#[test]
+fn aes_gcm_seg_segments_reject_short_window() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+    let len = ciphertext.len() as u64;
+
+    // A range response cut short inside the final segment
+    let last = aes_gcm_seg::segment_count(plaintext.len() as u64) - 1;
+    let range = aes_gcm_seg::segment_ciphertext_range(last, len).unwrap();
+    let window = &ciphertext[range.start as usize..range.end as usize - 1];
+    assert!(aes_gcm_seg::decrypt_segments(window, len, last, &key, &prefix).is_err());
+
+    // The full final segment decrypts, flagged as last
+    let window = &ciphertext[range.start as usize..range.end as usize];
+    let segments = aes_gcm_seg::decrypt_segments(window, len, last, &key, &prefix).unwrap();
+    let offset = (last * aes_gcm_seg::GCM_SEG_SEGMENT_SIZE as u64) as usize;
+    assert_eq!(&segments[0][..], &plaintext[offset..]);
+}
+
+#[test]
+fn aes_gcm_seg_truncation_fails() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = gcm_seg_test_plaintext();
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+
+    // Drop the final segment: the new last segment lacks the final flag
+    let truncated = &ciphertext[..2 * aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE];
+    assert!(aes_gcm_seg::decrypt_aes_gcm_seg(truncated, &key, &prefix).is_err());
+}
+
+#[test]
+fn aes_gcm_seg_reordered_segments_fail() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let plaintext = vec![0x11u8; aes_gcm_seg::GCM_SEG_SEGMENT_SIZE * 3];
+    let ciphertext = seal_gcm_seg(&plaintext, &key, &prefix);
+
+    let size = aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE;
+    let mut swapped = Vec::with_capacity(ciphertext.len());
+    swapped.extend_from_slice(&ciphertext[size..2 * size]);
+    swapped.extend_from_slice(&ciphertext[..size]);
+    swapped.extend_from_slice(&ciphertext[2 * size..]);
+    assert!(aes_gcm_seg::decrypt_aes_gcm_seg(&swapped, &key, &prefix).is_err());
+}
+
+#[test]
+fn aes_gcm_seg_wrong_nonce_prefix_fails() {
+    let key = utils::generate_file_key();
+    let prefix = aes_gcm_seg::generate_nonce_prefix();
+    let mut other_prefix = prefix;
+    other_prefix[0] ^= 0xff;
+
+    let ciphertext = seal_gcm_seg(b"segment", &key, &prefix);
+    assert!(aes_gcm_seg::decrypt_aes_gcm_seg(&ciphertext, &key, &other_prefix).is_err());
+}
+
+#[test]
+fn aes_gcm_seg_plaintext_size_rejects_invalid_lengths() {
+    assert!(aes_gcm_seg::plaintext_size(0).is_err());
+    assert!(aes_gcm_seg::plaintext_size(aes::AES_TAG_SIZE as u64 - 1).is_err());
+    assert_eq!(aes_gcm_seg::plaintext_size(aes::AES_TAG_SIZE as u64).unwrap(), 0);
+
+    // A full segment followed by a dangling partial tag is not a valid layout
+    let bad = aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE as u64 + 3;
+    assert!(aes_gcm_seg::plaintext_size(bad).is_err());
+
+    let len = gcm_seg_test_plaintext().len() as u64;
+    assert_eq!(
+        aes_gcm_seg::plaintext_size(aes_gcm_seg::encrypted_size(len)).unwrap(),
+        len
+    );
+}
+
+// ============================================================
+// FileMetadata Tests (per-file IPNS, separate schema)
+// ============================================================
+
+#[test]
+fn file_metadata_deserialization_with_encryption_mode() {
+    let json = r#"{
+        "version": "v1",
+        "cid": "bafybeicklkqcnlvtiscr2hzkubjwnwjinvskffn4xorqeduft3wq7vm5u4",
+        "fileKeyEncrypted": "deadbeef",
+        "fileIv": "aabbccdd",
         "size": 1024,
         "mimeType": "video/mp4",
         "encryptionMode": "CTR",
@@ -1130,7 +2010,7 @@ fn file_metadata_deserialization_without_encryption_mode_defaults_gcm() {
 
 #[test]
 fn file_metadata_encrypt_decrypt_roundtrip() {
-    let key = utils::generate_file_key();
+    let key = FolderKey::generate();
     let metadata = FileMetadata {
         version: "v1".to_string(),
         cid: "bafybeicklkqcnlvtiscr2hzkubjwnwjinvskffn4xorqeduft3wq7vm5u4".to_string(),
@@ -1139,6 +2019,8 @@ fn file_metadata_encrypt_decrypt_roundtrip() {
         size: 2048,
         mime_type: "video/mp4".to_string(),
         encryption_mode: "CTR".to_string(),
+        padding: None,
+        chunk_hashes: None,
         created_at: 1700000000000,
         modified_at: 1700000000000,
         versions: None,
@@ -1156,8 +2038,8 @@ fn file_metadata_encrypt_decrypt_roundtrip() {
 
 #[test]
 fn file_metadata_wrong_key_fails() {
-    let key1 = utils::generate_file_key();
-    let key2 = utils::generate_file_key();
+    let key1 = FolderKey::generate();
+    let key2 = FolderKey::generate();
     let metadata = FileMetadata {
         version: "v1".to_string(),
         cid: "bafytest".to_string(),
@@ -1166,6 +2048,8 @@ fn file_metadata_wrong_key_fails() {
         size: 100,
         mime_type: "text/plain".to_string(),
         encryption_mode: "GCM".to_string(),
+        padding: None,
+        chunk_hashes: None,
         created_at: 1000,
         modified_at: 2000,
         versions: None,
@@ -1186,6 +2070,8 @@ fn file_metadata_camel_case_serialization() {
         size: 100,
         mime_type: "video/mp4".to_string(),
         encryption_mode: "CTR".to_string(),
+        padding: None,
+        chunk_hashes: None,
         created_at: 1000,
         modified_at: 2000,
         versions: None,
@@ -1199,3 +2085,623 @@ fn file_metadata_camel_case_serialization() {
     assert!(!json.contains("file_key_encrypted"), "Should NOT contain snake_case");
     assert!(!json.contains("mime_type"), "Should NOT contain snake_case");
 }
+
+#[test]
+fn file_metadata_chunk_hashes_are_optional() {
+    let mut metadata = envelope_test_file();
+    let json = serde_json::to_string(&metadata).unwrap();
+    assert!(!json.contains("chunkHashes"), "Absent chunk hashes should be omitted");
+    let parsed: FileMetadata = serde_json::from_str(&json).unwrap();
+    assert!(parsed.chunk_hashes.is_none());
+
+    metadata.encryption_mode = "CTR".to_string();
+    metadata.chunk_hashes = Some(aes_ctr::ctr_chunk_hashes(b"ciphertext"));
+    let json = serde_json::to_string(&metadata).unwrap();
+    assert!(json.contains("chunkHashes"), "Must use camelCase: chunkHashes");
+    let parsed: FileMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.chunk_hashes, metadata.chunk_hashes);
+}
+
+// ============================================================
+// Metadata Envelope Tests (AAD bound to IPNS name)
+// ============================================================
+
+const ENVELOPE_FOLDER_A_IPNS: &str = "k51qzi5uqu5dljtg5upm7x7ugan9lql3ewyknv4r4mhhkwzn8n7cnbd1unfwgq";
+const ENVELOPE_FOLDER_B_IPNS: &str = "k51qzi5uqu5dljtg5upm7x7ugan9lql3ewyknv4r4mhhkwzn8n7cnbd1unfwgx";
+
+fn envelope_test_folder(name: &str) -> FolderMetadata {
+    FolderMetadata {
+        version: "v2".to_string(),
+        children: vec![FolderChild::File(FilePointer {
+            id: "file-001".to_string(),
+            name: name.to_string(),
+            file_meta_ipns_name: ENVELOPE_FOLDER_B_IPNS.to_string(),
+            ipns_private_key_encrypted: None,
+            created_at: 1700000000000,
+            modified_at: 1700000000000,
+        })],
+    }
+}
+
+fn envelope_test_file() -> FileMetadata {
+    FileMetadata {
+        version: "v1".to_string(),
+        cid: "bafytest".to_string(),
+        file_key_encrypted: "aa".to_string(),
+        file_iv: "bb".to_string(),
+        size: 100,
+        mime_type: "text/plain".to_string(),
+        encryption_mode: "GCM".to_string(),
+        padding: None,
+        chunk_hashes: None,
+        created_at: 1000,
+        modified_at: 2000,
+        versions: None,
+    }
+}
+
+const V2_ENVELOPE: MetadataFormat = MetadataFormat::V2 {
+    padding: PaddingScheme::None,
+};
+
+/// Legacy `{ iv, data }` envelope, as written before AAD binding.
+fn legacy_envelope(sealed: &[u8]) -> Vec<u8> {
+    use base64::Engine;
+    serde_json::to_vec(&serde_json::json!({
+        "iv": hex::encode(&sealed[..12]),
+        "data": base64::engine::general_purpose::STANDARD.encode(&sealed[12..]),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn folder_envelope_roundtrip() {
+    let key = FolderKey::generate();
+    let envelope =
+        encrypt_folder_metadata_envelope(&envelope_test_folder("a.txt"), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE)
+            .unwrap();
+
+    let value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    assert_eq!(value["envelope"], 2);
+    assert_eq!(value["type"], "folder");
+    assert_eq!(value["schema"], "v2");
+    assert!(value["iv"].is_string() && value["data"].is_string());
+
+    let decrypted = decrypt_folder_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_A_IPNS).unwrap();
+    assert_eq!(decrypted.children.len(), 1);
+}
+
+#[test]
+fn folder_envelope_accepts_legacy_format() {
+    let key = FolderKey::generate();
+    let sealed = encrypt_folder_metadata(&envelope_test_folder("a.txt"), &key).unwrap();
+
+    let decrypted =
+        decrypt_folder_metadata_envelope(&legacy_envelope(&sealed), &key, ENVELOPE_FOLDER_A_IPNS)
+            .unwrap();
+    assert_eq!(decrypted.version, "v2");
+}
+
+#[test]
+fn folder_envelope_swapped_into_other_ipns_name_fails() {
+    // Both folders share a key (e.g. a server replaying folder A's metadata CID
+    // in folder B's IPNS record). The envelope must not open under B's name.
+    let key = FolderKey::generate();
+    let envelope_a =
+        encrypt_folder_metadata_envelope(&envelope_test_folder("a.txt"), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE)
+            .unwrap();
+
+    let result = decrypt_folder_metadata_envelope(&envelope_a, &key, ENVELOPE_FOLDER_B_IPNS);
+    assert!(matches!(result, Err(FolderError::EncryptionFailed(_))));
+}
+
+#[test]
+fn file_envelope_swapped_into_other_ipns_name_fails() {
+    let key = FolderKey::generate();
+    let envelope =
+        encrypt_file_metadata_envelope(&envelope_test_file(), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE).unwrap();
+
+    assert!(decrypt_file_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_A_IPNS).is_ok());
+    assert!(decrypt_file_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_B_IPNS).is_err());
+}
+
+#[test]
+fn file_envelope_rejected_as_folder_metadata() {
+    // File metadata is encrypted with the parent folder key, so it could be
+    // served in place of that folder's metadata if the type were not bound.
+    let key = FolderKey::generate();
+    let envelope =
+        encrypt_file_metadata_envelope(&envelope_test_file(), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE).unwrap();
+
+    let result = decrypt_folder_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_A_IPNS);
+    assert!(matches!(result, Err(FolderError::ObjectTypeMismatch)));
+
+    // Rewriting the type header does not help: the type is part of the AAD.
+    let mut value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    value["type"] = "folder".into();
+    let relabeled = serde_json::to_vec(&value).unwrap();
+    let result = decrypt_folder_metadata_envelope(&relabeled, &key, ENVELOPE_FOLDER_A_IPNS);
+    assert!(matches!(result, Err(FolderError::EncryptionFailed(_))));
+}
+
+#[test]
+fn folder_envelope_tampered_schema_fails() {
+    let key = FolderKey::generate();
+    let envelope =
+        encrypt_folder_metadata_envelope(&envelope_test_folder("a.txt"), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE)
+            .unwrap();
+
+    let mut value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    value["schema"] = "v3".into();
+    let tampered = serde_json::to_vec(&value).unwrap();
+    assert!(decrypt_folder_metadata_envelope(&tampered, &key, ENVELOPE_FOLDER_A_IPNS).is_err());
+}
+
+#[test]
+fn folder_envelope_unknown_version_rejected() {
+    let key = FolderKey::generate();
+    let envelope =
+        encrypt_folder_metadata_envelope(&envelope_test_folder("a.txt"), &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE)
+            .unwrap();
+
+    let mut value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    value["envelope"] = 3.into();
+    let future = serde_json::to_vec(&value).unwrap();
+    let result = decrypt_folder_metadata_envelope(&future, &key, ENVELOPE_FOLDER_A_IPNS);
+    assert!(matches!(result, Err(FolderError::UnsupportedEnvelopeVersion(3))));
+}
+
+#[test]
+fn folder_envelope_legacy_format_roundtrip() {
+    let key = FolderKey::generate();
+    let envelope = encrypt_folder_metadata_envelope(
+        &envelope_test_folder("a.txt"),
+        &key,
+        ENVELOPE_FOLDER_A_IPNS,
+        MetadataFormat::default(),
+    )
+    .unwrap();
+
+    let value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    let fields: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(fields, ["data", "iv"]);
+
+    // Not bound to a name, so any name opens it.
+    assert!(decrypt_folder_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_A_IPNS).is_ok());
+    assert!(decrypt_folder_metadata_envelope(&envelope, &key, ENVELOPE_FOLDER_B_IPNS).is_ok());
+}
+
+#[test]
+fn strict_envelope_reader_rejects_legacy_after_v2() {
+    use super::folder::decrypt_folder_metadata_envelope_strict as decrypt_strict;
+
+    let key = FolderKey::generate();
+    let history = EnvelopeHistory::new();
+    let folder = envelope_test_folder("a.txt");
+    let legacy_a =
+        encrypt_folder_metadata_envelope(&folder, &key, ENVELOPE_FOLDER_A_IPNS, MetadataFormat::default()).unwrap();
+    let legacy_b =
+        encrypt_folder_metadata_envelope(&folder, &key, ENVELOPE_FOLDER_B_IPNS, MetadataFormat::default()).unwrap();
+    let v2_a = encrypt_folder_metadata_envelope(&folder, &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE).unwrap();
+
+    // Legacy is fine until the name has been seen in v2.
+    assert!(decrypt_strict(&legacy_a, &key, ENVELOPE_FOLDER_A_IPNS, &history).is_ok());
+    assert!(decrypt_strict(&v2_a, &key, ENVELOPE_FOLDER_A_IPNS, &history).is_ok());
+    assert!(history.is_v2(ENVELOPE_FOLDER_A_IPNS));
+
+    let result = decrypt_strict(&legacy_a, &key, ENVELOPE_FOLDER_A_IPNS, &history);
+    assert!(matches!(result, Err(FolderError::LegacyEnvelopeRejected)));
+    assert!(decrypt_strict(&legacy_b, &key, ENVELOPE_FOLDER_B_IPNS, &history).is_ok());
+
+    // Writers keep a name seen in v2 in v2.
+    assert_eq!(
+        history.format_for(ENVELOPE_FOLDER_A_IPNS, MetadataFormat::default()),
+        V2_ENVELOPE
+    );
+    assert_eq!(
+        history.format_for(ENVELOPE_FOLDER_B_IPNS, MetadataFormat::default()),
+        MetadataFormat::default()
+    );
+}
+
+#[test]
+fn envelope_history_survives_reload_until_cleared() {
+    use super::folder::decrypt_folder_metadata_envelope_strict as decrypt_strict;
+
+    let key = FolderKey::generate();
+    let folder = envelope_test_folder("a.txt");
+    let legacy_a =
+        encrypt_folder_metadata_envelope(&folder, &key, ENVELOPE_FOLDER_A_IPNS, MetadataFormat::default()).unwrap();
+    let v2_a = encrypt_folder_metadata_envelope(&folder, &key, ENVELOPE_FOLDER_A_IPNS, V2_ENVELOPE).unwrap();
+
+    let history = EnvelopeHistory::new();
+    decrypt_strict(&v2_a, &key, ENVELOPE_FOLDER_A_IPNS, &history).unwrap();
+
+    // A restarted reader seeded with the persisted names still rejects the downgrade
+    let restarted = EnvelopeHistory::new();
+    restarted.extend(history.v2_names());
+    let result = decrypt_strict(&legacy_a, &key, ENVELOPE_FOLDER_A_IPNS, &restarted);
+    assert!(matches!(result, Err(FolderError::LegacyEnvelopeRejected)));
+
+    restarted.clear();
+    assert!(!restarted.is_v2(ENVELOPE_FOLDER_A_IPNS));
+}
+
+#[test]
+fn metadata_format_from_name() {
+    let padme = PaddingScheme::Padme;
+    assert_eq!(MetadataFormat::from_name("", padme).unwrap(), MetadataFormat::Legacy { padding: padme });
+    assert_eq!(MetadataFormat::from_name("v1", padme).unwrap(), MetadataFormat::Legacy { padding: padme });
+    assert_eq!(
+        MetadataFormat::from_name("v2", padme).unwrap(),
+        MetadataFormat::V2 { padding: padme }
+    );
+    assert!(MetadataFormat::from_name("v3", padme).is_err());
+}
+
+// ============================================================
+// FastCDC Chunking Tests
+// ============================================================
+
+/// Deterministic pseudo-random bytes (xorshift64), so boundaries are reproducible.
+fn cdc_test_data(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn fastcdc_empty_input_has_no_chunks() {
+    assert!(fastcdc::chunk_boundaries(&[]).is_empty());
+}
+
+#[test]
+fn fastcdc_small_input_is_one_chunk() {
+    let data = cdc_test_data(1000, 1);
+    assert_eq!(fastcdc::chunk_boundaries(&data), vec![0..1000]);
+}
+
+#[test]
+fn fastcdc_chunks_cover_input_within_size_bounds() {
+    let data = cdc_test_data(12 * 1024 * 1024, 2);
+    let chunks = fastcdc::chunk_boundaries(&data);
+
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.first().unwrap().start, 0);
+    assert_eq!(chunks.last().unwrap().end, data.len());
+    for pair in chunks.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start, "chunks must be contiguous");
+    }
+    for chunk in &chunks[..chunks.len() - 1] {
+        assert!(chunk.len() >= fastcdc::MIN_CHUNK_SIZE);
+        assert!(chunk.len() <= fastcdc::MAX_CHUNK_SIZE);
+    }
+}
+
+#[test]
+fn fastcdc_insertion_only_moves_nearby_boundaries() {
+    let original = cdc_test_data(12 * 1024 * 1024, 3);
+    let mut edited = original.clone();
+    let insert_at = original.len() / 2;
+    edited.splice(insert_at..insert_at, b"inserted bytes".iter().copied());
+
+    let slices = |data: &[u8]| -> std::collections::HashSet<Vec<u8>> {
+        fastcdc::chunk_boundaries(data)
+            .into_iter()
+            .map(|r| data[r].to_vec())
+            .collect()
+    };
+    let before = slices(&original);
+    let after = slices(&edited);
+    let shared = before.intersection(&after).count();
+    assert!(
+        shared + 2 >= before.len(),
+        "only chunks around the edit should change ({} of {} shared)",
+        shared,
+        before.len()
+    );
+}
+
+#[test]
+fn fastcdc_stream_chunker_matches_chunk_boundaries() {
+    /// Hands out at most 100 KiB per read, like a pipe or network stream.
+    struct Trickle<'a>(&'a [u8]);
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(100 * 1024);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let data = cdc_test_data(12 * 1024 * 1024, 4);
+    let mut chunker = fastcdc::StreamChunker::new(Trickle(&data));
+    let mut streamed = Vec::new();
+    while let Some(chunk) = chunker.next_chunk().unwrap() {
+        streamed.push(chunk.to_vec());
+    }
+
+    let expected: Vec<Vec<u8>> = fastcdc::chunk_boundaries(&data)
+        .into_iter()
+        .map(|r| data[r].to_vec())
+        .collect();
+    assert_eq!(streamed, expected);
+    assert!(fastcdc::StreamChunker::new(&b""[..]).next_chunk().unwrap().is_none());
+}
+
+// ============================================================
+// Chunked File Tests
+// ============================================================
+
+fn chunked_roundtrip(plaintext: &[u8], key: &FileKey) -> Vec<u8> {
+    let chunks = chunked::encrypt_chunks(plaintext, key).unwrap();
+    let cids: Vec<String> = (0..chunks.len()).map(|i| format!("bafychunk{}", i)).collect();
+    let manifest = ChunkManifest::new(&chunks, cids);
+    let sealed = chunked::encrypt_manifest(&manifest, key).unwrap();
+    let manifest = chunked::decrypt_manifest(&sealed, key).unwrap();
+
+    let mut out = Vec::new();
+    for (chunk, entry) in chunks.iter().zip(&manifest.chunks) {
+        out.extend(chunked::decrypt_chunk(&chunk.ciphertext, key, entry).unwrap());
+    }
+    out
+}
+
+#[test]
+fn chunked_roundtrip_restores_plaintext() {
+    let key = FileKey::generate();
+    let plaintext = cdc_test_data(6 * 1024 * 1024, 4);
+    assert_eq!(chunked_roundtrip(&plaintext, &key), plaintext);
+}
+
+#[test]
+fn chunked_manifest_records_total_size() {
+    let key = FileKey::generate();
+    let plaintext = cdc_test_data(5 * 1024 * 1024, 5);
+    let chunks = chunked::encrypt_chunks(&plaintext, &key).unwrap();
+    let manifest = ChunkManifest::new(&chunks, vec![String::new(); chunks.len()]);
+    assert_eq!(manifest.version, "v1");
+    assert_eq!(manifest.size, plaintext.len() as u64);
+}
+
+#[test]
+fn chunked_unchanged_chunks_keep_ids_across_versions() {
+    let key = FileKey::generate();
+    let v1 = cdc_test_data(8 * 1024 * 1024, 6);
+    let mut v2 = v1.clone();
+    v2[100] ^= 0xff;
+
+    let ids = |data: &[u8]| -> Vec<String> {
+        chunked::encrypt_chunks(data, &key)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect()
+    };
+    let (ids1, ids2) = (ids(&v1), ids(&v2));
+    assert_eq!(ids1.len(), ids2.len());
+    assert_ne!(ids1[0], ids2[0], "edited chunk must get a new id");
+    assert_eq!(ids1[1..], ids2[1..], "untouched chunks must keep their ids");
+}
+
+#[test]
+fn chunked_ids_differ_between_file_keys() {
+    let data = cdc_test_data(1000, 7);
+    let a = chunked::chunk_id(&FileKey::generate(), &data).unwrap();
+    let b = chunked::chunk_id(&FileKey::generate(), &data).unwrap();
+    assert_ne!(a, b);
+}
+
+#[test]
+fn chunked_swapped_chunk_fails() {
+    let key = FileKey::generate();
+    let plaintext = cdc_test_data(6 * 1024 * 1024, 8);
+    let chunks = chunked::encrypt_chunks(&plaintext, &key).unwrap();
+    assert!(chunks.len() > 1);
+    let manifest = ChunkManifest::new(&chunks, vec![String::new(); chunks.len()]);
+
+    // Ciphertext of chunk 1 served for manifest entry 0
+    let result = chunked::decrypt_chunk(&chunks[1].ciphertext, &key, &manifest.chunks[0]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn chunked_tampered_chunk_fails() {
+    let key = FileKey::generate();
+    let chunks = chunked::encrypt_chunks(&cdc_test_data(1000, 9), &key).unwrap();
+    let manifest = ChunkManifest::new(&chunks, vec![String::new()]);
+
+    let mut tampered = chunks[0].ciphertext.clone();
+    tampered[20] ^= 0x01;
+    let result = chunked::decrypt_chunk(&tampered, &key, &manifest.chunks[0]);
+    assert!(matches!(result, Err(ChunkError::EncryptionFailed(_))));
+}
+
+#[test]
+fn chunked_manifest_wrong_key_fails() {
+    let key = FileKey::generate();
+    let chunks = chunked::encrypt_chunks(&cdc_test_data(1000, 10), &key).unwrap();
+    let sealed =
+        chunked::encrypt_manifest(&ChunkManifest::new(&chunks, vec!["bafy".to_string()]), &key)
+            .unwrap();
+    assert!(chunked::decrypt_manifest(&sealed, &FileKey::generate()).is_err());
+}
+
+#[test]
+fn chunked_manifest_unknown_version_rejected() {
+    let key = FileKey::generate();
+    let chunks = chunked::encrypt_chunks(&cdc_test_data(1000, 11), &key).unwrap();
+    let mut manifest = ChunkManifest::new(&chunks, vec!["bafy".to_string()]);
+    manifest.version = "v2".to_string();
+    let sealed = chunked::encrypt_manifest(&manifest, &key).unwrap();
+
+    let result = chunked::decrypt_manifest(&sealed, &key);
+    assert!(matches!(result, Err(ChunkError::UnsupportedManifestVersion(v)) if v == "v2"));
+}
+
+#[test]
+fn chunked_manifest_size_mismatch_rejected() {
+    let key = FileKey::generate();
+    let chunks = chunked::encrypt_chunks(&cdc_test_data(1000, 12), &key).unwrap();
+    let mut manifest = ChunkManifest::new(&chunks, vec!["bafy".to_string()]);
+    manifest.size += 1;
+    let sealed = chunked::encrypt_manifest(&manifest, &key).unwrap();
+
+    let result = chunked::decrypt_manifest(&sealed, &key);
+    assert!(matches!(result, Err(ChunkError::InvalidManifest)));
+}
+
+// ============================================================
+// Length Padding Tests
+// ============================================================
+
+#[test]
+fn padding_scheme_names_roundtrip() {
+    for scheme in [PaddingScheme::None, PaddingScheme::Padme, PaddingScheme::PowerOfTwo] {
+        assert_eq!(PaddingScheme::from_name(scheme.as_str()).unwrap(), scheme);
+    }
+    assert_eq!(PaddingScheme::from_name("").unwrap(), PaddingScheme::None);
+    assert!(matches!(
+        PaddingScheme::from_name("random"),
+        Err(padding::PaddingError::UnknownScheme(_))
+    ));
+    assert_eq!(PaddingScheme::None.metadata_value(), None);
+    assert_eq!(PaddingScheme::Padme.metadata_value().as_deref(), Some("padme"));
+}
+
+#[test]
+fn padme_known_lengths() {
+    let padme = PaddingScheme::Padme;
+    assert_eq!(padme.padded_len(0), 0);
+    assert_eq!(padme.padded_len(1), 1);
+    assert_eq!(padme.padded_len(9), 10);
+    assert_eq!(padme.padded_len(1000), 1024);
+    assert_eq!(padme.padded_len(1025), 1088);
+    assert_eq!(padme.padded_len(1 << 20), 1 << 20);
+}
+
+#[test]
+fn padme_overhead_is_bounded() {
+    for len in (2..200_000).step_by(7) {
+        let padded = PaddingScheme::Padme.padded_len(len);
+        assert!(padded >= len);
+        assert!((padded - len) as f64 / len as f64 <= 0.12, "len {} -> {}", len, padded);
+    }
+}
+
+#[test]
+fn pow2_rounds_to_power_of_two() {
+    let pow2 = PaddingScheme::PowerOfTwo;
+    assert_eq!(pow2.padded_len(1000), 1024);
+    assert_eq!(pow2.padded_len(1024), 1024);
+    assert_eq!(pow2.padded_len(1025), 2048);
+}
+
+#[test]
+fn pad_content_roundtrip() {
+    let inputs: [&[u8]; 4] = [b"", b"hello", &[0x80, 0x00, 0x00], &[0u8; 100]];
+    for data in inputs {
+        for scheme in [PaddingScheme::Padme, PaddingScheme::PowerOfTwo] {
+            let padded = padding::pad_content(data, scheme);
+            assert_eq!(padded.len(), scheme.padded_len(data.len() + 1));
+            let mut unpadded = padded.to_vec();
+            padding::unpad_content(&mut unpadded).unwrap();
+            assert_eq!(unpadded, data);
+        }
+    }
+}
+
+#[test]
+fn unpad_content_rejects_missing_marker() {
+    let mut zeros = vec![0u8; 16];
+    assert!(padding::unpad_content(&mut zeros).is_err());
+    let mut no_marker = b"hello\x01\x00\x00".to_vec();
+    assert!(padding::unpad_content(&mut no_marker).is_err());
+}
+
+#[test]
+fn pad_json_stays_valid_json() {
+    let json = serde_json::to_vec(&envelope_test_file()).unwrap();
+    let padded = padding::pad_json(&json, PaddingScheme::PowerOfTwo);
+    assert!(padded.len().is_power_of_two());
+    let parsed: FileMetadata = serde_json::from_slice(&padded).unwrap();
+    assert_eq!(parsed.cid, "bafytest");
+}
+
+#[test]
+fn folder_envelope_padding_recorded_and_hides_length() {
+    let key = FolderKey::generate();
+    let seal = |name: &str| {
+        encrypt_folder_metadata_envelope(
+            &envelope_test_folder(name),
+            &key,
+            ENVELOPE_FOLDER_A_IPNS,
+            MetadataFormat::V2 { padding: PaddingScheme::PowerOfTwo },
+        )
+        .unwrap()
+    };
+    let (short, long) = (seal("a.txt"), seal("a-much-longer-name.txt"));
+
+    let short_value: serde_json::Value = serde_json::from_slice(&short).unwrap();
+    let long_value: serde_json::Value = serde_json::from_slice(&long).unwrap();
+    assert_eq!(short_value["padding"], "pow2");
+    assert_eq!(short_value["data"].as_str().unwrap().len(), long_value["data"].as_str().unwrap().len());
+
+    let decrypted = decrypt_folder_metadata_envelope(&long, &key, ENVELOPE_FOLDER_A_IPNS).unwrap();
+    match &decrypted.children[0] {
+        FolderChild::File(f) => assert_eq!(f.name, "a-much-longer-name.txt"),
+        _ => panic!("Expected file pointer"),
+    }
+}
+
+#[test]
+fn legacy_envelope_padding_hides_length() {
+    let key = FolderKey::generate();
+    let seal = |name: &str| {
+        encrypt_folder_metadata_envelope(
+            &envelope_test_folder(name),
+            &key,
+            ENVELOPE_FOLDER_A_IPNS,
+            MetadataFormat::Legacy { padding: PaddingScheme::PowerOfTwo },
+        )
+        .unwrap()
+    };
+    let (short, long) = (seal("a.txt"), seal("a-much-longer-name.txt"));
+
+    // Still a plain v1 envelope: the padding is trailing whitespace in the JSON
+    let short_value: serde_json::Value = serde_json::from_slice(&short).unwrap();
+    let long_value: serde_json::Value = serde_json::from_slice(&long).unwrap();
+    let fields: Vec<&str> = short_value.as_object().unwrap().keys().map(String::as_str).collect();
+    assert_eq!(fields, ["data", "iv"]);
+    assert_eq!(short_value["data"].as_str().unwrap().len(), long_value["data"].as_str().unwrap().len());
+
+    let decrypted = decrypt_folder_metadata_envelope(&long, &key, ENVELOPE_FOLDER_B_IPNS).unwrap();
+    match &decrypted.children[0] {
+        FolderChild::File(f) => assert_eq!(f.name, "a-much-longer-name.txt"),
+        _ => panic!("Expected file pointer"),
+    }
+}
+
+#[test]
+fn unpadded_envelope_omits_padding_field() {
+    let key = FolderKey::generate();
+    let envelope = encrypt_file_metadata_envelope(
+        &envelope_test_file(),
+        &key,
+        ENVELOPE_FOLDER_A_IPNS,
+        V2_ENVELOPE,
+    )
+    .unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    assert!(value.get("padding").is_none());
+    assert!(!String::from_utf8(serde_json::to_vec(&envelope_test_file()).unwrap())
+        .unwrap()
+        .contains("padding"));
+}
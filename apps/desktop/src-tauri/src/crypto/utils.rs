@@ -5,6 +5,7 @@ use thiserror::Error;
 use zeroize::Zeroize;
 
 use super::aes::{AES_IV_SIZE, AES_KEY_SIZE};
+use super::aes_ctr::{AES_CTR_IV_SIZE, AES_CTR_NONCE_SIZE};
 
 #[derive(Debug, Error)]
 pub enum UtilError {
@@ -33,6 +34,14 @@ pub fn generate_iv() -> [u8; AES_IV_SIZE] {
     iv
 }
 
+/// Generate a random 16-byte AES-CTR IV: an 8-byte random nonce followed by
+/// a zero 64-bit counter.
+pub fn generate_ctr_iv() -> [u8; AES_CTR_IV_SIZE] {
+    let mut iv = [0u8; AES_CTR_IV_SIZE];
+    rand::rngs::OsRng.fill_bytes(&mut iv[..AES_CTR_NONCE_SIZE]);
+    iv
+}
+
 /// Convert a hex string to bytes.
 pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, UtilError> {
     hex::decode(hex).map_err(|_| UtilError::InvalidHex)
@@ -2,12 +2,17 @@
 //!
 //! - MetadataCache: Folder metadata keyed by IPNS name with 30s TTL
 //! - ContentCache: Decrypted file content keyed by CID with 256 MiB LRU budget
+//! - BlockCache: Decrypted blocks of range-read (CTR) files with a 32 MiB LRU budget
+//! - DiskCache: Encrypted content as downloaded from IPFS, persisted under the
+//!   user cache dir with a configurable LRU budget (second tier behind the above)
 
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
-use zeroize::Zeroize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::crypto::folder::FolderMetadata;
+use crate::crypto::secure_mem::SecureBuffer;
 
 /// Time-to-live for cached folder metadata (matches 30s sync polling interval).
 pub const METADATA_TTL: Duration = Duration::from_secs(30);
@@ -15,6 +20,16 @@ pub const METADATA_TTL: Duration = Duration::from_secs(30);
 /// Maximum memory budget for content cache (256 MiB).
 pub const MAX_CACHE_SIZE: usize = 256 * 1024 * 1024;
 
+/// Block size for range reads of CTR files: the 1 MiB chunk size covered by
+/// CTR chunk hashes (a multiple of the AES block).
+pub const STREAM_BLOCK_SIZE: u64 = crate::crypto::aes_ctr::CTR_CHUNK_SIZE as u64;
+
+/// Maximum memory budget for the block cache (32 MiB).
+pub const MAX_BLOCK_CACHE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Default on-disk cache budget (2 GiB), overridden by `CIPHERBOX_DISK_CACHE_MB`.
+pub const DEFAULT_DISK_CACHE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
 // ── Metadata Cache ────────────────────────────────────────────────────────────
 
 /// Cached folder metadata entry with timestamp.
@@ -74,22 +89,20 @@ impl MetadataCache {
 // ── Content Cache ─────────────────────────────────────────────────────────────
 
 /// Cached decrypted file content entry with LRU tracking.
+///
+/// The plaintext lives in a `SecureBuffer`: locked in RAM where possible and
+/// zeroized on drop.
 struct CachedContent {
-    data: Vec<u8>,
+    data: SecureBuffer,
     accessed_at: Instant,
     size: usize,
 }
 
-impl Drop for CachedContent {
-    fn drop(&mut self) {
-        self.data.zeroize();
-    }
-}
-
 /// In-memory LRU cache for decrypted file content, keyed by CID.
 ///
 /// Evicts least-recently-accessed entries when total size exceeds `MAX_CACHE_SIZE`.
-/// Content is decrypted plaintext -- never persisted to disk.
+/// Content is decrypted plaintext -- never persisted to disk, and kept out of
+/// swap while `RLIMIT_MEMLOCK` allows.
 pub struct ContentCache {
     entries: HashMap<String, CachedContent>,
     current_size: usize,
@@ -111,7 +124,7 @@ impl ContentCache {
         if self.entries.contains_key(cid) {
             let entry = self.entries.get_mut(cid).unwrap();
             entry.accessed_at = Instant::now();
-            Some(&entry.data)
+            Some(entry.data.as_slice())
         } else {
             None
         }
@@ -136,7 +149,7 @@ impl ContentCache {
         self.entries.insert(
             cid.to_string(),
             CachedContent {
-                data,
+                data: SecureBuffer::new(data),
                 accessed_at: Instant::now(),
                 size,
             },
@@ -166,11 +179,297 @@ impl ContentCache {
     /// Clear all cached content entries, zeroizing each one via Drop.
     /// Used during FUSE destroy() for defense-in-depth cleanup.
     pub fn clear(&mut self) {
-        self.entries.clear(); // Each CachedContent::drop() zeroizes data
+        self.entries.clear(); // Each SecureBuffer zeroizes on drop
         self.current_size = 0;
     }
 }
 
+// ── Block Cache ───────────────────────────────────────────────────────────────
+
+/// Cached decrypted block with LRU tracking.
+struct CachedBlock {
+    data: SecureBuffer,
+    accessed_at: Instant,
+}
+
+/// In-memory LRU cache for decrypted `STREAM_BLOCK_SIZE` blocks of CTR files
+/// and GCM-SEG segments, keyed by (CID, block or segment index).
+///
+/// Backs range reads of CTR and GCM-SEG files, which are never downloaded
+/// whole. The last block or segment of a file may be shorter.
+pub struct BlockCache {
+    entries: HashMap<(String, u64), CachedBlock>,
+    current_size: usize,
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            current_size: 0,
+        }
+    }
+
+    /// Get a cached block, updating the access time for LRU tracking.
+    pub fn get(&mut self, cid: &str, index: u64) -> Option<&[u8]> {
+        let entry = self.entries.get_mut(&(cid.to_string(), index))?;
+        entry.accessed_at = Instant::now();
+        Some(entry.data.as_slice())
+    }
+
+    /// Store a decrypted block, evicting LRU blocks if over budget.
+    pub fn set(&mut self, cid: &str, index: u64, data: Vec<u8>) {
+        let size = data.len();
+        if let Some(old) = self.entries.remove(&(cid.to_string(), index)) {
+            self.current_size = self.current_size.saturating_sub(old.data.as_slice().len());
+        }
+
+        while self.current_size + size > MAX_BLOCK_CACHE_SIZE && !self.entries.is_empty() {
+            self.evict_lru();
+        }
+
+        self.current_size += size;
+        self.entries.insert(
+            (cid.to_string(), index),
+            CachedBlock {
+                data: SecureBuffer::new(data),
+                accessed_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Evict the least recently accessed block.
+    fn evict_lru(&mut self) {
+        if let Some(oldest_key) = self
+            .entries
+            .iter()
+            .min_by_key(|(_, v)| v.accessed_at)
+            .map(|(k, _)| k.clone())
+        {
+            if let Some(evicted) = self.entries.remove(&oldest_key) {
+                self.current_size = self.current_size.saturating_sub(evicted.data.as_slice().len());
+            }
+        }
+    }
+
+    /// Current total size of cached blocks in bytes.
+    #[allow(dead_code)]
+    pub fn current_size(&self) -> usize {
+        self.current_size
+    }
+
+    /// Clear all cached blocks, zeroizing each one via Drop.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.current_size = 0;
+    }
+}
+
+// ── Disk Cache ────────────────────────────────────────────────────────────────
+
+/// Directory of the on-disk cache: `<user cache dir>/cipherbox/content`.
+pub fn disk_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("cipherbox").join("content"))
+}
+
+/// Disk cache key of block (or segment) `index` of a range-read file.
+pub fn block_key(cid: &str, index: u64) -> String {
+    format!("{}.{}", cid, index)
+}
+
+/// Keys become file names, so only CID characters (and the `.` of block keys)
+/// are accepted. Temp files start with `.` and never collide with a key.
+fn is_valid_disk_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with('.')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+}
+
+/// On-disk entry with LRU tracking.
+struct DiskEntry {
+    size: u64,
+    accessed_at: SystemTime,
+}
+
+struct DiskIndex {
+    entries: HashMap<String, DiskEntry>,
+    current_size: u64,
+}
+
+/// Persistent LRU cache of encrypted content, one file per key.
+///
+/// Holds the ciphertext exactly as served by IPFS (whole objects keyed by CID,
+/// CTR blocks keyed by `block_key`), so nothing readable reaches the disk and
+/// decryption authenticates cached bytes the same way as downloaded ones.
+/// Survives restarts: the index is rebuilt from the directory on open, with
+/// file mtimes as access times. A budget of 0 disables the cache.
+///
+/// Shared with background prefetch tasks, hence the internal lock.
+pub struct DiskCache {
+    dir: PathBuf,
+    budget: u64,
+    index: Mutex<DiskIndex>,
+}
+
+impl DiskCache {
+    /// Open (or create) the cache in `dir`, evicting down to `budget`.
+    ///
+    /// Falls back to a disabled cache if the directory is unusable.
+    pub fn open(dir: PathBuf, budget: u64) -> Self {
+        let mut cache = Self {
+            dir,
+            budget,
+            index: Mutex::new(DiskIndex {
+                entries: HashMap::new(),
+                current_size: 0,
+            }),
+        };
+        if budget == 0 {
+            return cache;
+        }
+        if let Err(e) = std::fs::create_dir_all(&cache.dir) {
+            log::warn!("Disk cache disabled: cannot create {}: {}", cache.dir.display(), e);
+            cache.budget = 0;
+            return cache;
+        }
+
+        let mut index = cache.index.lock().unwrap();
+        if let Ok(dir_entries) = std::fs::read_dir(&cache.dir) {
+            for entry in dir_entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !is_valid_disk_key(&name) {
+                    // Leftover temp file from an interrupted write
+                    let _ = std::fs::remove_file(entry.path());
+                    continue;
+                }
+                let Ok(meta) = entry.metadata() else { continue };
+                if !meta.is_file() {
+                    continue;
+                }
+                index.current_size += meta.len();
+                index.entries.insert(
+                    name,
+                    DiskEntry {
+                        size: meta.len(),
+                        accessed_at: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    },
+                );
+            }
+        }
+        cache.evict_to(&mut index, budget);
+        log::info!(
+            "Disk cache: {} entries, {} bytes in {}",
+            index.entries.len(),
+            index.current_size,
+            cache.dir.display()
+        );
+        drop(index);
+        cache
+    }
+
+    /// A cache that stores nothing.
+    pub fn disabled() -> Self {
+        Self::open(PathBuf::new(), 0)
+    }
+
+    /// Read cached content, updating its access time for LRU tracking.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let now = SystemTime::now();
+        {
+            let mut index = self.index.lock().unwrap();
+            index.entries.get_mut(key)?.accessed_at = now;
+        }
+
+        let path = self.dir.join(key);
+        match std::fs::read(&path) {
+            Ok(data) => {
+                // Persist the access time for the index rebuilt on next start
+                if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+                    let _ = file.set_modified(now);
+                }
+                Some(data)
+            }
+            Err(e) => {
+                log::debug!("Disk cache read of {} failed: {}", key, e);
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    /// Store content, evicting LRU entries if over budget. Content larger than
+    /// the whole budget is not stored. Failures are logged and ignored.
+    pub fn put(&self, key: &str, data: &[u8]) {
+        let size = data.len() as u64;
+        if size > self.budget || !is_valid_disk_key(key) {
+            return;
+        }
+
+        // Write under a temp name and rename, so a crash never leaves a
+        // truncated entry behind
+        let temp = self.dir.join(format!(".tmp-{}-{}", key, std::process::id()));
+        let written = std::fs::write(&temp, data).and_then(|_| std::fs::rename(&temp, self.dir.join(key)));
+        if let Err(e) = written {
+            log::warn!("Disk cache write of {} failed: {}", key, e);
+            let _ = std::fs::remove_file(&temp);
+            return;
+        }
+
+        let mut index = self.index.lock().unwrap();
+        if let Some(old) = index.entries.remove(key) {
+            index.current_size = index.current_size.saturating_sub(old.size);
+        }
+        self.evict_to(&mut index, self.budget - size);
+        index.current_size += size;
+        index.entries.insert(
+            key.to_string(),
+            DiskEntry {
+                size,
+                accessed_at: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Drop an entry, e.g. after its content failed to decrypt.
+    pub fn remove(&self, key: &str) {
+        let mut index = self.index.lock().unwrap();
+        if let Some(old) = index.entries.remove(key) {
+            index.current_size = index.current_size.saturating_sub(old.size);
+            let _ = std::fs::remove_file(self.dir.join(key));
+        }
+    }
+
+    /// Evict least recently accessed entries until at most `limit` bytes remain.
+    fn evict_to(&self, index: &mut DiskIndex, limit: u64) {
+        while index.current_size > limit {
+            let Some(oldest_key) = index
+                .entries
+                .iter()
+                .min_by_key(|(_, v)| v.accessed_at)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = index.entries.remove(&oldest_key) {
+                index.current_size = index.current_size.saturating_sub(evicted.size);
+                let _ = std::fs::remove_file(self.dir.join(&oldest_key));
+            }
+        }
+    }
+
+    /// Current total size of cached content in bytes.
+    #[allow(dead_code)]
+    pub fn current_size(&self) -> u64 {
+        self.index.lock().unwrap().current_size
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,4 +581,114 @@ mod tests {
         assert_eq!(cache.current_size(), 5);
         assert_eq!(cache.get("cid1").unwrap(), &[1, 2, 3, 4, 5]);
     }
+
+    // ── BlockCache tests ──────────────────────────────────────────────────
+
+    #[test]
+    fn test_block_cache_keyed_by_cid_and_index() {
+        let mut cache = BlockCache::new();
+        cache.set("cid1", 0, vec![1, 2]);
+        cache.set("cid1", 1, vec![3]);
+        cache.set("cid2", 0, vec![4]);
+
+        assert_eq!(cache.get("cid1", 0).unwrap(), &[1, 2]);
+        assert_eq!(cache.get("cid1", 1).unwrap(), &[3]);
+        assert_eq!(cache.get("cid2", 0).unwrap(), &[4]);
+        assert!(cache.get("cid2", 1).is_none());
+        assert_eq!(cache.current_size(), 4);
+    }
+
+    #[test]
+    fn test_block_cache_evicts_lru_block() {
+        let mut cache = BlockCache::new();
+        let block = MAX_BLOCK_CACHE_SIZE / 2;
+
+        cache.set("cid", 0, vec![0u8; block]);
+        cache.set("cid", 1, vec![1u8; block]);
+        let _ = cache.get("cid", 0);
+        cache.set("cid", 2, vec![2u8; block]);
+
+        assert!(cache.get("cid", 0).is_some());
+        assert!(cache.get("cid", 1).is_none(), "least recently used block is evicted");
+        assert!(cache.get("cid", 2).is_some());
+        assert_eq!(cache.current_size(), 2 * block);
+    }
+
+    #[test]
+    fn test_block_cache_clear() {
+        let mut cache = BlockCache::new();
+        cache.set("cid", 0, vec![1, 2, 3]);
+        cache.clear();
+        assert!(cache.get("cid", 0).is_none());
+        assert_eq!(cache.current_size(), 0);
+    }
+
+    // ── DiskCache tests ───────────────────────────────────────────────────
+
+    fn disk_cache_dir_for(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_disk_cache_persists_across_open() {
+        let dir = disk_cache_dir_for("cipherbox-test-disk-cache-persist");
+        let cache = DiskCache::open(dir.clone(), 1024);
+        cache.put("bafyone", b"ciphertext one");
+        cache.put(&block_key("bafytwo", 3), b"block");
+        assert_eq!(cache.get("bafyone").unwrap(), b"ciphertext one");
+        drop(cache);
+
+        let reopened = DiskCache::open(dir.clone(), 1024);
+        assert_eq!(reopened.get("bafyone").unwrap(), b"ciphertext one");
+        assert_eq!(reopened.get("bafytwo.3").unwrap(), b"block");
+        assert_eq!(reopened.current_size(), 19);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_lru_eviction_order() {
+        let dir = disk_cache_dir_for("cipherbox-test-disk-cache-lru");
+        let cache = DiskCache::open(dir.clone(), 250);
+        cache.put("bafya", &[1u8; 100]);
+        std::thread::sleep(Duration::from_millis(10));
+        cache.put("bafyb", &[2u8; 100]);
+        std::thread::sleep(Duration::from_millis(10));
+        cache.get("bafya"); // bafyb becomes least recently used
+        std::thread::sleep(Duration::from_millis(10));
+        cache.put("bafyc", &[3u8; 100]);
+
+        assert!(cache.get("bafya").is_some());
+        assert!(cache.get("bafyb").is_none());
+        assert!(!dir.join("bafyb").exists());
+        assert!(cache.get("bafyc").is_some());
+        assert!(cache.current_size() <= 250);
+
+        // Reopening with a smaller budget evicts down to it
+        drop(cache);
+        let reopened = DiskCache::open(dir.clone(), 100);
+        assert_eq!(reopened.current_size(), 100);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_rejects_oversized_and_unsafe_keys() {
+        let dir = disk_cache_dir_for("cipherbox-test-disk-cache-keys");
+        let cache = DiskCache::open(dir.clone(), 10);
+        cache.put("bafybig", &[0u8; 11]);
+        cache.put("../escape", b"x");
+        cache.put(".hidden", b"x");
+        assert!(cache.get("bafybig").is_none());
+        assert_eq!(cache.current_size(), 0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_cache_disabled_stores_nothing() {
+        let cache = DiskCache::disabled();
+        cache.put("bafyone", b"data");
+        assert!(cache.get("bafyone").is_none());
+    }
 }
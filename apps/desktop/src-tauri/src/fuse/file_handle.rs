@@ -8,8 +8,33 @@ use std::fs;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
 use zeroize::Zeroize;
 
+use crate::crypto::keys::FileKey;
+
+/// Remote revision of a file that a writable handle's content is based on,
+/// captured at open. On save, a remote that has moved past it means another
+/// device saved the file in the meantime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileBase {
+    /// Sequence number of the file's IPNS record, if known.
+    pub sequence: Option<u64>,
+    /// Content CID of the revision.
+    pub cid: String,
+}
+
+impl FileBase {
+    /// Whether the remote revision with content `remote_cid` is one this
+    /// handle may overwrite: its own base, or the last save of this device
+    /// (`own_cid`), which the handle's content follows locally.
+    pub fn accepts(&self, remote_cid: &str, own_cid: Option<&str>) -> bool {
+        remote_cid == self.cid || own_cid == Some(remote_cid)
+    }
+}
+
 /// Open file handle tracking active reads and writes.
 ///
 /// For read-only opens, only `cached_content` is populated.
@@ -26,8 +51,12 @@ pub struct OpenFileHandle {
     pub dirty: bool,
     /// Pre-fetched decrypted content for reads (populated on first read).
     pub cached_content: Option<Vec<u8>>,
+    /// Unwrapped file key for range reads of CTR files (set on first read).
+    pub stream_key: Option<FileKey>,
     /// Original file size before modifications.
     pub original_size: u64,
+    /// Remote revision a writable open started from (None for new files).
+    pub base: Option<FileBase>,
 }
 
 impl OpenFileHandle {
@@ -39,7 +68,9 @@ impl OpenFileHandle {
             temp_path: None,
             dirty: false,
             cached_content: None,
+            stream_key: None,
             original_size: 0,
+            base: None,
         }
     }
 
@@ -89,7 +120,9 @@ impl OpenFileHandle {
             temp_path: Some(temp_path),
             dirty: false,
             cached_content: None,
+            stream_key: None,
             original_size,
+            base: None,
         })
     }
 
@@ -164,6 +197,29 @@ impl OpenFileHandle {
         fs::read(temp_path).map_err(|e| format!("Failed to read temp file: {}", e))
     }
 
+    /// Open the temp file for reading (used to stream it to the upload).
+    pub fn open_reader(&self) -> Result<fs::File, String> {
+        let temp_path = self
+            .temp_path
+            .as_ref()
+            .ok_or("No temp file for read")?;
+
+        fs::File::open(temp_path).map_err(|e| format!("Failed to open temp file: {}", e))
+    }
+
+    /// Open the temp file for writing (used to restore spooled content into it).
+    pub fn open_writer(&self) -> Result<fs::File, String> {
+        let temp_path = self
+            .temp_path
+            .as_ref()
+            .ok_or("No temp file for write")?;
+
+        fs::OpenOptions::new()
+            .write(true)
+            .open(temp_path)
+            .map_err(|e| format!("Failed to open temp file for write: {}", e))
+    }
+
     /// Truncate the temp file to the given size.
     pub fn truncate(&self, size: u64) -> Result<(), String> {
         let temp_path = self
@@ -341,4 +397,14 @@ mod tests {
 
         let _ = fs::remove_dir(&temp_dir);
     }
+
+    #[test]
+    fn test_file_base_accepts_own_revisions_only() {
+        let base = FileBase { sequence: Some(3), cid: "bafybase".to_string() };
+
+        assert!(base.accepts("bafybase", None));
+        assert!(base.accepts("bafyown", Some("bafyown")));
+        assert!(!base.accepts("bafyother", None));
+        assert!(!base.accepts("bafyother", Some("bafyown")));
+    }
 }
@@ -14,10 +14,48 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use zeroize::Zeroizing;
-
 use crate::crypto;
 use crate::crypto::folder::{FolderChild, FolderMetadata};
+use crate::crypto::keys::{FolderKey, IpnsSigningKey, MlKemSecretKey, Secp256k1SecretKey};
+
+/// Keys of a folder created on this device.
+#[cfg(feature = "fuse")]
+pub struct NewFolderKeys {
+    pub folder_key: FolderKey,
+    /// Hex of `folder_key` wrapped to the user, for the parent's metadata.
+    pub encrypted_folder_key: String,
+    pub ipns_private_key: IpnsSigningKey,
+    pub ipns_name: String,
+}
+
+#[cfg(feature = "fuse")]
+impl NewFolderKeys {
+    /// Generate the keys of a new folder. The folder key is hybrid-wrapped
+    /// when the user has an ML-KEM-768 public key, ECIES-wrapped otherwise.
+    pub fn generate(public_key: &[u8], mlkem_public_key: Option<&[u8]>) -> Result<Self, String> {
+        let folder_key = FolderKey::generate();
+        let wrapped_folder_key = crypto::ecies::wrap_key_hybrid_if_available(
+            folder_key.as_bytes(),
+            public_key,
+            mlkem_public_key,
+        )
+        .map_err(|e| format!("Folder key wrapping failed: {}", e))?;
+
+        let (ipns_private_key, ipns_public_key) = IpnsSigningKey::generate();
+        let ipns_public_key: [u8; 32] = ipns_public_key
+            .try_into()
+            .map_err(|_| "Invalid IPNS public key length".to_string())?;
+        let ipns_name = crypto::ipns::derive_ipns_name(&ipns_public_key)
+            .map_err(|e| format!("Failed to derive IPNS name: {}", e))?;
+
+        Ok(Self {
+            folder_key,
+            encrypted_folder_key: hex::encode(&wrapped_folder_key),
+            ipns_private_key,
+            ipns_name,
+        })
+    }
+}
 
 /// Normalize a filename to NFC (composed) form for consistent HashMap lookups.
 /// macOS NFS client may send names in either NFC or NFD form; FUSE-T's go-nfsv4
@@ -44,8 +82,7 @@ pub enum InodeKind {
     Root {
         /// Decrypted Ed25519 IPNS private key for signing root folder metadata.
         /// Populated from AppState.root_ipns_private_key during init.
-        /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-        ipns_private_key: Option<Zeroizing<Vec<u8>>>,
+        ipns_private_key: Option<IpnsSigningKey>,
         /// Root folder IPNS name for metadata resolution.
         ipns_name: Option<String>,
     },
@@ -57,12 +94,10 @@ pub enum InodeKind {
         /// Hex-encoded ECIES-wrapped AES key for this folder's metadata.
         encrypted_folder_key: String,
         /// Decrypted 32-byte AES folder key for metadata encryption/decryption.
-        /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-        folder_key: Zeroizing<Vec<u8>>,
+        folder_key: FolderKey,
         /// Decrypted Ed25519 IPNS private key for signing this folder's IPNS records.
         /// Critical for write operations (plan 09-06).
-        /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-        ipns_private_key: Option<Zeroizing<Vec<u8>>>,
+        ipns_private_key: Option<IpnsSigningKey>,
         /// Whether children have been loaded from IPNS metadata.
         children_loaded: bool,
     },
@@ -77,8 +112,12 @@ pub enum InodeKind {
         iv: String,
         /// Original file size in bytes (before encryption).
         size: u64,
-        /// Encryption mode ("GCM" for v1/standard, "CTR" for streaming media).
+        /// Encryption mode ("GCM" for v1/standard, "CTR" for streaming media, "GCM-SEG" for large files, "CHUNKED" for large files with chunked uploads).
         encryption_mode: String,
+        /// Content padding scheme ("padme" or "pow2"), None if unpadded.
+        padding: Option<String>,
+        /// CTR only: hex SHA-256 of each 1 MiB ciphertext chunk, None if not recorded.
+        chunk_hashes: Option<Vec<String>>,
         /// Per-file IPNS name for FilePointer resolution (None for files loaded from remote metadata before IPNS resolve).
         file_meta_ipns_name: Option<String>,
         /// Whether per-file IPNS metadata has been resolved.
@@ -86,8 +125,7 @@ pub enum InodeKind {
         /// Decrypted Ed25519 IPNS private key for signing this file's IPNS record.
         /// For new files: generated randomly, ECIES-wrapped in FilePointer.
         /// For legacy files: derived via HKDF from user privateKey + fileId.
-        /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-        file_ipns_private_key: Option<Zeroizing<Vec<u8>>>,
+        file_ipns_private_key: Option<IpnsSigningKey>,
         /// Cached hex-encoded ECIES-wrapped IPNS private key for FilePointer serialization.
         /// Avoids redundant ECIES wrapping on every metadata publish.
         file_ipns_key_encrypted_hex: Option<String>,
@@ -129,6 +167,11 @@ pub struct InodeTable {
     pub name_to_ino: HashMap<(u64, String), u64>,
     /// Atomic counter for allocating new inode numbers.
     next_ino: AtomicU64,
+    /// Leave IPNS private keys wrapped (read-only mount): folders and files
+    /// get no signing key, so nothing can be published.
+    pub read_only: bool,
+    /// User's ML-KEM-768 private key, for hybrid-wrapped folder keys.
+    pub mlkem_private_key: Option<MlKemSecretKey>,
 }
 
 impl InodeTable {
@@ -173,6 +216,8 @@ impl InodeTable {
             inodes,
             name_to_ino: HashMap::new(),
             next_ino: AtomicU64::new(2),
+            read_only: false,
+            mlkem_private_key: None,
         }
     }
 
@@ -226,7 +271,8 @@ impl InodeTable {
     ///
     /// For each child:
     /// - **Subfolder:** Decrypts `folder_key_encrypted` and `ipns_private_key_encrypted`
-    ///   using the user's secp256k1 private key (ECIES unwrap).
+    ///   using the user's secp256k1 private key (ECIES unwrap). With `read_only`
+    ///   set, IPNS private keys (of folders and files) are never unwrapped.
     /// - **FilePointer:** Creates a placeholder inode with fileMetaIpnsName set.
     ///   The file's CID/key/IV/size are NOT yet known -- they require IPNS resolution.
     ///   Callers must resolve FilePointers before the first READDIR (NFS stability).
@@ -242,7 +288,7 @@ impl InodeTable {
         &mut self,
         parent_ino: u64,
         metadata: &FolderMetadata,
-        private_key: &[u8],
+        private_key: &Secp256k1SecretKey,
         public_key: &[u8],
         merge_only: bool,
     ) -> Result<(), String> {
@@ -292,34 +338,44 @@ impl InodeTable {
                                 "Invalid folderKeyEncrypted hex for folder '{}'",
                                 folder.name
                             ))?;
-                    let folder_key = Zeroizing::new(
-                        crypto::ecies::unwrap_key(&encrypted_folder_key_bytes, private_key)
-                            .map_err(|e| format!(
-                                "Failed to decrypt folder key for '{}': {}",
-                                folder.name, e
-                            ))?
-                    );
+                    let folder_key: FolderKey = crypto::ecies::unwrap_secret_key_hybrid(
+                        &encrypted_folder_key_bytes,
+                        private_key,
+                        self.mlkem_private_key.as_ref(),
+                    )
+                    .map_err(|e| format!(
+                        "Failed to decrypt folder key for '{}': {}",
+                        folder.name, e
+                    ))?;
 
                     // Decrypt IPNS private key (ECIES unwrap)
-                    let encrypted_ipns_key_bytes =
-                        hex::decode(&folder.ipns_private_key_encrypted)
-                            .map_err(|_| format!(
-                                "Invalid ipnsPrivateKeyEncrypted hex for folder '{}'",
-                                folder.name
-                            ))?;
-                    let ipns_private_key = Zeroizing::new(
-                        crypto::ecies::unwrap_key(&encrypted_ipns_key_bytes, private_key)
-                            .map_err(|e| format!(
-                                "Failed to decrypt IPNS private key for '{}': {}",
-                                folder.name, e
-                            ))?
-                    );
+                    let ipns_private_key: Option<IpnsSigningKey> = if self.read_only {
+                        None
+                    } else {
+                        let encrypted_ipns_key_bytes =
+                            hex::decode(&folder.ipns_private_key_encrypted)
+                                .map_err(|_| format!(
+                                    "Invalid ipnsPrivateKeyEncrypted hex for folder '{}'",
+                                    folder.name
+                                ))?;
+                        Some(
+                            crypto::ecies::unwrap_secret_key(&encrypted_ipns_key_bytes, private_key)
+                                .map_err(|e| format!(
+                                    "Failed to decrypt IPNS private key for '{}': {}",
+                                    folder.name, e
+                                ))?,
+                        )
+                    };
 
                     let created = UNIX_EPOCH + Duration::from_millis(folder.created_at);
                     let modified = UNIX_EPOCH + Duration::from_millis(folder.modified_at);
 
                     // Preserve existing children list and loaded state for existing folders
-                    let (existing_children, was_loaded) = if existing_ino.is_some() {
+                    // (not when the name now belongs to a different folder)
+                    let same_folder = self.inodes.get(&ino).is_some_and(|old| {
+                        matches!(&old.kind, InodeKind::Folder { ipns_name, .. } if *ipns_name == folder.ipns_name)
+                    });
+                    let (existing_children, was_loaded) = if same_folder {
                         let old = self.inodes.get(&ino);
                         let ch = old.and_then(|o| o.children.clone());
                         let loaded = old.map(|o| matches!(&o.kind, InodeKind::Folder { children_loaded: true, .. })).unwrap_or(false);
@@ -354,7 +410,7 @@ impl InodeTable {
                             ipns_name: folder.ipns_name.clone(),
                             encrypted_folder_key: folder.folder_key_encrypted.clone(),
                             folder_key,
-                            ipns_private_key: Some(ipns_private_key),
+                            ipns_private_key,
                             children_loaded: was_loaded,
                         },
                         attr,
@@ -377,7 +433,10 @@ impl InodeTable {
                         .and_then(|ino| self.inodes.get(&ino))
                     {
                         match &existing.kind {
-                            InodeKind::File { file_meta_resolved: true, .. } => {
+                            // Only while the name still points at the same file
+                            InodeKind::File { file_meta_resolved: true, file_meta_ipns_name, .. }
+                                if file_meta_ipns_name.as_deref() == Some(file_pointer.file_meta_ipns_name.as_str()) =>
+                            {
                                 (true, Some(existing.kind.clone()))
                             }
                             _ => (false, None),
@@ -393,11 +452,13 @@ impl InodeTable {
                         // Decrypt file IPNS private key from FilePointer if available,
                         // falling back to HKDF derivation for legacy files only.
                         let has_encrypted_key = file_pointer.ipns_private_key_encrypted.is_some();
-                        let file_ipns_key = if let Some(ref encrypted_hex) = file_pointer.ipns_private_key_encrypted {
+                        let file_ipns_key = if self.read_only {
+                            None
+                        } else if let Some(ref encrypted_hex) = file_pointer.ipns_private_key_encrypted {
                             match hex::decode(encrypted_hex) {
                                 Ok(encrypted_bytes) => {
-                                    match crypto::ecies::unwrap_key(&encrypted_bytes, private_key) {
-                                        Ok(key) => Some(Zeroizing::new(key)),
+                                    match crypto::ecies::unwrap_secret_key::<IpnsSigningKey>(&encrypted_bytes, private_key) {
+                                        Ok(key) => Some(key),
                                         Err(e) => {
                                             log::error!(
                                                 "File '{}': failed to decrypt ipnsPrivateKeyEncrypted: {}. Cannot use HKDF for random-key files.",
@@ -422,32 +483,24 @@ impl InodeTable {
                         // HKDF fallback ONLY for legacy FilePointers (no encrypted key present).
                         // If ipnsPrivateKeyEncrypted was present but decryption failed, HKDF would
                         // produce a key that doesn't match the file's random IPNS name.
-                        let file_ipns_key = if file_ipns_key.is_some() {
+                        let file_ipns_key = if file_ipns_key.is_some() || self.read_only {
                             file_ipns_key
                         } else if !has_encrypted_key {
-                            if let Ok(pk_arr) = <[u8; 32]>::try_from(private_key) {
-                                match crypto::hkdf::derive_file_ipns_keypair(&pk_arr, &file_pointer.id) {
-                                    Ok((derived_key, _, _)) => {
-                                        log::debug!(
-                                            "File '{}': derived IPNS key via HKDF fallback (legacy file).",
-                                            file_pointer.name
-                                        );
-                                        Some(derived_key)
-                                    }
-                                    Err(e) => {
-                                        log::warn!(
-                                            "File '{}': HKDF fallback also failed: {}. File IPNS updates will be unavailable.",
-                                            file_pointer.name, e
-                                        );
-                                        None
-                                    }
+                            match crypto::hkdf::derive_file_ipns_keypair(private_key, &file_pointer.id) {
+                                Ok((derived_key, _, _)) => {
+                                    log::debug!(
+                                        "File '{}': derived IPNS key via HKDF fallback (legacy file).",
+                                        file_pointer.name
+                                    );
+                                    Some(derived_key)
+                                }
+                                Err(e) => {
+                                    log::warn!(
+                                        "File '{}': HKDF fallback also failed: {}. File IPNS updates will be unavailable.",
+                                        file_pointer.name, e
+                                    );
+                                    None
                                 }
-                            } else {
-                                log::warn!(
-                                    "File '{}': private key is not 32 bytes, cannot derive HKDF fallback.",
-                                    file_pointer.name
-                                );
-                                None
                             }
                         } else {
                             None
@@ -459,7 +512,7 @@ impl InodeTable {
                         let cached_encrypted_hex = if file_pointer.ipns_private_key_encrypted.is_some() {
                             file_pointer.ipns_private_key_encrypted.clone()
                         } else if let Some(ref key) = file_ipns_key {
-                            match crypto::ecies::wrap_key(key, public_key) {
+                            match crypto::ecies::wrap_key(key.as_bytes(), public_key) {
                                 Ok(wrapped) => {
                                     log::info!(
                                         "File '{}': wrapped HKDF-derived IPNS key for lazy migration.",
@@ -485,6 +538,8 @@ impl InodeTable {
                             iv: String::new(),
                             size: 0,
                             encryption_mode: "GCM".to_string(),
+                            padding: None,
+                            chunk_hashes: None,
                             file_meta_ipns_name: Some(file_pointer.file_meta_ipns_name.clone()),
                             file_meta_resolved: false,
                             file_ipns_private_key: file_ipns_key,
@@ -565,7 +620,7 @@ impl InodeTable {
         Ok(())
     }
 
-    /// Update a FilePointer inode with resolved metadata (CID, key, IV, size, mode, versions).
+    /// Update a FilePointer inode with resolved metadata (CID, key, IV, size, mode, padding, chunk hashes, versions).
     ///
     /// Called after per-file IPNS resolution succeeds. Updates the inode in place.
     #[cfg(feature = "fuse")]
@@ -577,6 +632,8 @@ impl InodeTable {
         iv: String,
         size: u64,
         encryption_mode: String,
+        padding: Option<String>,
+        chunk_hashes: Option<Vec<String>>,
         versions: Option<Vec<crate::crypto::folder::VersionEntry>>,
     ) {
         if let Some(inode) = self.inodes.get_mut(&ino) {
@@ -586,6 +643,8 @@ impl InodeTable {
                 iv,
                 size,
                 encryption_mode,
+                padding,
+                chunk_hashes,
                 file_meta_ipns_name: match &inode.kind {
                     InodeKind::File { file_meta_ipns_name, .. } => file_meta_ipns_name.clone(),
                     _ => None,
@@ -607,6 +666,19 @@ impl InodeTable {
         }
     }
 
+    /// Find the root, folder or file inode published under `ipns_name`
+    /// (for files, the name of their metadata record).
+    pub fn find_by_ipns_name(&self, ipns_name: &str) -> Option<u64> {
+        self.inodes.values().find_map(|inode| {
+            let name = match &inode.kind {
+                InodeKind::Root { ipns_name, .. } => ipns_name.as_deref(),
+                InodeKind::Folder { ipns_name, .. } => Some(ipns_name.as_str()),
+                InodeKind::File { file_meta_ipns_name, .. } => file_meta_ipns_name.as_deref(),
+            };
+            (name == Some(ipns_name)).then_some(inode.ino)
+        })
+    }
+
     /// Get all unresolved FilePointer inodes (for batch IPNS resolution).
     /// Returns Vec of (ino, file_meta_ipns_name).
     #[cfg(feature = "fuse")]
@@ -664,8 +736,8 @@ mod tests {
             kind: InodeKind::Folder {
                 ipns_name: "k51test".to_string(),
                 encrypted_folder_key: "deadbeef".to_string(),
-                folder_key: Zeroizing::new(vec![0u8; 32]),
-                ipns_private_key: Some(Zeroizing::new(vec![0u8; 32])),
+                folder_key: FolderKey::from_bytes([0u8; 32]),
+                ipns_private_key: Some(IpnsSigningKey::from_bytes([0u8; 32])),
                 children_loaded: false,
             },
             attr: FileAttr {
@@ -732,6 +804,8 @@ mod tests {
                 iv: "ccdd".to_string(),
                 size: 1024,
                 encryption_mode: "GCM".to_string(),
+                padding: None,
+                chunk_hashes: None,
                 file_meta_ipns_name: None,
                 file_meta_resolved: true,
                 file_ipns_private_key: None,
@@ -772,8 +846,8 @@ mod tests {
         let kind = InodeKind::Folder {
             ipns_name: "k51test".to_string(),
             encrypted_folder_key: "deadbeef".to_string(),
-            folder_key: Zeroizing::new(vec![0u8; 32]),
-            ipns_private_key: Some(Zeroizing::new(vec![42u8; 32])),
+            folder_key: FolderKey::from_bytes([0u8; 32]),
+            ipns_private_key: Some(IpnsSigningKey::from_bytes([42u8; 32])),
             children_loaded: false,
         };
 
@@ -782,7 +856,7 @@ mod tests {
                 ipns_private_key, ..
             } => {
                 assert!(ipns_private_key.is_some());
-                assert_eq!(ipns_private_key.unwrap().len(), 32);
+                assert_eq!(ipns_private_key.unwrap().as_bytes().len(), 32);
             }
             _ => panic!("Expected Folder kind"),
         }
@@ -791,7 +865,7 @@ mod tests {
     #[test]
     fn test_inode_kind_root_has_ipns_private_key() {
         let kind = InodeKind::Root {
-            ipns_private_key: Some(Zeroizing::new(vec![42u8; 32])),
+            ipns_private_key: Some(IpnsSigningKey::from_bytes([42u8; 32])),
             ipns_name: Some("k51root".to_string()),
         };
 
@@ -815,6 +889,8 @@ mod tests {
             iv: "ccdd".to_string(),
             size: 1024,
             encryption_mode: "GCM".to_string(),
+            padding: None,
+            chunk_hashes: None,
             file_meta_ipns_name: None,
             file_meta_resolved: true,
             file_ipns_private_key: None,
@@ -852,7 +928,7 @@ mod tests {
 
         // For FilePointer children without ipnsPrivateKeyEncrypted, HKDF derivation
         // is used. Public key is needed for wrapping during lazy migration.
-        let private_key = vec![0u8; 32];
+        let private_key = Secp256k1SecretKey::from_bytes([0u8; 32]);
         let public_key = vec![0u8; 33]; // dummy compressed public key
         let result = table.populate_folder(ROOT_INO, &metadata, &private_key, &public_key, false);
         assert!(result.is_ok());
@@ -877,4 +953,146 @@ mod tests {
             _ => panic!("Expected File kind"),
         }
     }
+
+    #[test]
+    fn test_populate_folder_read_only_leaves_ipns_keys_wrapped() {
+        let secret = [7u8; 32];
+        let private_key = Secp256k1SecretKey::from_bytes(secret);
+        let public_key = ::ecies::PublicKey::from_secret_key(
+            &::ecies::SecretKey::parse_slice(&secret).unwrap(),
+        )
+        .serialize();
+        let folder_key = FolderKey::generate();
+        let metadata = FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![
+                FolderChild::Folder(crate::crypto::folder::FolderEntry {
+                    id: "folder-1".to_string(),
+                    name: "docs".to_string(),
+                    ipns_name: "k51docs".to_string(),
+                    folder_key_encrypted: hex::encode(
+                        crypto::ecies::wrap_key(folder_key.as_bytes(), &public_key).unwrap(),
+                    ),
+                    // Not a wrapped key: unwrapping it would fail
+                    ipns_private_key_encrypted: "00".to_string(),
+                    created_at: 1700000000000,
+                    modified_at: 1700000000000,
+                }),
+                FolderChild::File(crate::crypto::folder::FilePointer {
+                    id: "file-1".to_string(),
+                    name: "hello.txt".to_string(),
+                    file_meta_ipns_name: "k51hello".to_string(),
+                    ipns_private_key_encrypted: None,
+                    created_at: 1700000000000,
+                    modified_at: 1700000000000,
+                }),
+            ],
+        };
+
+        let mut table = InodeTable::new();
+        assert!(table.populate_folder(ROOT_INO, &metadata, &private_key, &public_key, false).is_err());
+
+        table.read_only = true;
+        table.populate_folder(ROOT_INO, &metadata, &private_key, &public_key, false).unwrap();
+        let docs = table.get(table.find_child(ROOT_INO, "docs").unwrap()).unwrap();
+        match &docs.kind {
+            InodeKind::Folder { folder_key: key, ipns_private_key, .. } => {
+                assert_eq!(key.as_bytes(), folder_key.as_bytes());
+                assert!(ipns_private_key.is_none());
+            }
+            _ => panic!("Expected Folder kind"),
+        }
+        // Not even derived for a legacy file
+        let hello = table.get(table.find_child(ROOT_INO, "hello.txt").unwrap()).unwrap();
+        match &hello.kind {
+            InodeKind::File { file_ipns_private_key, file_ipns_key_encrypted_hex, .. } => {
+                assert!(file_ipns_private_key.is_none());
+                assert!(file_ipns_key_encrypted_hex.is_none());
+            }
+            _ => panic!("Expected File kind"),
+        }
+    }
+
+    #[test]
+    fn test_new_folder_key_is_hybrid_wrapped() {
+        let secret = [7u8; 32];
+        let private_key = Secp256k1SecretKey::from_bytes(secret);
+        let public_key = ::ecies::PublicKey::from_secret_key(
+            &::ecies::SecretKey::parse_slice(&secret).unwrap(),
+        )
+        .serialize();
+        let (mlkem_private_key, mlkem_public_key) = crypto::ecies::generate_mlkem_keypair();
+
+        let folder = NewFolderKeys::generate(&public_key, Some(&mlkem_public_key)).unwrap();
+        let wrapped = hex::decode(&folder.encrypted_folder_key).unwrap();
+        assert_eq!(wrapped[0], crypto::ecies::HYBRID_WRAP_VERSION);
+
+        // Listed in a parent, it only opens with the ML-KEM key
+        let metadata = FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![FolderChild::Folder(crate::crypto::folder::FolderEntry {
+                id: "folder-1".to_string(),
+                name: "docs".to_string(),
+                ipns_name: folder.ipns_name.clone(),
+                folder_key_encrypted: folder.encrypted_folder_key.clone(),
+                ipns_private_key_encrypted: hex::encode(
+                    crypto::ecies::wrap_key(folder.ipns_private_key.as_bytes(), &public_key).unwrap(),
+                ),
+                created_at: 1700000000000,
+                modified_at: 1700000000000,
+            })],
+        };
+        let mut table = InodeTable::new();
+        assert!(table.populate_folder(ROOT_INO, &metadata, &private_key, &public_key, false).is_err());
+
+        table.mlkem_private_key = Some(mlkem_private_key);
+        table.populate_folder(ROOT_INO, &metadata, &private_key, &public_key, false).unwrap();
+        let docs = table.get(table.find_child(ROOT_INO, "docs").unwrap()).unwrap();
+        match &docs.kind {
+            InodeKind::Folder { folder_key, .. } => {
+                assert_eq!(folder_key.as_bytes(), folder.folder_key.as_bytes());
+            }
+            _ => panic!("Expected Folder kind"),
+        }
+
+        // Vaults without an ML-KEM key keep plain ECIES
+        let folder = NewFolderKeys::generate(&public_key, None).unwrap();
+        assert_eq!(hex::decode(&folder.encrypted_folder_key).unwrap()[0], 0x04);
+    }
+
+    #[test]
+    fn test_populate_folder_repoints_name_taken_by_other_file() {
+        let mut table = InodeTable::new();
+        let private_key = Secp256k1SecretKey::from_bytes([0u8; 32]);
+        let public_key = vec![0u8; 33];
+        let pointer = |ipns_name: &str| FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![FolderChild::File(crate::crypto::folder::FilePointer {
+                id: "file-1".to_string(),
+                name: "report.txt".to_string(),
+                file_meta_ipns_name: ipns_name.to_string(),
+                ipns_private_key_encrypted: None,
+                created_at: 1700000000000,
+                modified_at: 1700000000000,
+            })],
+        };
+
+        table.populate_folder(ROOT_INO, &pointer("k51mine"), &private_key, &public_key, false).unwrap();
+        let ino = table.find_child(ROOT_INO, "report.txt").unwrap();
+        table.resolve_file_pointer(
+            ino, "bafymine".to_string(), String::new(), String::new(), 1,
+            "GCM".to_string(), None, None, None,
+        );
+
+        // Another device's file now has the name (e.g. after a merge)
+        table.populate_folder(ROOT_INO, &pointer("k51theirs"), &private_key, &public_key, true).unwrap();
+        match &table.get(ino).unwrap().kind {
+            InodeKind::File { file_meta_ipns_name, file_meta_resolved, cid, .. } => {
+                assert_eq!(file_meta_ipns_name.as_deref(), Some("k51theirs"));
+                assert!(!file_meta_resolved, "Other file's metadata must be resolved afresh");
+                assert!(cid.is_empty());
+            }
+            _ => panic!("Expected File kind"),
+        }
+    }
 }
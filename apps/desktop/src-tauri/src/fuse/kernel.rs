@@ -0,0 +1,125 @@
+//! Kernel cache invalidation for remote changes.
+//!
+//! The kernel caches entries and attributes (see `FILE_TTL`) and directory
+//! listings, so a remote change applied to the inode table stays invisible to
+//! other processes until those expire. The FUSE thread therefore queues a
+//! `KernelInval` for everything a remote change touched, and a separate thread
+//! sends them to the kernel as fuser notifications. (Sending them from a
+//! filesystem callback can deadlock against the request being answered.)
+//!
+//! Remote changes reach the FUSE thread as channel messages it drains at the
+//! start of operations; `wake` makes it do so without waiting for the next
+//! access.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Name looked up by `wake`. Never exists in the vault.
+pub const WAKE_NAME: &str = ".cipherbox-wake";
+
+/// Kernel cache entry to drop.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KernelInval {
+    /// Attributes and cached data of an inode (for folders, the listing).
+    Inode(u64),
+    /// A name in a folder, so the next path walk looks it up again.
+    Entry { parent: u64, name: String },
+}
+
+/// Children of a folder as the kernel may have cached them:
+/// name -> (inode, content CID; empty for folders).
+pub type Listing = HashMap<String, (u64, String)>;
+
+/// What to invalidate after the children of folder `parent` went from
+/// `before` to `after`: names that appeared, disappeared or point at another
+/// inode, files whose content changed, and the folder's own listing.
+pub fn listing_invalidations(parent: u64, before: &Listing, after: &Listing) -> Vec<KernelInval> {
+    if before == after {
+        return vec![];
+    }
+
+    let mut invals = vec![KernelInval::Inode(parent)];
+    for (name, (ino, cid)) in before {
+        match after.get(name) {
+            Some((after_ino, after_cid)) if after_ino == ino => {
+                if after_cid != cid {
+                    invals.push(KernelInval::Inode(*ino));
+                }
+            }
+            _ => invals.push(KernelInval::Entry { parent, name: name.clone() }),
+        }
+    }
+    for name in after.keys() {
+        if !before.contains_key(name) {
+            invals.push(KernelInval::Entry { parent, name: name.clone() });
+        }
+    }
+    invals.sort();
+    invals
+}
+
+/// Send invalidations to the kernel until every sender is dropped (on unmount).
+pub fn spawn_notifier(
+    notifier: fuser::Notifier,
+    rx: std::sync::mpsc::Receiver<KernelInval>,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("fuse-notify".to_string())
+        .spawn(move || {
+            for inval in rx {
+                let result = match &inval {
+                    KernelInval::Inode(ino) => notifier.inval_inode(*ino, 0, 0),
+                    KernelInval::Entry { parent, name } => {
+                        notifier.inval_entry(*parent, OsStr::new(name))
+                    }
+                };
+                // Not every FUSE implementation supports notifications
+                if let Err(e) = result {
+                    log::debug!("Kernel invalidation {:?} failed: {}", inval, e);
+                }
+            }
+        })
+}
+
+/// Make the FUSE thread of the mount at `mount_point` apply queued remote
+/// changes now, by looking up `WAKE_NAME` (which drains them). Blocks until
+/// the lookup is answered.
+pub fn wake(mount_point: &Path) {
+    let _ = std::fs::symlink_metadata(mount_point.join(WAKE_NAME));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(entries: &[(&str, u64, &str)]) -> Listing {
+        entries
+            .iter()
+            .map(|(name, ino, cid)| (name.to_string(), (*ino, cid.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_listing_needs_no_invalidation() {
+        let before = listing(&[("a.txt", 2, "bafya"), ("docs", 3, "")]);
+        assert!(listing_invalidations(1, &before, &before.clone()).is_empty());
+    }
+
+    #[test]
+    fn changed_listing_invalidates_touched_names_and_content() {
+        let before = listing(&[("a.txt", 2, "bafya"), ("b.txt", 3, "bafyb"), ("old", 4, "")]);
+        let after = listing(&[("a.txt", 2, "bafya2"), ("b.txt", 5, "bafyc"), ("new", 4, "")]);
+
+        let entry = |name: &str| KernelInval::Entry { parent: 1, name: name.to_string() };
+        let mut expected = vec![
+            KernelInval::Inode(1),
+            KernelInval::Inode(2),
+            entry("b.txt"),
+            entry("new"),
+            entry("old"),
+        ];
+        expected.sort();
+        assert_eq!(listing_invalidations(1, &before, &after), expected);
+    }
+}
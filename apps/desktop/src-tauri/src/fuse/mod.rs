@@ -1,16 +1,28 @@
 //! FUSE filesystem module for CipherBox Desktop.
 //!
-//! Mounts the encrypted vault at ~/CipherBox as a native macOS filesystem
-//! using FUSE-T. All crypto operations happen in Rust via the crypto module.
+//! Mounts the encrypted vault (at ~/CipherBox unless configured otherwise) as
+//! a native filesystem: through FUSE-T on macOS, libfuse3 on Linux (see
+//! `profile`). All crypto operations happen in Rust via the crypto module.
+//! A vault folder can also be mounted on its own, with that folder as the
+//! filesystem root; every mount is a separate `CipherBoxFS` (see `supervisor`).
 //!
-//! The cache and inode modules are always available (they don't depend on libfuse).
+//! The cache, inode and profile modules are always available (they don't depend on libfuse).
 //! The operations module and mount/unmount functions require the `fuse` feature.
 
 pub mod cache;
 pub mod file_handle;
 pub mod inode;
 #[cfg(feature = "fuse")]
+pub mod kernel;
+#[cfg(feature = "fuse")]
 pub mod operations;
+pub mod profile;
+#[cfg(feature = "fuse")]
+pub mod snapshot;
+#[cfg(feature = "fuse")]
+pub mod supervisor;
+#[cfg(feature = "fuse")]
+pub mod upload;
 
 #[cfg(feature = "fuse")]
 use std::collections::HashMap;
@@ -26,13 +38,20 @@ use zeroize::{Zeroize, Zeroizing};
 #[cfg(feature = "fuse")]
 use std::time::Duration;
 
-#[cfg(feature = "fuse")]
-use fuser::MountOption;
-
 #[cfg(feature = "fuse")]
 use crate::api::client::ApiClient;
 #[cfg(feature = "fuse")]
+use crate::crypto::keys::{FolderKey, IpnsSigningKey, MlKemSecretKey, Secp256k1SecretKey};
+#[cfg(feature = "fuse")]
+use crate::crypto::folder::{EnvelopeHistory, MetadataFormat};
+#[cfg(feature = "fuse")]
+use crate::crypto::padding::PaddingScheme;
+#[cfg(feature = "fuse")]
 use crate::state::AppState;
+#[cfg(feature = "fuse")]
+use crate::sync::journal::{Journal, JournalAck};
+#[cfg(feature = "fuse")]
+use crate::sync::{NameKind, SaveFailures, WatchList};
 
 /// Timeout for network I/O in FUSE callbacks to prevent blocking the NFS thread.
 #[cfg(feature = "fuse")]
@@ -53,6 +72,20 @@ where
     })
 }
 
+/// What the app keeps of a mount (the whole vault or a folder of it) for the
+/// sync daemon. Registered in `supervisor::Mounts` by `mount_filesystem`.
+#[cfg(feature = "fuse")]
+pub struct VaultMount {
+    /// IPNS name of the mounted folder (the vault root for the whole vault).
+    pub root_ipns_name: String,
+    /// IPNS names the sync daemon saw change remotely, for the mount to
+    /// re-resolve and apply.
+    pub remote_changes: tokio::sync::mpsc::UnboundedSender<crate::sync::RemoteChange>,
+    /// Folder and file IPNS names polled by the sync daemon. Filled from the
+    /// mount's inode tree, which also records accesses.
+    pub watch_list: Arc<tokio::sync::Mutex<WatchList>>,
+}
+
 /// Pending folder refresh result sent from background tasks.
 #[cfg(feature = "fuse")]
 pub struct PendingRefresh {
@@ -78,6 +111,94 @@ pub struct UploadComplete {
     pub old_file_cid: Option<String>,
     /// CIDs of pruned versions (exceeded MAX_VERSIONS_PER_FILE) to unpin.
     pub pruned_cids: Vec<String>,
+    /// CTR chunk hashes, known only once the content has been streamed.
+    pub chunk_hashes: Option<Vec<String>>,
+    /// Set when another device saved the file since it was opened here.
+    pub conflict: Option<SaveConflict>,
+    /// Set by journal replay, which does not know the file's inode: the
+    /// file is looked up by its metadata IPNS name instead of `ino`.
+    pub file_meta_ipns_name: Option<String>,
+}
+
+/// A save that found the file's remote record past the revision it was based
+/// on. The saved content was published as a new file, which the FUSE thread
+/// adds next to the original as "name (conflict from <device>).ext".
+#[cfg(feature = "fuse")]
+pub struct SaveConflict {
+    /// Current remote metadata of the file; the local file reverts to it.
+    pub remote: crate::crypto::folder::FileMetadata,
+    /// IPNS name the saved content was published under.
+    pub copy_ipns_name: String,
+    pub copy_ipns_private_key: IpnsSigningKey,
+    /// Hex-encoded ECIES-wrapped `copy_ipns_private_key`.
+    pub copy_ipns_key_encrypted_hex: String,
+}
+
+/// A save kept as a new file because it conflicts with a newer remote version
+/// of the original: a fresh IPNS name, and the save's metadata without the
+/// original's history. Its parent folder gets a pointer to it separately
+/// (by the FUSE thread, or by the journal replayer).
+#[cfg(feature = "fuse")]
+pub struct ConflictCopy {
+    /// IPNS name of the copy's metadata record.
+    pub ipns_name: String,
+    pub ipns_private_key: IpnsSigningKey,
+    /// Hex-encoded `ipns_private_key`, ECIES-wrapped to the user.
+    pub ipns_key_encrypted_hex: String,
+    /// Metadata of the copy: the save's, without versions.
+    pub metadata: crate::crypto::folder::FileMetadata,
+}
+
+#[cfg(feature = "fuse")]
+impl ConflictCopy {
+    /// Give the save described by `metadata` a new IPNS keypair (wrapped to
+    /// `public_key`) and start the sequence of its name.
+    pub fn new(
+        mut metadata: crate::crypto::folder::FileMetadata,
+        public_key: &[u8],
+        coordinator: &PublishCoordinator,
+    ) -> Result<Self, String> {
+        let (ipns_private_key, ipns_public_key) = IpnsSigningKey::generate();
+        let ipns_public_key: [u8; 32] = ipns_public_key
+            .try_into()
+            .map_err(|_| "Invalid IPNS public key length".to_string())?;
+        let ipns_name = crate::crypto::ipns::derive_ipns_name(&ipns_public_key)
+            .map_err(|e| format!("IPNS name derivation failed: {}", e))?;
+        let ipns_key_encrypted_hex = hex::encode(
+            crate::crypto::ecies::wrap_key(ipns_private_key.as_bytes(), public_key)
+                .map_err(|e| format!("Failed to ECIES-wrap IPNS key: {}", e))?,
+        );
+
+        // The copy is a new file: it starts without the original's history
+        metadata.versions = None;
+        coordinator.record_new_name(&ipns_name);
+        Ok(Self {
+            ipns_name,
+            ipns_private_key,
+            ipns_key_encrypted_hex,
+            metadata,
+        })
+    }
+
+    /// Publish the copy's metadata to its own IPNS record.
+    pub async fn publish(
+        &self,
+        api: &ApiClient,
+        coordinator: &PublishCoordinator,
+        folder_key: &FolderKey,
+        metadata_format: MetadataFormat,
+    ) -> Result<(), String> {
+        operations::publish_file_metadata_public(
+            api,
+            &self.metadata,
+            folder_key,
+            &self.ipns_private_key,
+            &self.ipns_name,
+            coordinator,
+            metadata_format,
+        )
+        .await
+    }
 }
 
 /// Entry in the debounced publish queue.
@@ -90,16 +211,38 @@ struct PublishQueueEntry {
     pending_uploads: usize,
 }
 
+/// Remote folder metadata the local state of a folder is based on.
+#[cfg(feature = "fuse")]
+#[derive(Clone)]
+pub struct FolderBase {
+    pub cid: String,
+    pub metadata: crate::crypto::folder::FolderMetadata,
+}
+
 /// Coordinates IPNS publish operations to prevent sequence number races
 /// and maintain a monotonic sequence number cache per IPNS name.
 ///
+/// Also tracks each folder's merge base (see `sync::merge`), the merged
+/// metadata the FUSE thread still has to apply, and the content last saved to
+/// each file, against which saves compare the remote (see `FileBase`).
+///
 /// Shared via `Arc` between `CipherBoxFS` and background publish threads.
+/// A read-only mount gets a `disabled` one, through which nothing publishes.
 #[cfg(feature = "fuse")]
 pub struct PublishCoordinator {
     /// Per-IPNS-name sequence number cache (monotonically increasing).
     seq_cache: std::sync::Mutex<HashMap<String, u64>>,
     /// Per-IPNS-name publish locks to serialize concurrent publishes.
     publish_locks: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// Per-IPNS-name merge base: the remote record the local folder was last
+    /// loaded from or published as.
+    bases: std::sync::Mutex<HashMap<String, FolderBase>>,
+    /// Merged metadata published on behalf of the local folder, by IPNS name.
+    merged: std::sync::Mutex<Vec<(String, FolderBase)>>,
+    /// Content CID of the last file metadata this device published, by IPNS name.
+    file_contents: std::sync::Mutex<HashMap<String, String>>,
+    /// Refuse every publish (read-only mount).
+    disabled: bool,
 }
 
 #[cfg(feature = "fuse")]
@@ -108,6 +251,18 @@ impl PublishCoordinator {
         Self {
             seq_cache: std::sync::Mutex::new(HashMap::new()),
             publish_locks: std::sync::Mutex::new(HashMap::new()),
+            bases: std::sync::Mutex::new(HashMap::new()),
+            merged: std::sync::Mutex::new(Vec::new()),
+            file_contents: std::sync::Mutex::new(HashMap::new()),
+            disabled: false,
+        }
+    }
+
+    /// A coordinator that refuses to publish, for a read-only mount.
+    pub fn disabled() -> Self {
+        Self {
+            disabled: true,
+            ..Self::new()
         }
     }
 
@@ -130,6 +285,20 @@ impl PublishCoordinator {
         api: &crate::api::client::ApiClient,
         ipns_name: &str,
     ) -> Result<u64, String> {
+        self.resolve_current(api, ipns_name).await.map(|(_, seq)| seq)
+    }
+
+    /// Like `resolve_sequence`, also returning the current record's CID
+    /// (`None` when falling back to the cached sequence number).
+    pub async fn resolve_current(
+        &self,
+        api: &crate::api::client::ApiClient,
+        ipns_name: &str,
+    ) -> Result<(Option<String>, u64), String> {
+        // Every publish starts here
+        if self.disabled {
+            return Err(format!("Not publishing {}: the vault is mounted read-only", ipns_name));
+        }
         match crate::api::ipns::resolve_ipns(api, ipns_name).await {
             Ok(resp) => {
                 let resolved = match resp.sequence_number.parse::<u64>() {
@@ -145,7 +314,7 @@ impl PublishCoordinator {
                 let cached = self.get_cached(ipns_name).unwrap_or(0);
                 let seq = std::cmp::max(resolved, cached);
                 self.update_cache(ipns_name, seq);
-                Ok(seq)
+                Ok((Some(resp.cid), seq))
             }
             Err(e) => match self.get_cached(ipns_name) {
                 Some(cached) => {
@@ -155,7 +324,7 @@ impl PublishCoordinator {
                         cached,
                         e
                     );
-                    Ok(cached)
+                    Ok((None, cached))
                 }
                 None => Err(format!(
                     "IPNS resolve failed and no cached sequence for {}: {}",
@@ -170,6 +339,67 @@ impl PublishCoordinator {
         self.update_cache(ipns_name, published_seq);
     }
 
+    /// Whether a record of `ipns_name` is known to exist: this device resolved
+    /// or published one past the sequence 0 a new name starts at.
+    pub fn has_record(&self, ipns_name: &str) -> bool {
+        self.get_cached(ipns_name).is_some_and(|seq| seq > 0)
+    }
+
+    /// Start the sequence of `ipns_name`, a name generated on this device that
+    /// has no record to resolve yet: its first publish is sequence 1.
+    pub fn record_new_name(&self, ipns_name: &str) {
+        self.update_cache(ipns_name, 0);
+    }
+
+    /// Record the remote record the local folder `ipns_name` now reflects.
+    pub fn set_base(
+        &self,
+        ipns_name: &str,
+        cid: &str,
+        metadata: &crate::crypto::folder::FolderMetadata,
+    ) {
+        self.bases.lock().unwrap().insert(
+            ipns_name.to_string(),
+            FolderBase {
+                cid: cid.to_string(),
+                metadata: metadata.clone(),
+            },
+        );
+    }
+
+    /// Merge base of `ipns_name`, if its folder has been loaded.
+    pub fn base(&self, ipns_name: &str) -> Option<FolderBase> {
+        self.bases.lock().unwrap().get(ipns_name).cloned()
+    }
+
+    /// Hand merged metadata of `ipns_name` to the FUSE thread.
+    fn push_merged(&self, ipns_name: &str, merged: FolderBase) {
+        self.merged.lock().unwrap().push((ipns_name.to_string(), merged));
+    }
+
+    /// Merged metadata published since the last call, oldest first.
+    pub fn take_merged(&self) -> Vec<(String, FolderBase)> {
+        std::mem::take(&mut *self.merged.lock().unwrap())
+    }
+
+    /// Record that the file metadata published to `ipns_name` points at `cid`.
+    pub fn record_file_content(&self, ipns_name: &str, cid: &str) {
+        self.file_contents
+            .lock()
+            .unwrap()
+            .insert(ipns_name.to_string(), cid.to_string());
+    }
+
+    /// Content CID this device last published for the file `ipns_name`.
+    pub fn file_content(&self, ipns_name: &str) -> Option<String> {
+        self.file_contents.lock().unwrap().get(ipns_name).cloned()
+    }
+
+    /// Last known sequence number of `ipns_name`, without network I/O.
+    pub fn cached_sequence(&self, ipns_name: &str) -> Option<u64> {
+        self.get_cached(ipns_name)
+    }
+
     fn get_cached(&self, ipns_name: &str) -> Option<u64> {
         self.seq_cache.lock().unwrap().get(ipns_name).copied()
     }
@@ -184,36 +414,163 @@ impl PublishCoordinator {
 }
 
 /// Encrypt a FolderMetadata struct and package as JSON bytes ready for IPFS upload.
-/// CPU-only, no network I/O.
+/// A v2 envelope is bound to `ipns_name`; names in `history` are always
+/// written in v2. CPU-only, no network I/O.
 #[cfg(feature = "fuse")]
 fn encrypt_metadata_to_json(
     metadata: &crate::crypto::folder::FolderMetadata,
-    folder_key: &[u8],
+    folder_key: &FolderKey,
+    ipns_name: &str,
+    format: MetadataFormat,
+    history: &EnvelopeHistory,
 ) -> Result<Vec<u8>, String> {
-    let folder_key_arr: [u8; 32] = folder_key
-        .try_into()
-        .map_err(|_| "Invalid folder key length".to_string())?;
-    let sealed = crate::crypto::folder::encrypt_folder_metadata(metadata, &folder_key_arr)
-        .map_err(|e| format!("Metadata encryption failed: {}", e))?;
-    let iv_hex = hex::encode(&sealed[..12]);
+    let format = history.format_for(ipns_name, format);
+    crate::crypto::folder::encrypt_folder_metadata_envelope(metadata, folder_key, ipns_name, format)
+        .map_err(|e| format!("Metadata encryption failed: {}", e))
+}
+
+/// Encrypt `metadata`, upload it and publish it as the next IPNS record of `ipns_name`.
+/// The caller holds the folder's publish lock.
+#[cfg(feature = "fuse")]
+pub async fn publish_folder_metadata(
+    api: &ApiClient,
+    coordinator: &PublishCoordinator,
+    metadata: &crate::crypto::folder::FolderMetadata,
+    folder_key: &FolderKey,
+    ipns_private_key: &IpnsSigningKey,
+    ipns_name: &str,
+    format: MetadataFormat,
+) -> Result<(), String> {
+    // Encrypt metadata (CPU)
+    let json_bytes = encrypt_metadata_to_json(metadata, folder_key, ipns_name, format, api.envelope_history())?;
+
+    // Resolve current IPNS sequence number (monotonic cache fallback)
+    let seq = coordinator.resolve_sequence(api, ipns_name).await?;
+
+    publish_folder_record(api, coordinator, json_bytes, ipns_private_key, ipns_name, seq).await?;
+    Ok(())
+}
+
+/// Upload encrypted folder metadata and publish it with sequence number `seq + 1`.
+/// Returns the new metadata CID.
+#[cfg(feature = "fuse")]
+async fn publish_folder_record(
+    api: &ApiClient,
+    coordinator: &PublishCoordinator,
+    json_bytes: Vec<u8>,
+    ipns_private_key: &IpnsSigningKey,
+    ipns_name: &str,
+    seq: u64,
+) -> Result<String, String> {
+    // Upload encrypted metadata to IPFS
+    let new_cid = crate::api::ipfs::upload_content(api, &json_bytes).await?;
+
+    // Create and sign IPNS record
+    let new_seq = seq + 1;
+    let value = format!("/ipfs/{}", new_cid);
+    let record = crate::crypto::ipns::create_ipns_record(
+        ipns_private_key,
+        &value,
+        new_seq,
+        86_400_000,
+    )
+    .map_err(|e| format!("IPNS record creation failed: {}", e))?;
+    let marshaled = crate::crypto::ipns::marshal_ipns_record(&record)
+        .map_err(|e| format!("IPNS record marshal failed: {}", e))?;
+
     use base64::Engine;
-    let data_base64 = base64::engine::general_purpose::STANDARD.encode(&sealed[12..]);
-    let json = serde_json::json!({ "iv": iv_hex, "data": data_base64 });
-    serde_json::to_vec(&json).map_err(|e| format!("JSON serialization failed: {}", e))
+    let record_b64 = base64::engine::general_purpose::STANDARD.encode(&marshaled);
+
+    let req = crate::api::ipns::IpnsPublishRequest {
+        ipns_name: ipns_name.to_string(),
+        record: record_b64,
+        metadata_cid: new_cid.clone(),
+        encrypted_ipns_private_key: None,
+        key_epoch: None,
+    };
+    crate::api::ipns::publish_ipns(api, &req).await?;
+
+    // Record successful publish in coordinator cache
+    coordinator.record_publish(ipns_name, new_seq);
+    Ok(new_cid)
+}
+
+/// Publish the locally built `metadata` of a folder, merging it with the
+/// remote record first if another device published since the folder's base
+/// (see `sync::merge`). The caller holds the folder's publish lock.
+///
+/// Returns the CID that was replaced, if any.
+#[cfg(feature = "fuse")]
+async fn publish_folder_merged(
+    api: &ApiClient,
+    coordinator: &PublishCoordinator,
+    metadata: crate::crypto::folder::FolderMetadata,
+    folder_key: &FolderKey,
+    ipns_private_key: &IpnsSigningKey,
+    ipns_name: &str,
+    format: MetadataFormat,
+) -> Result<Option<String>, String> {
+    let (remote_cid, seq) = coordinator.resolve_current(api, ipns_name).await?;
+    let base = coordinator.base(ipns_name);
+
+    let mut merged = false;
+    let mut metadata = metadata;
+    if let Some(remote_cid) = remote_cid.as_deref() {
+        if base.as_ref().map(|b| b.cid.as_str()) != Some(remote_cid) {
+            // Another device published since this folder was loaded. Without
+            // a base (never loaded) every remote entry counts as new.
+            let bytes = crate::api::ipfs::fetch_content(api, remote_cid).await?;
+            let remote = operations::decrypt_metadata_from_ipfs_public(&bytes, folder_key, ipns_name, api.envelope_history())?;
+            let base_metadata = base.map(|b| b.metadata).unwrap_or_else(|| crate::crypto::folder::FolderMetadata {
+                version: "v2".to_string(),
+                children: vec![],
+            });
+            let merge = crate::sync::merge::merge_folder_metadata(
+                &base_metadata,
+                &metadata,
+                &remote,
+                &crate::registry::get_device_name(),
+            );
+            log::info!(
+                "Merged concurrent changes to {} ({} conflicted copies)",
+                ipns_name,
+                merge.conflicts.len()
+            );
+            for name in &merge.conflicts {
+                log::warn!("Name conflict in {}: local entry renamed to '{}'", ipns_name, name);
+            }
+            metadata = merge.metadata;
+            merged = true;
+        }
+    }
+
+    let json_bytes = encrypt_metadata_to_json(&metadata, folder_key, ipns_name, format, api.envelope_history())?;
+    let new_cid = publish_folder_record(api, coordinator, json_bytes, ipns_private_key, ipns_name, seq).await?;
+
+    let published = FolderBase { cid: new_cid, metadata };
+    coordinator.set_base(ipns_name, &published.cid, &published.metadata);
+    if merged {
+        coordinator.push_merged(ipns_name, published);
+    }
+    Ok(remote_cid)
 }
 
 /// Spawn a background OS thread to upload encrypted metadata and publish via IPNS.
-/// Returns immediately — does NOT block the calling thread.
+/// Returns immediately — does NOT block the calling thread. `metadata` is
+/// merged with concurrent remote changes first if needed. On success the
+/// journaled ops included in `metadata` are acknowledged through `ack`.
 #[cfg(feature = "fuse")]
 fn spawn_metadata_publish(
     api: Arc<ApiClient>,
     rt: tokio::runtime::Handle,
     metadata: crate::crypto::folder::FolderMetadata,
-    folder_key: Vec<u8>,
-    ipns_private_key: Vec<u8>,
+    folder_key: FolderKey,
+    ipns_private_key: IpnsSigningKey,
     ipns_name: String,
     old_metadata_cid: Option<String>,
     coordinator: Arc<PublishCoordinator>,
+    format: MetadataFormat,
+    ack: JournalAck,
 ) {
     std::thread::spawn(move || {
         let result = rt.block_on(async {
@@ -221,48 +578,14 @@ fn spawn_metadata_publish(
             let lock = coordinator.get_lock(&ipns_name);
             let _guard = lock.lock().await;
 
-            // Encrypt metadata (CPU)
-            let json_bytes = encrypt_metadata_to_json(&metadata, &folder_key)?;
-
-            // Resolve current IPNS sequence number (monotonic cache fallback)
-            let seq = coordinator.resolve_sequence(&api, &ipns_name).await?;
-
-            // Upload encrypted metadata to IPFS
-            let new_cid = crate::api::ipfs::upload_content(&api, &json_bytes).await?;
-
-            // Create and sign IPNS record
-            let ipns_key_arr: [u8; 32] = ipns_private_key
-                .try_into()
-                .map_err(|_| "Invalid IPNS private key length".to_string())?;
-            let new_seq = seq + 1;
-            let value = format!("/ipfs/{}", new_cid);
-            let record = crate::crypto::ipns::create_ipns_record(
-                &ipns_key_arr,
-                &value,
-                new_seq,
-                86_400_000,
+            let replaced = publish_folder_merged(
+                &api, &coordinator, metadata, &folder_key, &ipns_private_key, &ipns_name, format,
             )
-            .map_err(|e| format!("IPNS record creation failed: {}", e))?;
-            let marshaled = crate::crypto::ipns::marshal_ipns_record(&record)
-                .map_err(|e| format!("IPNS record marshal failed: {}", e))?;
-
-            use base64::Engine;
-            let record_b64 = base64::engine::general_purpose::STANDARD.encode(&marshaled);
-
-            let req = crate::api::ipns::IpnsPublishRequest {
-                ipns_name: ipns_name.clone(),
-                record: record_b64,
-                metadata_cid: new_cid.clone(),
-                encrypted_ipns_private_key: None,
-                key_epoch: None,
-            };
-            crate::api::ipns::publish_ipns(&api, &req).await?;
-
-            // Record successful publish in coordinator cache
-            coordinator.record_publish(&ipns_name, new_seq);
+            .await?;
+            ack.ack(&ipns_name);
 
-            // Unpin old metadata CID
-            if let Some(old) = old_metadata_cid {
+            // Unpin old metadata CID (the one actually replaced, when known)
+            if let Some(old) = replaced.or(old_metadata_cid) {
                 let _ = crate::api::ipfs::unpin_content(&api, &old).await;
             }
 
@@ -288,19 +611,28 @@ pub struct CipherBoxFS {
     pub metadata_cache: cache::MetadataCache,
     /// File content cache with 256 MiB LRU eviction.
     pub content_cache: cache::ContentCache,
+    /// Decrypted blocks of CTR files served by range reads (32 MiB LRU).
+    pub block_cache: cache::BlockCache,
+    /// Encrypted content persisted across restarts, checked after the memory caches.
+    pub disk_cache: Arc<cache::DiskCache>,
     /// API client for IPFS/IPNS operations.
     pub api: Arc<ApiClient>,
-    /// User's secp256k1 private key for ECIES decryption (32 bytes).
-    /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-    pub private_key: Zeroizing<Vec<u8>>,
+    /// User's secp256k1 private key for ECIES decryption.
+    pub private_key: Secp256k1SecretKey,
     /// User's uncompressed secp256k1 public key (65 bytes, 0x04 prefix).
     /// Wrapped in `Zeroizing` for automatic zeroization on drop.
     pub public_key: Zeroizing<Vec<u8>>,
-    /// Root folder AES-256 key (32 bytes).
-    /// Wrapped in `Zeroizing` for automatic zeroization on drop.
-    pub root_folder_key: Zeroizing<Vec<u8>>,
+    /// User's ML-KEM-768 public key, when the vault has an ML-KEM key on this
+    /// device and hybrid wrapping is enabled with a confirmed vault secret
+    /// (`AppState::hybrid_wrap_ready`). New folder keys are hybrid-wrapped with it.
+    pub mlkem_public_key: Option<Vec<u8>>,
+    /// Root folder AES-256 key.
+    pub root_folder_key: FolderKey,
     /// Root IPNS name (k51... format).
     pub root_ipns_name: String,
+    /// IPNS name of the vault root, which differs from `root_ipns_name` when
+    /// a folder of the vault is mounted on its own.
+    pub vault_root_ipns_name: String,
     /// Tokio runtime handle for spawning async tasks from FUSE threads.
     pub rt: tokio::runtime::Handle,
     /// Next file handle counter.
@@ -327,28 +659,52 @@ pub struct CipherBoxFS {
     pub content_rx: std::sync::mpsc::Receiver<PendingContent>,
     /// Sender for background content prefetch tasks.
     pub content_tx: std::sync::mpsc::Sender<PendingContent>,
-    /// Plaintext cache for files whose upload is still in flight (keyed by inode).
-    pub pending_content: HashMap<u64, Vec<u8>>,
+    /// Saved temp files whose upload is still in flight (keyed by inode).
+    /// Reads are served from them; the upload thread holds a second reference.
+    pub pending_content: HashMap<u64, Arc<file_handle::OpenFileHandle>>,
     /// Receiver for background upload completion notifications.
     pub upload_rx: std::sync::mpsc::Receiver<UploadComplete>,
     /// Sender for background upload threads to notify completion.
     pub upload_tx: std::sync::mpsc::Sender<UploadComplete>,
+    /// Receiver for changes found by the background vault crawler.
+    pub crawl_rx: std::sync::mpsc::Receiver<snapshot::CrawlUpdate>,
+    /// Background vault crawler task, aborted in destroy().
+    pub crawler: Option<tokio::task::JoinHandle<()>>,
+    /// Kernel cache entries made stale by applied remote changes, sent to the
+    /// kernel by the mount's notifier thread.
+    pub kernel_tx: std::sync::mpsc::Sender<kernel::KernelInval>,
     /// Shared coordinator for IPNS publish sequencing and per-folder locking.
     pub publish_coordinator: Arc<PublishCoordinator>,
+    /// Length padding for uploaded file content.
+    pub padding: PaddingScheme,
+    /// Envelope (and padding) of published folder and file metadata.
+    pub metadata_format: MetadataFormat,
+    /// Whether large files are uploaded chunked.
+    pub chunked_uploads: bool,
+    /// Whether large files that are not chunked are uploaded as GCM-SEG.
+    pub gcm_seg_uploads: bool,
+    /// Mutations whose publishes have not all succeeded yet, replayed by the sync daemon.
+    pub journal: Arc<Journal>,
+    /// Saves that failed, for the sync daemon to retry or show in the tray.
+    pub save_failures: Arc<SaveFailures>,
+    /// Folder and file IPNS names polled by the sync daemon (see `sync::watch`).
+    pub watch_list: Arc<tokio::sync::Mutex<WatchList>>,
     /// Debounced publish queue: folders needing metadata publish after mutations.
     /// Publishes are coalesced and deferred until uploads settle.
     publish_queue: HashMap<u64, PublishQueueEntry>,
+    /// Mounted read-only: operations that would modify the vault fail with EROFS.
+    pub read_only: bool,
 }
 
 #[cfg(feature = "fuse")]
 impl CipherBoxFS {
     /// Get the decrypted folder key for a folder/root inode.
     /// Returns None if the inode is not a folder or root.
-    pub fn get_folder_key(&self, folder_ino: u64) -> Option<Vec<u8>> {
+    pub fn get_folder_key(&self, folder_ino: u64) -> Option<FolderKey> {
         self.inodes.get(folder_ino).and_then(|inode| {
             match &inode.kind {
-                inode::InodeKind::Root { .. } => Some(self.root_folder_key.to_vec()),
-                inode::InodeKind::Folder { folder_key, .. } => Some(folder_key.to_vec()),
+                inode::InodeKind::Root { .. } => Some(self.root_folder_key.clone()),
+                inode::InodeKind::Folder { folder_key, .. } => Some(folder_key.clone()),
                 _ => None,
             }
         })
@@ -362,8 +718,8 @@ impl CipherBoxFS {
     ) -> Result<
         (
             crate::crypto::folder::FolderMetadata,
-            Vec<u8>,
-            Vec<u8>,
+            FolderKey,
+            IpnsSigningKey,
             String,
             Option<String>,
         ),
@@ -385,12 +741,12 @@ impl CipherBoxFS {
                     let key = ipns_private_key
                         .as_ref()
                         .ok_or("Root folder IPNS private key not available")?
-                        .to_vec();
+                        .clone();
                     let name = ipns_name
                         .as_ref()
                         .ok_or("Root folder IPNS name not available")?
                         .clone();
-                    (self.root_folder_key.to_vec(), key, name, children)
+                    (self.root_folder_key.clone(), key, name, children)
                 }
                 inode::InodeKind::Folder {
                     folder_key,
@@ -401,8 +757,8 @@ impl CipherBoxFS {
                     let key = ipns_private_key
                         .as_ref()
                         .ok_or("Subfolder IPNS private key not available")?
-                        .to_vec();
-                    (folder_key.to_vec(), key, ipns_name.clone(), children)
+                        .clone();
+                    (folder_key.clone(), key, ipns_name.clone(), children)
                 }
                 _ => return Err("Cannot update metadata for non-folder inode".to_string()),
             }
@@ -410,121 +766,8 @@ impl CipherBoxFS {
 
         let mut metadata_children = Vec::new();
         for &child_ino in &child_inos {
-            let child = self
-                .inodes
-                .get(child_ino)
-                .ok_or_else(|| format!("Child inode {} not found", child_ino))?;
-
-            match &child.kind {
-                inode::InodeKind::Folder {
-                    ipns_name: child_ipns_name,
-                    encrypted_folder_key,
-                    ipns_private_key: child_ipns_key,
-                    ..
-                } => {
-                    let ipns_key_encrypted = if let Some(key) = child_ipns_key {
-                        let wrapped = crate::crypto::ecies::wrap_key(key, &self.public_key)
-                            .map_err(|e| format!("Failed to wrap IPNS key: {}", e))?;
-                        hex::encode(&wrapped)
-                    } else {
-                        String::new()
-                    };
-
-                    let now_ms = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-                    let created_ms = child
-                        .attr
-                        .crtime
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-                    let modified_ms = child
-                        .attr
-                        .mtime
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-
-                    metadata_children.push(crate::crypto::folder::FolderChild::Folder(
-                        crate::crypto::folder::FolderEntry {
-                            id: uuid_from_ino(child_ino),
-                            name: child.name.clone(),
-                            ipns_name: child_ipns_name.clone(),
-                            folder_key_encrypted: encrypted_folder_key.clone(),
-                            ipns_private_key_encrypted: ipns_key_encrypted,
-                            created_at: if created_ms > 0 { created_ms } else { now_ms },
-                            modified_at: if modified_ms > 0 { modified_ms } else { now_ms },
-                        },
-                    ));
-                }
-                inode::InodeKind::File {
-                    file_meta_ipns_name,
-                    file_ipns_private_key,
-                    file_ipns_key_encrypted_hex,
-                    ..
-                } => {
-                    let now_ms = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-                    let created_ms = child
-                        .attr
-                        .crtime
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-                    let modified_ms = child
-                        .attr
-                        .mtime
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as u64;
-
-                    // FilePointer IPNS name is required for v2 metadata.
-                    // create() generates a random IPNS keypair, so this should always be Some.
-                    let ipns_name = match file_meta_ipns_name {
-                        Some(name) if !name.is_empty() => name.clone(),
-                        _ => {
-                            log::error!(
-                                "File '{}' (ino {}) has no fileMetaIpnsName -- this should not happen (create() generates IPNS keypair). Skipping file.",
-                                child.name, child_ino
-                            );
-                            continue;
-                        }
-                    };
-
-                    // Use cached ECIES-wrapped hex if available; only re-wrap if cache is empty
-                    let ipns_key_encrypted = if let Some(hex) = file_ipns_key_encrypted_hex {
-                        Some(hex.clone())
-                    } else if let Some(key) = file_ipns_private_key {
-                        match crate::crypto::ecies::wrap_key(key, &self.public_key) {
-                            Ok(wrapped) => Some(hex::encode(&wrapped)),
-                            Err(e) => {
-                                log::warn!(
-                                    "File '{}' (ino {}): failed to wrap IPNS key: {}. Omitting ipnsPrivateKeyEncrypted.",
-                                    child.name, child_ino, e
-                                );
-                                None
-                            }
-                        }
-                    } else {
-                        None
-                    };
-
-                    metadata_children.push(crate::crypto::folder::FolderChild::File(
-                        crate::crypto::folder::FilePointer {
-                            id: uuid_from_ino(child_ino),
-                            name: child.name.clone(),
-                            file_meta_ipns_name: ipns_name,
-                            ipns_private_key_encrypted: ipns_key_encrypted,
-                            created_at: if created_ms > 0 { created_ms } else { now_ms },
-                            modified_at: if modified_ms > 0 { modified_ms } else { now_ms },
-                        },
-                    ));
-                }
-                _ => {}
+            if let Some(child) = self.folder_child(child_ino)? {
+                metadata_children.push(child);
             }
         }
 
@@ -538,6 +781,222 @@ impl CipherBoxFS {
         Ok((metadata, folder_key, ipns_private_key, ipns_name, old_cid))
     }
 
+    /// Wrap `folder_key` to the user: hybrid ECIES + ML-KEM-768 when the vault
+    /// has an ML-KEM key, plain ECIES otherwise.
+    pub fn wrap_folder_key(&self, folder_key: &FolderKey) -> Result<Vec<u8>, String> {
+        crate::crypto::ecies::wrap_key_hybrid_if_available(
+            folder_key.as_bytes(),
+            &self.public_key,
+            self.mlkem_public_key.as_deref(),
+        )
+        .map_err(|e| format!("Failed to wrap folder key: {}", e))
+    }
+
+    /// Journal reference to the folder `folder_ino`. The root's keys are not recorded.
+    pub fn folder_ref(&self, folder_ino: u64) -> Result<crate::sync::journal::FolderRef, String> {
+        let inode = self
+            .inodes
+            .get(folder_ino)
+            .ok_or_else(|| format!("Folder inode {} not found", folder_ino))?;
+        match &inode.kind {
+            inode::InodeKind::Root { ipns_name, ipns_private_key } => {
+                let ipns_name = ipns_name.clone().ok_or("Root folder IPNS name not available")?;
+                if ipns_name == self.vault_root_ipns_name {
+                    return Ok(crate::sync::journal::FolderRef {
+                        ipns_name,
+                        folder_key_encrypted: None,
+                        ipns_private_key_encrypted: None,
+                    });
+                }
+                // A mounted folder: the journal is the vault's, whose replayer
+                // only holds the vault root's keys
+                Ok(crate::sync::journal::FolderRef {
+                    ipns_name,
+                    folder_key_encrypted: Some(hex::encode(
+                        self.wrap_folder_key(&self.root_folder_key)?,
+                    )),
+                    ipns_private_key_encrypted: ipns_private_key
+                        .as_ref()
+                        .map(|key| {
+                            crate::crypto::ecies::wrap_key(key.as_bytes(), &self.public_key)
+                                .map(hex::encode)
+                                .map_err(|e| format!("Failed to wrap IPNS key: {}", e))
+                        })
+                        .transpose()?,
+                })
+            }
+            inode::InodeKind::Folder {
+                ipns_name,
+                encrypted_folder_key,
+                ipns_private_key,
+                ..
+            } => {
+                let key = ipns_private_key
+                    .as_ref()
+                    .ok_or("Subfolder IPNS private key not available")?;
+                let wrapped = crate::crypto::ecies::wrap_key(key.as_bytes(), &self.public_key)
+                    .map_err(|e| format!("Failed to wrap IPNS key: {}", e))?;
+                Ok(crate::sync::journal::FolderRef {
+                    ipns_name: ipns_name.clone(),
+                    folder_key_encrypted: Some(encrypted_folder_key.clone()),
+                    ipns_private_key_encrypted: Some(hex::encode(&wrapped)),
+                })
+            }
+            _ => Err("Not a folder inode".to_string()),
+        }
+    }
+
+    /// Record `op` in the journal. Failing to build it only loses replay of this mutation.
+    pub fn journal_op(
+        &self,
+        op: impl FnOnce(&Self) -> Result<crate::sync::journal::JournalOp, String>,
+    ) {
+        match op(self) {
+            Ok(op) => {
+                self.journal.record(op);
+            }
+            Err(e) => log::warn!("Mutation not journaled: {}", e),
+        }
+    }
+
+    /// Journal the removal of `child_ino` (still in the inode table) from `parent`.
+    pub fn journal_unlink(&self, parent: u64, child_ino: u64) {
+        self.journal_op(|fs| {
+            let child = fs
+                .folder_child(child_ino)?
+                .ok_or_else(|| format!("ino {} is not published", child_ino))?;
+            Ok(crate::sync::journal::JournalOp::Unlink {
+                parent: fs.folder_ref(parent)?,
+                name: crate::sync::journal::child_name(&child).to_string(),
+                child_ipns_name: crate::sync::journal::child_ipns_name(&child).to_string(),
+            })
+        });
+    }
+
+    /// The folder metadata entry of inode `child_ino` (CPU-only, no network I/O).
+    /// Returns None for inodes that are not published (e.g. files without an IPNS name).
+    pub fn folder_child(
+        &self,
+        child_ino: u64,
+    ) -> Result<Option<crate::crypto::folder::FolderChild>, String> {
+        let child = self
+            .inodes
+            .get(child_ino)
+            .ok_or_else(|| format!("Child inode {} not found", child_ino))?;
+
+        match &child.kind {
+            inode::InodeKind::Folder {
+                ipns_name: child_ipns_name,
+                encrypted_folder_key,
+                ipns_private_key: child_ipns_key,
+                ..
+            } => {
+                let ipns_key_encrypted = if let Some(key) = child_ipns_key {
+                    let wrapped = crate::crypto::ecies::wrap_key(key.as_bytes(), &self.public_key)
+                        .map_err(|e| format!("Failed to wrap IPNS key: {}", e))?;
+                    hex::encode(&wrapped)
+                } else {
+                    String::new()
+                };
+
+                let now_ms = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                let created_ms = child
+                    .attr
+                    .crtime
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                let modified_ms = child
+                    .attr
+                    .mtime
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+
+                Ok(Some(crate::crypto::folder::FolderChild::Folder(
+                    crate::crypto::folder::FolderEntry {
+                        id: uuid_from_ino(child_ino),
+                        name: child.name.clone(),
+                        ipns_name: child_ipns_name.clone(),
+                        folder_key_encrypted: encrypted_folder_key.clone(),
+                        ipns_private_key_encrypted: ipns_key_encrypted,
+                        created_at: if created_ms > 0 { created_ms } else { now_ms },
+                        modified_at: if modified_ms > 0 { modified_ms } else { now_ms },
+                    },
+                )))
+            }
+            inode::InodeKind::File {
+                file_meta_ipns_name,
+                file_ipns_private_key,
+                file_ipns_key_encrypted_hex,
+                ..
+            } => {
+                let now_ms = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                let created_ms = child
+                    .attr
+                    .crtime
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+                let modified_ms = child
+                    .attr
+                    .mtime
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64;
+
+                // FilePointer IPNS name is required for v2 metadata.
+                // create() generates a random IPNS keypair, so this should always be Some.
+                let ipns_name = match file_meta_ipns_name {
+                    Some(name) if !name.is_empty() => name.clone(),
+                    _ => {
+                        log::error!(
+                            "File '{}' (ino {}) has no fileMetaIpnsName -- this should not happen (create() generates IPNS keypair). Skipping file.",
+                            child.name, child_ino
+                        );
+                        return Ok(None);
+                    }
+                };
+
+                // Use cached ECIES-wrapped hex if available; only re-wrap if cache is empty
+                let ipns_key_encrypted = if let Some(hex) = file_ipns_key_encrypted_hex {
+                    Some(hex.clone())
+                } else if let Some(key) = file_ipns_private_key {
+                    match crate::crypto::ecies::wrap_key(key.as_bytes(), &self.public_key) {
+                        Ok(wrapped) => Some(hex::encode(&wrapped)),
+                        Err(e) => {
+                            log::warn!(
+                                "File '{}' (ino {}): failed to wrap IPNS key: {}. Omitting ipnsPrivateKeyEncrypted.",
+                                child.name, child_ino, e
+                            );
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                Ok(Some(crate::crypto::folder::FolderChild::File(
+                    crate::crypto::folder::FilePointer {
+                        id: uuid_from_ino(child_ino),
+                        name: child.name.clone(),
+                        file_meta_ipns_name: ipns_name,
+                        ipns_private_key_encrypted: ipns_key_encrypted,
+                        created_at: if created_ms > 0 { created_ms } else { now_ms },
+                        modified_at: if modified_ms > 0 { modified_ms } else { now_ms },
+                    },
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
     /// Publish folder metadata immediately (no debounce).
     /// Used for non-upload mutations (unlink, rmdir, rename) where the inode tree
     /// is already in its final state and we want the IPNS record updated ASAP.
@@ -556,30 +1015,55 @@ impl CipherBoxFS {
             ipns_name,
             old_cid,
             self.publish_coordinator.clone(),
+            self.metadata_format,
+            self.journal.acker(),
         );
         Ok(())
     }
 
+    /// Largest saved file copied into the content cache once its upload completes.
+    const PROMOTE_PENDING_MAX_BYTES: u64 = 4 * 1024 * 1024;
+
     /// Drain completed upload notifications and update inode CIDs + caches.
     /// Also flushes the debounced publish queue when uploads settle.
     pub fn drain_upload_completions(&mut self) {
-        while let Ok(result) = self.upload_rx.try_recv() {
+        while let Ok(mut result) = self.upload_rx.try_recv() {
+            let replayed = result.file_meta_ipns_name.is_some();
+            if let Some(ipns_name) = result.file_meta_ipns_name.take() {
+                let Some(ino) = self.inodes.find_by_ipns_name(&ipns_name) else {
+                    continue;
+                };
+                result.ino = ino;
+            }
             log::debug!(
                 "Upload complete: ino {} -> CID {}",
                 result.ino,
                 result.new_cid
             );
-            // Update inode CID from empty to real
-            if let Some(inode) = self.inodes.get_mut(result.ino) {
-                if let inode::InodeKind::File { ref mut cid, .. } = inode.kind {
-                    if cid.is_empty() {
-                        *cid = result.new_cid.clone();
+            // Update inode CID from empty to real. A conflicting save lands
+            // in a new inode instead.
+            let content_ino = match result.conflict.take() {
+                Some(conflict) => self.add_conflict_copy(&result, conflict),
+                None => {
+                    if let Some(inode) = self.inodes.get_mut(result.ino) {
+                        if let inode::InodeKind::File { ref mut cid, ref mut chunk_hashes, .. } = inode.kind {
+                            if cid.is_empty() {
+                                *cid = result.new_cid.clone();
+                                *chunk_hashes = result.chunk_hashes.clone();
+                            }
+                        }
+                    }
+                    result.ino
+                }
+            };
+            // Small files move from the pending temp file to content_cache;
+            // dropping the last reference deletes the temp file.
+            if let Some(pending) = self.pending_content.remove(&content_ino) {
+                if pending.get_size().is_ok_and(|size| size <= Self::PROMOTE_PENDING_MAX_BYTES) {
+                    if let Ok(plaintext) = pending.read_all() {
+                        self.content_cache.set(&result.new_cid, plaintext);
                     }
                 }
-            }
-            // Move plaintext from pending_content to content_cache
-            if let Some(plaintext) = self.pending_content.remove(&result.ino) {
-                self.content_cache.set(&result.new_cid, plaintext);
             }
             // Old file CID is now preserved as a version entry -- do NOT unpin it.
             // Only unpin CIDs from pruned versions that exceeded MAX_VERSIONS_PER_FILE.
@@ -590,8 +1074,9 @@ impl CipherBoxFS {
                     let _ = crate::api::ipfs::unpin_content(&api, &cid).await;
                 });
             }
-            // Decrement pending upload count for this folder
-            if let Some(entry) = self.publish_queue.get_mut(&result.parent_ino) {
+            // Decrement pending upload count for this folder (replayed
+            // uploads were never counted)
+            if let Some(entry) = self.publish_queue.get_mut(&result.parent_ino).filter(|_| !replayed) {
                 entry.pending_uploads = entry.pending_uploads.saturating_sub(1);
             }
         }
@@ -599,6 +1084,103 @@ impl CipherBoxFS {
         self.flush_publish_queue();
     }
 
+    /// Keep the content of a save that lost to a newer remote version as a
+    /// conflicted copy next to the file, and revert the file to the remote
+    /// version. Returns the inode of the copy.
+    fn add_conflict_copy(&mut self, result: &UploadComplete, conflict: SaveConflict) -> u64 {
+        let Some(original) = self.inodes.get(result.ino).cloned() else {
+            return result.ino;
+        };
+        let inode::InodeKind::File {
+            encrypted_file_key, iv, size, encryption_mode, padding, ..
+        } = original.kind
+        else {
+            return result.ino;
+        };
+
+        let taken: std::collections::HashSet<String> = self
+            .inodes
+            .get(original.parent_ino)
+            .and_then(|parent| parent.children.as_ref())
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|&child| self.inodes.get(child).map(|c| c.name.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let label = format!("conflict from {}", crate::registry::get_device_name());
+        let name = crate::sync::merge::free_conflict_copy_name(&original.name, &label, &taken);
+        log::warn!(
+            "'{}' was saved on another device since it was opened; keeping this save as '{}'",
+            original.name,
+            name
+        );
+
+        let ino = self.inodes.allocate_ino();
+        let mut attr = original.attr;
+        attr.ino = ino;
+        self.inodes.insert(inode::InodeData {
+            ino,
+            parent_ino: original.parent_ino,
+            name,
+            kind: inode::InodeKind::File {
+                cid: result.new_cid.clone(),
+                encrypted_file_key,
+                iv,
+                size,
+                encryption_mode,
+                padding,
+                chunk_hashes: result.chunk_hashes.clone(),
+                file_meta_ipns_name: Some(conflict.copy_ipns_name),
+                file_meta_resolved: true,
+                file_ipns_private_key: Some(conflict.copy_ipns_private_key),
+                file_ipns_key_encrypted_hex: Some(conflict.copy_ipns_key_encrypted_hex),
+                versions: None,
+            },
+            attr,
+            children: None,
+        });
+        if let Some(parent) = self.inodes.get_mut(original.parent_ino) {
+            if let Some(ref mut children) = parent.children {
+                children.push(ino);
+            }
+            parent.attr.mtime = std::time::SystemTime::now();
+            parent.attr.ctime = std::time::SystemTime::now();
+        }
+
+        let remote = conflict.remote;
+        self.inodes.resolve_file_pointer(
+            result.ino,
+            remote.cid,
+            remote.file_key_encrypted,
+            remote.file_iv,
+            remote.size,
+            remote.encryption_mode,
+            remote.padding,
+            remote.chunk_hashes,
+            remote.versions,
+        );
+        if let Some(inode) = self.inodes.get_mut(result.ino) {
+            inode.attr.mtime = std::time::SystemTime::now();
+        }
+        if let Some(pending) = self.pending_content.remove(&result.ino) {
+            self.pending_content.insert(ino, pending);
+        }
+
+        self.journal_op(|fs| match fs.folder_child(ino)? {
+            Some(crate::crypto::folder::FolderChild::File(pointer)) => {
+                Ok(crate::sync::journal::JournalOp::Create {
+                    parent: fs.folder_ref(original.parent_ino)?,
+                    pointer,
+                })
+            }
+            _ => Err(format!("conflict copy: no file pointer for ino {}", ino)),
+        });
+        self.queue_publish(original.parent_ino, false);
+        ino
+    }
+
     /// Queue a folder for debounced metadata publish.
     /// Called after mutations (create, unlink, rmdir, rename) that change folder contents.
     pub fn queue_publish(&mut self, folder_ino: u64, has_pending_upload: bool) {
@@ -653,6 +1235,8 @@ impl CipherBoxFS {
                         ipns_name,
                         old_cid,
                         self.publish_coordinator.clone(),
+                        self.metadata_format,
+                        self.journal.acker(),
                     );
                 }
                 Err(e) => {
@@ -671,11 +1255,28 @@ impl CipherBoxFS {
         let cutoff = std::time::Instant::now() - std::time::Duration::from_secs(30);
         self.mutated_folders.retain(|_, ts| *ts > cutoff);
 
+        self.apply_crawl_updates();
+
+        // Metadata merged with another device's changes on publish is applied
+        // even to mutated folders: it already contains the local changes.
+        let mut refreshes: Vec<(PendingRefresh, bool)> = Vec::new();
+        for (ipns_name, merged) in self.publish_coordinator.take_merged() {
+            if let Some(ino) = self.inodes.find_by_ipns_name(&ipns_name) {
+                let refresh = PendingRefresh { ino, ipns_name, metadata: merged.metadata, cid: merged.cid };
+                refreshes.push((refresh, true));
+            }
+        }
         while let Ok(refresh) = self.refresh_rx.try_recv() {
+            refreshes.push((refresh, false));
+        }
+
+        let mut applied = false;
+        for (refresh, merged) in refreshes {
             // Skip stale refreshes for recently-mutated folders or folders
             // with pending publishes (to prevent re-adding deleted/stale children)
-            if self.mutated_folders.contains_key(&refresh.ino)
-                || self.publish_queue.contains_key(&refresh.ino)
+            if !merged
+                && (self.mutated_folders.contains_key(&refresh.ino)
+                    || self.publish_queue.contains_key(&refresh.ino))
             {
                 log::debug!(
                     "refresh skipped for ino {} (locally mutated or publish pending)",
@@ -686,7 +1287,9 @@ impl CipherBoxFS {
                 continue;
             }
 
+            let before = self.child_listing(refresh.ino);
             self.metadata_cache.set(&refresh.ipns_name, refresh.metadata.clone(), refresh.cid.clone());
+            self.publish_coordinator.set_base(&refresh.ipns_name, &refresh.cid, &refresh.metadata);
             // Background refresh: merge_only=true to preserve locally-created files
             // that haven't been published to IPNS yet.
             if let Err(e) = self.inodes.populate_folder(
@@ -703,44 +1306,148 @@ impl CipherBoxFS {
             let unresolved = self.inodes.get_unresolved_file_pointers();
             if !unresolved.is_empty() {
                 // Get folder key for FilePointer resolution
-                let folder_key = match self.inodes.get(refresh.ino) {
-                    Some(inode) => match &inode.kind {
-                        inode::InodeKind::Root { .. } => Some(self.root_folder_key.to_vec()),
-                        inode::InodeKind::Folder { folder_key, .. } => Some(folder_key.to_vec()),
-                        _ => None,
-                    },
-                    None => None,
-                };
-                if let Some(fk) = folder_key {
+                if let Some(fk) = self.get_folder_key(refresh.ino) {
                     let api = self.api.clone();
                     let rt = self.rt.clone();
                     for (ino, ipns_name) in &unresolved {
-                        let fk_arr: Result<[u8; 32], _> = fk.as_slice().try_into();
-                        if let Ok(fk_arr) = fk_arr {
-                            let resolve_result = block_with_timeout(&rt, async {
-                                let resp = crate::api::ipns::resolve_ipns(&api, ipns_name).await?;
-                                let bytes = crate::api::ipfs::fetch_content(&api, &resp.cid).await?;
-                                Ok::<Vec<u8>, String>(bytes)
-                            });
-                            match resolve_result {
-                                Ok(enc_bytes) => {
-                                    match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, &fk_arr) {
-                                        Ok(fm) => {
-                                            self.inodes.resolve_file_pointer(
-                                                *ino, fm.cid, fm.file_key_encrypted,
-                                                fm.file_iv, fm.size, fm.encryption_mode,
-                                                fm.versions,
-                                            );
-                                        }
-                                        Err(e) => log::warn!("Drain FilePointer decrypt failed for ino {}: {}", ino, e),
+                        let resolve_result = block_with_timeout(&rt, async {
+                            let resp = crate::api::ipns::resolve_ipns(&api, ipns_name).await?;
+                            let bytes = crate::api::ipfs::fetch_content(&api, &resp.cid).await?;
+                            Ok::<Vec<u8>, String>(bytes)
+                        });
+                        match resolve_result {
+                            Ok(enc_bytes) => {
+                                match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, &fk, ipns_name, api.envelope_history()) {
+                                    Ok(fm) => {
+                                        self.inodes.resolve_file_pointer(
+                                            *ino, fm.cid, fm.file_key_encrypted,
+                                            fm.file_iv, fm.size, fm.encryption_mode, fm.padding, fm.chunk_hashes,
+                                            fm.versions,
+                                        );
                                     }
+                                    Err(e) => log::warn!("Drain FilePointer decrypt failed for ino {}: {}", ino, e),
                                 }
-                                Err(e) => log::warn!("Drain FilePointer resolve failed for ino {}: {}", ino, e),
                             }
+                            Err(e) => log::warn!("Drain FilePointer resolve failed for ino {}: {}", ino, e),
                         }
                     }
                 }
             }
+
+            let after = self.child_listing(refresh.ino);
+            for inval in kernel::listing_invalidations(refresh.ino, &before, &after) {
+                let _ = self.kernel_tx.send(inval);
+            }
+            applied = true;
+        }
+        if applied {
+            self.update_watch_list();
+        }
+    }
+
+    /// Hand the folder and file names of the inode tree to the sync daemon.
+    pub fn update_watch_list(&self) {
+        let folder_name = |ino: u64| match self.inodes.get(ino).map(|inode| &inode.kind) {
+            Some(inode::InodeKind::Root { ipns_name, .. }) => ipns_name.clone(),
+            Some(inode::InodeKind::Folder { ipns_name, .. }) => Some(ipns_name.clone()),
+            _ => None,
+        };
+        let names: Vec<_> = self
+            .inodes
+            .inodes
+            .values()
+            .filter_map(|inode| {
+                let (name, kind) = match &inode.kind {
+                    inode::InodeKind::Folder { ipns_name, .. } => (ipns_name.clone(), NameKind::Folder),
+                    inode::InodeKind::File { file_meta_ipns_name: Some(name), .. } => {
+                        (name.clone(), NameKind::File)
+                    }
+                    _ => return None,
+                };
+                Some((name, kind, folder_name(inode.parent_ino)))
+            })
+            .collect();
+
+        let mut watch_list = self.watch_list.blocking_lock();
+        watch_list.set_root(&self.root_ipns_name);
+        watch_list.replace(names);
+    }
+
+    /// Record an access to the folder or file `ino`, which the sync daemon
+    /// then polls first.
+    pub fn touch_watched(&self, ino: u64) {
+        let name = match self.inodes.get(ino).map(|inode| &inode.kind) {
+            Some(inode::InodeKind::Root { ipns_name, .. }) => ipns_name.as_deref(),
+            Some(inode::InodeKind::Folder { ipns_name, .. }) => Some(ipns_name.as_str()),
+            Some(inode::InodeKind::File { file_meta_ipns_name, .. }) => file_meta_ipns_name.as_deref(),
+            None => None,
+        };
+        if let Some(name) = name {
+            self.watch_list.blocking_lock().touch(name);
+        }
+    }
+
+    /// Children of folder `ino` as the kernel may have cached them.
+    fn child_listing(&self, ino: u64) -> kernel::Listing {
+        let Some(children) = self.inodes.get(ino).and_then(|inode| inode.children.as_ref()) else {
+            return kernel::Listing::new();
+        };
+        children
+            .iter()
+            .filter_map(|&child| self.inodes.get(child))
+            .map(|child| {
+                let cid = match &child.kind {
+                    inode::InodeKind::File { cid, .. } => cid.clone(),
+                    _ => String::new(),
+                };
+                (child.name.clone(), (child.ino, cid))
+            })
+            .collect()
+    }
+
+    /// Apply changes found by the vault crawler (non-blocking).
+    ///
+    /// Folder changes are queued as regular refreshes. File changes update the
+    /// inode directly, unless the file is being written or saved, or its
+    /// folder was just mutated -- the local version is newer then. Names not in
+    /// the inode table are ignored; the crawler's snapshot still has them.
+    fn apply_crawl_updates(&mut self) {
+        while let Ok(update) = self.crawl_rx.try_recv() {
+            match update {
+                snapshot::CrawlUpdate::Folder { ipns_name, cid, metadata } => {
+                    if let Some(ino) = self.inodes.find_by_ipns_name(&ipns_name) {
+                        let _ = self.refresh_tx.send(PendingRefresh { ino, ipns_name, metadata, cid });
+                    }
+                }
+                snapshot::CrawlUpdate::File { ipns_name, metadata: fm } => {
+                    let Some(ino) = self.inodes.find_by_ipns_name(&ipns_name) else {
+                        continue;
+                    };
+                    let parent_ino = self.inodes.get(ino).map(|i| i.parent_ino).unwrap_or(inode::ROOT_INO);
+                    let busy = self.pending_content.contains_key(&ino)
+                        || self.mutated_folders.contains_key(&parent_ino)
+                        || self.open_files.values().any(|h| h.ino == ino && h.temp_path.is_some());
+                    let unchanged = matches!(
+                        self.inodes.get(ino).map(|i| &i.kind),
+                        Some(inode::InodeKind::File { cid, .. }) if *cid == fm.cid
+                    );
+                    if busy || unchanged {
+                        continue;
+                    }
+                    log::debug!("Crawl: file ino={} changed remotely", ino);
+                    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_millis(fm.modified_at);
+                    self.inodes.resolve_file_pointer(
+                        ino, fm.cid, fm.file_key_encrypted,
+                        fm.file_iv, fm.size, fm.encryption_mode, fm.padding, fm.chunk_hashes,
+                        fm.versions,
+                    );
+                    if let Some(inode) = self.inodes.get_mut(ino) {
+                        inode.attr.mtime = modified;
+                        inode.attr.ctime = modified;
+                    }
+                    let _ = self.kernel_tx.send(kernel::KernelInval::Inode(ino));
+                }
+            }
         }
     }
 
@@ -775,34 +1482,118 @@ fn uuid_from_ino(ino: u64) -> String {
     )
 }
 
-/// Get the mount point path: ~/CipherBox
+/// Directory where the journal replayer decrypts spooled saves for upload.
 #[cfg(feature = "fuse")]
-pub fn mount_point() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not determine home directory")
-        .join("CipherBox")
+pub fn replay_temp_dir() -> PathBuf {
+    std::env::temp_dir().join("cipherbox").join("replay")
+}
+
+/// Directory buffering writes of the mount at `mount_point`.
+#[cfg(feature = "fuse")]
+pub fn mount_temp_dir(mount_point: &std::path::Path) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(mount_point.as_os_str().as_encoded_bytes());
+    std::env::temp_dir()
+        .join("cipherbox")
+        .join(hex::encode(&digest[..8]))
+}
+
+/// Find the vault folder at `path` (names from the root down) and unwrap its
+/// keys from its parent's `FolderEntry`. Returns its IPNS name, folder key
+/// and IPNS signing key (`None` unless `with_ipns_key`).
+#[cfg(feature = "fuse")]
+pub async fn resolve_vault_folder(
+    api: &ApiClient,
+    private_key: &Secp256k1SecretKey,
+    mlkem_private_key: Option<&MlKemSecretKey>,
+    root_ipns_name: &str,
+    root_folder_key: &FolderKey,
+    path: &[String],
+    with_ipns_key: bool,
+) -> Result<(String, FolderKey, Option<IpnsSigningKey>), String> {
+    use unicode_normalization::UnicodeNormalization;
+
+    let unwrap_hex = |hex_key: &str| {
+        hex::decode(hex_key).map_err(|e| format!("Invalid wrapped key hex: {}", e))
+    };
+    if path.is_empty() {
+        return Err("No vault folder to mount".to_string());
+    }
+    let mut folder = (root_ipns_name.to_string(), root_folder_key.clone());
+    let mut ipns_key = None;
+    for (depth, name) in path.iter().enumerate() {
+        let (ipns_name, folder_key) = &folder;
+        let resolved = crate::api::ipns::resolve_ipns(api, ipns_name).await?;
+        let bytes = crate::api::ipfs::fetch_content(api, &resolved.cid).await?;
+        let metadata = operations::decrypt_metadata_from_ipfs_public(&bytes, folder_key, ipns_name, api.envelope_history())?;
+
+        let wanted: String = name.nfc().collect();
+        let entry = metadata
+            .children
+            .into_iter()
+            .find_map(|child| match child {
+                crate::crypto::folder::FolderChild::Folder(entry)
+                    if entry.name.nfc().collect::<String>() == wanted => Some(entry),
+                _ => None,
+            })
+            .ok_or_else(|| format!("No folder /{} in the vault", path[..=depth].join("/")))?;
+        let folder_key: FolderKey = crate::crypto::ecies::unwrap_secret_key_hybrid(
+            &unwrap_hex(&entry.folder_key_encrypted)?,
+            private_key,
+            mlkem_private_key,
+        )
+        .map_err(|e| format!("Folder key unwrapping failed: {}", e))?;
+        // Only the mounted folder's own IPNS key is needed
+        if with_ipns_key && depth + 1 == path.len() {
+            let key: IpnsSigningKey = crate::crypto::ecies::unwrap_secret_key(
+                &unwrap_hex(&entry.ipns_private_key_encrypted)?,
+                private_key,
+            )
+            .map_err(|e| format!("IPNS key unwrapping failed: {}", e))?;
+            ipns_key = Some(key);
+        }
+        folder = (entry.ipns_name, folder_key);
+    }
+    let (ipns_name, folder_key) = folder;
+    Ok((ipns_name, folder_key, ipns_key))
 }
 
 /// Mount the FUSE filesystem after successful authentication.
 ///
-/// Creates the ~/CipherBox directory if it doesn't exist, builds the
+/// Mounts the folder `root_ipns_name` (the vault root, or a folder mounted on
+/// its own) as the filesystem root at `mount_path`, creating it if it doesn't
+/// exist. Mounts with the platform's `profile` options, builds the
 /// CipherBoxFS with keys from AppState, and spawns the FUSE event loop
 /// on a dedicated std::thread (not tokio -- fuser runs its own event loop).
 ///
+/// The inode tree is restored from the local snapshot of that root when one
+/// exists; otherwise the root and its immediate subfolders are fetched before
+/// mounting. Either way a background crawler then keeps the whole tree
+/// current. The mount shares the vault's publish coordinator and mutation
+/// journal with the other mounts (see `supervisor::Mounts`), and is
+/// registered as a `VaultMount` in `AppState::mounts`, where the sync daemon
+/// finds it to report remote changes.
+///
 /// Returns a JoinHandle for the mount thread.
 #[cfg(feature = "fuse")]
 pub async fn mount_filesystem(
     state: &AppState,
     rt: tokio::runtime::Handle,
-    private_key: Vec<u8>,
+    mount_path: PathBuf,
+    private_key: Secp256k1SecretKey,
     public_key: Vec<u8>,
-    root_folder_key: Vec<u8>,
+    root_folder_key: FolderKey,
     root_ipns_name: String,
-    root_ipns_private_key: Option<Vec<u8>>,
+    root_ipns_private_key: Option<IpnsSigningKey>,
     tee_public_key: Option<Vec<u8>>,
     tee_key_epoch: Option<u32>,
 ) -> Result<std::thread::JoinHandle<()>, String> {
-    let mount_path = mount_point();
+    let platform = profile::Platform::current();
+    let options =
+        profile::mount_options(platform, &state.mount, &mount_path, profile::user_allow_other())?;
+
+    // A dead mount left by a crash fails every access with ENOTCONN
+    supervisor::recover_stale_mount(&mount_path)?;
 
     // Refuse to proceed if mount point is a symlink (TOCTOU defense)
     if mount_path.is_symlink() {
@@ -818,7 +1609,12 @@ pub async fn mount_filesystem(
             use std::os::unix::fs::PermissionsExt;
             let _ = std::fs::set_permissions(&mount_path, std::fs::Permissions::from_mode(0o700));
         }
-    } else {
+    } else if platform == profile::Platform::MacOs {
+        let has_entries = std::fs::read_dir(&mount_path).is_ok_and(|mut entries| entries.next().is_some());
+        if has_entries && mount_path != profile::default_mount_point() {
+            // Only ~/CipherBox belongs to the app; never wipe a configured directory
+            return Err(format!("Mount point {} is not empty", mount_path.display()));
+        }
         // Clean stale files left after a crash (e.g. .DS_Store, .metadata_never_index).
         // FUSE mount will fail or behave unexpectedly if the directory isn't empty.
         if let Ok(entries) = std::fs::read_dir(&mount_path) {
@@ -835,13 +1631,15 @@ pub async fn mount_filesystem(
     }
 
     // Prevent Spotlight from indexing the mount (creates .metadata_never_index)
-    let never_index = mount_path.join(".metadata_never_index");
-    if !never_index.exists() {
-        let _ = std::fs::File::create(&never_index);
+    if platform == profile::Platform::MacOs {
+        let never_index = mount_path.join(".metadata_never_index");
+        if !never_index.exists() {
+            let _ = std::fs::File::create(&never_index);
+        }
     }
 
     // Create temp directory for write buffering
-    let temp_dir = std::env::temp_dir().join("cipherbox");
+    let temp_dir = mount_temp_dir(&mount_path);
     std::fs::create_dir_all(&temp_dir)
         .map_err(|e| format!("Failed to create temp directory: {}", e))?;
     #[cfg(unix)]
@@ -851,12 +1649,26 @@ pub async fn mount_filesystem(
     }
 
     // Build the filesystem
+    let read_only = state.mount.read_only;
+    let mlkem_private_key = state.mlkem_private_key.read().await.clone();
+    // Hybrid-wrap new folder keys only when opted in with a confirmed vault secret
+    let mlkem_public_key = if state.hybrid_wrap_ready().await {
+        mlkem_private_key
+            .as_ref()
+            .map(crate::crypto::ecies::mlkem_public_key)
+            .transpose()
+            .map_err(|e| format!("Invalid ML-KEM private key: {}", e))?
+    } else {
+        None
+    };
     let mut inodes = inode::InodeTable::new();
+    inodes.read_only = read_only;
+    inodes.mlkem_private_key = mlkem_private_key.clone();
 
     // Set root inode's IPNS data
     if let Some(root) = inodes.get_mut(inode::ROOT_INO) {
         root.kind = inode::InodeKind::Root {
-            ipns_private_key: root_ipns_private_key.map(Zeroizing::new),
+            ipns_private_key: root_ipns_private_key,
             ipns_name: Some(root_ipns_name.clone()),
         };
     }
@@ -865,36 +1677,69 @@ pub async fn mount_filesystem(
     let (refresh_tx, refresh_rx) = std::sync::mpsc::channel::<PendingRefresh>();
     let (content_tx, content_rx) = std::sync::mpsc::channel::<PendingContent>();
     let (upload_tx, upload_rx) = std::sync::mpsc::channel::<UploadComplete>();
+    let (crawl_tx, crawl_rx) = std::sync::mpsc::channel::<snapshot::CrawlUpdate>();
+    let (kernel_tx, kernel_rx) = std::sync::mpsc::channel::<kernel::KernelInval>();
+    let (changes_tx, changes_rx) = tokio::sync::mpsc::unbounded_channel::<crate::sync::RemoteChange>();
 
     // Pre-populate root folder BEFORE mounting so init()/readdir() have no network I/O.
     // This runs on the calling thread (tokio context available via rt handle).
     let mut metadata_cache = cache::MetadataCache::new();
-    log::info!("Pre-populating root folder from IPNS...");
-    let fetch_result: Result<(Vec<u8>, String), String> = async {
-        let resolve_resp =
-            crate::api::ipns::resolve_ipns(&state.api, &root_ipns_name).await?;
-        let encrypted_bytes =
-            crate::api::ipfs::fetch_content(&state.api, &resolve_resp.cid).await?;
-        Ok((encrypted_bytes, resolve_resp.cid))
-    }.await;
-    match fetch_result {
-        Ok((encrypted_bytes, cid)) => {
-            match operations::decrypt_metadata_from_ipfs_public(&encrypted_bytes, &root_folder_key) {
-                Ok(metadata) => {
-                    // Cache metadata directly for readdir staleness checks
-                    metadata_cache.set(&root_ipns_name, metadata.clone(), cid);
-
-                    // Populate inode table -- initial mount, full replace
-                    match inodes.populate_folder(inode::ROOT_INO, &metadata, &private_key, &public_key, false) {
-                        Ok(()) => {
-                            log::info!("Root folder pre-populated successfully");
-
-                            // Resolve FilePointers eagerly before mount
-                            let unresolved = inodes.get_unresolved_file_pointers();
-                            if !unresolved.is_empty() {
-                                log::info!("Resolving {} root FilePointer(s)...", unresolved.len());
-                                let root_folder_key_arr: Result<[u8; 32], _> = root_folder_key.as_slice().try_into();
-                                if let Ok(fk) = root_folder_key_arr {
+    let publish_coordinator = state.mounts.publish_coordinator(read_only);
+    // Restore the tree from the local snapshot when there is one: the mount
+    // comes up without network I/O and the crawler reconciles it afterwards.
+    let state_key = crate::crypto::hkdf::derive_local_state_key(&private_key)
+        .map_err(|e| format!("Local state key derivation failed: {}", e))?;
+    let snapshot_path = snapshot::snapshot_path(&root_ipns_name);
+    let vault_snapshot = snapshot_path
+        .as_deref()
+        .and_then(|path| snapshot::VaultSnapshot::load(path, &state_key, &root_ipns_name));
+    // Names this vault was read in v2 under stay strict across restarts, and
+    // records older than the snapshot's are rollbacks
+    if let Some(snap) = &vault_snapshot {
+        state.api.envelope_history().extend(snap.v2_envelopes.iter().cloned());
+        let sequences = state.api.ipns_sequences();
+        for (ipns_name, folder) in &snap.folders {
+            sequences.raise(ipns_name, folder.sequence);
+        }
+        for (ipns_name, file) in &snap.files {
+            sequences.raise(ipns_name, file.sequence);
+        }
+    }
+    let restored = vault_snapshot
+        .as_ref()
+        .is_some_and(|snap| snap.restore(&mut inodes, &private_key, &public_key));
+    if let Some(snap) = vault_snapshot.as_ref().filter(|_| restored) {
+        for (ipns_name, folder) in &snap.folders {
+            publish_coordinator.set_base(ipns_name, &folder.cid, &folder.metadata);
+        }
+    }
+
+    if !restored {
+        log::info!("Pre-populating root folder from IPNS...");
+        let fetch_result: Result<(Vec<u8>, String), String> = async {
+            let resolve_resp =
+                crate::api::ipns::resolve_ipns(&state.api, &root_ipns_name).await?;
+            let encrypted_bytes =
+                crate::api::ipfs::fetch_content(&state.api, &resolve_resp.cid).await?;
+            Ok((encrypted_bytes, resolve_resp.cid))
+        }.await;
+        match fetch_result {
+            Ok((encrypted_bytes, cid)) => {
+                match operations::decrypt_metadata_from_ipfs_public(&encrypted_bytes, &root_folder_key, &root_ipns_name, state.api.envelope_history()) {
+                    Ok(metadata) => {
+                        // Cache metadata directly for readdir staleness checks
+                        publish_coordinator.set_base(&root_ipns_name, &cid, &metadata);
+                        metadata_cache.set(&root_ipns_name, metadata.clone(), cid);
+
+                        // Populate inode table -- initial mount, full replace
+                        match inodes.populate_folder(inode::ROOT_INO, &metadata, &private_key, &public_key, false) {
+                            Ok(()) => {
+                                log::info!("Root folder pre-populated successfully");
+
+                                // Resolve FilePointers eagerly before mount
+                                let unresolved = inodes.get_unresolved_file_pointers();
+                                if !unresolved.is_empty() {
+                                    log::info!("Resolving {} root FilePointer(s)...", unresolved.len());
                                     for (fp_ino, fp_ipns) in &unresolved {
                                         let fp_result: Result<Vec<u8>, String> = async {
                                             let resp = crate::api::ipns::resolve_ipns(&state.api, fp_ipns).await?;
@@ -903,11 +1748,11 @@ pub async fn mount_filesystem(
                                         }.await;
                                         match fp_result {
                                             Ok(enc_bytes) => {
-                                                match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, &fk) {
+                                                match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, &root_folder_key, fp_ipns, state.api.envelope_history()) {
                                                     Ok(fm) => {
                                                         inodes.resolve_file_pointer(
                                                             *fp_ino, fm.cid, fm.file_key_encrypted,
-                                                            fm.file_iv, fm.size, fm.encryption_mode,
+                                                            fm.file_iv, fm.size, fm.encryption_mode, fm.padding, fm.chunk_hashes,
                                                             fm.versions,
                                                         );
                                                     }
@@ -919,47 +1764,45 @@ pub async fn mount_filesystem(
                                     }
                                 }
                             }
+                            Err(e) => log::warn!("Root folder populate failed: {}", e),
                         }
-                        Err(e) => log::warn!("Root folder populate failed: {}", e),
-                    }
 
-                    // Pre-populate immediate subfolders so Finder's first READDIR
-                    // returns correct data. NFS clients cache READDIR aggressively
-                    // and won't re-fetch even when mtime changes, so returning empty
-                    // on first access causes permanently stale Finder listings.
-                    let subfolder_infos: Vec<(u64, String, Zeroizing<Vec<u8>>)> = inodes
-                        .inodes
-                        .values()
-                        .filter_map(|inode| {
-                            if inode.parent_ino != inode::ROOT_INO { return None; }
-                            if let inode::InodeKind::Folder { ref ipns_name, ref folder_key, .. } = inode.kind {
-                                Some((inode.ino, ipns_name.clone(), folder_key.clone()))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-
-                    for (sub_ino, sub_ipns, sub_key) in &subfolder_infos {
-                        log::info!("Pre-populating subfolder ino={} ipns={}", sub_ino, sub_ipns);
-                        let sub_result: Result<(Vec<u8>, String), String> = async {
-                            let resp = crate::api::ipns::resolve_ipns(&state.api, sub_ipns).await?;
-                            let bytes = crate::api::ipfs::fetch_content(&state.api, &resp.cid).await?;
-                            Ok((bytes, resp.cid))
-                        }.await;
-                        match sub_result {
-                            Ok((enc_bytes, sub_cid)) => {
-                                match operations::decrypt_metadata_from_ipfs_public(&enc_bytes, sub_key) {
-                                    Ok(sub_metadata) => {
-                                        metadata_cache.set(sub_ipns, sub_metadata.clone(), sub_cid);
-                                        match inodes.populate_folder(*sub_ino, &sub_metadata, &private_key, &public_key, false) {
-                                            Ok(()) => {
-                                                log::info!("Subfolder ino={} pre-populated", sub_ino);
-                                                // Resolve FilePointers in subfolder
-                                                let sub_unresolved = inodes.get_unresolved_file_pointers();
-                                                if !sub_unresolved.is_empty() {
-                                                    let sk_arr: Result<[u8; 32], _> = sub_key.as_slice().try_into();
-                                                    if let Ok(sk) = sk_arr {
+                        // Pre-populate immediate subfolders so Finder's first READDIR
+                        // returns correct data. NFS clients cache READDIR aggressively
+                        // and won't re-fetch even when mtime changes, so returning empty
+                        // on first access causes permanently stale Finder listings.
+                        let subfolder_infos: Vec<(u64, String, FolderKey)> = inodes
+                            .inodes
+                            .values()
+                            .filter_map(|inode| {
+                                if inode.parent_ino != inode::ROOT_INO { return None; }
+                                if let inode::InodeKind::Folder { ref ipns_name, ref folder_key, .. } = inode.kind {
+                                    Some((inode.ino, ipns_name.clone(), folder_key.clone()))
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect();
+
+                        for (sub_ino, sub_ipns, sub_key) in &subfolder_infos {
+                            log::info!("Pre-populating subfolder ino={} ipns={}", sub_ino, sub_ipns);
+                            let sub_result: Result<(Vec<u8>, String), String> = async {
+                                let resp = crate::api::ipns::resolve_ipns(&state.api, sub_ipns).await?;
+                                let bytes = crate::api::ipfs::fetch_content(&state.api, &resp.cid).await?;
+                                Ok((bytes, resp.cid))
+                            }.await;
+                            match sub_result {
+                                Ok((enc_bytes, sub_cid)) => {
+                                    match operations::decrypt_metadata_from_ipfs_public(&enc_bytes, sub_key, sub_ipns, state.api.envelope_history()) {
+                                        Ok(sub_metadata) => {
+                                            publish_coordinator.set_base(sub_ipns, &sub_cid, &sub_metadata);
+                                            metadata_cache.set(sub_ipns, sub_metadata.clone(), sub_cid);
+                                            match inodes.populate_folder(*sub_ino, &sub_metadata, &private_key, &public_key, false) {
+                                                Ok(()) => {
+                                                    log::info!("Subfolder ino={} pre-populated", sub_ino);
+                                                    // Resolve FilePointers in subfolder
+                                                    let sub_unresolved = inodes.get_unresolved_file_pointers();
+                                                    if !sub_unresolved.is_empty() {
                                                         for (fp_ino, fp_ipns) in &sub_unresolved {
                                                             let fp_result: Result<Vec<u8>, String> = async {
                                                                 let resp = crate::api::ipns::resolve_ipns(&state.api, fp_ipns).await?;
@@ -968,11 +1811,11 @@ pub async fn mount_filesystem(
                                                             }.await;
                                                             match fp_result {
                                                                 Ok(enc_bytes) => {
-                                                                    match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, &sk) {
+                                                                    match operations::decrypt_file_metadata_from_ipfs_public(&enc_bytes, sub_key, fp_ipns, state.api.envelope_history()) {
                                                                         Ok(fm) => {
                                                                             inodes.resolve_file_pointer(
                                                                                 *fp_ino, fm.cid, fm.file_key_encrypted,
-                                                                                fm.file_iv, fm.size, fm.encryption_mode,
+                                                                                fm.file_iv, fm.size, fm.encryption_mode, fm.padding, fm.chunk_hashes,
                                                                                 fm.versions,
                                                                             );
                                                                         }
@@ -984,32 +1827,127 @@ pub async fn mount_filesystem(
                                                         }
                                                     }
                                                 }
+                                                Err(e) => log::warn!("Subfolder ino={} populate failed: {}", sub_ino, e),
                                             }
-                                            Err(e) => log::warn!("Subfolder ino={} populate failed: {}", sub_ino, e),
                                         }
+                                        Err(e) => log::warn!("Subfolder ino={} decrypt failed: {}", sub_ino, e),
                                     }
-                                    Err(e) => log::warn!("Subfolder ino={} decrypt failed: {}", sub_ino, e),
                                 }
+                                Err(e) => log::warn!("Subfolder ino={} fetch failed: {}", sub_ino, e),
                             }
-                            Err(e) => log::warn!("Subfolder ino={} fetch failed: {}", sub_ino, e),
                         }
                     }
+                    Err(e) => log::warn!("Root metadata decryption failed: {}", e),
                 }
-                Err(e) => log::warn!("Root metadata decryption failed: {}", e),
             }
+            Err(e) => log::warn!("Root folder fetch failed (mount will show empty): {}", e),
         }
-        Err(e) => log::warn!("Root folder fetch failed (mount will show empty): {}", e),
     }
 
+    // Mutations not yet published (e.g. made offline) are kept in the vault's
+    // journal until the sync daemon replays them. The writable mounts share
+    // it (and its replayer), so a change in a folder reachable from two mounts
+    // is journaled and replayed once. A read-only mount makes none: its
+    // journal stays in memory, leaving the one on disk to the writable mounts.
+    let vault_root_ipns_name = state
+        .root_ipns_name
+        .read()
+        .await
+        .clone()
+        .ok_or("Root IPNS name not available for FUSE mount")?;
+    let journal_replayer = if read_only {
+        None
+    } else {
+        let vault_root_folder_key = state
+            .root_folder_key
+            .read()
+            .await
+            .clone()
+            .ok_or("Root folder key not available for FUSE mount")?;
+        let vault_root_ipns_private_key = state.root_ipns_private_key.read().await.clone();
+        let replay_dir = replay_temp_dir();
+        std::fs::create_dir_all(&replay_dir)
+            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&replay_dir, std::fs::Permissions::from_mode(0o700));
+        }
+        let replayer = state.mounts.journal_replayer(|| crate::sync::replay::JournalReplayer {
+            api: state.api.clone(),
+            journal: Arc::new(Journal::open(
+                crate::sync::journal::journal_path(&vault_root_ipns_name),
+                state_key.clone(),
+                &vault_root_ipns_name,
+            )),
+            coordinator: publish_coordinator.clone(),
+            private_key: private_key.clone(),
+            mlkem_private_key: mlkem_private_key.clone(),
+            root_ipns_name: vault_root_ipns_name.clone(),
+            root_folder_key: vault_root_folder_key,
+            root_ipns_private_key: vault_root_ipns_private_key,
+            tee_public_key: tee_public_key.clone(),
+            tee_key_epoch,
+            metadata_format: state.metadata_format,
+            upload_txs: Default::default(),
+            temp_dir: replay_dir,
+            public_key: public_key.clone(),
+        });
+        replayer.add_mount(upload_tx.clone());
+        Some(replayer)
+    };
+    let journal = match &journal_replayer {
+        Some(replayer) => replayer.journal.clone(),
+        None => Arc::new(Journal::open(None, state_key.clone(), &vault_root_ipns_name)),
+    };
+
+    // Keep the whole tree warm (and the snapshot current) in the background
+    let crawler = snapshot::Crawler {
+        api: state.api.clone(),
+        private_key: private_key.clone(),
+        mlkem_private_key,
+        root_ipns_name: root_ipns_name.clone(),
+        root_folder_key: root_folder_key.clone(),
+        snapshot: vault_snapshot.unwrap_or_else(|| snapshot::VaultSnapshot::new(&root_ipns_name)),
+        path: snapshot_path,
+        state_key,
+        tx: crawl_tx,
+        changes: changes_rx,
+        mount_point: mount_path.clone(),
+    };
+    let crawler = rt.spawn(crawler.run());
+
+    // The watch list is carried over from before a remount
+    let watch_list = {
+        let mut mounts = state.mounts.entries.write().await;
+        let watch_list = match mounts.get(&mount_path) {
+            Some(previous) => previous.watch_list.clone(),
+            None => Default::default(),
+        };
+        mounts.insert(
+            mount_path.clone(),
+            Arc::new(VaultMount {
+                root_ipns_name: root_ipns_name.clone(),
+                remote_changes: changes_tx,
+                watch_list: watch_list.clone(),
+            }),
+        );
+        watch_list
+    };
+
     let fs = CipherBoxFS {
         inodes,
         metadata_cache,
         content_cache: cache::ContentCache::new(),
+        block_cache: cache::BlockCache::new(),
+        disk_cache: state.mounts.disk_cache(state.disk_cache_budget),
         api: state.api.clone(),
-        private_key: Zeroizing::new(private_key),
+        private_key,
         public_key: Zeroizing::new(public_key),
-        root_folder_key: Zeroizing::new(root_folder_key),
+        mlkem_public_key,
+        root_folder_key,
         root_ipns_name,
+        vault_root_ipns_name,
         rt,
         next_fh: AtomicU64::new(1),
         open_files: HashMap::new(),
@@ -1025,32 +1963,26 @@ pub async fn mount_filesystem(
         upload_rx,
         upload_tx,
         mutated_folders: HashMap::new(),
-        publish_coordinator: Arc::new(PublishCoordinator::new()),
+        crawl_rx,
+        crawler: Some(crawler),
+        kernel_tx,
+        publish_coordinator,
+        padding: state.padding,
+        metadata_format: state.metadata_format,
+        chunked_uploads: state.chunked_uploads,
+        gcm_seg_uploads: state.gcm_seg_uploads,
+        journal,
+        save_failures: state.save_failures.clone(),
+        watch_list,
         publish_queue: HashMap::new(),
+        read_only,
     };
 
     let mount_path_clone = mount_path.clone();
 
-    // Mount options
-    // Note: AutoUnmount and DefaultPermissions removed for FUSE-T compatibility.
-    // FUSE-T is NFS-based and does not support kernel-level permission checks
-    // or fusermount3-based auto-unmount.
-    // FUSE-T mount options:
-    // - backend=smb: Use SMB instead of NFS backend. NFS has a known macOS kernel
-    //   bug where WRITE RPCs never reach the FUSE-T server for newly created files,
-    //   causing permanent process hangs. SMB backend avoids this entirely.
-    let options = vec![
-        MountOption::FSName("CipherBox".to_string()),
-        MountOption::CUSTOM("volname=CipherBox".to_string()),
-        MountOption::CUSTOM("noappledouble".to_string()),
-        MountOption::CUSTOM("noapplexattr".to_string()),
-        MountOption::CUSTOM("backend=smb".to_string()),
-        MountOption::RW,
-    ];
-
     // Spawn FUSE event loop on a dedicated OS thread (not tokio).
-    // Use a channel so the thread can signal back if mount2 fails immediately
-    // (e.g. macFUSE kext not loaded). If mount2 succeeds, it blocks until
+    // Use a channel so the thread can signal back if mounting fails immediately
+    // (e.g. macFUSE kext not loaded). If it succeeds, the session blocks until
     // unmount and never sends on the channel, so we use a recv_timeout.
     let (tx, rx) = std::sync::mpsc::sync_channel::<Result<(), String>>(1);
 
@@ -1061,7 +1993,12 @@ pub async fn mount_filesystem(
                 "Mounting CipherBoxFS at {}",
                 mount_path_clone.display()
             );
-            match fuser::mount2(fs, &mount_path_clone, &options) {
+            fs.update_watch_list();
+            let session = fuser::Session::new(fs, &mount_path_clone, &options).and_then(|mut session| {
+                kernel::spawn_notifier(session.notifier(), kernel_rx)?;
+                session.run()
+            });
+            match session {
                 Ok(()) => {
                     log::info!("FUSE filesystem unmounted cleanly");
                     let _ = tx.send(Ok(()));
@@ -1075,8 +2012,8 @@ pub async fn mount_filesystem(
         .map_err(|e| format!("Failed to spawn FUSE thread: {}", e))?;
 
     // Wait up to 2 seconds for the mount to either fail or stabilize.
-    // If mount2 fails (e.g. missing kext), the error arrives quickly.
-    // If mount2 succeeds, it blocks (running the event loop) and we get a timeout.
+    // If mounting fails (e.g. missing kext), the error arrives quickly.
+    // If it succeeds, the session blocks (running the event loop) and we get a timeout.
     match rx.recv_timeout(std::time::Duration::from_secs(2)) {
         Ok(Ok(())) => {
             // Filesystem was unmounted immediately (unusual)
@@ -1097,46 +2034,48 @@ pub async fn mount_filesystem(
     }
 }
 
-/// Unmount the FUSE filesystem.
+/// Unmount the FUSE filesystem at `mount_path`.
 ///
-/// Calls the system `umount` command to cleanly unmount ~/CipherBox.
+/// Runs the platform's unmount commands (`profile::Platform::unmount_commands`)
+/// until one succeeds. Stop the `supervisor` first, or it remounts.
 #[cfg(feature = "fuse")]
-pub fn unmount_filesystem() -> Result<(), String> {
-    let mount_path = mount_point();
+pub fn unmount_filesystem(mount_path: &std::path::Path) -> Result<(), String> {
     log::info!("Unmounting CipherBoxFS at {}", mount_path.display());
 
     // Clean up temp directory
-    let temp_dir = std::env::temp_dir().join("cipherbox");
+    let temp_dir = mount_temp_dir(mount_path);
     if temp_dir.exists() {
         if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
             log::warn!("Failed to clean temp directory: {}", e);
         }
     }
 
-    let status = std::process::Command::new("umount")
-        .arg(mount_path.to_str().unwrap())
-        .status()
-        .map_err(|e| format!("Failed to run umount: {}", e))?;
+    run_unmount_commands(mount_path, profile::Platform::unmount_commands)
+}
 
-    if status.success() {
-        log::info!("FUSE filesystem unmounted successfully");
-        Ok(())
-    } else {
-        // Try diskutil unmount force as fallback on macOS — Finder keeps handles open
-        log::info!("umount failed (likely busy), trying diskutil unmount force");
-        let status = std::process::Command::new("diskutil")
-            .args(["unmount", "force", mount_path.to_str().unwrap()])
-            .status()
-            .map_err(|e| format!("Failed to run diskutil unmount force: {}", e))?;
-
-        if status.success() {
-            log::info!("FUSE filesystem force-unmounted via diskutil");
-            Ok(())
-        } else {
-            Err(format!(
-                "Failed to unmount {} — close Finder windows and retry",
-                mount_path.display()
-            ))
+/// Run the platform's `commands` (e.g. `profile::Platform::unmount_commands`)
+/// until one unmounts `mount_path`.
+#[cfg(feature = "fuse")]
+fn run_unmount_commands(
+    mount_path: &std::path::Path,
+    commands: fn(profile::Platform, &str) -> Vec<Vec<String>>,
+) -> Result<(), String> {
+    let mount_str = mount_path
+        .to_str()
+        .ok_or_else(|| format!("Mount point {} is not valid UTF-8", mount_path.display()))?;
+    for command in commands(profile::Platform::current(), mount_str) {
+        match std::process::Command::new(&command[0]).args(&command[1..]).status() {
+            Ok(status) if status.success() => {
+                log::info!("FUSE filesystem unmounted via {}", command.join(" "));
+                return Ok(());
+            }
+            // Busy, or not installed: try the next one
+            Ok(status) => log::info!("{} failed ({})", command.join(" "), status),
+            Err(e) => log::info!("Failed to run {}: {}", command[0], e),
         }
     }
+    Err(format!(
+        "Failed to unmount {} — close files open in it and retry",
+        mount_path.display()
+    ))
 }
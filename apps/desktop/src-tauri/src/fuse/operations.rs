@@ -10,15 +10,25 @@
 mod implementation {
     use fuser::{
         FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory,
-        ReplyEntry, ReplyEmpty, ReplyOpen, ReplyStatfs, ReplyWrite, ReplyXattr, Request,
+        ReplyDirectoryPlus, ReplyEntry, ReplyEmpty, ReplyOpen, ReplyStatfs, ReplyWrite, ReplyXattr,
+        Request,
     };
+    use std::collections::{HashMap, HashSet};
     use std::ffi::OsStr;
     use std::sync::atomic::Ordering;
+    use std::sync::Arc;
     use std::time::{Duration, SystemTime};
 
+    use crate::api::ipns::ResolveError;
+    use crate::crypto::chunked::{ChunkManifest, ChunkRef, CHUNKED_MODE};
+    use crate::crypto::padding::PaddingScheme;
+    use crate::crypto::keys::{FileKey, FolderKey, IpnsSigningKey, Secp256k1SecretKey};
     use crate::fuse::CipherBoxFS;
-    use crate::fuse::file_handle::OpenFileHandle;
-    use crate::fuse::inode::{InodeData, InodeKind, ROOT_INO, BLOCK_SIZE};
+    use crate::fuse::cache::DiskCache;
+    use crate::fuse::file_handle::{FileBase, OpenFileHandle};
+    use crate::fuse::inode::{InodeData, InodeKind, NewFolderKeys, ROOT_INO, BLOCK_SIZE};
+    use crate::crypto::folder::{FolderChild, MetadataFormat};
+    use crate::sync::journal::JournalOp;
 
     /// TTL for FUSE attribute/entry cache replies on files.
     /// Longer TTL = fewer kernel callbacks = less FUSE-T NFS thread contention.
@@ -41,6 +51,34 @@ mod implementation {
     /// Maximum number of past versions to keep per file.
     const MAX_VERSIONS_PER_FILE: usize = 10;
 
+    /// Files at or above this size (4 MiB) are encrypted while they upload,
+    /// if enabled. With chunked uploads enabled they are stored chunked
+    /// ("CHUNKED"): FastCDC chunks plus an encrypted manifest, so later
+    /// versions only upload the chunks that changed. Otherwise, with GCM-SEG
+    /// uploads enabled, they are stored as GCM-SEG.
+    const LARGE_FILE_THRESHOLD_BYTES: u64 = 4 * 1024 * 1024;
+
+    /// Streamable media above this size (256 KiB) is encrypted with
+    /// AES-256-CTR, so it can be read with range requests here and in the
+    /// web app. Takes precedence over chunking. Matches the web app policy.
+    const CTR_THRESHOLD_BYTES: u64 = 256 * 1024;
+
+    /// MIME types written as CTR: the media types the web app can stream.
+    const STREAMING_MIME_TYPES: &[&str] = &[
+        "video/mp4",
+        "video/webm",
+        "audio/mpeg",
+        "audio/mp4",
+        "audio/webm",
+        "audio/ogg",
+        "audio/aac",
+    ];
+
+    /// Whether a file being saved should be encrypted with AES-256-CTR.
+    fn use_ctr_mode(mime_type: &str, size: u64) -> bool {
+        size > CTR_THRESHOLD_BYTES && STREAMING_MIME_TYPES.contains(&mime_type)
+    }
+
     /// Cooldown period for desktop FUSE version creation (15 minutes in milliseconds).
     /// Only creates a new version entry if the most recent version is older than this.
     const VERSION_COOLDOWN_MS: u64 = 15 * 60 * 1000;
@@ -84,6 +122,7 @@ mod implementation {
             "avi" => "video/x-msvideo",
             "mkv" => "video/x-matroska",
             "mp3" => "audio/mpeg",
+            "m4a" => "audio/mp4",
             "wav" => "audio/wav",
             "ogg" => "audio/ogg",
             "flac" => "audio/flac",
@@ -120,6 +159,20 @@ mod implementation {
     /// open(), all subsequent reads are instant.
     const CONTENT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
 
+    /// Extra blocks fetched after a missing block on CTR range reads, so
+    /// sequential playback issues one request per couple of blocks.
+    const RANGE_READAHEAD_BLOCKS: u64 = 1;
+
+    /// Extra segments fetched after a missing segment on GCM-SEG range reads
+    /// (1 MiB, the same readahead as CTR).
+    const SEGMENT_READAHEAD: u64 = 16;
+
+    /// Whether reads of this encryption mode are served with range requests
+    /// instead of downloading the whole file (CTR and GCM-SEG allow random access).
+    fn is_range_read(encryption_mode: &str) -> bool {
+        encryption_mode == "CTR" || encryption_mode == crate::crypto::aes_gcm_seg::GCM_SEG_MODE
+    }
+
     /// Run an async operation with a timeout, blocking the current thread.
     /// Returns Err if the operation fails or times out.
     fn block_with_timeout<F, T>(rt: &tokio::runtime::Handle, fut: F) -> Result<T, String>
@@ -134,53 +187,21 @@ mod implementation {
         })
     }
 
-    /// Encrypted folder metadata format from IPFS (JSON with iv + data).
-    #[derive(serde::Deserialize)]
-    struct EncryptedFolderMetadata {
-        /// Hex-encoded 12-byte IV for AES-GCM.
-        iv: String,
-        /// Base64-encoded AES-GCM ciphertext (includes 16-byte auth tag).
-        data: String,
-    }
-
     /// Decrypt folder metadata fetched from IPFS (v2 only).
     ///
-    /// The IPFS content is JSON: `{ "iv": "<hex>", "data": "<base64>" }`.
-    /// Decode IV from hex, decode ciphertext from base64, then AES-256-GCM decrypt.
-    /// Rejects non-v2 metadata.
+    /// The IPFS content is a metadata envelope bound to `ipns_name`, or a legacy
+    /// `{ "iv": "<hex>", "data": "<base64>" }` envelope (rejected for names in
+    /// `history`). Rejects non-v2 metadata.
     fn decrypt_metadata_from_ipfs(
         encrypted_bytes: &[u8],
-        folder_key: &[u8],
+        folder_key: &FolderKey,
+        ipns_name: &str,
+        history: &crate::crypto::folder::EnvelopeHistory,
     ) -> Result<crate::crypto::folder::FolderMetadata, String> {
-        let encrypted: EncryptedFolderMetadata = serde_json::from_slice(encrypted_bytes)
-            .map_err(|e| format!("Failed to parse encrypted metadata JSON: {}", e))?;
-
-        // Decode IV from hex
-        let iv_bytes = hex::decode(&encrypted.iv)
-            .map_err(|_| "Invalid metadata IV hex".to_string())?;
-        if iv_bytes.len() != 12 {
-            return Err(format!("Invalid IV length: {} (expected 12)", iv_bytes.len()));
-        }
-        let iv: [u8; 12] = iv_bytes.try_into().unwrap();
-
-        // Decode ciphertext from base64
-        use base64::Engine;
-        let ciphertext = base64::engine::general_purpose::STANDARD
-            .decode(&encrypted.data)
-            .map_err(|e| format!("Invalid metadata base64: {}", e))?;
-
-        // Decrypt with AES-256-GCM
-        let folder_key_arr: [u8; 32] = folder_key
-            .try_into()
-            .map_err(|_| "Invalid folder key length".to_string())?;
-
-        // Reconstruct sealed format: IV || ciphertext (includes tag)
-        let mut sealed = Vec::with_capacity(12 + ciphertext.len());
-        sealed.extend_from_slice(&iv);
-        sealed.extend_from_slice(&ciphertext);
-
-        crate::crypto::folder::decrypt_folder_metadata(&sealed, &folder_key_arr)
-            .map_err(|e| format!("Metadata decryption failed: {}", e))
+        crate::crypto::folder::decrypt_folder_metadata_envelope_strict(
+            encrypted_bytes, folder_key, ipns_name, history,
+        )
+        .map_err(|e| format!("Metadata decryption failed: {}", e))
     }
 
     /// Helper: Fetch, decrypt, and populate a folder's children.
@@ -192,11 +213,11 @@ mod implementation {
         fs: &mut CipherBoxFS,
         ino: u64,
         ipns_name: &str,
-        folder_key: &[u8],
+        folder_key: &FolderKey,
     ) -> Result<(), String> {
         let api = fs.api.clone();
         let ipns_name_owned = ipns_name.to_string();
-        let folder_key_owned = folder_key.to_vec();
+        let folder_key_owned = folder_key.clone();
         let private_key = fs.private_key.clone();
 
         let rt = fs.rt.clone();
@@ -211,9 +232,12 @@ mod implementation {
         let (encrypted_bytes, cid) = result;
 
         // Decrypt metadata (v2 only)
-        let metadata = decrypt_metadata_from_ipfs(&encrypted_bytes, &folder_key_owned)?;
+        let metadata = decrypt_metadata_from_ipfs(
+            &encrypted_bytes, &folder_key_owned, ipns_name, api.envelope_history(),
+        )?;
 
         // Cache metadata directly
+        fs.publish_coordinator.set_base(ipns_name, &cid, &metadata);
         fs.metadata_cache.set(&ipns_name.to_string(), metadata.clone(), cid);
 
         // Populate inode table with children.
@@ -226,6 +250,7 @@ mod implementation {
             log::info!("Resolving {} FilePointer(s) for folder ino {}", unresolved.len(), ino);
             resolve_file_pointers_blocking(fs, &unresolved, &folder_key_owned)?;
         }
+        fs.update_watch_list();
 
         Ok(())
     }
@@ -237,12 +262,10 @@ mod implementation {
     fn resolve_file_pointers_blocking(
         fs: &mut CipherBoxFS,
         unresolved: &[(u64, String)],
-        folder_key: &[u8],
+        folder_key: &FolderKey,
     ) -> Result<(), String> {
         let api = fs.api.clone();
         let rt = fs.rt.clone();
-        let folder_key_arr: [u8; 32] = folder_key.try_into()
-            .map_err(|_| "Invalid folder key length for FilePointer resolution".to_string())?;
 
         for (ino, ipns_name) in unresolved {
             let resolve_result = block_with_timeout(&rt, async {
@@ -253,8 +276,10 @@ mod implementation {
 
             match resolve_result {
                 Ok(encrypted_bytes) => {
-                    // Decrypt file metadata JSON (same format as folder metadata: { iv, data })
-                    match decrypt_file_metadata_from_ipfs(&encrypted_bytes, &folder_key_arr) {
+                    // Decrypt file metadata envelope (same format as folder metadata)
+                    match decrypt_file_metadata_from_ipfs(
+                        &encrypted_bytes, folder_key, ipns_name, api.envelope_history(),
+                    ) {
                         Ok(file_meta) => {
                             fs.inodes.resolve_file_pointer(
                                 *ino,
@@ -263,6 +288,8 @@ mod implementation {
                                 file_meta.file_iv,
                                 file_meta.size,
                                 file_meta.encryption_mode,
+                                file_meta.padding,
+                                file_meta.chunk_hashes,
                                 file_meta.versions,
                             );
                         }
@@ -288,159 +315,775 @@ mod implementation {
 
     /// Decrypt per-file metadata fetched from IPFS.
     ///
-    /// The IPFS content is JSON: `{ "iv": "<hex>", "data": "<base64>" }`.
-    /// Uses the parent folder's key for decryption.
+    /// The IPFS content is a metadata envelope bound to the file's `ipns_name`
+    /// (or a legacy `{ iv, data }` envelope, rejected for names in `history`).
+    /// Uses the parent folder's key.
     fn decrypt_file_metadata_from_ipfs(
         encrypted_bytes: &[u8],
-        folder_key: &[u8; 32],
+        folder_key: &FolderKey,
+        ipns_name: &str,
+        history: &crate::crypto::folder::EnvelopeHistory,
     ) -> Result<crate::crypto::folder::FileMetadata, String> {
-        let encrypted: EncryptedFolderMetadata = serde_json::from_slice(encrypted_bytes)
-            .map_err(|e| format!("Failed to parse encrypted file metadata JSON: {}", e))?;
-
-        let iv_bytes = hex::decode(&encrypted.iv)
-            .map_err(|_| "Invalid file metadata IV hex".to_string())?;
-        if iv_bytes.len() != 12 {
-            return Err(format!("Invalid IV length: {} (expected 12)", iv_bytes.len()));
-        }
-        let iv: [u8; 12] = iv_bytes.try_into().unwrap();
-
-        use base64::Engine;
-        let ciphertext = base64::engine::general_purpose::STANDARD
-            .decode(&encrypted.data)
-            .map_err(|e| format!("Invalid file metadata base64: {}", e))?;
-
-        // Reconstruct sealed format: IV || ciphertext (includes tag)
-        let mut sealed = Vec::with_capacity(12 + ciphertext.len());
-        sealed.extend_from_slice(&iv);
-        sealed.extend_from_slice(&ciphertext);
-
-        crate::crypto::folder::decrypt_file_metadata(&sealed, folder_key)
-            .map_err(|e| format!("File metadata decryption failed: {}", e))
+        crate::crypto::folder::decrypt_file_metadata_envelope_strict(
+            encrypted_bytes, folder_key, ipns_name, history,
+        )
+        .map_err(|e| format!("File metadata decryption failed: {}", e))
     }
 
     /// Helper: Fetch and decrypt existing file content for editing.
     ///
     /// Used when opening an existing file for writing -- need to pre-populate
     /// the temp file with the current decrypted content.
-    /// Dispatches to AES-GCM or AES-CTR based on encryption_mode.
+    /// Dispatches to AES-GCM, AES-CTR, GCM-SEG or chunked based on encryption_mode.
     fn fetch_and_decrypt_file_content(
         fs: &CipherBoxFS,
         cid: &str,
         encrypted_file_key_hex: &str,
         iv_hex: &str,
         encryption_mode: &str,
+        padding: Option<&str>,
     ) -> Result<Vec<u8>, String> {
         let api = fs.api.clone();
+        let disk_cache = fs.disk_cache.clone();
         let private_key = fs.private_key.clone();
-        let cid_owned = cid.to_string();
-        let key_hex = encrypted_file_key_hex.to_string();
-        let iv_hex_owned = iv_hex.to_string();
-        let mode = encryption_mode.to_string();
         let rt = fs.rt.clone();
 
-        block_with_timeout(&rt, async {
-            let encrypted_bytes =
-                crate::api::ipfs::fetch_content(&api, &cid_owned).await?;
-            let encrypted_file_key = hex::decode(&key_hex)
-                .map_err(|_| "Invalid file key hex".to_string())?;
-            let file_key = zeroize::Zeroizing::new(
-                crate::crypto::ecies::unwrap_key(&encrypted_file_key, &private_key)
-                    .map_err(|e| format!("File key unwrap failed: {}", e))?,
-            );
-            let file_key_arr: [u8; 32] = file_key.as_slice().try_into()
-                .map_err(|_| "Invalid file key length".to_string())?;
+        block_with_timeout(&rt, fetch_and_decrypt_content_async(
+            &api, &disk_cache, cid, encrypted_file_key_hex, iv_hex, encryption_mode, padding, &private_key,
+        ))
+    }
+
+    /// Decrypt downloaded file content according to its encryption mode.
+    ///
+    /// - "CTR": 16-byte IV, no auth tag
+    /// - "GCM-SEG": 7-byte nonce prefix, per-segment auth tags
+    /// - anything else: AES-GCM with 12-byte IV and 16-byte auth tag appended
+    fn decrypt_file_content(
+        encrypted_bytes: &[u8],
+        file_key: &FileKey,
+        iv_hex: &str,
+        encryption_mode: &str,
+    ) -> Result<Vec<u8>, String> {
+        let iv = hex::decode(iv_hex)
+            .map_err(|_| "Invalid file IV hex".to_string())?;
 
-            let plaintext = if mode == "CTR" {
-                // AES-CTR: 16-byte IV, no auth tag
-                let iv = hex::decode(&iv_hex_owned)
-                    .map_err(|_| "Invalid file IV hex".to_string())?;
+        match encryption_mode {
+            "CTR" => {
                 let iv_arr: [u8; 16] = iv.try_into()
                     .map_err(|_| "Invalid CTR IV length (expected 16)".to_string())?;
-                crate::crypto::aes_ctr::decrypt_aes_ctr(&encrypted_bytes, &file_key_arr, &iv_arr)
-                    .map_err(|e| format!("CTR file decryption failed: {}", e))?
-            } else {
-                // AES-GCM: 12-byte IV, 16-byte auth tag appended
-                let iv = hex::decode(&iv_hex_owned)
-                    .map_err(|_| "Invalid file IV hex".to_string())?;
+                crate::crypto::aes_ctr::decrypt_aes_ctr(encrypted_bytes, file_key.as_bytes(), &iv_arr)
+                    .map_err(|e| format!("CTR decryption failed: {}", e))
+            }
+            crate::crypto::aes_gcm_seg::GCM_SEG_MODE => {
+                let prefix: [u8; crate::crypto::aes_gcm_seg::GCM_SEG_NONCE_PREFIX_SIZE] = iv.try_into()
+                    .map_err(|_| "Invalid GCM-SEG nonce prefix length (expected 7)".to_string())?;
+                crate::crypto::aes_gcm_seg::decrypt_aes_gcm_seg(encrypted_bytes, file_key.as_bytes(), &prefix)
+                    .map_err(|e| format!("GCM-SEG decryption failed: {}", e))
+            }
+            _ => {
                 let iv_arr: [u8; 12] = iv.try_into()
                     .map_err(|_| "Invalid GCM IV length (expected 12)".to_string())?;
-                crate::crypto::aes::decrypt_aes_gcm(
-                    &encrypted_bytes, &file_key_arr, &iv_arr,
-                )
-                .map_err(|e| format!("GCM file decryption failed: {}", e))?
-            };
+                crate::crypto::aes::decrypt_aes_gcm(encrypted_bytes, file_key.as_bytes(), &iv_arr)
+                    .map_err(|e| format!("GCM decryption failed: {}", e))
+            }
+        }
+    }
 
-            Ok(plaintext)
-        })
+    /// Fetch encrypted content from the disk cache, or download it and cache it there.
+    async fn fetch_encrypted_content(
+        api: &crate::api::client::ApiClient,
+        disk_cache: &DiskCache,
+        cid: &str,
+    ) -> Result<Vec<u8>, String> {
+        if let Some(bytes) = disk_cache.get(cid) {
+            return Ok(bytes);
+        }
+        let bytes = crate::api::ipfs::fetch_content(api, cid).await?;
+        disk_cache.put(cid, &bytes);
+        Ok(bytes)
     }
 
     /// Async version of content download + decrypt for use in background prefetch tasks.
     /// Does not require a reference to CipherBoxFS — takes all needed params by value.
+    /// Ciphertext comes from the disk cache when present; entries that fail to
+    /// decrypt are dropped from it so the next attempt downloads them again.
+    #[allow(clippy::too_many_arguments)]
     async fn fetch_and_decrypt_content_async(
         api: &crate::api::client::ApiClient,
+        disk_cache: &DiskCache,
         cid: &str,
         encrypted_file_key_hex: &str,
         iv_hex: &str,
         encryption_mode: &str,
-        private_key: &[u8],
+        padding: Option<&str>,
+        private_key: &Secp256k1SecretKey,
     ) -> Result<Vec<u8>, String> {
-        let encrypted_bytes =
-            crate::api::ipfs::fetch_content(api, cid).await?;
+        // Reject unknown schemes before downloading anything
+        if let Some(scheme) = padding {
+            PaddingScheme::from_name(scheme).map_err(|e| e.to_string())?;
+        }
+
         let encrypted_file_key = hex::decode(encrypted_file_key_hex)
             .map_err(|_| "Invalid file key hex".to_string())?;
-        let file_key = zeroize::Zeroizing::new(
-            crate::crypto::ecies::unwrap_key(&encrypted_file_key, private_key)
-                .map_err(|e| format!("File key unwrap failed: {}", e))?,
-        );
-        let file_key_arr: [u8; 32] = file_key.as_slice().try_into()
-            .map_err(|_| "Invalid file key length".to_string())?;
-
-        let plaintext = if encryption_mode == "CTR" {
-            let iv = hex::decode(iv_hex)
-                .map_err(|_| "Invalid file IV hex".to_string())?;
-            let iv_arr: [u8; 16] = iv.try_into()
-                .map_err(|_| "Invalid CTR IV length (expected 16)".to_string())?;
-            crate::crypto::aes_ctr::decrypt_aes_ctr(&encrypted_bytes, &file_key_arr, &iv_arr)
-                .map_err(|e| format!("CTR decryption failed: {}", e))?
+        let file_key: FileKey =
+            crate::crypto::ecies::unwrap_secret_key(&encrypted_file_key, private_key)
+                .map_err(|e| format!("File key unwrap failed: {}", e))?;
+
+        let mut plaintext = if encryption_mode == CHUNKED_MODE {
+            fetch_and_decrypt_chunked(api, disk_cache, cid, &file_key).await?
         } else {
-            let iv = hex::decode(iv_hex)
-                .map_err(|_| "Invalid file IV hex".to_string())?;
-            let iv_arr: [u8; 12] = iv.try_into()
-                .map_err(|_| "Invalid GCM IV length (expected 12)".to_string())?;
-            crate::crypto::aes::decrypt_aes_gcm(&encrypted_bytes, &file_key_arr, &iv_arr)
-                .map_err(|e| format!("GCM decryption failed: {}", e))?
+            let encrypted_bytes = fetch_encrypted_content(api, disk_cache, cid).await?;
+            decrypt_file_content(&encrypted_bytes, &file_key, iv_hex, encryption_mode)
+                .inspect_err(|_| disk_cache.remove(cid))?
         };
 
+        if padding.is_some() {
+            if let Err(e) = crate::crypto::padding::unpad_content(&mut plaintext) {
+                use zeroize::Zeroize;
+                plaintext.zeroize();
+                return Err(format!("Content unpadding failed: {}", e));
+            }
+        }
         Ok(plaintext)
     }
 
+    /// Fetch and decrypt the chunk manifest stored at `manifest_cid`.
+    async fn fetch_chunk_manifest(
+        api: &crate::api::client::ApiClient,
+        manifest_cid: &str,
+        file_key: &FileKey,
+    ) -> Result<ChunkManifest, String> {
+        let sealed = crate::api::ipfs::fetch_content(api, manifest_cid).await?;
+        crate::crypto::chunked::decrypt_manifest(&sealed, file_key)
+            .map_err(|e| format!("Chunk manifest decryption failed: {}", e))
+    }
+
+    /// Download and reassemble a chunked file from its manifest.
+    async fn fetch_and_decrypt_chunked(
+        api: &crate::api::client::ApiClient,
+        disk_cache: &DiskCache,
+        manifest_cid: &str,
+        file_key: &FileKey,
+    ) -> Result<Vec<u8>, String> {
+        let sealed = fetch_encrypted_content(api, disk_cache, manifest_cid).await?;
+        let manifest = crate::crypto::chunked::decrypt_manifest(&sealed, file_key)
+            .map_err(|e| {
+                disk_cache.remove(manifest_cid);
+                format!("Chunk manifest decryption failed: {}", e)
+            })?;
+        let mut plaintext = Vec::with_capacity(manifest.size as usize);
+        for chunk in &manifest.chunks {
+            let sealed = fetch_encrypted_content(api, disk_cache, &chunk.cid).await?;
+            let part = zeroize::Zeroizing::new(
+                crate::crypto::chunked::decrypt_chunk(&sealed, file_key, chunk)
+                    .map_err(|e| {
+                        disk_cache.remove(&chunk.cid);
+                        format!("Chunk {} decryption failed: {}", chunk.cid, e)
+                    })?,
+            );
+            plaintext.extend_from_slice(&part);
+        }
+        Ok(plaintext)
+    }
+
+    /// Read `size` bytes at `offset` of a CTR file via HTTP range requests.
+    ///
+    /// Decrypted `STREAM_BLOCK_SIZE` blocks are kept in the block cache. If any
+    /// block of the window is missing, one request fetches from the first
+    /// missing block through the end of the window plus readahead. Reads are
+    /// clamped to the plaintext size, so padding is never served. When the file
+    /// metadata carries chunk hashes, whole chunks are fetched and each is
+    /// verified before it is decrypted.
+    fn read_ctr_range(
+        fs: &mut CipherBoxFS,
+        ino: u64,
+        fh: u64,
+        offset: u64,
+        size: u32,
+    ) -> Result<zeroize::Zeroizing<Vec<u8>>, String> {
+        use crate::fuse::cache::STREAM_BLOCK_SIZE;
+
+        let (cid, encrypted_file_key_hex, iv_hex, file_size) = match fs.inodes.get(ino).map(|i| &i.kind) {
+            Some(InodeKind::File { cid, encrypted_file_key, iv, size, .. }) => {
+                (cid.clone(), encrypted_file_key.clone(), iv.clone(), *size)
+            }
+            _ => return Err("Not a file".to_string()),
+        };
+
+        let end = file_size.min(offset.saturating_add(size as u64));
+        if offset >= end {
+            return Ok(zeroize::Zeroizing::new(Vec::new()));
+        }
+
+        let iv: [u8; 16] = hex::decode(&iv_hex)
+            .ok()
+            .and_then(|iv| iv.try_into().ok())
+            .ok_or_else(|| "Invalid CTR IV (expected 16 bytes hex)".to_string())?;
+
+        let file_key = stream_file_key(fs, fh, &encrypted_file_key_hex)?;
+
+        let first = offset / STREAM_BLOCK_SIZE;
+        let last = (end - 1) / STREAM_BLOCK_SIZE;
+
+        let mut fetched: HashMap<u64, zeroize::Zeroizing<Vec<u8>>> = HashMap::new();
+        let uncached: Vec<u64> = (first..=last)
+            .filter(|&i| fs.block_cache.get(&cid, i).is_none())
+            .collect();
+        let hashes = match fs.inodes.get(ino).map(|i| &i.kind) {
+            Some(InodeKind::File { chunk_hashes, .. }) => chunk_hashes.as_deref(),
+            _ => None,
+        };
+
+        // Blocks missing from memory are looked up on disk before the network
+        for &index in &uncached {
+            let key = crate::fuse::cache::block_key(&cid, index);
+            let Some(ciphertext) = fs.disk_cache.get(&key) else { continue };
+            match decrypt_ctr_blocks(&ciphertext, index, hashes, &file_key, &iv, &cid) {
+                Ok(plaintext) => {
+                    fetched.insert(index, plaintext);
+                }
+                Err(e) => {
+                    log::warn!("Dropping disk-cached block: {}", e);
+                    fs.disk_cache.remove(&key);
+                }
+            }
+        }
+
+        if let Some(missing) = uncached.into_iter().find(|i| !fetched.contains_key(i)) {
+            let last_block = (file_size - 1) / STREAM_BLOCK_SIZE;
+            let fetch_to = (last + RANGE_READAHEAD_BLOCKS).min(last_block);
+            let range_start = missing * STREAM_BLOCK_SIZE;
+            // Hashes cover whole ciphertext chunks, which may run past the
+            // plaintext size when the content is padded; the server stops at EOF.
+            let range_end = if hashes.is_some() {
+                (fetch_to + 1) * STREAM_BLOCK_SIZE - 1
+            } else {
+                ((fetch_to + 1) * STREAM_BLOCK_SIZE).min(file_size) - 1
+            };
+
+            let api = fs.api.clone();
+            let rt = fs.rt.clone();
+            let ciphertext = block_with_timeout(
+                &rt,
+                crate::api::ipfs::fetch_content_range(&api, &cid, range_start, range_end),
+            )?;
+            let plaintext = decrypt_ctr_blocks(&ciphertext, missing, hashes, &file_key, &iv, &cid)?;
+            log::debug!(
+                "Range read: fetched bytes {}-{} of CID {}",
+                range_start,
+                range_end,
+                &cid[..cid.len().min(12)]
+            );
+            // Only complete blocks (or the file's last) go to disk, so a
+            // truncated response is never served from the cache later
+            for (i, block) in ciphertext.chunks(STREAM_BLOCK_SIZE as usize).enumerate() {
+                let index = missing + i as u64;
+                if block.len() as u64 == STREAM_BLOCK_SIZE || index == last_block {
+                    fs.disk_cache.put(&crate::fuse::cache::block_key(&cid, index), block);
+                }
+            }
+            for (i, block) in plaintext.chunks(STREAM_BLOCK_SIZE as usize).enumerate() {
+                fetched.insert(missing + i as u64, zeroize::Zeroizing::new(block.to_vec()));
+            }
+        }
+
+        let mut data = zeroize::Zeroizing::new(Vec::with_capacity((end - offset) as usize));
+        for index in first..=last {
+            let block_start = index * STREAM_BLOCK_SIZE;
+            let from = (offset.max(block_start) - block_start) as usize;
+            let to = (end.min(block_start + STREAM_BLOCK_SIZE) - block_start) as usize;
+            let block: &[u8] = match fetched.get(&index) {
+                Some(block) => block,
+                None => fs.block_cache.get(&cid, index).unwrap_or_default(),
+            };
+            if block.len() < to {
+                return Err(format!("Short range response for CID {}", cid));
+            }
+            data.extend_from_slice(&block[from..to]);
+        }
+
+        for (index, mut block) in fetched {
+            fs.block_cache.set(&cid, index, std::mem::take(&mut *block));
+        }
+        Ok(data)
+    }
+
+    /// File key of a range-read file, unwrapped once per open handle.
+    fn stream_file_key(
+        fs: &mut CipherBoxFS,
+        fh: u64,
+        encrypted_file_key_hex: &str,
+    ) -> Result<FileKey, String> {
+        if let Some(key) = fs.open_files.get(&fh).and_then(|h| h.stream_key.clone()) {
+            return Ok(key);
+        }
+        let encrypted_file_key = hex::decode(encrypted_file_key_hex)
+            .map_err(|_| "Invalid file key hex".to_string())?;
+        let key: FileKey =
+            crate::crypto::ecies::unwrap_secret_key(&encrypted_file_key, &fs.private_key)
+                .map_err(|e| format!("File key unwrap failed: {}", e))?;
+        if let Some(handle) = fs.open_files.get_mut(&fh) {
+            handle.stream_key = Some(key.clone());
+        }
+        Ok(key)
+    }
+
+    /// Read `size` bytes at `offset` of a GCM-SEG file via HTTP range requests.
+    ///
+    /// Only the segments covering the window are fetched (plus readahead),
+    /// and each is authenticated before it is served. Decrypted segments are
+    /// kept in the block cache and their ciphertext in the disk cache, keyed
+    /// by segment index. Reads are clamped to the plaintext size, so padding
+    /// is never served.
+    fn read_gcm_seg_range(
+        fs: &mut CipherBoxFS,
+        ino: u64,
+        fh: u64,
+        offset: u64,
+        size: u32,
+    ) -> Result<zeroize::Zeroizing<Vec<u8>>, String> {
+        use crate::crypto::aes_gcm_seg::{self, GCM_SEG_NONCE_PREFIX_SIZE, GCM_SEG_SEGMENT_SIZE};
+
+        let (cid, encrypted_file_key_hex, iv_hex, file_size, padding) = match fs.inodes.get(ino).map(|i| &i.kind) {
+            Some(InodeKind::File { cid, encrypted_file_key, iv, size, padding, .. }) => {
+                (cid.clone(), encrypted_file_key.clone(), iv.clone(), *size, padding.clone())
+            }
+            _ => return Err("Not a file".to_string()),
+        };
+
+        let end = file_size.min(offset.saturating_add(size as u64));
+        if offset >= end {
+            return Ok(zeroize::Zeroizing::new(Vec::new()));
+        }
+
+        let nonce_prefix: [u8; GCM_SEG_NONCE_PREFIX_SIZE] = hex::decode(&iv_hex)
+            .ok()
+            .and_then(|iv| iv.try_into().ok())
+            .ok_or_else(|| "Invalid GCM-SEG nonce prefix (expected 7 bytes hex)".to_string())?;
+
+        // Padded content ends with a padding tail; the ciphertext length
+        // follows from the size and padding scheme recorded in the metadata.
+        let stored_len = match padding.as_deref() {
+            Some(name) => {
+                let scheme = PaddingScheme::from_name(name).map_err(|e| e.to_string())?;
+                scheme.padded_len(file_size as usize + 1) as u64
+            }
+            None => file_size,
+        };
+        let ciphertext_len = aes_gcm_seg::encrypted_size(stored_len);
+        let final_segment = aes_gcm_seg::segment_count(stored_len) - 1;
+
+        let file_key = stream_file_key(fs, fh, &encrypted_file_key_hex)?;
+
+        let segments = aes_gcm_seg::segments_for_range(ciphertext_len, offset, end - 1)
+            .map_err(|e| format!("GCM-SEG range failed: {}", e))?;
+
+        let mut fetched: HashMap<u64, zeroize::Zeroizing<Vec<u8>>> = HashMap::new();
+        let uncached: Vec<u64> = segments.clone()
+            .filter(|&i| fs.block_cache.get(&cid, i).is_none())
+            .collect();
+
+        // Segments missing from memory are looked up on disk before the network
+        for &index in &uncached {
+            let key = crate::fuse::cache::block_key(&cid, index);
+            let Some(ciphertext) = fs.disk_cache.get(&key) else { continue };
+            match aes_gcm_seg::decrypt_segments(&ciphertext, ciphertext_len, index, file_key.as_bytes(), &nonce_prefix) {
+                Ok(mut plaintext) if plaintext.len() == 1 => {
+                    fetched.insert(index, plaintext.remove(0));
+                }
+                _ => {
+                    log::warn!("Dropping disk-cached segment {} of CID {}", index, cid);
+                    fs.disk_cache.remove(&key);
+                }
+            }
+        }
+
+        if let Some(missing) = uncached.into_iter().find(|i| !fetched.contains_key(i)) {
+            let fetch_to = (segments.end - 1 + SEGMENT_READAHEAD).min(final_segment);
+            let range = |index| aes_gcm_seg::segment_ciphertext_range(index, ciphertext_len)
+                .map_err(|e| format!("GCM-SEG range failed: {}", e));
+            let range_start = range(missing)?.start;
+            let range_end = range(fetch_to)?.end - 1;
+
+            let api = fs.api.clone();
+            let rt = fs.rt.clone();
+            let ciphertext = block_with_timeout(
+                &rt,
+                crate::api::ipfs::fetch_content_range(&api, &cid, range_start, range_end),
+            )?;
+            let plaintext = aes_gcm_seg::decrypt_segments(
+                &ciphertext, ciphertext_len, missing, file_key.as_bytes(), &nonce_prefix,
+            )
+            .map_err(|e| format!("GCM-SEG decryption of CID {} failed: {}", cid, e))?;
+            log::debug!(
+                "Range read: fetched bytes {}-{} of CID {}",
+                range_start,
+                range_end,
+                &cid[..cid.len().min(12)]
+            );
+            // Every segment was authenticated, so all of them may go to disk
+            let segment_ciphertext = ciphertext.chunks(aes_gcm_seg::GCM_SEG_ENCRYPTED_SEGMENT_SIZE);
+            for (i, (sealed, segment)) in segment_ciphertext.zip(plaintext).enumerate() {
+                let index = missing + i as u64;
+                fs.disk_cache.put(&crate::fuse::cache::block_key(&cid, index), sealed);
+                fetched.insert(index, segment);
+            }
+        }
+
+        let segment_size = GCM_SEG_SEGMENT_SIZE as u64;
+        let mut data = zeroize::Zeroizing::new(Vec::with_capacity((end - offset) as usize));
+        for index in segments {
+            let segment_start = index * segment_size;
+            let from = (offset.max(segment_start) - segment_start) as usize;
+            let to = (end.min(segment_start + segment_size) - segment_start) as usize;
+            let segment: &[u8] = match fetched.get(&index) {
+                Some(segment) => segment,
+                None => fs.block_cache.get(&cid, index).unwrap_or_default(),
+            };
+            if segment.len() < to {
+                return Err(format!("Short range response for CID {}", cid));
+            }
+            data.extend_from_slice(&segment[from..to]);
+        }
+
+        for (index, mut segment) in fetched {
+            fs.block_cache.set(&cid, index, std::mem::take(&mut *segment));
+        }
+        Ok(data)
+    }
+
+    /// Verify and decrypt CTR ciphertext that starts at block `first`.
+    ///
+    /// Each `STREAM_BLOCK_SIZE` chunk is checked against `hashes` (when the
+    /// file metadata has them) before anything is decrypted.
+    fn decrypt_ctr_blocks(
+        ciphertext: &[u8],
+        first: u64,
+        hashes: Option<&[String]>,
+        file_key: &FileKey,
+        iv: &[u8; 16],
+        cid: &str,
+    ) -> Result<zeroize::Zeroizing<Vec<u8>>, String> {
+        use crate::fuse::cache::STREAM_BLOCK_SIZE;
+
+        if let Some(hashes) = hashes {
+            for (i, chunk) in ciphertext.chunks(STREAM_BLOCK_SIZE as usize).enumerate() {
+                let index = first + i as u64;
+                crate::crypto::aes_ctr::verify_ctr_chunk(chunk, index as usize, hashes)
+                    .map_err(|e| format!("Block {} of CID {}: {}", index, cid, e))?;
+            }
+        }
+        crate::crypto::aes_ctr::decrypt_aes_ctr_at(ciphertext, file_key.as_bytes(), iv, first * STREAM_BLOCK_SIZE)
+            .map(zeroize::Zeroizing::new)
+            .map_err(|e| format!("CTR decryption failed: {}", e))
+    }
+
+    /// Content upload prepared in `release()` for the background thread.
+    enum PreparedUpload {
+        /// Single-shot AES-GCM ciphertext.
+        Whole(Vec<u8>),
+        /// AES-CTR, encrypted from the temp file while uploading.
+        Ctr { file_key: FileKey, iv: [u8; 16] },
+        /// GCM-SEG, encrypted from the temp file while uploading.
+        Segmented {
+            file_key: FileKey,
+            nonce_prefix: [u8; crate::crypto::aes_gcm_seg::GCM_SEG_NONCE_PREFIX_SIZE],
+        },
+        /// FastCDC chunks, read from the temp file and uploaded with
+        /// `upload_chunked_content`.
+        Chunked {
+            file_key: FileKey,
+            previous_manifest_cid: Option<String>,
+        },
+    }
+
+    /// Upload a chunked file and its manifest. Returns the manifest CID.
+    ///
+    /// Chunks whose id already appears in the previous version's manifest (or
+    /// earlier in this file) are not uploaded again; their CID is reused.
+    async fn upload_chunked_content(
+        api: &crate::api::client::ApiClient,
+        mut chunks: crate::fuse::upload::ChunkSource,
+        file_key: &FileKey,
+        previous_manifest_cid: Option<&str>,
+    ) -> Result<String, String> {
+        let mut known: HashMap<String, String> = HashMap::new();
+        if let Some(prev_cid) = previous_manifest_cid {
+            match fetch_chunk_manifest(api, prev_cid, file_key).await {
+                Ok(prev) => known.extend(prev.chunks.into_iter().map(|c| (c.id, c.cid))),
+                Err(e) => log::warn!("Previous chunk manifest unavailable, uploading all chunks: {}", e),
+            }
+        }
+
+        let mut refs = Vec::new();
+        let mut uploaded = 0usize;
+        while let Some(chunk) = chunks.next_chunk().await? {
+            let cid = match known.get(&chunk.id) {
+                Some(cid) => cid.clone(),
+                None => {
+                    let len = chunk.ciphertext.len() as u64;
+                    let cid = crate::api::ipfs::upload_content_stream(api, chunk.ciphertext.into(), len).await?;
+                    known.insert(chunk.id.clone(), cid.clone());
+                    uploaded += 1;
+                    cid
+                }
+            };
+            refs.push(ChunkRef { id: chunk.id, cid, size: chunk.size });
+        }
+        log::info!(
+            "Chunked upload: {} of {} chunks uploaded, {} reused",
+            uploaded,
+            refs.len(),
+            refs.len() - uploaded
+        );
+
+        let manifest = ChunkManifest::from_refs(refs);
+        let sealed = crate::crypto::chunked::encrypt_manifest(&manifest, file_key)
+            .map_err(|e| format!("Chunk manifest encryption failed: {}", e))?;
+        crate::api::ipfs::upload_content(api, &sealed).await
+    }
+
+    /// Single-shot AES-GCM encryption of `source`'s content, padded if enabled.
+    fn encrypt_whole(
+        source: &OpenFileHandle,
+        file_key: &FileKey,
+        iv: &[u8; 12],
+        padding: PaddingScheme,
+    ) -> Result<Vec<u8>, String> {
+        let plaintext = zeroize::Zeroizing::new(source.read_all()?);
+        let padded = match padding {
+            PaddingScheme::None => None,
+            scheme => Some(crate::crypto::padding::pad_content(&plaintext, scheme)),
+        };
+        let content: &[u8] = padded.as_ref().map_or(&plaintext[..], |p| &p[..]);
+        crate::crypto::aes::encrypt_aes_gcm(content, file_key.as_bytes(), iv)
+            .map_err(|e| format!("File encryption failed: {}", e))
+    }
+
+    /// Upload `source`'s content as prepared in `upload`. Returns the content
+    /// CID and, for CTR content, the chunk hashes.
+    async fn upload_prepared(
+        api: &crate::api::client::ApiClient,
+        upload: PreparedUpload,
+        source: &Arc<OpenFileHandle>,
+        padding: PaddingScheme,
+    ) -> Result<(String, Option<Vec<String>>), String> {
+        match upload {
+            PreparedUpload::Whole(ciphertext) => {
+                let len = ciphertext.len() as u64;
+                let cid = crate::api::ipfs::upload_content_stream(api, ciphertext.into(), len).await?;
+                Ok((cid, None))
+            }
+            PreparedUpload::Ctr { file_key, iv } => {
+                let (body, len, hashes_rx) = crate::fuse::upload::ctr_upload_body(
+                    source.clone(), &file_key, &iv, padding,
+                )?;
+                let cid = crate::api::ipfs::upload_content_stream(api, body, len).await?;
+                let hashes = hashes_rx.await
+                    .map_err(|_| "CTR upload ended before the whole file was sent".to_string())?;
+                Ok((cid, Some(hashes)))
+            }
+            PreparedUpload::Segmented { file_key, nonce_prefix } => {
+                let (body, len) = crate::fuse::upload::gcm_seg_upload_body(
+                    source.clone(), &file_key, &nonce_prefix, padding,
+                )?;
+                let cid = crate::api::ipfs::upload_content_stream(api, body, len).await?;
+                Ok((cid, None))
+            }
+            PreparedUpload::Chunked { file_key, previous_manifest_cid } => {
+                let chunks = crate::fuse::upload::ChunkSource::new(
+                    source.clone(), file_key.clone(), padding,
+                )?;
+                let cid = upload_chunked_content(
+                    api, chunks, &file_key, previous_manifest_cid.as_deref(),
+                ).await?;
+                Ok((cid, None))
+            }
+        }
+    }
+
+    /// Upload the content of a journaled save from `source`, with the key,
+    /// IV, mode and padding recorded in its `metadata`. Encryption is
+    /// deterministic given those, so this uploads exactly what the save would
+    /// have. Returns the content CID and, for CTR content, the chunk hashes.
+    pub(super) async fn upload_saved_content(
+        api: &crate::api::client::ApiClient,
+        private_key: &Secp256k1SecretKey,
+        metadata: &crate::crypto::folder::FileMetadata,
+        source: Arc<OpenFileHandle>,
+    ) -> Result<(String, Option<Vec<String>>), String> {
+        let encrypted_file_key = hex::decode(&metadata.file_key_encrypted)
+            .map_err(|_| "Invalid file key hex".to_string())?;
+        let file_key: FileKey = crate::crypto::ecies::unwrap_secret_key(&encrypted_file_key, private_key)
+            .map_err(|e| format!("File key unwrap failed: {}", e))?;
+        let padding = PaddingScheme::from_name(metadata.padding.as_deref().unwrap_or(""))
+            .map_err(|e| e.to_string())?;
+        let iv = hex::decode(&metadata.file_iv).map_err(|_| "Invalid file IV hex".to_string())?;
+        let upload = match metadata.encryption_mode.as_str() {
+            CHUNKED_MODE => PreparedUpload::Chunked { file_key, previous_manifest_cid: None },
+            "CTR" => {
+                let iv: [u8; 16] = iv.try_into().map_err(|_| "Invalid CTR IV length".to_string())?;
+                PreparedUpload::Ctr { file_key, iv }
+            }
+            crate::crypto::aes_gcm_seg::GCM_SEG_MODE => {
+                let nonce_prefix = iv.try_into()
+                    .map_err(|_| "Invalid GCM-SEG nonce prefix length".to_string())?;
+                PreparedUpload::Segmented { file_key, nonce_prefix }
+            }
+            "GCM" => {
+                let iv: [u8; 12] = iv.try_into().map_err(|_| "Invalid IV length".to_string())?;
+                PreparedUpload::Whole(encrypt_whole(&source, &file_key, &iv, padding)?)
+            }
+            other => return Err(format!("Unsupported encryption mode: {}", other)),
+        };
+        upload_prepared(api, upload, &source, padding).await
+    }
+
+    /// CIDs of all chunks referenced by the given chunked versions' manifests.
+    ///
+    /// Fails if any manifest cannot be fetched, so callers never unpin a chunk
+    /// that might still be referenced.
+    async fn referenced_chunk_cids(
+        api: &crate::api::client::ApiClient,
+        private_key: &Secp256k1SecretKey,
+        versions: &[(String, String)],
+    ) -> Result<HashSet<String>, String> {
+        let mut cids = HashSet::new();
+        for (manifest_cid, encrypted_file_key_hex) in versions {
+            let encrypted_file_key = hex::decode(encrypted_file_key_hex)
+                .map_err(|_| "Invalid file key hex".to_string())?;
+            let file_key: FileKey =
+                crate::crypto::ecies::unwrap_secret_key(&encrypted_file_key, private_key)
+                    .map_err(|e| format!("File key unwrap failed: {}", e))?;
+            let manifest = fetch_chunk_manifest(api, manifest_cid, &file_key).await?;
+            cids.extend(manifest.chunks.into_iter().map(|c| c.cid));
+        }
+        Ok(cids)
+    }
+
+    /// Unpin a file's content. For chunked files this also unpins every chunk
+    /// listed in the manifest.
+    async fn unpin_file_content(
+        api: &crate::api::client::ApiClient,
+        private_key: &Secp256k1SecretKey,
+        cid: &str,
+        encrypted_file_key_hex: &str,
+        encryption_mode: &str,
+    ) {
+        if encryption_mode == CHUNKED_MODE {
+            let versions = [(cid.to_string(), encrypted_file_key_hex.to_string())];
+            match referenced_chunk_cids(api, private_key, &versions).await {
+                Ok(chunk_cids) => {
+                    for chunk_cid in chunk_cids {
+                        if let Err(e) = crate::api::ipfs::unpin_content(api, &chunk_cid).await {
+                            log::debug!("Background unpin failed for chunk {}: {}", chunk_cid, e);
+                        }
+                    }
+                }
+                Err(e) => log::warn!("Could not list chunks of {} for unpinning: {}", cid, e),
+            }
+        }
+        if let Err(e) = crate::api::ipfs::unpin_content(api, cid).await {
+            log::debug!("Background unpin failed for {}: {}", cid, e);
+        }
+    }
+
+    /// Current remote metadata of the file `ipns_name` if its record has moved
+    /// past `base` to a revision this device did not save; `None` if the save
+    /// may overwrite it.
+    pub(super) async fn remote_file_if_advanced(
+        api: &crate::api::client::ApiClient,
+        coordinator: &crate::fuse::PublishCoordinator,
+        folder_key: &FolderKey,
+        ipns_name: &str,
+        base: &FileBase,
+    ) -> Result<Option<crate::crypto::folder::FileMetadata>, String> {
+        let resolved = match crate::api::ipns::resolve_ipns(api, ipns_name).await {
+            Ok(resolved) => resolved,
+            // Never published: there is nothing to overwrite
+            Err(ResolveError::NotFound) if !coordinator.has_record(ipns_name) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if base.sequence.is_some() && resolved.sequence_number.parse::<u64>().ok() == base.sequence {
+            return Ok(None);
+        }
+
+        // The sequence alone is not conclusive (it may not have been known at
+        // open); compare what the record points at.
+        let encrypted_bytes = crate::api::ipfs::fetch_content(api, &resolved.cid).await?;
+        let remote =
+            decrypt_file_metadata_from_ipfs(&encrypted_bytes, folder_key, ipns_name, api.envelope_history())?;
+        if base.accepts(&remote.cid, coordinator.file_content(ipns_name).as_deref()) {
+            return Ok(None);
+        }
+        Ok(Some(remote))
+    }
+
+    /// Publish a save that conflicts with a newer remote version as a new file
+    /// with its own IPNS record, and hand it to the FUSE thread, which adds it
+    /// to the parent folder as a conflicted copy.
+    #[allow(clippy::too_many_arguments)]
+    async fn publish_conflict_copy(
+        api: &crate::api::client::ApiClient,
+        coordinator: &crate::fuse::PublishCoordinator,
+        journal: &crate::sync::journal::Journal,
+        upload_tx: &std::sync::mpsc::Sender<crate::fuse::UploadComplete>,
+        mut upload: crate::fuse::UploadComplete,
+        file_meta: crate::crypto::folder::FileMetadata,
+        remote: crate::crypto::folder::FileMetadata,
+        parent: Option<crate::sync::journal::FolderRef>,
+        folder_key: &FolderKey,
+        public_key: &[u8],
+        metadata_format: MetadataFormat,
+    ) -> Result<(), String> {
+        let copy = crate::fuse::ConflictCopy::new(file_meta, public_key, coordinator)?;
+
+        // The FUSE thread adds the copy to the parent folder
+        upload.old_file_cid = None;
+        upload.pruned_cids.clear();
+        upload.conflict = Some(crate::fuse::SaveConflict {
+            remote,
+            copy_ipns_name: copy.ipns_name.clone(),
+            copy_ipns_private_key: copy.ipns_private_key.clone(),
+            copy_ipns_key_encrypted_hex: copy.ipns_key_encrypted_hex.clone(),
+        });
+        let _ = upload_tx.send(upload);
+
+        let journaled = parent.map(|parent| journal.record(JournalOp::Write {
+            parent,
+            file_ipns_name: copy.ipns_name.clone(),
+            ipns_private_key_encrypted: copy.ipns_key_encrypted_hex.clone(),
+            metadata: copy.metadata.clone(),
+            content: None,
+            base: None,
+        }));
+        copy.publish(api, coordinator, folder_key, metadata_format).await?;
+        if let Some(seq) = journaled {
+            journal.ack(&copy.ipns_name, seq);
+        }
+        Ok(())
+    }
+
     /// Encrypt and publish per-file FileMetadata to the file's own IPNS record.
     ///
     /// Encrypts with parent folder key (matching web app behavior), uploads to IPFS,
     /// creates signed IPNS record, and publishes via API.
-    async fn publish_file_metadata(
+    pub(super) async fn publish_file_metadata(
         api: &crate::api::client::ApiClient,
         file_meta: &crate::crypto::folder::FileMetadata,
-        folder_key: &[u8],
-        file_ipns_private_key: &zeroize::Zeroizing<Vec<u8>>,
+        folder_key: &FolderKey,
+        file_ipns_private_key: &IpnsSigningKey,
         file_ipns_name: &str,
         coordinator: &crate::fuse::PublishCoordinator,
+        metadata_format: MetadataFormat,
     ) -> Result<(), String> {
-        let folder_key_arr: [u8; 32] = folder_key
-            .try_into()
-            .map_err(|_| "Invalid folder key length for FileMetadata encryption".to_string())?;
-
-        // Encrypt FileMetadata with parent folder key
-        let sealed = crate::crypto::folder::encrypt_file_metadata(file_meta, &folder_key_arr)
-            .map_err(|e| format!("FileMetadata encryption failed: {}", e))?;
-
-        // Package as JSON envelope: { "iv": hex, "data": base64 }
-        let iv_hex = hex::encode(&sealed[..12]);
-        use base64::Engine;
-        let data_base64 = base64::engine::general_purpose::STANDARD.encode(&sealed[12..]);
-        let json = serde_json::json!({ "iv": iv_hex, "data": data_base64 });
-        let json_bytes = serde_json::to_vec(&json)
-            .map_err(|e| format!("FileMetadata JSON serialization failed: {}", e))?;
+        // Encrypt FileMetadata with parent folder key (in v2, into an envelope
+        // bound to the file's IPNS name)
+        let metadata_format = api.envelope_history().format_for(file_ipns_name, metadata_format);
+        let json_bytes = crate::crypto::folder::encrypt_file_metadata_envelope(
+            file_meta, folder_key, file_ipns_name, metadata_format,
+        )
+        .map_err(|e| format!("FileMetadata encryption failed: {}", e))?;
 
         // Upload encrypted file metadata to IPFS
         let file_meta_cid = crate::api::ipfs::upload_content(api, &json_bytes).await?;
@@ -449,13 +1092,10 @@ mod implementation {
         let seq = coordinator.resolve_sequence(api, file_ipns_name).await?;
 
         // Create and sign IPNS record
-        let ipns_key_arr: [u8; 32] = file_ipns_private_key.as_slice()
-            .try_into()
-            .map_err(|_| "Invalid file IPNS private key length".to_string())?;
         let new_seq = seq + 1;
         let value = format!("/ipfs/{}", file_meta_cid);
         let record = crate::crypto::ipns::create_ipns_record(
-            &ipns_key_arr,
+            file_ipns_private_key,
             &value,
             new_seq,
             86_400_000, // 24h validity
@@ -464,6 +1104,7 @@ mod implementation {
         let marshaled = crate::crypto::ipns::marshal_ipns_record(&record)
             .map_err(|e| format!("File IPNS record marshal failed: {}", e))?;
 
+        use base64::Engine;
         let record_b64 = base64::engine::general_purpose::STANDARD.encode(&marshaled);
 
         let req = crate::api::ipns::IpnsPublishRequest {
@@ -476,22 +1117,194 @@ mod implementation {
         crate::api::ipns::publish_ipns(api, &req).await?;
 
         coordinator.record_publish(file_ipns_name, new_seq);
+        coordinator.record_file_content(file_ipns_name, &file_meta.cid);
         log::info!("Per-file IPNS publish succeeded for {}", file_ipns_name);
 
         Ok(())
     }
 
+    /// Helper: Entries of folder `ino` for readdir/readdirplus, from the
+    /// (possibly stale) inode tree. `None` if there is no such inode.
+    fn directory_entries(
+        fs: &mut CipherBoxFS,
+        ino: u64,
+        offset: i64,
+    ) -> Option<Vec<(u64, FileType, String)>> {
+        // 1. Drain any pending background refresh results (non-blocking)
+        fs.drain_refresh_completions();
+        fs.touch_watched(ino);
+
+        // 2. Check if metadata is stale — fire background refresh if so
+        let stale_info: Option<(String, FolderKey)> = {
+            let inode = fs.inodes.get(ino)?;
+
+            match &inode.kind {
+                InodeKind::Root { ipns_name, .. } => {
+                    ipns_name.as_ref().and_then(|name| {
+                        if fs.metadata_cache.get(name).is_none() {
+                            Some((name.clone(), fs.root_folder_key.clone()))
+                        } else {
+                            None
+                        }
+                    })
+                }
+                InodeKind::Folder { ipns_name, folder_key, .. } => {
+                    if fs.metadata_cache.get(ipns_name).is_none() {
+                        Some((ipns_name.clone(), folder_key.clone()))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        };
+
+        // Fire background refresh (non-blocking, results applied on next readdir)
+        // Only on offset=0 to avoid duplicate refreshes (NFS calls readdir twice)
+        if let Some((ipns_name, folder_key)) = stale_info.filter(|_| offset == 0) {
+            let api = fs.api.clone();
+            let rt = fs.rt.clone();
+            let tx = fs.refresh_tx.clone();
+            let refresh_ino = ino;
+            rt.spawn(async move {
+                match crate::api::ipns::resolve_ipns(&api, &ipns_name).await {
+                    Ok(resolve_resp) => {
+                        match crate::api::ipfs::fetch_content(&api, &resolve_resp.cid).await {
+                            Ok(encrypted_bytes) => {
+                                match crate::fuse::operations::decrypt_metadata_from_ipfs_public(
+                                    &encrypted_bytes, &folder_key, &ipns_name, api.envelope_history(),
+                                ) {
+                                    Ok(metadata) => {
+                                        let _ = tx.send(crate::fuse::PendingRefresh {
+                                            ino: refresh_ino,
+                                            ipns_name,
+                                            metadata,
+                                            cid: resolve_resp.cid,
+                                        });
+                                    }
+                                    Err(e) => log::warn!("Refresh decrypt failed: {}", e),
+                                }
+                            }
+                            Err(e) => log::warn!("Refresh fetch failed: {}", e),
+                        }
+                    }
+                    Err(e) => log::warn!("Refresh resolve failed for {}: {}", ipns_name, e),
+                }
+            });
+        }
+
+        // 3. Return current (possibly stale) entries immediately — no blocking
+        let (parent_ino, children) = {
+            let inode = fs.inodes.get(ino)?;
+            (inode.parent_ino, inode.children.clone().unwrap_or_default())
+        };
+
+        let mut entries: Vec<(u64, FileType, String)> = Vec::new();
+        entries.push((ino, FileType::Directory, ".".to_string()));
+        entries.push((parent_ino, FileType::Directory, "..".to_string()));
+
+        for &child_ino in &children {
+            if let Some(child) = fs.inodes.get(child_ino) {
+                // Filter out platform special files — readdir must be
+                // consistent with lookup or Finder/NFS will hang retrying.
+                if is_platform_special(&child.name) {
+                    continue;
+                }
+                let file_type = match &child.kind {
+                    InodeKind::Root { .. } | InodeKind::Folder { .. } => {
+                        FileType::Directory
+                    }
+                    InodeKind::File { .. } => FileType::RegularFile,
+                };
+                entries.push((child_ino, file_type, child.name.clone()));
+            }
+        }
+
+        Some(entries)
+    }
+
+    /// Helper: Proactive content prefetch after a folder listing: start
+    /// downloading the content of its files so it's cached by the time the
+    /// user reads them.
+    fn prefetch_children(fs: &mut CipherBoxFS, ino: u64) {
+        fs.drain_content_prefetches();
+        let children = fs.inodes.get(ino).and_then(|i| i.children.clone()).unwrap_or_default();
+        for &child_ino in &children {
+            if let Some(child) = fs.inodes.get(child_ino) {
+                if let InodeKind::File { cid, encrypted_file_key, iv, encryption_mode, padding, .. } = &child.kind {
+                    if !cid.is_empty()
+                        && !is_range_read(encryption_mode)
+                        && fs.content_cache.get(cid).is_none()
+                        && !fs.prefetching.contains(cid)
+                    {
+                        let api = fs.api.clone();
+                        let disk_cache = fs.disk_cache.clone();
+                        let rt = fs.rt.clone();
+                        let tx = fs.content_tx.clone();
+                        let cid_clone = cid.clone();
+                        let efk = encrypted_file_key.clone();
+                        let iv_clone = iv.clone();
+                        let enc_mode = encryption_mode.clone();
+                        let pad = padding.clone();
+                        let pk = fs.private_key.clone();
+                        fs.prefetching.insert(cid.clone());
+
+                        rt.spawn(async move {
+                            let result = tokio::time::timeout(
+                                CONTENT_DOWNLOAD_TIMEOUT,
+                                fetch_and_decrypt_content_async(
+                                    &api, &disk_cache, &cid_clone, &efk, &iv_clone, &enc_mode, pad.as_deref(), &pk,
+                                ),
+                            )
+                            .await;
+
+                            match result {
+                                Ok(Ok(plaintext)) => {
+                                    log::debug!(
+                                        "prefetch(readdir): cached {} bytes for CID {}",
+                                        plaintext.len(),
+                                        &cid_clone[..cid_clone.len().min(12)]
+                                    );
+                                    let _ = tx.send(crate::fuse::PendingContent::Success {
+                                        cid: cid_clone,
+                                        data: plaintext,
+                                    });
+                                }
+                                Ok(Err(e)) => {
+                                    log::error!("Prefetch(readdir) failed for CID {}: {}", cid_clone, e);
+                                    let _ = tx.send(crate::fuse::PendingContent::Failure { cid: cid_clone });
+                                }
+                                Err(_) => {
+                                    log::error!("Prefetch(readdir) timed out for CID {}", cid_clone);
+                                    let _ = tx.send(crate::fuse::PendingContent::Failure { cid: cid_clone });
+                                }
+                            }
+                        });
+                    }
+                }
+            }
+        }
+    }
+
     impl Filesystem for CipherBoxFS {
         /// Initialize the filesystem.
         ///
         /// Root folder is pre-populated in mount_filesystem() before the FUSE
         /// event loop starts. No network I/O happens here — FUSE-T's NFS layer
         /// requires fast init() responses to avoid connection timeouts.
+        ///
+        /// Requests the platform's kernel capabilities (`profile`); the ones
+        /// the kernel lacks are skipped.
         fn init(
             &mut self,
             _req: &Request<'_>,
-            _config: &mut fuser::KernelConfig,
+            config: &mut fuser::KernelConfig,
         ) -> Result<(), libc::c_int> {
+            let capabilities = crate::fuse::profile::kernel_capabilities();
+            if let Err(unsupported) = config.add_capabilities(capabilities) {
+                log::info!("Kernel lacks FUSE capabilities {:#x}", unsupported);
+                let _ = config.add_capabilities(capabilities & !unsupported);
+            }
             log::info!("CipherBoxFS::init (root pre-populated, no network I/O)");
             log::info!("Root IPNS name: {}", self.root_ipns_name);
             log::info!("Inode count: {}", self.inodes.inodes.len());
@@ -503,14 +1316,16 @@ mod implementation {
             use zeroize::Zeroize;
 
             self.content_cache.clear();
+            self.block_cache.clear();
             self.metadata_cache.clear();
 
-            // Zeroize pending_content values
-            for (_, content) in self.pending_content.iter_mut() {
-                content.zeroize();
-            }
+            // Pending temp files are wiped when their last reference drops
             self.pending_content.clear();
 
+            if let Some(crawler) = self.crawler.take() {
+                crawler.abort();
+            }
+
             // Zeroize open file handles' cached content
             for (_, handle) in self.open_files.iter_mut() {
                 if let Some(ref mut c) = handle.cached_content {
@@ -541,6 +1356,12 @@ mod implementation {
                 }
             };
 
+            // Only sent to have the remote changes above applied (kernel::wake)
+            if name_str == crate::fuse::kernel::WAKE_NAME {
+                reply.error(libc::ENOENT);
+                return;
+            }
+
             // Handle "." and ".." — NFS clients rely on these working.
             // Returning ENOENT for ".." causes the NFS client to disconnect.
             if name_str == "." {
@@ -605,7 +1426,7 @@ mod implementation {
                             match crate::api::ipfs::fetch_content(&api, &resolve_resp.cid).await {
                                 Ok(encrypted_bytes) => {
                                     match crate::fuse::operations::decrypt_metadata_from_ipfs_public(
-                                        &encrypted_bytes, &folder_key,
+                                        &encrypted_bytes, &folder_key, &ipns_name, api.envelope_history(),
                                     ) {
                                         Ok(metadata) => {
                                             let _ = tx.send(crate::fuse::PendingRefresh {
@@ -678,6 +1499,12 @@ mod implementation {
             _flags: Option<u32>,
             reply: ReplyAttr,
         ) {
+            // Read-only mount: nothing may modify the vault (the kernel
+            // already refuses most of it for an `ro` mount)
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             // Handle truncate if size is specified
             if let Some(new_size) = size {
                 // Truncate temp file if file handle exists
@@ -730,174 +1557,57 @@ mod implementation {
             offset: i64,
             mut reply: ReplyDirectory,
         ) {
-            // 1. Drain any pending background refresh results (non-blocking)
-            self.drain_refresh_completions();
-
-            // 2. Check if metadata is stale — fire background refresh if so
-            let stale_info: Option<(String, zeroize::Zeroizing<Vec<u8>>)> = {
-                let inode = match self.inodes.get(ino) {
-                    Some(i) => i,
-                    None => {
-                        reply.error(libc::ENOENT);
-                        return;
-                    }
-                };
-
-                match &inode.kind {
-                    InodeKind::Root { ipns_name, .. } => {
-                        ipns_name.as_ref().and_then(|name| {
-                            if self.metadata_cache.get(name).is_none() {
-                                Some((name.clone(), self.root_folder_key.clone()))
-                            } else {
-                                None
-                            }
-                        })
-                    }
-                    InodeKind::Folder { ipns_name, folder_key, .. } => {
-                        if self.metadata_cache.get(ipns_name).is_none() {
-                            Some((ipns_name.clone(), folder_key.clone()))
-                        } else {
-                            None
-                        }
-                    }
-                    _ => None,
-                }
+            let Some(entries) = directory_entries(self, ino, offset) else {
+                reply.error(libc::ENOENT);
+                return;
             };
 
-            // Fire background refresh (non-blocking, results applied on next readdir)
-            // Only on offset=0 to avoid duplicate refreshes (NFS calls readdir twice)
-            if let Some((ipns_name, folder_key)) = stale_info.filter(|_| offset == 0) {
-                let api = self.api.clone();
-                let rt = self.rt.clone();
-                let tx = self.refresh_tx.clone();
-                let refresh_ino = ino;
-                rt.spawn(async move {
-                    match crate::api::ipns::resolve_ipns(&api, &ipns_name).await {
-                        Ok(resolve_resp) => {
-                            match crate::api::ipfs::fetch_content(&api, &resolve_resp.cid).await {
-                                Ok(encrypted_bytes) => {
-                                    match crate::fuse::operations::decrypt_metadata_from_ipfs_public(
-                                        &encrypted_bytes, &folder_key,
-                                    ) {
-                                        Ok(metadata) => {
-                                            let _ = tx.send(crate::fuse::PendingRefresh {
-                                                ino: refresh_ino,
-                                                ipns_name,
-                                                metadata,
-                                                cid: resolve_resp.cid,
-                                            });
-                                        }
-                                        Err(e) => log::warn!("Refresh decrypt failed: {}", e),
-                                    }
-                                }
-                                Err(e) => log::warn!("Refresh fetch failed: {}", e),
-                            }
-                        }
-                        Err(e) => log::warn!("Refresh resolve failed for {}: {}", ipns_name, e),
-                    }
-                });
+            for (i, (ino, file_type, name)) in
+                entries.iter().enumerate().skip(offset as usize)
+            {
+                if reply.add(*ino, (i + 1) as i64, *file_type, &name) {
+                    break;
+                }
             }
 
-            // 3. Return current (possibly stale) entries immediately — no blocking
-            let (parent_ino, children) = {
-                let inode = match self.inodes.get(ino) {
-                    Some(i) => i,
-                    None => {
-                        reply.error(libc::ENOENT);
-                        return;
-                    }
-                };
-                (inode.parent_ino, inode.children.clone().unwrap_or_default())
-            };
-
-            let mut entries: Vec<(u64, FileType, String)> = Vec::new();
-            entries.push((ino, FileType::Directory, ".".to_string()));
-            entries.push((parent_ino, FileType::Directory, "..".to_string()));
+            reply.ok();
 
-            for &child_ino in &children {
-                if let Some(child) = self.inodes.get(child_ino) {
-                    // Filter out platform special files — readdir must be
-                    // consistent with lookup or Finder/NFS will hang retrying.
-                    if is_platform_special(&child.name) {
-                        continue;
-                    }
-                    let file_type = match &child.kind {
-                        InodeKind::Root { .. } | InodeKind::Folder { .. } => {
-                            FileType::Directory
-                        }
-                        InodeKind::File { .. } => FileType::RegularFile,
-                    };
-                    entries.push((child_ino, file_type, child.name.clone()));
-                }
+            // Only on offset=0 to avoid duplicate prefetches
+            if offset == 0 {
+                prefetch_children(self, ino);
             }
+        }
 
-            for (i, (ino, file_type, name)) in
+        /// List directory entries with their attributes (Linux, see
+        /// `profile::kernel_capabilities`), saving the kernel a lookup per entry.
+        fn readdirplus(
+            &mut self,
+            _req: &Request<'_>,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            mut reply: ReplyDirectoryPlus,
+        ) {
+            let Some(entries) = directory_entries(self, ino, offset) else {
+                reply.error(libc::ENOENT);
+                return;
+            };
+
+            for (i, (entry_ino, file_type, name)) in
                 entries.iter().enumerate().skip(offset as usize)
             {
-                if reply.add(*ino, (i + 1) as i64, *file_type, &name) {
+                let Some(attr) = self.inodes.get(*entry_ino).map(|inode| inode.attr) else {
+                    continue;
+                };
+                if reply.add(*entry_ino, (i + 1) as i64, name, &ttl_for(*file_type), &attr, 0) {
                     break;
                 }
             }
 
             reply.ok();
 
-            // Proactive content prefetch: start downloading file content for
-            // all children so it's cached by the time the user reads them.
-            // Only on offset=0 to avoid duplicate prefetches.
             if offset == 0 {
-                self.drain_content_prefetches();
-                for &child_ino in &children {
-                    if let Some(child) = self.inodes.get(child_ino) {
-                        if let InodeKind::File { cid, encrypted_file_key, iv, encryption_mode, .. } = &child.kind {
-                            if !cid.is_empty()
-                                && self.content_cache.get(cid).is_none()
-                                && !self.prefetching.contains(cid)
-                            {
-                                let api = self.api.clone();
-                                let rt = self.rt.clone();
-                                let tx = self.content_tx.clone();
-                                let cid_clone = cid.clone();
-                                let efk = encrypted_file_key.clone();
-                                let iv_clone = iv.clone();
-                                let enc_mode = encryption_mode.clone();
-                                let pk = self.private_key.clone();
-                                self.prefetching.insert(cid.clone());
-
-                                rt.spawn(async move {
-                                    let result = tokio::time::timeout(
-                                        CONTENT_DOWNLOAD_TIMEOUT,
-                                        fetch_and_decrypt_content_async(
-                                            &api, &cid_clone, &efk, &iv_clone, &enc_mode, &pk,
-                                        ),
-                                    )
-                                    .await;
-
-                                    match result {
-                                        Ok(Ok(plaintext)) => {
-                                            log::debug!(
-                                                "prefetch(readdir): cached {} bytes for CID {}",
-                                                plaintext.len(),
-                                                &cid_clone[..cid_clone.len().min(12)]
-                                            );
-                                            let _ = tx.send(crate::fuse::PendingContent::Success {
-                                                cid: cid_clone,
-                                                data: plaintext,
-                                            });
-                                        }
-                                        Ok(Err(e)) => {
-                                            log::error!("Prefetch(readdir) failed for CID {}: {}", cid_clone, e);
-                                            let _ = tx.send(crate::fuse::PendingContent::Failure { cid: cid_clone });
-                                        }
-                                        Err(_) => {
-                                            log::error!("Prefetch(readdir) timed out for CID {}", cid_clone);
-                                            let _ = tx.send(crate::fuse::PendingContent::Failure { cid: cid_clone });
-                                        }
-                                    }
-                                });
-                            }
-                        }
-                    }
-                }
+                prefetch_children(self, ino);
             }
         }
 
@@ -916,6 +1626,10 @@ mod implementation {
             flags: i32,
             reply: ReplyCreate,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             let name_str = match name.to_str() {
                 Some(n) => n,
                 None => {
@@ -967,10 +1681,15 @@ mod implementation {
             };
 
             // Generate random Ed25519 IPNS keypair for this file
-            let signing_key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
-            let verifying_key = signing_key.verifying_key();
-            let file_ipns_private_key = signing_key.to_bytes().to_vec();
-            let file_ipns_public_key_bytes: [u8; 32] = verifying_key.to_bytes();
+            let (file_ipns_private_key, file_ipns_public_key) = IpnsSigningKey::generate();
+            let file_ipns_public_key_bytes: [u8; 32] = match file_ipns_public_key.try_into() {
+                Ok(arr) => arr,
+                Err(_) => {
+                    log::error!("create: invalid IPNS public key length");
+                    reply.error(libc::EIO);
+                    return;
+                }
+            };
             let file_ipns_name = match crate::crypto::ipns::derive_ipns_name(&file_ipns_public_key_bytes) {
                 Ok(name) => name,
                 Err(e) => {
@@ -982,7 +1701,7 @@ mod implementation {
 
             // ECIES-wrap the IPNS private key — this is fatal for random keys since
             // losing the wrapped key means the IPNS keypair is unrecoverable
-            let ipns_key_encrypted_hex = match crate::crypto::ecies::wrap_key(&file_ipns_private_key, &self.public_key) {
+            let ipns_key_encrypted_hex = match crate::crypto::ecies::wrap_key(file_ipns_private_key.as_bytes(), &self.public_key) {
                 Ok(wrapped) => Some(hex::encode(&wrapped)),
                 Err(e) => {
                     log::error!("create: failed to ECIES-wrap IPNS key: {}. Cannot proceed without wrapped key.", e);
@@ -1002,9 +1721,11 @@ mod implementation {
                     iv: String::new(),
                     size: 0,
                     encryption_mode: "GCM".to_string(),
+                    padding: None,
+                    chunk_hashes: None,
                     file_meta_ipns_name: Some(file_ipns_name),
                     file_meta_resolved: true,
-                    file_ipns_private_key: Some(zeroize::Zeroizing::new(file_ipns_private_key)),
+                    file_ipns_private_key: Some(file_ipns_private_key),
                     file_ipns_key_encrypted_hex: ipns_key_encrypted_hex,
                     versions: None,
                 },
@@ -1042,6 +1763,14 @@ mod implementation {
             // from overwriting this new file before IPNS publish propagates.
             self.mutated_folders.insert(parent, std::time::Instant::now());
 
+            self.journal_op(|fs| match fs.folder_child(ino)? {
+                Some(FolderChild::File(pointer)) => Ok(JournalOp::Create {
+                    parent: fs.folder_ref(parent)?,
+                    pointer,
+                }),
+                _ => Err(format!("create: no file pointer for ino {}", ino)),
+            });
+
             log::debug!("create: {} in parent {} -> ino {} fh {}", name_str, parent, ino, fh);
             reply.created(&FILE_TTL, &attr, 0, fh, 0);
         }
@@ -1057,11 +1786,15 @@ mod implementation {
             flags: i32,
             reply: ReplyOpen,
         ) {
+            if self.read_only && (flags & libc::O_ACCMODE != libc::O_RDONLY || flags & libc::O_TRUNC != 0) {
+                reply.error(libc::EROFS);
+                return;
+            }
             // Get file info
             let file_info = match self.inodes.get(ino) {
                 Some(inode) => match &inode.kind {
-                    InodeKind::File { cid, encrypted_file_key, iv, encryption_mode, .. } => {
-                        Some((cid.clone(), encrypted_file_key.clone(), iv.clone(), encryption_mode.clone()))
+                    InodeKind::File { cid, encrypted_file_key, iv, encryption_mode, padding, file_meta_ipns_name, .. } => {
+                        Some((cid.clone(), encrypted_file_key.clone(), iv.clone(), encryption_mode.clone(), padding.clone(), file_meta_ipns_name.clone()))
                     }
                     _ => {
                         reply.error(libc::EISDIR);
@@ -1074,8 +1807,9 @@ mod implementation {
                 }
             };
 
-            let (cid, encrypted_file_key, iv, encryption_mode) = file_info.unwrap();
+            let (cid, encrypted_file_key, iv, encryption_mode, padding, file_meta_ipns_name) = file_info.unwrap();
             let access_mode = flags & libc::O_ACCMODE;
+            self.touch_watched(ino);
 
             if access_mode == libc::O_WRONLY || access_mode == libc::O_RDWR {
                 // Writable open: create temp file
@@ -1086,7 +1820,7 @@ mod implementation {
                     if let Some(cached) = self.content_cache.get(&cid) {
                         Some(cached.to_vec())
                     } else {
-                        match fetch_and_decrypt_file_content(self, &cid, &encrypted_file_key, &iv, &encryption_mode) {
+                        match fetch_and_decrypt_file_content(self, &cid, &encrypted_file_key, &iv, &encryption_mode, padding.as_deref()) {
                             Ok(content) => Some(content),
                             Err(e) => {
                                 log::error!("Failed to fetch content for write-open: {}", e);
@@ -1106,7 +1840,19 @@ mod implementation {
                     &self.temp_dir,
                     existing_content.as_deref(),
                 ) {
-                    Ok(handle) => {
+                    Ok(mut handle) => {
+                        // The revision the save will compare the remote against.
+                        // A file whose last save is still uploading is based on
+                        // what that save was based on.
+                        handle.base = if cid.is_empty() {
+                            self.pending_content.get(&ino).and_then(|pending| pending.base.clone())
+                        } else {
+                            Some(FileBase {
+                                sequence: file_meta_ipns_name.as_deref()
+                                    .and_then(|name| self.publish_coordinator.cached_sequence(name)),
+                                cid: cid.clone(),
+                            })
+                        };
                         self.open_files.insert(fh, handle);
                         reply.opened(fh, 0);
                     }
@@ -1123,19 +1869,23 @@ mod implementation {
                 // Instead, start an async background prefetch so content is
                 // likely cached by the time read() is called. If it's not ready
                 // yet, read() will do a synchronous fallback download.
+                // CTR and GCM-SEG files are read with range requests and never prefetched.
                 self.drain_content_prefetches();
 
                 if !cid.is_empty()
+                    && !is_range_read(&encryption_mode)
                     && self.content_cache.get(&cid).is_none()
                     && !self.prefetching.contains(&cid)
                 {
                     let api = self.api.clone();
+                    let disk_cache = self.disk_cache.clone();
                     let rt = self.rt.clone();
                     let tx = self.content_tx.clone();
                     let cid_clone = cid.clone();
                     let efk = encrypted_file_key.clone();
                     let iv_clone = iv.clone();
                     let enc_mode = encryption_mode.clone();
+                    let pad = padding.clone();
                     let pk = self.private_key.clone();
                     self.prefetching.insert(cid.clone());
 
@@ -1143,7 +1893,7 @@ mod implementation {
                         let result = tokio::time::timeout(
                             CONTENT_DOWNLOAD_TIMEOUT,
                             fetch_and_decrypt_content_async(
-                                &api, &cid_clone, &efk, &iv_clone, &enc_mode, &pk,
+                                &api, &disk_cache, &cid_clone, &efk, &iv_clone, &enc_mode, pad.as_deref(), &pk,
                             ),
                         )
                         .await;
@@ -1198,6 +1948,10 @@ mod implementation {
             _lock_owner: Option<u64>,
             reply: ReplyWrite,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             let handle = match self.open_files.get_mut(&fh) {
                 Some(h) => h,
                 None => {
@@ -1274,7 +2028,7 @@ mod implementation {
             }
 
             // Read-only path: get file metadata
-            let (cid, encrypted_file_key_hex, iv_hex, encryption_mode) = {
+            let (cid, encrypted_file_key_hex, iv_hex, encryption_mode, padding) = {
                 match self.inodes.get(ino) {
                     Some(inode) => match &inode.kind {
                         InodeKind::File {
@@ -1282,8 +2036,9 @@ mod implementation {
                             encrypted_file_key,
                             iv,
                             encryption_mode,
+                            padding,
                             ..
-                        } => (cid.clone(), encrypted_file_key.clone(), iv.clone(), encryption_mode.clone()),
+                        } => (cid.clone(), encrypted_file_key.clone(), iv.clone(), encryption_mode.clone(), padding.clone()),
                         _ => {
                             reply.error(libc::EISDIR);
                             return;
@@ -1296,15 +2051,15 @@ mod implementation {
                 }
             };
 
-            // Empty CID means file upload is in flight — serve from pending cache
+            // Empty CID means file upload is in flight — serve from its temp file
             if cid.is_empty() {
-                if let Some(content) = self.pending_content.get(&ino) {
-                    let start = offset as usize;
-                    if start >= content.len() {
-                        reply.data(&[]);
-                    } else {
-                        let end = std::cmp::min(start + size as usize, content.len());
-                        reply.data(&content[start..end]);
+                if let Some(pending) = self.pending_content.get(&ino) {
+                    match pending.read_at(offset, size) {
+                        Ok(data) => reply.data(&data),
+                        Err(e) => {
+                            log::error!("Pending read failed for ino {}: {}", ino, e);
+                            reply.error(libc::EIO);
+                        }
                     }
                     return;
                 }
@@ -1345,6 +2100,24 @@ mod implementation {
                 return;
             }
 
+            // CTR and GCM-SEG files are never downloaded whole: serve the
+            // requested window with range requests backed by the block cache.
+            if is_range_read(&encryption_mode) {
+                let result = if encryption_mode == "CTR" {
+                    read_ctr_range(self, ino, fh, offset as u64, size)
+                } else {
+                    read_gcm_seg_range(self, ino, fh, offset as u64, size)
+                };
+                match result {
+                    Ok(data) => reply.data(&data),
+                    Err(e) => {
+                        log::error!("Range read failed for ino {}: {}", ino, e);
+                        reply.error(libc::EIO);
+                    }
+                }
+                return;
+            }
+
             // Content not in cache. Download it on-demand:
             //
             // 1. Ensure a background prefetch is running (async download+decrypt)
@@ -1360,12 +2133,14 @@ mod implementation {
             // Start prefetch if not already in progress
             if !self.prefetching.contains(&cid) {
                 let api = self.api.clone();
+                let disk_cache = self.disk_cache.clone();
                 let rt = self.rt.clone();
                 let tx = self.content_tx.clone();
                 let cid_clone = cid.clone();
                 let efk = encrypted_file_key_hex.clone();
                 let iv_clone = iv_hex.clone();
                 let enc_mode = encryption_mode.clone();
+                let pad = padding.clone();
                 let pk = self.private_key.clone();
                 self.prefetching.insert(cid.clone());
 
@@ -1373,7 +2148,7 @@ mod implementation {
                     let result = tokio::time::timeout(
                         CONTENT_DOWNLOAD_TIMEOUT,
                         fetch_and_decrypt_content_async(
-                            &api, &cid_clone, &efk, &iv_clone, &enc_mode, &pk,
+                            &api, &disk_cache, &cid_clone, &efk, &iv_clone, &enc_mode, pad.as_deref(), &pk,
                         ),
                     )
                     .await;
@@ -1439,6 +2214,10 @@ mod implementation {
         /// If the handle is dirty (has been written to), encrypts the temp file
         /// content and spawns a background upload to IPFS. Metadata publish is
         /// debounced — handled by flush_publish_queue() after uploads settle.
+        /// The save is journaled first, with a sealed copy of its content, so
+        /// if the upload fails the sync daemon uploads it on replay. If another device saved the file
+        /// since it was opened (see `FileBase`), the save is kept as a
+        /// conflicted copy instead of overwriting that version.
         fn release(
             &mut self,
             _req: &Request<'_>,
@@ -1466,34 +2245,18 @@ mod implementation {
                 };
                 let needs_upload = handle.temp_path.is_some() && (handle.dirty || is_new_file);
                 if needs_upload {
-                    // Dirty or new file: do CPU work synchronously, spawn network I/O
+                    // Dirty or new file: prepare metadata synchronously, spawn
+                    // encryption + network I/O. The temp file is shared with
+                    // the upload thread and deleted when both are done with it.
                     log::debug!("release: uploading ino {} (dirty={}, new={})", ino, handle.dirty, is_new_file);
+                    let handle = Arc::new(handle);
+                    let file_name = self.inodes.get(ino).map(|i| i.name.clone()).unwrap_or_default();
 
                     let prepare_result = (|| -> Result<(), String> {
-                        // Read complete temp file content (local I/O, fast)
-                        let plaintext = handle.read_all()?;
-
-                        // Generate new random file key and IV
-                        let mut file_key = crate::crypto::utils::generate_file_key();
-                        let iv = crate::crypto::utils::generate_iv();
-
-                        // Encrypt content with AES-256-GCM
-                        let ciphertext = crate::crypto::aes::encrypt_aes_gcm(
-                            &plaintext, &file_key, &iv,
-                        )
-                        .map_err(|e| format!("File encryption failed: {}", e))?;
-
-                        // Wrap file key with user's public key (ECIES)
-                        let wrapped_key = crate::crypto::ecies::wrap_key(
-                            &file_key, &self.public_key,
-                        )
-                        .map_err(|e| format!("Key wrapping failed: {}", e))?;
-
-                        // Zero file key from memory
-                        crate::crypto::utils::clear_bytes(&mut file_key);
+                        let file_size = handle.get_size()?;
 
                         // Get old file metadata for versioning and per-file IPNS data
-                        let (old_file_cid, old_encrypted_key, old_iv, old_size, old_mode,
+                        let (old_file_cid, old_encrypted_key, old_iv, old_size, old_mode, old_padding,
                              existing_versions, file_ipns_private_key, file_meta_ipns_name) =
                             self.inodes.get(ino).map(|inode| {
                                 match &inode.kind {
@@ -1503,6 +2266,7 @@ mod implementation {
                                         iv,
                                         size,
                                         encryption_mode,
+                                        padding,
                                         versions,
                                         file_ipns_private_key,
                                         file_meta_ipns_name,
@@ -1513,23 +2277,82 @@ mod implementation {
                                         iv.clone(),
                                         *size,
                                         encryption_mode.clone(),
+                                        padding.clone(),
                                         versions.clone(),
                                         file_ipns_private_key.clone(),
                                         file_meta_ipns_name.clone(),
                                     ),
-                                    _ => (None, String::new(), String::new(), 0, "GCM".to_string(), None, None, None),
+                                    _ => (None, String::new(), String::new(), 0, "GCM".to_string(), None, None, None, None),
                                 }
-                            }).unwrap_or((None, String::new(), String::new(), 0, "GCM".to_string(), None, None, None));
+                            }).unwrap_or((None, String::new(), String::new(), 0, "GCM".to_string(), None, None, None, None));
 
-                        // Update local inode (CID="" for now — drain_upload_completions will fix it)
-                        let encrypted_file_key_hex = hex::encode(&wrapped_key);
-                        let iv_hex = hex::encode(&iv);
-                        let file_size = plaintext.len() as u64;
+                        let content_padding = self.padding.metadata_value();
 
                         // Detect MIME type from filename extension
-                        let file_name = self.inodes.get(ino).map(|i| i.name.clone()).unwrap_or_default();
                         let mime_type = mime_from_extension(&file_name);
 
+                        // Encrypt content. Streamable media uses AES-256-CTR with
+                        // per-chunk hashes so it can be read by range. Other large
+                        // files are chunked if enabled, so later versions only
+                        // upload changed chunks; they keep the previous version's
+                        // file key, since chunk ids and keys derive from it. Large
+                        // files that are not chunked use GCM-SEG if enabled.
+                        // Everything else uses single-shot AES-256-GCM. CTR,
+                        // GCM-SEG and GCM always get a fresh key.
+                        let ctr = use_ctr_mode(&mime_type, file_size);
+                        let large = !ctr && file_size >= LARGE_FILE_THRESHOLD_BYTES;
+                        let chunked = large && self.chunked_uploads;
+                        let segmented = large && !chunked && self.gcm_seg_uploads;
+                        let reused_key = if chunked && old_mode == CHUNKED_MODE {
+                            hex::decode(&old_encrypted_key).ok().and_then(|wrapped| {
+                                crate::crypto::ecies::unwrap_secret_key::<FileKey>(&wrapped, &self.private_key).ok()
+                            })
+                        } else {
+                            None
+                        };
+                        let (file_key, encrypted_file_key_hex) = match reused_key {
+                            Some(key) => (key, old_encrypted_key.clone()),
+                            None => {
+                                let key = FileKey::generate();
+                                // Wrap file key with user's public key (ECIES)
+                                let wrapped_key = crate::crypto::ecies::wrap_key(
+                                    key.as_bytes(), &self.public_key,
+                                )
+                                .map_err(|e| format!("Key wrapping failed: {}", e))?;
+                                (key, hex::encode(&wrapped_key))
+                            }
+                        };
+                        let previous_manifest_cid = old_file_cid.clone()
+                            .filter(|_| old_mode == CHUNKED_MODE);
+
+                        // Chunked, CTR and GCM-SEG content is read from the temp
+                        // file and encrypted while it uploads. GCM content is
+                        // encrypted here in memory. All of it is padded if enabled (padding is
+                        // recorded in FileMetadata.padding).
+                        let (upload, iv_hex, encryption_mode) = if chunked {
+                            (
+                                PreparedUpload::Chunked { file_key, previous_manifest_cid },
+                                String::new(),
+                                CHUNKED_MODE.to_string(),
+                            )
+                        } else if ctr {
+                            let iv = crate::crypto::utils::generate_ctr_iv();
+                            (PreparedUpload::Ctr { file_key, iv }, hex::encode(iv), "CTR".to_string())
+                        } else if segmented {
+                            let nonce_prefix = crate::crypto::aes_gcm_seg::generate_nonce_prefix();
+                            (
+                                PreparedUpload::Segmented { file_key, nonce_prefix },
+                                hex::encode(nonce_prefix),
+                                crate::crypto::aes_gcm_seg::GCM_SEG_MODE.to_string(),
+                            )
+                        } else {
+                            let iv = crate::crypto::utils::generate_iv();
+                            let ciphertext = encrypt_whole(&handle, &file_key, &iv, self.padding)?;
+                            (PreparedUpload::Whole(ciphertext), hex::encode(iv), "GCM".to_string())
+                        };
+
+                        // Update local inode (CID="" for now — drain_upload_completions will fix it)
+
                         // ── Version creation with 15-minute cooldown ──
                         let now_ms = SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
@@ -1549,7 +2372,7 @@ mod implementation {
                             old_file_cid.as_ref().is_some_and(|c| !c.is_empty())
                         };
 
-                        let (new_versions, pruned_cids) = if should_version {
+                        let (new_versions, pruned_versions) = if should_version {
                             if let Some(ref old_c) = old_file_cid {
                                 if !old_c.is_empty() {
                                     let version_entry = crate::crypto::folder::VersionEntry {
@@ -1559,12 +2382,13 @@ mod implementation {
                                         size: old_size,
                                         timestamp: now_ms,
                                         encryption_mode: old_mode.clone(),
+                                        padding: old_padding.clone(),
                                     };
                                     let mut versions = vec![version_entry];
                                     versions.extend(existing_versions.unwrap_or_default());
                                     // Prune to MAX_VERSIONS_PER_FILE
-                                    let pruned: Vec<String> = if versions.len() > MAX_VERSIONS_PER_FILE {
-                                        versions.split_off(MAX_VERSIONS_PER_FILE).into_iter().map(|v| v.cid).collect()
+                                    let pruned: Vec<crate::crypto::folder::VersionEntry> = if versions.len() > MAX_VERSIONS_PER_FILE {
+                                        versions.split_off(MAX_VERSIONS_PER_FILE)
                                     } else {
                                         vec![]
                                     };
@@ -1602,7 +2426,9 @@ mod implementation {
                                 encrypted_file_key: encrypted_file_key_hex.clone(),
                                 iv: iv_hex.clone(),
                                 size: file_size,
-                                encryption_mode: "GCM".to_string(),
+                                encryption_mode: encryption_mode.clone(),
+                                padding: content_padding.clone(),
+                                chunk_hashes: None,
                                 file_meta_ipns_name: file_meta_ipns_name.clone(),
                                 file_meta_resolved: true,
                                 file_ipns_private_key: file_ipns_private_key.clone(),
@@ -1614,8 +2440,8 @@ mod implementation {
                             inode.attr.mtime = SystemTime::now();
                         }
 
-                        // Cache plaintext so reads work before upload completes
-                        self.pending_content.insert(ino, plaintext);
+                        // Serve reads from the temp file until the upload completes
+                        self.pending_content.insert(ino, handle.clone());
 
                         // Get parent inode + folder key for metadata publish queue and FileMetadata encryption
                         let parent_ino = self.inodes.get(ino)
@@ -1624,14 +2450,38 @@ mod implementation {
 
                         let folder_key_for_file_meta = self.get_folder_key(parent_ino);
 
+                        // What the journal needs to upload and publish the save
+                        // if the upload or the per-file publish below does not go through
+                        let journal = self.journal.clone();
+                        let journal_parent = self.folder_ref(parent_ino).ok();
+                        let file_ipns_key_hex = self.inodes.get(ino).and_then(|i| match &i.kind {
+                            InodeKind::File { file_ipns_key_encrypted_hex, .. } => file_ipns_key_encrypted_hex.clone(),
+                            _ => None,
+                        });
+
                         // Queue debounced metadata publish (with pending upload)
                         self.queue_publish(parent_ino, true);
 
+                        // Chunked versions still referenced after this save; their
+                        // chunks must survive pruning.
+                        let retained_chunked: Vec<(String, String)> = versions_for_meta.iter()
+                            .flatten()
+                            .filter(|v| v.encryption_mode == CHUNKED_MODE)
+                            .map(|v| (v.cid.clone(), v.file_key_encrypted.clone()))
+                            .collect();
+
                         // Clone data for background thread
                         let api = self.api.clone();
                         let rt = self.rt.clone();
                         let upload_tx = self.upload_tx.clone();
                         let coordinator = self.publish_coordinator.clone();
+                        let private_key = self.private_key.clone();
+                        let public_key = self.public_key.clone();
+                        let padding = self.padding;
+                        let metadata_format = self.metadata_format;
+                        let base = handle.base.clone();
+                        let save_failures = self.save_failures.clone();
+                        let file_name = file_name.clone();
 
                         // Build FileMetadata for per-file IPNS publish
                         let file_meta = crate::crypto::folder::FileMetadata {
@@ -1641,49 +2491,181 @@ mod implementation {
                             file_iv: iv_hex.clone(),
                             size: file_size,
                             mime_type,
-                            encryption_mode: "GCM".to_string(),
+                            encryption_mode,
+                            padding: content_padding,
+                            chunk_hashes: None, // CTR: set once the content is streamed
                             created_at: now_ms,
                             modified_at: now_ms,
                             versions: versions_for_meta,
                         };
 
+                        // Journal the save before uploading it. A newer save of
+                        // the file supersedes this one's entry; replay leaves the
+                        // entry alone while the upload below is running.
+                        let journaled = match (&journal_parent, file_ipns_key_hex, &file_meta_ipns_name) {
+                            (Some(parent), Some(key_hex), Some(ipns_name)) => {
+                                let content_id = crate::sync::journal::Journal::new_spool_id();
+                                let seq = journal.record(JournalOp::Write {
+                                    parent: parent.clone(),
+                                    file_ipns_name: ipns_name.clone(),
+                                    ipns_private_key_encrypted: key_hex,
+                                    metadata: file_meta.clone(),
+                                    content: Some(content_id.clone()),
+                                    base: base.clone(),
+                                });
+                                Some((seq, content_id, journal.claim(seq)))
+                            }
+                            _ => None,
+                        };
+
+                        let source = handle.clone();
+
                         // Spawn background OS thread for file upload + per-file IPNS publish
                         std::thread::spawn(move || {
+                            // 1. Keep a sealed copy of the content next to the journal,
+                            //    which uploads it on replay if the upload below fails
+                            let spooled = journaled.as_ref().is_some_and(|(_, content_id, _)| {
+                                match source.open_reader()
+                                    .and_then(|reader| journal.spool().store(content_id, reader))
+                                {
+                                    Ok(_) => true,
+                                    Err(e) => {
+                                        log::error!("Could not keep a copy of ino {} for retrying its upload: {}", ino, e);
+                                        false
+                                    }
+                                }
+                            });
+
                             let result = rt.block_on(async {
-                                // 1. Upload encrypted file content to IPFS
-                                let file_cid = crate::api::ipfs::upload_content(
-                                    &api, &ciphertext,
-                                ).await?;
+                                // 2. Encrypt and upload file content to IPFS
+                                let is_chunked = matches!(upload, PreparedUpload::Chunked { .. });
+                                let (file_cid, chunk_hashes) = match upload_prepared(&api, upload, &source, padding).await {
+                                    Ok(uploaded) => uploaded,
+                                    Err(e) if spooled => {
+                                        log::warn!("Upload failed for ino {}, journaled for retry: {}", ino, e);
+                                        save_failures.retry();
+                                        return Ok(());
+                                    }
+                                    Err(e) => return Err(e),
+                                };
 
                                 log::info!("File uploaded: ino {} -> CID {}", ino, file_cid);
 
-                                // 2. Notify main thread of completed upload
-                                //    Old file CID is preserved as a version — NOT unpinned.
-                                //    Only pruned CIDs (excess versions) are sent for unpinning.
-                                let _ = upload_tx.send(crate::fuse::UploadComplete {
+                                // Replay only has to publish from here on. A newer
+                                // save of the file publishes instead of this one.
+                                let current = journaled.as_ref().is_none_or(|(_, content_id, _)| {
+                                    journal.uploaded(content_id, &file_cid, chunk_hashes.clone())
+                                });
+
+                                // Pruned versions are unpinned; for chunked ones that
+                                // includes chunks no retained version still uses.
+                                let mut pruned_cids: Vec<String> =
+                                    pruned_versions.iter().map(|v| v.cid.clone()).collect();
+                                let pruned_chunked: Vec<(String, String)> = pruned_versions.iter()
+                                    .filter(|v| v.encryption_mode == CHUNKED_MODE)
+                                    .map(|v| (v.cid.clone(), v.file_key_encrypted.clone()))
+                                    .collect();
+                                if !pruned_chunked.is_empty() {
+                                    let mut live = retained_chunked;
+                                    if is_chunked {
+                                        live.push((file_cid.clone(), encrypted_file_key_hex.clone()));
+                                    }
+                                    match (
+                                        referenced_chunk_cids(&api, &private_key, &pruned_chunked).await,
+                                        referenced_chunk_cids(&api, &private_key, &live).await,
+                                    ) {
+                                        (Ok(pruned_chunks), Ok(live_chunks)) => {
+                                            pruned_cids.extend(pruned_chunks.difference(&live_chunks).cloned());
+                                        }
+                                        (Err(e), _) | (_, Err(e)) => {
+                                            // Leaving chunks pinned is safe; unpinning a live one is not
+                                            log::warn!("Skipping chunk cleanup for pruned versions of ino {}: {}", ino, e);
+                                        }
+                                    }
+                                }
+
+                                let upload_complete = crate::fuse::UploadComplete {
                                     ino,
                                     new_cid: file_cid.clone(),
                                     parent_ino,
                                     old_file_cid,
                                     pruned_cids,
-                                });
+                                    chunk_hashes: chunk_hashes.clone(),
+                                    conflict: None,
+                                    file_meta_ipns_name: None,
+                                };
+
+                                // 3. Compare-and-swap: if another device saved the file
+                                //    since it was opened here, keep this save as a
+                                //    conflicted copy instead of overwriting theirs.
+                                if let (Some(base), Some(ipns_name), Some(folder_key)) =
+                                    (&base, &file_meta_ipns_name, &folder_key_for_file_meta)
+                                {
+                                    match remote_file_if_advanced(&api, &coordinator, folder_key, ipns_name, base).await {
+                                        Ok(Some(remote)) => {
+                                            let mut copy_meta = file_meta;
+                                            copy_meta.cid = file_cid;
+                                            copy_meta.chunk_hashes = chunk_hashes;
+                                            publish_conflict_copy(
+                                                &api, &coordinator, &journal, &upload_tx, upload_complete,
+                                                copy_meta, remote, journal_parent, folder_key, &public_key, metadata_format,
+                                            ).await?;
+                                            // The copy has a journal entry of its own
+                                            if let Some((seq, ..)) = &journaled {
+                                                journal.ack(ipns_name, *seq);
+                                            }
+                                            return Ok(());
+                                        }
+                                        Ok(None) => {}
+                                        // Never save over a version that could not be
+                                        // checked; replay compares again later.
+                                        Err(e) if journaled.is_some() => {
+                                            log::warn!(
+                                                "Could not compare ino {} with its remote version, journaled for retry: {}",
+                                                ino, e
+                                            );
+                                            save_failures.retry();
+                                            return Ok(());
+                                        }
+                                        Err(e) => {
+                                            return Err(format!("could not compare with the remote version: {}", e));
+                                        }
+                                    }
+                                }
 
-                                // 3. Publish per-file FileMetadata to file's own IPNS record
-                                if let (Some(ipns_key), Some(ipns_name), Some(folder_key)) =
+                                // 4. Notify main thread of completed upload
+                                //    Old file CID is preserved as a version — NOT unpinned.
+                                //    Only pruned CIDs (excess versions) are sent for unpinning.
+                                let _ = upload_tx.send(upload_complete);
+
+                                // 5. Publish per-file FileMetadata to file's own IPNS record
+                                if !current {
+                                    log::debug!("release: ino {} was saved again meanwhile, not publishing this save", ino);
+                                } else if let (Some(ipns_key), Some(ipns_name), Some(folder_key)) =
                                     (&file_ipns_private_key, &file_meta_ipns_name, &folder_key_for_file_meta)
                                 {
                                     let mut file_meta_with_cid = file_meta;
                                     file_meta_with_cid.cid = file_cid;
+                                    file_meta_with_cid.chunk_hashes = chunk_hashes;
 
-                                    if let Err(e) = publish_file_metadata(
+                                    match publish_file_metadata(
                                         &api,
                                         &file_meta_with_cid,
                                         folder_key,
                                         ipns_key,
                                         ipns_name,
                                         &coordinator,
+                                        metadata_format,
                                     ).await {
-                                        log::warn!("Per-file IPNS publish failed for ino {}: {}", ino, e);
+                                        Ok(()) => {
+                                            if let Some((seq, ..)) = &journaled {
+                                                journal.ack(ipns_name, *seq);
+                                            }
+                                        }
+                                        Err(e) => {
+                                            log::warn!("Per-file IPNS publish failed for ino {}: {}", ino, e);
+                                            save_failures.retry();
+                                        }
                                     }
                                 } else {
                                     log::warn!(
@@ -1697,6 +2679,7 @@ mod implementation {
 
                             if let Err(e) = result {
                                 log::error!("Background upload failed for ino {}: {}", ino, e);
+                                save_failures.report(format!("Could not save '{}': {}", file_name, e));
                             }
                         });
 
@@ -1705,10 +2688,8 @@ mod implementation {
 
                     if let Err(e) = prepare_result {
                         log::error!("File upload preparation failed for ino {}: {}", ino, e);
+                        self.save_failures.report(format!("Could not save '{}': {}", file_name, e));
                     }
-
-                    // Cleanup temp file
-                    handle.cleanup();
                 }
                 // Non-dirty handles: just drop (cleanup happens via Drop impl)
             }
@@ -1736,6 +2717,10 @@ mod implementation {
             name: &OsStr,
             reply: ReplyEmpty,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             let name_str = match name.to_str() {
                 Some(n) => n,
                 None => {
@@ -1754,10 +2739,14 @@ mod implementation {
             };
 
             // Verify it's a file (not a directory)
-            let cid_to_unpin = match self.inodes.get(child_ino) {
+            let content_to_unpin = match self.inodes.get(child_ino) {
                 Some(inode) => match &inode.kind {
-                    InodeKind::File { cid, .. } => {
-                        if cid.is_empty() { None } else { Some(cid.clone()) }
+                    InodeKind::File { cid, encrypted_file_key, encryption_mode, .. } => {
+                        if cid.is_empty() {
+                            None
+                        } else {
+                            Some((cid.clone(), encrypted_file_key.clone(), encryption_mode.clone()))
+                        }
                     }
                     _ => {
                         reply.error(libc::EISDIR);
@@ -1771,6 +2760,7 @@ mod implementation {
             };
 
             log::debug!("unlink: {} from parent {}", name_str, parent);
+            self.journal_unlink(parent, child_ino);
 
             // Remove inode from table (also removes from parent's children)
             self.inodes.remove(child_ino);
@@ -1787,13 +2777,12 @@ mod implementation {
                 // Don't fail -- the local state is already updated
             }
 
-            // Fire-and-forget unpin of file CID
-            if let Some(cid) = cid_to_unpin {
+            // Fire-and-forget unpin of file content (and chunks, if chunked)
+            if let Some((cid, encrypted_file_key, encryption_mode)) = content_to_unpin {
                 let api = self.api.clone();
+                let private_key = self.private_key.clone();
                 self.rt.spawn(async move {
-                    if let Err(e) = crate::api::ipfs::unpin_content(&api, &cid).await {
-                        log::debug!("Background unpin failed for {}: {}", cid, e);
-                    }
+                    unpin_file_content(&api, &private_key, &cid, &encrypted_file_key, &encryption_mode).await;
                 });
             }
 
@@ -1815,6 +2804,10 @@ mod implementation {
             _umask: u32,
             reply: ReplyEntry,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             let name_str = match name.to_str() {
                 Some(n) => n,
                 None => {
@@ -1841,25 +2834,14 @@ mod implementation {
             log::debug!("mkdir: {} in parent {}", name_str, parent);
 
             let result = (|| -> Result<FileAttr, String> {
-                // Generate new folder key (32 random bytes)
-                let folder_key = crate::crypto::utils::generate_file_key();
-
-                // Generate new Ed25519 keypair for this folder's IPNS
-                let (ipns_public_key, ipns_private_key) =
-                    crate::crypto::ed25519::generate_ed25519_keypair();
-
-                // Derive IPNS name from public key
-                let ipns_pub_arr: [u8; 32] = ipns_public_key.clone().try_into()
-                    .map_err(|_| "Invalid IPNS public key length".to_string())?;
-                let ipns_name = crate::crypto::ipns::derive_ipns_name(&ipns_pub_arr)
-                    .map_err(|e| format!("Failed to derive IPNS name: {}", e))?;
-
-                // Wrap folder key with user's public key (ECIES) for parent metadata
-                let wrapped_folder_key = crate::crypto::ecies::wrap_key(
-                    &folder_key, &self.public_key,
-                )
-                .map_err(|e| format!("Folder key wrapping failed: {}", e))?;
-                let encrypted_folder_key_hex = hex::encode(&wrapped_folder_key);
+                // Generate the folder key and IPNS keypair, wrapping the folder
+                // key to the user (hybrid with ML-KEM) for the parent metadata
+                let NewFolderKeys {
+                    folder_key,
+                    encrypted_folder_key: encrypted_folder_key_hex,
+                    ipns_private_key,
+                    ipns_name,
+                } = NewFolderKeys::generate(&self.public_key, self.mlkem_public_key.as_deref())?;
 
                 // Allocate inode and create InodeData (locally, no network I/O)
                 let ino = self.inodes.allocate_ino();
@@ -1894,8 +2876,8 @@ mod implementation {
                     kind: InodeKind::Folder {
                         ipns_name: ipns_name.clone(),
                         encrypted_folder_key: encrypted_folder_key_hex,
-                        folder_key: zeroize::Zeroizing::new(folder_key.to_vec()),
-                        ipns_private_key: Some(zeroize::Zeroizing::new(ipns_private_key.clone())),
+                        folder_key: folder_key.clone(),
+                        ipns_private_key: Some(ipns_private_key.clone()),
                         children_loaded: true, // empty folder, so "loaded"
                     },
                     attr,
@@ -1921,12 +2903,12 @@ mod implementation {
 
                 // Encrypt metadata (CPU-only)
                 let json_bytes = crate::fuse::encrypt_metadata_to_json(
-                    &metadata, &folder_key,
+                    &metadata, &folder_key, &ipns_name, self.metadata_format, self.api.envelope_history(),
                 )?;
 
                 // Encrypt IPNS private key with TEE public key for republishing
                 let encrypted_ipns_for_tee = if let Some(ref tee_key) = self.tee_public_key {
-                    let wrapped = crate::crypto::ecies::wrap_key(&ipns_private_key, tee_key)
+                    let wrapped = crate::crypto::ecies::wrap_key(ipns_private_key.as_bytes(), tee_key)
                         .map_err(|e| format!("TEE key wrapping failed: {}", e))?;
                     Some(hex::encode(&wrapped))
                 } else {
@@ -1934,6 +2916,15 @@ mod implementation {
                 };
                 let tee_key_epoch = self.tee_key_epoch;
 
+                self.journal_op(|fs| match fs.folder_child(ino)? {
+                    Some(FolderChild::Folder(entry)) => Ok(JournalOp::Mkdir {
+                        parent: fs.folder_ref(parent)?,
+                        entry,
+                    }),
+                    _ => Err(format!("mkdir: no folder entry for ino {}", ino)),
+                });
+                let ack = self.journal.acker();
+
                 // Build parent folder metadata for background publish
                 let (parent_metadata, parent_folder_key, parent_ipns_key, parent_ipns_name, parent_old_cid) =
                     self.build_folder_metadata(parent)?;
@@ -1946,6 +2937,8 @@ mod implementation {
                 let rt = self.rt.clone();
                 let ipns_name_clone = ipns_name.clone();
                 let coordinator = self.publish_coordinator.clone();
+                let metadata_format = self.metadata_format;
+                let initial_metadata = metadata.clone();
 
                 std::thread::spawn(move || {
                     let result = rt.block_on(async {
@@ -1955,11 +2948,9 @@ mod implementation {
                         ).await?;
 
                         // Create and sign IPNS record for new folder (seq 0 is correct for brand new folder)
-                        let ipns_key_arr: [u8; 32] = ipns_private_key.try_into()
-                            .map_err(|_| "Invalid IPNS key length".to_string())?;
                         let value = format!("/ipfs/{}", initial_cid);
                         let record = crate::crypto::ipns::create_ipns_record(
-                            &ipns_key_arr, &value, 0, 86_400_000,
+                            &ipns_private_key, &value, 0, 86_400_000,
                         ).map_err(|e| format!("IPNS record creation failed: {}", e))?;
                         let marshaled = crate::crypto::ipns::marshal_ipns_record(&record)
                             .map_err(|e| format!("IPNS marshal failed: {}", e))?;
@@ -1971,7 +2962,7 @@ mod implementation {
                         let req = crate::api::ipns::IpnsPublishRequest {
                             ipns_name: ipns_name_clone.clone(),
                             record: record_b64,
-                            metadata_cid: initial_cid,
+                            metadata_cid: initial_cid.clone(),
                             encrypted_ipns_private_key: encrypted_ipns_for_tee,
                             key_epoch: tee_key_epoch,
                         };
@@ -1979,6 +2970,8 @@ mod implementation {
 
                         // Record new folder's initial publish
                         coordinator.record_publish(&ipns_name_clone, 0);
+                        coordinator.set_base(&ipns_name_clone, &initial_cid, &initial_metadata);
+                        ack.ack(&ipns_name_clone);
                         log::info!("New folder IPNS published: {}", ipns_name_clone);
 
                         // Now publish parent folder metadata
@@ -1986,43 +2979,14 @@ mod implementation {
                         let lock = coordinator.get_lock(&parent_ipns_name);
                         let _guard = lock.lock().await;
 
-                        let parent_json = crate::fuse::encrypt_metadata_to_json(
-                            &parent_metadata, &parent_folder_key,
-                        )?;
-
-                        // Resolve parent seq (monotonic cache fallback)
-                        let seq = coordinator.resolve_sequence(&api, &parent_ipns_name).await?;
-
-                        let parent_meta_cid = crate::api::ipfs::upload_content(
-                            &api, &parent_json,
+                        // Merged with concurrent remote changes if needed
+                        let replaced = crate::fuse::publish_folder_merged(
+                            &api, &coordinator, parent_metadata, &parent_folder_key,
+                            &parent_ipns_key, &parent_ipns_name, metadata_format,
                         ).await?;
+                        ack.ack(&parent_ipns_name);
 
-                        let parent_key_arr: [u8; 32] = parent_ipns_key.try_into()
-                            .map_err(|_| "Invalid parent IPNS key length".to_string())?;
-                        let new_seq = seq + 1;
-                        let parent_value = format!("/ipfs/{}", parent_meta_cid);
-                        let parent_record = crate::crypto::ipns::create_ipns_record(
-                            &parent_key_arr, &parent_value, new_seq, 86_400_000,
-                        ).map_err(|e| format!("Parent IPNS record failed: {}", e))?;
-                        let parent_marshaled = crate::crypto::ipns::marshal_ipns_record(
-                            &parent_record,
-                        ).map_err(|e| format!("Parent IPNS marshal failed: {}", e))?;
-                        let parent_record_b64 = base64::engine::general_purpose::STANDARD
-                            .encode(&parent_marshaled);
-
-                        let parent_req = crate::api::ipns::IpnsPublishRequest {
-                            ipns_name: parent_ipns_name.clone(),
-                            record: parent_record_b64,
-                            metadata_cid: parent_meta_cid,
-                            encrypted_ipns_private_key: None,
-                            key_epoch: None,
-                        };
-                        crate::api::ipns::publish_ipns(&api, &parent_req).await?;
-
-                        // Record successful parent publish
-                        coordinator.record_publish(&parent_ipns_name, new_seq);
-
-                        if let Some(old) = parent_old_cid {
+                        if let Some(old) = replaced.or(parent_old_cid) {
                             let _ = crate::api::ipfs::unpin_content(&api, &old).await;
                         }
 
@@ -2057,6 +3021,10 @@ mod implementation {
             name: &OsStr,
             reply: ReplyEmpty,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             let name_str = match name.to_str() {
                 Some(n) => n,
                 None => {
@@ -2103,6 +3071,7 @@ mod implementation {
             };
 
             log::debug!("rmdir: {} from parent {}", name_str, parent);
+            self.journal_unlink(parent, child_ino);
 
             // Remove inode from table (also removes from parent's children)
             self.inodes.remove(child_ino);
@@ -2145,6 +3114,10 @@ mod implementation {
             _flags: u32,
             reply: ReplyEmpty,
         ) {
+            if self.read_only {
+                reply.error(libc::EROFS);
+                return;
+            }
             log::debug!(
                 "rename: {:?} (parent {}) -> {:?} (parent {})",
                 name, parent, newname, newparent,
@@ -2217,6 +3190,19 @@ mod implementation {
                 name_str, source_ino, parent, newname_str, newparent,
             );
 
+            self.journal_op(|fs| {
+                let child = fs
+                    .folder_child(source_ino)?
+                    .ok_or_else(|| format!("rename: ino {} is not published", source_ino))?;
+                Ok(JournalOp::Rename {
+                    from: fs.folder_ref(parent)?,
+                    from_name: name_str.to_string(),
+                    to: fs.folder_ref(newparent)?,
+                    to_name: newname_str.to_string(),
+                    child,
+                })
+            });
+
             // If destination exists, handle replacement
             if let Some(dest_ino) = self.inodes.find_child(newparent, newname_str) {
                 // Check if destination is a non-empty directory
@@ -2230,14 +3216,17 @@ mod implementation {
                                 }
                             }
                         }
-                        InodeKind::File { cid, .. } => {
+                        InodeKind::File { cid, encrypted_file_key, encryption_mode, .. } => {
                             // Fire-and-forget unpin of replaced file
                             if !cid.is_empty() {
                                 let cid_clone = cid.clone();
+                                let key_clone = encrypted_file_key.clone();
+                                let mode_clone = encryption_mode.clone();
                                 let api = self.api.clone();
+                                let private_key = self.private_key.clone();
                                 self.rt.spawn(async move {
-                                    let _ = crate::api::ipfs::unpin_content(
-                                        &api, &cid_clone,
+                                    unpin_file_content(
+                                        &api, &private_key, &cid_clone, &key_clone, &mode_clone,
                                     ).await;
                                 });
                             }
@@ -2453,74 +3442,66 @@ mod implementation {
 #[cfg(feature = "fuse")]
 pub fn decrypt_metadata_from_ipfs_public(
     encrypted_bytes: &[u8],
-    folder_key: &[u8],
+    folder_key: &crate::crypto::keys::FolderKey,
+    ipns_name: &str,
+    history: &crate::crypto::folder::EnvelopeHistory,
 ) -> Result<crate::crypto::folder::FolderMetadata, String> {
-    #[derive(serde::Deserialize)]
-    struct EncryptedFolderMetadata {
-        iv: String,
-        data: String,
-    }
-
-    let encrypted: EncryptedFolderMetadata = serde_json::from_slice(encrypted_bytes)
-        .map_err(|e| format!("Failed to parse encrypted metadata JSON: {}", e))?;
-
-    let iv_bytes = hex::decode(&encrypted.iv)
-        .map_err(|_| "Invalid metadata IV hex".to_string())?;
-    if iv_bytes.len() != 12 {
-        return Err(format!("Invalid IV length: {} (expected 12)", iv_bytes.len()));
-    }
-    let iv: [u8; 12] = iv_bytes.try_into().unwrap();
-
-    use base64::Engine;
-    let ciphertext = base64::engine::general_purpose::STANDARD
-        .decode(&encrypted.data)
-        .map_err(|e| format!("Invalid metadata base64: {}", e))?;
-
-    let folder_key_arr: [u8; 32] = folder_key
-        .try_into()
-        .map_err(|_| "Invalid folder key length".to_string())?;
-
-    // Reconstruct sealed format: IV || ciphertext (includes tag)
-    let mut sealed = Vec::with_capacity(12 + ciphertext.len());
-    sealed.extend_from_slice(&iv);
-    sealed.extend_from_slice(&ciphertext);
-
-    crate::crypto::folder::decrypt_folder_metadata(&sealed, &folder_key_arr)
-        .map_err(|e| format!("Metadata decryption failed: {}", e))
+    crate::crypto::folder::decrypt_folder_metadata_envelope_strict(
+        encrypted_bytes, folder_key, ipns_name, history,
+    )
+    .map_err(|e| format!("Metadata decryption failed: {}", e))
 }
 
 /// Public wrapper for decrypt_file_metadata_from_ipfs, used by mod.rs for FilePointer resolution.
 #[cfg(feature = "fuse")]
 pub fn decrypt_file_metadata_from_ipfs_public(
     encrypted_bytes: &[u8],
-    folder_key: &[u8; 32],
+    folder_key: &crate::crypto::keys::FolderKey,
+    ipns_name: &str,
+    history: &crate::crypto::folder::EnvelopeHistory,
 ) -> Result<crate::crypto::folder::FileMetadata, String> {
-    #[derive(serde::Deserialize)]
-    struct EncryptedFolderMetadata {
-        iv: String,
-        data: String,
-    }
-
-    let encrypted: EncryptedFolderMetadata = serde_json::from_slice(encrypted_bytes)
-        .map_err(|e| format!("Failed to parse encrypted file metadata JSON: {}", e))?;
-
-    let iv_bytes = hex::decode(&encrypted.iv)
-        .map_err(|_| "Invalid file metadata IV hex".to_string())?;
-    if iv_bytes.len() != 12 {
-        return Err(format!("Invalid IV length: {} (expected 12)", iv_bytes.len()));
-    }
-    let iv: [u8; 12] = iv_bytes.try_into().unwrap();
+    crate::crypto::folder::decrypt_file_metadata_envelope_strict(
+        encrypted_bytes, folder_key, ipns_name, history,
+    )
+    .map_err(|e| format!("File metadata decryption failed: {}", e))
+}
 
-    use base64::Engine;
-    let ciphertext = base64::engine::general_purpose::STANDARD
-        .decode(&encrypted.data)
-        .map_err(|e| format!("Invalid file metadata base64: {}", e))?;
+/// Public wrapper for publish_file_metadata, used by the journal replayer.
+#[cfg(feature = "fuse")]
+pub async fn publish_file_metadata_public(
+    api: &crate::api::client::ApiClient,
+    file_meta: &crate::crypto::folder::FileMetadata,
+    folder_key: &crate::crypto::keys::FolderKey,
+    file_ipns_private_key: &crate::crypto::keys::IpnsSigningKey,
+    file_ipns_name: &str,
+    coordinator: &crate::fuse::PublishCoordinator,
+    metadata_format: crate::crypto::folder::MetadataFormat,
+) -> Result<(), String> {
+    implementation::publish_file_metadata(
+        api, file_meta, folder_key, file_ipns_private_key, file_ipns_name, coordinator, metadata_format,
+    )
+    .await
+}
 
-    // Reconstruct sealed format: IV || ciphertext (includes tag)
-    let mut sealed = Vec::with_capacity(12 + ciphertext.len());
-    sealed.extend_from_slice(&iv);
-    sealed.extend_from_slice(&ciphertext);
+/// Public wrapper for upload_saved_content, used by the journal replayer.
+#[cfg(feature = "fuse")]
+pub async fn upload_saved_content_public(
+    api: &crate::api::client::ApiClient,
+    private_key: &crate::crypto::keys::Secp256k1SecretKey,
+    metadata: &crate::crypto::folder::FileMetadata,
+    source: std::sync::Arc<crate::fuse::file_handle::OpenFileHandle>,
+) -> Result<(String, Option<Vec<String>>), String> {
+    implementation::upload_saved_content(api, private_key, metadata, source).await
+}
 
-    crate::crypto::folder::decrypt_file_metadata(&sealed, folder_key)
-        .map_err(|e| format!("File metadata decryption failed: {}", e))
+/// Public wrapper for remote_file_if_advanced, used by the journal replayer.
+#[cfg(feature = "fuse")]
+pub async fn remote_file_if_advanced_public(
+    api: &crate::api::client::ApiClient,
+    coordinator: &crate::fuse::PublishCoordinator,
+    folder_key: &crate::crypto::keys::FolderKey,
+    ipns_name: &str,
+    base: &crate::fuse::file_handle::FileBase,
+) -> Result<Option<crate::crypto::folder::FileMetadata>, String> {
+    implementation::remote_file_if_advanced(api, coordinator, folder_key, ipns_name, base).await
 }
@@ -0,0 +1,408 @@
+//! Platform mount profiles and the user's mount configuration.
+//!
+//! macOS mounts through FUSE-T, which serves the vault over SMB: no kernel
+//! permission checks or auto-unmount, plus Finder-specific options. Linux
+//! mounts through libfuse3 with:
+//!
+//! - `auto_unmount`, so a crashed app doesn't leave a dead mount behind,
+//! - `default_permissions`, so the kernel checks file modes itself,
+//! - `allow_other` when configured,
+//! - the kernel's readdirplus and writeback caches, requested in `init`.
+//!
+//! fusermount3 only accepts `auto_unmount` and `allow_other` from non-root
+//! users when /etc/fuse.conf contains `user_allow_other`; without it the
+//! mount comes up without `auto_unmount`.
+//!
+//! `MountConfig` is set once at startup from `CIPHERBOX_MOUNT_POINT`,
+//! `CIPHERBOX_ALLOW_OTHER`, `CIPHERBOX_FOLDER_MOUNTS` and
+//! `CIPHERBOX_READ_ONLY` (or `--read-only`). Besides the whole vault, single
+//! vault folders can be mounted at mount points of their own
+//! (`Projects/ClientX=/srv/clientx`), showing nothing outside that folder.
+//! Read-only mounts are `ro` on every platform; the app then never unwraps
+//! an IPNS private key, so it could not publish even if asked to.
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "fuse")]
+use fuser::MountOption;
+
+/// Mount directory (under the home directory) when none is configured.
+pub const DEFAULT_MOUNT_DIR: &str = "CipherBox";
+
+/// Platform whose FUSE implementation the vault is mounted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// FUSE-T.
+    MacOs,
+    /// libfuse3 and the kernel FUSE driver.
+    Linux,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        }
+    }
+
+    /// Command that opens a folder in the file manager.
+    pub fn open_command(self) -> &'static str {
+        match self {
+            Platform::MacOs => "open",
+            Platform::Linux => "xdg-open",
+        }
+    }
+
+    /// Commands (program and arguments) tried in order to unmount
+    /// `mount_point`; the later ones also detach a busy mount.
+    pub fn unmount_commands(self, mount_point: &str) -> Vec<Vec<String>> {
+        let command = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+        match self {
+            Platform::MacOs => vec![
+                command(&["umount", mount_point]),
+                // Finder keeps handles open
+                command(&["diskutil", "unmount", "force", mount_point]),
+            ],
+            Platform::Linux => vec![
+                command(&["fusermount3", "-u", mount_point]),
+                // libfuse 2 installs
+                command(&["fusermount", "-u", mount_point]),
+                command(&["fusermount3", "-u", "-z", mount_point]),
+            ],
+        }
+    }
+
+    /// Commands tried in order to detach a dead mount at `mount_point` (its
+    /// FUSE server is gone), lazily where the platform supports it.
+    pub fn lazy_unmount_commands(self, mount_point: &str) -> Vec<Vec<String>> {
+        let command = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+        match self {
+            Platform::MacOs => vec![
+                command(&["umount", "-f", mount_point]),
+                command(&["diskutil", "unmount", "force", mount_point]),
+            ],
+            Platform::Linux => vec![
+                command(&["fusermount3", "-u", "-z", mount_point]),
+                command(&["fusermount", "-u", "-z", mount_point]),
+                command(&["umount", "-l", mount_point]),
+            ],
+        }
+    }
+}
+
+/// One mount: the whole vault, or a folder of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountSpec {
+    pub mount_point: PathBuf,
+    /// Path of the mounted folder in the vault (names from the root down);
+    /// `None` for the whole vault.
+    pub folder: Option<Vec<String>>,
+}
+
+impl MountSpec {
+    /// The vault path of the mounted folder, for logs and errors.
+    pub fn describe(&self) -> String {
+        match &self.folder {
+            Some(path) => format!("vault folder /{}", path.join("/")),
+            None => "vault".to_string(),
+        }
+    }
+}
+
+/// Where and how to mount the vault.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountConfig {
+    pub mount_point: PathBuf,
+    /// Let other users (and root) access the mount. Linux only.
+    pub allow_other: bool,
+    /// Vault folders mounted on their own, besides the whole vault.
+    pub folders: Vec<MountSpec>,
+    /// Mount everything read-only and never publish (audits, CI).
+    pub read_only: bool,
+}
+
+impl Default for MountConfig {
+    /// `~/CipherBox`, accessible to this user only.
+    fn default() -> Self {
+        Self {
+            mount_point: default_mount_point(),
+            allow_other: false,
+            folders: vec![],
+            read_only: false,
+        }
+    }
+}
+
+impl MountConfig {
+    /// Whether the vault mounts at `~/CipherBox`, a directory the app owns.
+    pub fn is_default_mount_point(&self) -> bool {
+        self.mount_point == default_mount_point()
+    }
+
+    /// Every mount: the whole vault first, then the folder mounts.
+    pub fn mounts(&self) -> Vec<MountSpec> {
+        let vault = MountSpec {
+            mount_point: self.mount_point.clone(),
+            folder: None,
+        };
+        std::iter::once(vault).chain(self.folders.iter().cloned()).collect()
+    }
+}
+
+/// `~/CipherBox`.
+pub fn default_mount_point() -> PathBuf {
+    dirs::home_dir()
+        .expect("Could not determine home directory")
+        .join(DEFAULT_MOUNT_DIR)
+}
+
+/// Parse a configured mount point: an absolute path, or one starting with
+/// `~/` (relative to `home`).
+pub fn parse_mount_point(value: &str, home: Option<&Path>) -> Result<PathBuf, String> {
+    let value = value.trim();
+    let path = match value.strip_prefix("~/") {
+        Some(rest) => home
+            .ok_or_else(|| format!("Cannot expand mount point {:?} without a home directory", value))?
+            .join(rest),
+        None => PathBuf::from(value),
+    };
+    if !path.is_absolute() || path.parent().is_none() {
+        return Err(format!("Mount point {:?} must be an absolute path below /", value));
+    }
+    Ok(path)
+}
+
+/// Parse configured folder mounts: `;`-separated `vault/path=mount_point`
+/// pairs, mount points as in `parse_mount_point`. No mount point may be
+/// inside another, or inside the vault's own `vault_mount_point`.
+pub fn parse_folder_mounts(
+    value: &str,
+    home: Option<&Path>,
+    vault_mount_point: &Path,
+) -> Result<Vec<MountSpec>, String> {
+    let mut mounts: Vec<MountSpec> = Vec::new();
+    for entry in value.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (folder, mount_point) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("Folder mount {:?} is not vault/path=mount_point", entry))?;
+        let folder: Vec<String> = folder
+            .split('/')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        if folder.is_empty() || folder.iter().any(|name| name == "." || name == "..") {
+            return Err(format!("Folder mount {:?} does not name a vault folder", entry));
+        }
+        let mount_point = parse_mount_point(mount_point, home)?;
+
+        let overlaps = |other: &Path| mount_point.starts_with(other) || other.starts_with(&mount_point);
+        if overlaps(vault_mount_point) || mounts.iter().any(|m| overlaps(&m.mount_point)) {
+            return Err(format!(
+                "Mount point {} overlaps another mount point",
+                mount_point.display()
+            ));
+        }
+        mounts.push(MountSpec {
+            mount_point,
+            folder: Some(folder),
+        });
+    }
+    Ok(mounts)
+}
+
+/// Whether fusermount accepts `allow_other` and `auto_unmount` from this
+/// user: root, or `user_allow_other` set in /etc/fuse.conf.
+pub fn user_allow_other() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let is_root = unsafe { libc::geteuid() } == 0;
+    is_root
+        || std::fs::read_to_string("/etc/fuse.conf")
+            .is_ok_and(|conf| fuse_conf_allows_other(&conf))
+}
+
+/// Whether a fuse.conf sets `user_allow_other`.
+pub fn fuse_conf_allows_other(conf: &str) -> bool {
+    conf.lines()
+        .any(|line| line.split('#').next().unwrap_or("").trim() == "user_allow_other")
+}
+
+/// Mount options for `platform` and the mount at `mount_point`.
+/// `user_allow_other` is what `user_allow_other()` returned.
+#[cfg(feature = "fuse")]
+pub fn mount_options(
+    platform: Platform,
+    config: &MountConfig,
+    mount_point: &Path,
+    user_allow_other: bool,
+) -> Result<Vec<MountOption>, String> {
+    let access = if config.read_only {
+        MountOption::RO
+    } else {
+        MountOption::RW
+    };
+    match platform {
+        // AutoUnmount and DefaultPermissions are left out: FUSE-T is
+        // NFS/SMB-based and supports neither kernel-level permission checks
+        // nor fusermount3-based auto-unmount.
+        // - backend=smb: Use SMB instead of NFS backend. NFS has a known macOS kernel
+        //   bug where WRITE RPCs never reach the FUSE-T server for newly created files,
+        //   causing permanent process hangs. SMB backend avoids this entirely.
+        Platform::MacOs => Ok(vec![
+            MountOption::FSName("CipherBox".to_string()),
+            MountOption::CUSTOM("volname=CipherBox".to_string()),
+            MountOption::CUSTOM("noappledouble".to_string()),
+            MountOption::CUSTOM("noapplexattr".to_string()),
+            MountOption::CUSTOM("backend=smb".to_string()),
+            access,
+        ]),
+        Platform::Linux => {
+            let mut options = vec![
+                MountOption::FSName("cipherbox".to_string()),
+                MountOption::Subtype("cipherbox".to_string()),
+                MountOption::DefaultPermissions,
+                access,
+            ];
+            if config.allow_other {
+                if !user_allow_other {
+                    return Err(
+                        "allow_other requires user_allow_other in /etc/fuse.conf".to_string()
+                    );
+                }
+                options.push(MountOption::AllowOther);
+            }
+            if user_allow_other {
+                options.push(MountOption::AutoUnmount);
+            } else {
+                log::warn!(
+                    "Mounting without auto_unmount (needs user_allow_other in /etc/fuse.conf); \
+                     a crash will leave a stale mount at {}",
+                    mount_point.display()
+                );
+            }
+            Ok(options)
+        }
+    }
+}
+
+/// FUSE capabilities to request in `init`. On Linux: readdirplus (listings
+/// carry attributes, so `ls -l` needs no lookup per entry; adaptive, so plain
+/// listings stay cheap) and the writeback cache (the kernel buffers writes in
+/// the page cache and flushes them before `flush`/`release`).
+#[cfg(all(feature = "fuse", target_os = "linux"))]
+pub fn kernel_capabilities() -> u64 {
+    use fuser::consts::{FUSE_DO_READDIRPLUS, FUSE_READDIRPLUS_AUTO, FUSE_WRITEBACK_CACHE};
+    FUSE_DO_READDIRPLUS | FUSE_READDIRPLUS_AUTO | FUSE_WRITEBACK_CACHE
+}
+
+/// FUSE capabilities to request in `init`: none beyond fuser's defaults.
+#[cfg(all(feature = "fuse", not(target_os = "linux")))]
+pub fn kernel_capabilities() -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mount_point_is_absolute_or_home_relative() {
+        let home = Path::new("/home/ada");
+        assert_eq!(
+            parse_mount_point("~/Vaults/CipherBox", Some(home)),
+            Ok(PathBuf::from("/home/ada/Vaults/CipherBox"))
+        );
+        assert_eq!(
+            parse_mount_point(" /mnt/cipherbox ", None),
+            Ok(PathBuf::from("/mnt/cipherbox"))
+        );
+        assert!(parse_mount_point("CipherBox", Some(home)).is_err());
+        assert!(parse_mount_point("/", Some(home)).is_err());
+        assert!(parse_mount_point("~/CipherBox", None).is_err());
+    }
+
+    #[test]
+    fn folder_mounts_name_a_folder_and_a_separate_mount_point() {
+        let home = Path::new("/home/ada");
+        let vault = Path::new("/home/ada/CipherBox");
+        let mounts =
+            parse_folder_mounts("Projects/ClientX=/srv/clientx; /Notes/ = ~/notes;", Some(home), vault)
+                .unwrap();
+        assert_eq!(
+            mounts,
+            vec![
+                MountSpec {
+                    mount_point: PathBuf::from("/srv/clientx"),
+                    folder: Some(vec!["Projects".to_string(), "ClientX".to_string()]),
+                },
+                MountSpec {
+                    mount_point: PathBuf::from("/home/ada/notes"),
+                    folder: Some(vec!["Notes".to_string()]),
+                },
+            ]
+        );
+        assert_eq!(parse_folder_mounts("", Some(home), vault), Ok(vec![]));
+
+        assert!(parse_folder_mounts("Projects", Some(home), vault).is_err());
+        assert!(parse_folder_mounts("/=/srv/vault", Some(home), vault).is_err());
+        assert!(parse_folder_mounts("../x=/srv/x", Some(home), vault).is_err());
+        assert!(parse_folder_mounts("A=relative", Some(home), vault).is_err());
+        // Inside the vault mount, or one inside the other
+        assert!(parse_folder_mounts("A=~/CipherBox/a", Some(home), vault).is_err());
+        assert!(parse_folder_mounts("A=/srv/a;B=/srv/a/b", Some(home), vault).is_err());
+    }
+
+    #[test]
+    fn fuse_conf_user_allow_other_must_be_uncommented() {
+        assert!(fuse_conf_allows_other("# mount_max = 1000\nuser_allow_other\n"));
+        assert!(fuse_conf_allows_other("  user_allow_other  # for CipherBox\n"));
+        assert!(!fuse_conf_allows_other("#user_allow_other\n"));
+        assert!(!fuse_conf_allows_other(""));
+    }
+
+    #[cfg(feature = "fuse")]
+    #[test]
+    fn linux_options_follow_fuse_conf_and_config() {
+        let mut config = MountConfig {
+            mount_point: PathBuf::from("/mnt/cipherbox"),
+            allow_other: false,
+            folders: vec![],
+            read_only: false,
+        };
+        let at = config.mount_point.clone();
+
+        let options = mount_options(Platform::Linux, &config, &at, true).unwrap();
+        assert!(options.contains(&MountOption::AutoUnmount));
+        assert!(options.contains(&MountOption::DefaultPermissions));
+        assert!(options.contains(&MountOption::RW));
+        assert!(!options.contains(&MountOption::AllowOther));
+
+        let options = mount_options(Platform::Linux, &config, &at, false).unwrap();
+        assert!(!options.contains(&MountOption::AutoUnmount));
+
+        config.allow_other = true;
+        let options = mount_options(Platform::Linux, &config, &at, true).unwrap();
+        assert!(options.contains(&MountOption::AllowOther));
+        assert!(mount_options(Platform::Linux, &config, &at, false).is_err());
+
+        config.read_only = true;
+        let options = mount_options(Platform::Linux, &config, &at, true).unwrap();
+        assert!(options.contains(&MountOption::RO));
+        assert!(!options.contains(&MountOption::RW));
+    }
+
+    #[cfg(feature = "fuse")]
+    #[test]
+    fn macos_options_use_fuse_t_smb_backend() {
+        let config = MountConfig::default();
+        let options = mount_options(Platform::MacOs, &config, &config.mount_point, true).unwrap();
+        assert!(options.contains(&MountOption::CUSTOM("backend=smb".to_string())));
+        assert!(!options.contains(&MountOption::AutoUnmount));
+
+        let config = MountConfig { read_only: true, ..config };
+        let options = mount_options(Platform::MacOs, &config, &config.mount_point, true).unwrap();
+        assert!(options.contains(&MountOption::RO));
+    }
+}
@@ -0,0 +1,589 @@
+//! Persistent snapshot of the decrypted vault tree.
+//!
+//! The snapshot holds every folder's `FolderMetadata` and every file's
+//! `FileMetadata` seen so far, keyed by IPNS name together with the CID and
+//! sequence number they were resolved at. It is sealed with AES-256-GCM under
+//! the device-local state key and stored under the user cache dir, one file per
+//! vault.
+//!
+//! On mount the inode table is restored from the snapshot without network I/O,
+//! so the tree is browsable immediately (and offline). The `Crawler` then walks
+//! the whole tree in the background, re-resolving every IPNS name, sending
+//! changes to the FUSE thread as `CrawlUpdate`s and saving the snapshot when
+//! anything changed. Between walks it re-resolves the names the sync daemon
+//! reports as changed, and wakes the FUSE thread to apply what it found.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::api::client::ApiClient;
+use crate::crypto::folder::{FileMetadata, FolderChild, FolderEntry, FolderMetadata};
+use crate::crypto::keys::{FolderKey, LocalStateKey, MlKemSecretKey, Secp256k1SecretKey};
+use crate::fuse::inode::{InodeKind, InodeTable, ROOT_INO};
+
+/// Snapshot format version.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Pause between two full crawls of the vault.
+pub const CRAWL_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Folder metadata as last resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFolder {
+    pub cid: String,
+    pub sequence: u64,
+    pub metadata: FolderMetadata,
+}
+
+/// File metadata as last resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFile {
+    pub cid: String,
+    pub sequence: u64,
+    pub metadata: FileMetadata,
+}
+
+/// Decrypted vault tree, keyed by IPNS name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultSnapshot {
+    pub version: u32,
+    pub root_ipns_name: String,
+    pub folders: HashMap<String, SnapshotFolder>,
+    pub files: HashMap<String, SnapshotFile>,
+    /// IPNS names whose metadata was read in a v2 envelope
+    /// (`ApiClient::envelope_history`), so a legacy envelope served for them after
+    /// a restart is still rejected.
+    #[serde(default)]
+    pub v2_envelopes: BTreeSet<String>,
+}
+
+/// A change found by the crawler, applied on the FUSE thread.
+pub enum CrawlUpdate {
+    Folder {
+        ipns_name: String,
+        cid: String,
+        metadata: FolderMetadata,
+    },
+    File {
+        ipns_name: String,
+        metadata: FileMetadata,
+    },
+}
+
+/// Path of the snapshot of the vault rooted at `root_ipns_name`:
+/// `<user cache dir>/cipherbox/snapshots/<root IPNS name>`.
+pub fn snapshot_path(root_ipns_name: &str) -> Option<PathBuf> {
+    if root_ipns_name.is_empty() || !root_ipns_name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    dirs::cache_dir().map(|dir| dir.join("cipherbox").join("snapshots").join(root_ipns_name))
+}
+
+/// Associated data binding a sealed snapshot to its vault.
+fn snapshot_aad(root_ipns_name: &str) -> Vec<u8> {
+    format!("cipherbox-snapshot-v{}:{}", SNAPSHOT_VERSION, root_ipns_name).into_bytes()
+}
+
+impl VaultSnapshot {
+    pub fn new(root_ipns_name: &str) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            root_ipns_name: root_ipns_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Load and unseal the snapshot at `path`.
+    ///
+    /// Returns `None` if there is none, or if it cannot be decrypted or parsed
+    /// (e.g. written for another vault or by an older format); the caller then
+    /// starts from an empty snapshot.
+    pub fn load(path: &Path, key: &LocalStateKey, root_ipns_name: &str) -> Option<Self> {
+        let sealed = std::fs::read(path).ok()?;
+        let json = match crate::crypto::aes::unseal_aes_gcm_with_aad(
+            &sealed,
+            key.as_bytes(),
+            &snapshot_aad(root_ipns_name),
+        ) {
+            Ok(json) => Zeroizing::new(json),
+            Err(e) => {
+                log::warn!("Ignoring unreadable vault snapshot {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        match serde_json::from_slice::<Self>(&json) {
+            Ok(snapshot)
+                if snapshot.version == SNAPSHOT_VERSION
+                    && snapshot.root_ipns_name == root_ipns_name =>
+            {
+                Some(snapshot)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Ignoring malformed vault snapshot {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Seal and write the snapshot to `path` (atomically, via a temp file).
+    pub fn save(&self, path: &Path, key: &LocalStateKey) -> Result<(), String> {
+        let json = Zeroizing::new(
+            serde_json::to_vec(self).map_err(|e| format!("Snapshot serialization failed: {}", e))?,
+        );
+        let sealed = crate::crypto::aes::seal_aes_gcm_with_aad(
+            &json,
+            key.as_bytes(),
+            &snapshot_aad(&self.root_ipns_name),
+        )
+        .map_err(|e| format!("Snapshot encryption failed: {}", e))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+        }
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, &sealed).map_err(|e| format!("Failed to write snapshot: {}", e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o600));
+        }
+        std::fs::rename(&temp, path).map_err(|e| format!("Failed to write snapshot: {}", e))
+    }
+
+    /// Rebuild the inode table from the snapshot, starting at the root.
+    ///
+    /// Every folder reachable from the root that the snapshot knows is
+    /// populated, and every file pointer with snapshot metadata is resolved.
+    /// Returns `false` (leaving `inodes` untouched) if the snapshot has no root.
+    pub fn restore(
+        &self,
+        inodes: &mut InodeTable,
+        private_key: &Secp256k1SecretKey,
+        public_key: &[u8],
+    ) -> bool {
+        let Some(root) = self.folders.get(&self.root_ipns_name) else {
+            return false;
+        };
+
+        let mut folders = 0usize;
+        let mut queue = VecDeque::from([(ROOT_INO, &root.metadata)]);
+        while let Some((ino, metadata)) = queue.pop_front() {
+            if let Err(e) = inodes.populate_folder(ino, metadata, private_key, public_key, false) {
+                log::warn!("Snapshot restore of folder ino={} failed: {}", ino, e);
+                continue;
+            }
+            folders += 1;
+
+            let children = inodes.get(ino).and_then(|i| i.children.clone()).unwrap_or_default();
+            for child_ino in children {
+                if let Some(InodeKind::Folder { ipns_name, .. }) = inodes.get(child_ino).map(|i| &i.kind) {
+                    if let Some(folder) = self.folders.get(ipns_name) {
+                        queue.push_back((child_ino, &folder.metadata));
+                    }
+                }
+            }
+        }
+
+        let mut files = 0usize;
+        for (ino, ipns_name) in inodes.get_unresolved_file_pointers() {
+            if let Some(file) = self.files.get(&ipns_name) {
+                let fm = file.metadata.clone();
+                inodes.resolve_file_pointer(
+                    ino, fm.cid, fm.file_key_encrypted,
+                    fm.file_iv, fm.size, fm.encryption_mode, fm.padding, fm.chunk_hashes,
+                    fm.versions,
+                );
+                files += 1;
+            }
+        }
+
+        log::info!("Restored {} folders and {} files from the vault snapshot", folders, files);
+        true
+    }
+}
+
+/// Background walker keeping the snapshot (and the mounted tree) current.
+pub struct Crawler {
+    pub api: Arc<ApiClient>,
+    pub private_key: Secp256k1SecretKey,
+    /// For hybrid-wrapped folder keys.
+    pub mlkem_private_key: Option<MlKemSecretKey>,
+    pub root_ipns_name: String,
+    pub root_folder_key: FolderKey,
+    pub snapshot: VaultSnapshot,
+    /// Where to save the snapshot; `None` keeps it in memory only.
+    pub path: Option<PathBuf>,
+    pub state_key: LocalStateKey,
+    pub tx: std::sync::mpsc::Sender<CrawlUpdate>,
+    /// Changes the sync daemon saw, re-resolved between crawls.
+    pub changes: tokio::sync::mpsc::UnboundedReceiver<crate::sync::RemoteChange>,
+    /// Mount point of the vault, woken up (see `kernel::wake`) after changes.
+    pub mount_point: PathBuf,
+}
+
+impl Crawler {
+    /// Crawl now and then every `CRAWL_INTERVAL`, refreshing reported changes
+    /// in between, until the task is aborted.
+    pub async fn run(mut self) {
+        loop {
+            match self.crawl().await {
+                Ok(changed) => self.changes_applied(changed).await,
+                Err(e) => log::info!("Vault crawl skipped: {}", e),
+            }
+
+            let next_crawl = tokio::time::sleep(CRAWL_INTERVAL);
+            tokio::pin!(next_crawl);
+            loop {
+                tokio::select! {
+                    _ = &mut next_crawl => break,
+                    Some(change) = self.changes.recv() => {
+                        let mut names = vec![change.ipns_name];
+                        while let Ok(change) = self.changes.try_recv() {
+                            names.push(change.ipns_name);
+                        }
+                        let changed = self.refresh_names(&names).await;
+                        self.changes_applied(changed).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Save the snapshot and have the FUSE thread apply the updates, if
+    /// `changed`. Names newly read in v2 envelopes are saved too.
+    async fn changes_applied(&mut self, changed: bool) {
+        let v2_envelopes = self.api.envelope_history().v2_names();
+        let envelopes_changed = v2_envelopes != self.snapshot.v2_envelopes;
+        self.snapshot.v2_envelopes = v2_envelopes;
+        if !changed && !envelopes_changed {
+            return;
+        }
+        if let Some(path) = &self.path {
+            if let Err(e) = self.snapshot.save(path, &self.state_key) {
+                log::warn!("Vault snapshot not saved: {}", e);
+            }
+        }
+        if !changed {
+            return;
+        }
+        let mount_point = self.mount_point.clone();
+        let _ = tokio::task::spawn_blocking(move || crate::fuse::kernel::wake(&mount_point)).await;
+    }
+
+    /// Re-resolve the folders and files `names`, if the snapshot knows them.
+    /// Returns whether the snapshot changed.
+    pub async fn refresh_names(&mut self, names: &[String]) -> bool {
+        let mut changed = false;
+        for name in names {
+            if let Some(folder_key) = self.folder_key(name) {
+                match self.refresh_folder(name, &folder_key).await {
+                    Ok(folder_changed) => changed |= folder_changed,
+                    Err(e) => log::debug!("Refresh of folder {} failed: {}", name, e),
+                }
+                continue;
+            }
+            let parent = self.snapshot.folders.iter().find_map(|(parent, folder)| {
+                folder.metadata.children.iter().any(|child| {
+                    matches!(child, FolderChild::File(pointer) if pointer.file_meta_ipns_name == *name)
+                })
+                .then(|| parent.clone())
+            });
+            match parent.and_then(|parent| self.folder_key(&parent)) {
+                Some(folder_key) => changed |= self.refresh_file(name, &folder_key).await,
+                None => log::debug!("Refresh: {} is not in the vault snapshot", name),
+            }
+        }
+        changed
+    }
+
+    /// Key of the folder `ipns_name`, from its entry in the snapshot.
+    fn folder_key(&self, ipns_name: &str) -> Option<FolderKey> {
+        if ipns_name == self.root_ipns_name {
+            return Some(self.root_folder_key.clone());
+        }
+        let entry = self.snapshot.folders.values().find_map(|folder| {
+            folder.metadata.children.iter().find_map(|child| match child {
+                FolderChild::Folder(entry) if entry.ipns_name == ipns_name => Some(entry),
+                _ => None,
+            })
+        })?;
+        match self.unwrap_folder_key(entry) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                log::warn!("Folder key of '{}' unavailable: {}", entry.name, e);
+                None
+            }
+        }
+    }
+
+    fn unwrap_folder_key(&self, entry: &FolderEntry) -> Result<FolderKey, String> {
+        let wrapped = hex::decode(&entry.folder_key_encrypted).map_err(|_| "invalid hex".to_string())?;
+        crate::crypto::ecies::unwrap_secret_key_hybrid::<FolderKey>(
+            &wrapped,
+            &self.private_key,
+            self.mlkem_private_key.as_ref(),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Walk the whole tree once. Returns whether the snapshot changed.
+    ///
+    /// Metadata is only downloaded for names whose CID moved. Fails only if
+    /// the root cannot be resolved (e.g. offline); other failures keep the
+    /// snapshot's entries for that subtree.
+    pub async fn crawl(&mut self) -> Result<bool, String> {
+        let root_name = self.root_ipns_name.clone();
+        let root_key = self.root_folder_key.clone();
+
+        let mut changed = false;
+        let mut seen_folders = HashSet::new();
+        let mut seen_files = HashSet::new();
+        let mut queue = VecDeque::from([(root_name, root_key)]);
+
+        while let Some((ipns_name, folder_key)) = queue.pop_front() {
+            if !seen_folders.insert(ipns_name.clone()) {
+                continue;
+            }
+            match self.refresh_folder(&ipns_name, &folder_key).await {
+                Ok(folder_changed) => changed |= folder_changed,
+                Err(e) if ipns_name == self.root_ipns_name => return Err(e),
+                Err(e) => log::debug!("Crawl: resolve of folder {} failed: {}", ipns_name, e),
+            }
+            let Some(folder) = self.snapshot.folders.get(&ipns_name) else {
+                continue;
+            };
+
+            let mut file_names = Vec::new();
+            for child in &folder.metadata.children {
+                match child {
+                    FolderChild::Folder(entry) => {
+                        match self.unwrap_folder_key(entry) {
+                            Ok(key) => queue.push_back((entry.ipns_name.clone(), key)),
+                            Err(e) => log::warn!("Crawl: folder key of '{}' unavailable: {}", entry.name, e),
+                        }
+                    }
+                    FolderChild::File(pointer) => file_names.push(pointer.file_meta_ipns_name.clone()),
+                }
+            }
+
+            for file_name in file_names {
+                changed |= self.refresh_file(&file_name, &folder_key).await;
+                seen_files.insert(file_name);
+            }
+        }
+
+        // Forget folders and files no longer reachable from the root
+        let before = self.snapshot.folders.len() + self.snapshot.files.len();
+        self.snapshot.folders.retain(|name, _| seen_folders.contains(name));
+        self.snapshot.files.retain(|name, _| seen_files.contains(name));
+        changed |= self.snapshot.folders.len() + self.snapshot.files.len() != before;
+        Ok(changed)
+    }
+
+    /// Re-resolve one folder. Returns whether its snapshot entry changed, or
+    /// the error if the name could not be resolved.
+    async fn refresh_folder(&mut self, ipns_name: &str, folder_key: &FolderKey) -> Result<bool, String> {
+        let resolved = crate::api::ipns::resolve_ipns(&self.api, ipns_name).await?;
+        let sequence = resolved.sequence_number.parse::<u64>().unwrap_or(0);
+        if let Some(known) = self.snapshot.folders.get(ipns_name) {
+            // An older record can come back from a lagging resolver
+            if known.cid == resolved.cid || sequence < known.sequence {
+                return Ok(false);
+            }
+        }
+
+        let metadata = async {
+            let bytes = crate::api::ipfs::fetch_content(&self.api, &resolved.cid).await?;
+            crate::fuse::operations::decrypt_metadata_from_ipfs_public(
+                &bytes,
+                folder_key,
+                ipns_name,
+                self.api.envelope_history(),
+            )
+        }
+        .await;
+        match metadata {
+            Ok(metadata) => {
+                let _ = self.tx.send(CrawlUpdate::Folder {
+                    ipns_name: ipns_name.to_string(),
+                    cid: resolved.cid.clone(),
+                    metadata: metadata.clone(),
+                });
+                self.snapshot.folders.insert(
+                    ipns_name.to_string(),
+                    SnapshotFolder { cid: resolved.cid, sequence, metadata },
+                );
+                Ok(true)
+            }
+            Err(e) => {
+                log::warn!("Crawl: folder {} unavailable: {}", ipns_name, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Re-resolve one file's metadata. Returns whether its snapshot entry changed.
+    async fn refresh_file(&mut self, ipns_name: &str, folder_key: &FolderKey) -> bool {
+        let resolved = match crate::api::ipns::resolve_ipns(&self.api, ipns_name).await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                log::debug!("Crawl: resolve of file {} failed: {}", ipns_name, e);
+                return false;
+            }
+        };
+        let sequence = resolved.sequence_number.parse::<u64>().unwrap_or(0);
+        if let Some(known) = self.snapshot.files.get(ipns_name) {
+            if known.cid == resolved.cid || sequence < known.sequence {
+                return false;
+            }
+        }
+
+        let metadata = async {
+            let bytes = crate::api::ipfs::fetch_content(&self.api, &resolved.cid).await?;
+            crate::fuse::operations::decrypt_file_metadata_from_ipfs_public(
+                &bytes,
+                folder_key,
+                ipns_name,
+                self.api.envelope_history(),
+            )
+        }
+        .await;
+        match metadata {
+            Ok(metadata) => {
+                let _ = self.tx.send(CrawlUpdate::File {
+                    ipns_name: ipns_name.to_string(),
+                    metadata: metadata.clone(),
+                });
+                self.snapshot.files.insert(
+                    ipns_name.to_string(),
+                    SnapshotFile { cid: resolved.cid, sequence, metadata },
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!("Crawl: file metadata {} unavailable: {}", ipns_name, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_snapshot() -> VaultSnapshot {
+        let mut snapshot = VaultSnapshot::new("k51root");
+        snapshot.folders.insert(
+            "k51root".to_string(),
+            SnapshotFolder {
+                cid: "bafyroot".to_string(),
+                sequence: 7,
+                metadata: FolderMetadata { version: "v2".to_string(), children: vec![] },
+            },
+        );
+        snapshot.v2_envelopes.insert("k51root".to_string());
+        snapshot
+    }
+
+    #[test]
+    fn snapshot_round_trips_sealed() {
+        let path = std::env::temp_dir().join("cipherbox-test-snapshot-roundtrip");
+        let key = LocalStateKey::from_bytes([7u8; 32]);
+        sample_snapshot().save(&path, &key).unwrap();
+
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!on_disk.windows(7).any(|w| w == b"k51root"), "snapshot must be encrypted");
+
+        let loaded = VaultSnapshot::load(&path, &key, "k51root").unwrap();
+        assert_eq!(loaded.folders["k51root"].cid, "bafyroot");
+        assert_eq!(loaded.folders["k51root"].sequence, 7);
+        assert!(loaded.v2_envelopes.contains("k51root"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn snapshot_rejects_wrong_key_or_vault() {
+        let path = std::env::temp_dir().join("cipherbox-test-snapshot-reject");
+        let key = LocalStateKey::from_bytes([7u8; 32]);
+        sample_snapshot().save(&path, &key).unwrap();
+
+        assert!(VaultSnapshot::load(&path, &LocalStateKey::from_bytes([8u8; 32]), "k51root").is_none());
+        assert!(VaultSnapshot::load(&path, &key, "k51other").is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn restore_populates_tree_and_resolves_files() {
+        let file_name = "k51qzi5uqu5dljtg5upm7x7ugan9lql3ewyknv4r4mhhkwzn8n7cnbd1unfwgx";
+        let mut snapshot = sample_snapshot();
+        snapshot.folders.get_mut("k51root").unwrap().metadata.children =
+            vec![FolderChild::File(crate::crypto::folder::FilePointer {
+                id: "file-1".to_string(),
+                name: "hello.txt".to_string(),
+                file_meta_ipns_name: file_name.to_string(),
+                ipns_private_key_encrypted: None,
+                created_at: 1700000000000,
+                modified_at: 1700000000000,
+            })];
+        snapshot.files.insert(
+            file_name.to_string(),
+            SnapshotFile {
+                cid: "bafymeta".to_string(),
+                sequence: 2,
+                metadata: FileMetadata {
+                    version: "v1".to_string(),
+                    cid: "bafycontent".to_string(),
+                    file_key_encrypted: "04aa".to_string(),
+                    file_iv: "00".repeat(12),
+                    size: 42,
+                    mime_type: "text/plain".to_string(),
+                    encryption_mode: "GCM".to_string(),
+                    padding: None,
+                    chunk_hashes: None,
+                    created_at: 1700000000000,
+                    modified_at: 1700000000000,
+                    versions: None,
+                },
+            },
+        );
+
+        let mut inodes = InodeTable::new();
+        let private_key = Secp256k1SecretKey::from_bytes([0u8; 32]);
+        assert!(snapshot.restore(&mut inodes, &private_key, &[0u8; 33]));
+
+        let ino = inodes.find_child(ROOT_INO, "hello.txt").unwrap();
+        assert_eq!(inodes.find_by_ipns_name(file_name), Some(ino));
+        let inode = inodes.get(ino).unwrap();
+        assert_eq!(inode.attr.size, 42);
+        assert!(matches!(
+            &inode.kind,
+            InodeKind::File { cid, file_meta_resolved: true, .. } if cid == "bafycontent"
+        ));
+    }
+
+    #[test]
+    fn restore_without_root_leaves_table_alone() {
+        let snapshot = VaultSnapshot::new("k51root");
+        let mut inodes = InodeTable::new();
+        let private_key = Secp256k1SecretKey::from_bytes([0u8; 32]);
+        assert!(!snapshot.restore(&mut inodes, &private_key, &[0u8; 33]));
+        assert!(inodes.get(ROOT_INO).unwrap().children.as_ref().is_none_or(|c| c.is_empty()));
+    }
+
+    #[test]
+    fn snapshot_path_rejects_unsafe_names() {
+        assert!(snapshot_path("../k51").is_none());
+        assert!(snapshot_path("").is_none());
+    }
+}
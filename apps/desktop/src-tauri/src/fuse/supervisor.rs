@@ -0,0 +1,457 @@
+//! Mount supervisor: keeps the vault mounted while the user is signed in.
+//!
+//! - A crashed instance leaves a dead mount behind, whose mount point fails
+//!   with ENOTCONN. `recover_stale_mount` detects it and detaches it lazily;
+//!   it runs at startup and before every mount.
+//! - `supervise` watches the FUSE session thread. When the thread exits
+//!   without the supervisor having been stopped first (logout, quit), or a
+//!   mount attempt fails, the mount goes to `MountStatus::Error` (shown in the
+//!   tray, with a "Retry Mount" action) and is remounted, backing off after
+//!   repeated failures.
+//!
+//! Every mount (the whole vault, and each folder mounted on its own) has its
+//! own supervisor and status, kept in `Mounts`. `AppState::mount_status` and
+//! the tray show them summed up (`overall_status`).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::{Notify, RwLock};
+
+use crate::fuse::profile::{MountSpec, Platform};
+use crate::fuse::{cache, PublishCoordinator, VaultMount};
+use crate::sync::replay::JournalReplayer;
+use crate::state::{AppState, MountStatus};
+use crate::tray::TrayStatus;
+
+/// How often the FUSE session thread is checked.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Wait before the first retry of a failed mount; doubles per failure.
+const RETRY_MIN: Duration = Duration::from_secs(5);
+
+/// Longest wait between mount retries.
+const RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// The vault's mounts, by mount point (`AppState::mounts`).
+#[derive(Default)]
+pub struct Mounts {
+    /// Mounts that came up. An entry stays after its mount goes down, so its
+    /// journal is still replayed and its watch list carries over to the
+    /// remount; cleared on logout.
+    pub entries: RwLock<BTreeMap<PathBuf, Arc<VaultMount>>>,
+    /// Status of each mount. Uses std::sync::Mutex (as do `supervisors`)
+    /// because the tray menu event handler is synchronous.
+    status: std::sync::Mutex<BTreeMap<PathBuf, MountStatus>>,
+    /// Supervisor task of each mount. Aborted before unmounting on purpose.
+    supervisors: std::sync::Mutex<BTreeMap<PathBuf, tokio::task::JoinHandle<()>>>,
+    /// Wakes the supervisors to retry failed mounts now (tray "Retry Mount").
+    pub retry: Notify,
+    /// On-disk content cache, shared by the mounts.
+    disk_cache: OnceLock<Arc<cache::DiskCache>>,
+    /// IPNS publish sequencing and locks, shared by the mounts: a folder
+    /// reachable from two mounts is published through one coordinator.
+    publish_coordinator: std::sync::Mutex<Option<Arc<PublishCoordinator>>>,
+    /// Replayer of the vault's mutation journal, shared by the writable mounts
+    /// so a change in a folder reachable from two of them is journaled once.
+    journal_replayer: std::sync::Mutex<Option<Arc<JournalReplayer>>>,
+}
+
+impl Mounts {
+    /// The on-disk content cache, opened with `budget` by the first mount.
+    pub fn disk_cache(&self, budget: u64) -> Arc<cache::DiskCache> {
+        self.disk_cache
+            .get_or_init(|| {
+                Arc::new(match cache::disk_cache_dir() {
+                    Some(dir) => cache::DiskCache::open(dir, budget),
+                    None => cache::DiskCache::disabled(),
+                })
+            })
+            .clone()
+    }
+
+    /// The publish coordinator, created by the first mount after sign-in
+    /// (refusing every publish if `read_only`).
+    pub fn publish_coordinator(&self, read_only: bool) -> Arc<PublishCoordinator> {
+        self.publish_coordinator
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_or_insert_with(|| {
+                Arc::new(if read_only {
+                    PublishCoordinator::disabled()
+                } else {
+                    PublishCoordinator::new()
+                })
+            })
+            .clone()
+    }
+
+    /// The journal replayer, created with `init` by the first writable mount
+    /// after sign-in.
+    pub fn journal_replayer(&self, init: impl FnOnce() -> JournalReplayer) -> Arc<JournalReplayer> {
+        self.journal_replayer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_or_insert_with(|| Arc::new(init()))
+            .clone()
+    }
+
+    /// The journal replayer, if a writable mount came up since sign-in.
+    pub fn replayer(&self) -> Option<Arc<JournalReplayer>> {
+        self.journal_replayer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Forget the mounts, their coordinator and the journal, on logout: they
+    /// hold the user's wrapped keys.
+    pub async fn clear(&self) {
+        self.entries.write().await.clear();
+        *self
+            .publish_coordinator
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        *self
+            .journal_replayer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+
+    /// Whether any mount is up.
+    pub fn any_mounted(&self) -> bool {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .any(|status| *status == MountStatus::Mounted)
+    }
+}
+
+/// The status of several mounts taken together: the first failure, else
+/// `Mounting` while any mount is, else `Mounted` if any mount is up.
+pub fn overall_status<'a>(statuses: impl IntoIterator<Item = &'a MountStatus>) -> MountStatus {
+    let mut overall = MountStatus::Unmounted;
+    for status in statuses {
+        match (status, &overall) {
+            (MountStatus::Error(_), _) => return status.clone(),
+            (MountStatus::Mounting, _) | (MountStatus::Mounted, MountStatus::Unmounted) => {
+                overall = status.clone()
+            }
+            _ => {}
+        }
+    }
+    overall
+}
+
+/// Whether `stat` failed the way it does on a mount whose FUSE server is gone.
+pub fn is_dead_mount_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::ENOTCONN) | Some(libc::ECONNABORTED) | Some(libc::ENXIO)
+    )
+}
+
+/// Detach the mount at `mount_point` if it is dead. Returns whether it was.
+pub fn recover_stale_mount(mount_point: &Path) -> Result<bool, String> {
+    match std::fs::metadata(mount_point) {
+        Err(e) if is_dead_mount_error(&e) => {
+            log::warn!("Stale mount at {} ({}); detaching it", mount_point.display(), e);
+            super::run_unmount_commands(mount_point, Platform::lazy_unmount_commands)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Wait before remount attempt number `failures + 1`.
+pub fn retry_delay(failures: u32) -> Duration {
+    RETRY_MIN
+        .saturating_mul(1 << failures.saturating_sub(1).min(8))
+        .min(RETRY_MAX)
+}
+
+/// Record a status change of the mount at `mount_point` and show the overall
+/// status in the tray.
+async fn set_status(app: &AppHandle, state: &AppState, mount_point: &Path, status: MountStatus) {
+    let overall = {
+        let mut statuses = state
+            .mounts
+            .status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        statuses.insert(mount_point.to_path_buf(), status);
+        overall_status(statuses.values())
+    };
+    let tray_status = match &overall {
+        MountStatus::Unmounted => TrayStatus::NotConnected,
+        MountStatus::Mounting => TrayStatus::Mounting,
+        MountStatus::Mounted => TrayStatus::Synced,
+        MountStatus::Error(message) => TrayStatus::MountError(message.clone()),
+    };
+    *state.mount_status.write().await = overall;
+    let _ = crate::tray::update_tray_status(app, &tray_status);
+}
+
+/// Mount `spec` with the keys in `state`, reporting progress in the mount
+/// status and tray. Returns the FUSE session thread.
+pub async fn mount(
+    app: &AppHandle,
+    state: &AppState,
+    spec: &MountSpec,
+) -> Option<std::thread::JoinHandle<()>> {
+    set_status(app, state, &spec.mount_point, MountStatus::Mounting).await;
+    match mount_from_state(state, spec).await {
+        Ok(handle) => {
+            set_status(app, state, &spec.mount_point, MountStatus::Mounted).await;
+            log::info!(
+                "FUSE filesystem of the {} mounted at {}",
+                spec.describe(),
+                spec.mount_point.display()
+            );
+            Some(handle)
+        }
+        Err(e) => {
+            let err_msg = format!("FUSE mount of the {} failed: {}", spec.describe(), e);
+            log::error!("{}", err_msg);
+            set_status(app, state, &spec.mount_point, MountStatus::Error(err_msg)).await;
+            None
+        }
+    }
+}
+
+/// `mount_filesystem` of `spec` with the keys in `state`. A folder mount
+/// takes its folder's keys from the folder's entry in its parent.
+async fn mount_from_state(
+    state: &AppState,
+    spec: &MountSpec,
+) -> Result<std::thread::JoinHandle<()>, String> {
+    let private_key = state
+        .private_key
+        .read()
+        .await
+        .as_ref()
+        .ok_or("Private key not available for FUSE mount")?
+        .clone();
+    let public_key = state
+        .public_key
+        .read()
+        .await
+        .as_ref()
+        .ok_or("Public key not available for FUSE mount")?
+        .clone();
+    let root_folder_key = state
+        .root_folder_key
+        .read()
+        .await
+        .as_ref()
+        .ok_or("Root folder key not available for FUSE mount")?
+        .clone();
+    let root_ipns_name = state
+        .root_ipns_name
+        .read()
+        .await
+        .as_ref()
+        .ok_or("Root IPNS name not available for FUSE mount")?
+        .clone();
+    let root_ipns_private_key = state.root_ipns_private_key.read().await.clone();
+    let mlkem_private_key = state.mlkem_private_key.read().await.clone();
+    let (root_ipns_name, root_folder_key, root_ipns_private_key) = match &spec.folder {
+        Some(path) => {
+            super::resolve_vault_folder(
+                &state.api,
+                &private_key,
+                mlkem_private_key.as_ref(),
+                &root_ipns_name,
+                &root_folder_key,
+                path,
+                !state.mount.read_only,
+            )
+            .await?
+        }
+        None => (root_ipns_name, root_folder_key, root_ipns_private_key),
+    };
+
+    // Extract TEE keys for new folder creation
+    let tee_keys = state.tee_keys.read().await;
+    let tee_public_key = tee_keys.as_ref().and_then(|tk| {
+        hex::decode(&tk.current_public_key).ok()
+    });
+    let tee_key_epoch = tee_keys.as_ref().map(|tk| tk.current_epoch);
+    drop(tee_keys);
+
+    let rt = tokio::runtime::Handle::current();
+    super::mount_filesystem(
+        state,
+        rt,
+        spec.mount_point.clone(),
+        private_key,
+        public_key,
+        root_folder_key,
+        root_ipns_name,
+        root_ipns_private_key,
+        tee_public_key,
+        tee_key_epoch,
+    )
+    .await
+}
+
+/// Supervise the mount of `spec`, whose session thread is `mount` (`None` if
+/// mounting failed), until `stop`. Replaces any previous supervisor of its
+/// mount point.
+pub fn supervise(app: &AppHandle, spec: MountSpec, mount: Option<std::thread::JoinHandle<()>>) {
+    let mount_point = spec.mount_point.clone();
+    let task = tokio::spawn(run(app.clone(), spec, mount));
+    let state = app.state::<AppState>();
+    let previous = state
+        .mounts
+        .supervisors
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(mount_point, task);
+    if let Some(previous) = previous {
+        previous.abort();
+    }
+}
+
+/// Stop supervising every mount, before unmounting them on purpose.
+pub fn stop(state: &AppState) {
+    let tasks = std::mem::take(
+        &mut *state
+            .mounts
+            .supervisors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    for task in tasks.into_values() {
+        task.abort();
+    }
+}
+
+/// Stop the supervisors and unmount every mount (detaching dead ones).
+/// Fails with every failure, after trying them all.
+pub fn unmount_all(state: &AppState) -> Result<(), String> {
+    stop(state);
+    let statuses = std::mem::take(
+        &mut *state
+            .mounts
+            .status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    let errors: Vec<String> = statuses
+        .into_iter()
+        .filter_map(|(mount_point, status)| match status {
+            MountStatus::Mounted => super::unmount_filesystem(&mount_point).err(),
+            _ => recover_stale_mount(&mount_point).err(),
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+async fn run(app: AppHandle, spec: MountSpec, mut mount: Option<std::thread::JoinHandle<()>>) {
+    let state = app.state::<AppState>();
+    let mut failures = 0;
+    loop {
+        match mount.take() {
+            Some(thread) if !thread.is_finished() => {
+                mount = Some(thread);
+                tokio::time::sleep(WATCH_INTERVAL).await;
+                continue;
+            }
+            // Remount right away
+            Some(thread) => {
+                let message = match thread.join() {
+                    Ok(()) => format!("The {} was unmounted unexpectedly", spec.describe()),
+                    Err(_) => format!("The filesystem of the {} crashed", spec.describe()),
+                };
+                log::error!("FUSE session ended: {}", message);
+                set_status(&app, &state, &spec.mount_point, MountStatus::Error(message)).await;
+                failures = 0;
+            }
+            None => {
+                failures += 1;
+                let delay = retry_delay(failures);
+                log::info!(
+                    "Retrying mount at {} in {}s",
+                    spec.mount_point.display(),
+                    delay.as_secs()
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = state.mounts.retry.notified() => log::info!("Mount retry requested"),
+                }
+            }
+        }
+
+        if !*state.is_authenticated.read().await {
+            return;
+        }
+        mount = self::mount(&app, &state, &spec).await;
+        if mount.is_some() {
+            failures = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_mount_errors_are_told_apart() {
+        let dead = std::io::Error::from_raw_os_error(libc::ENOTCONN);
+        assert!(is_dead_mount_error(&dead));
+        let missing = std::io::Error::from_raw_os_error(libc::ENOENT);
+        assert!(!is_dead_mount_error(&missing));
+
+        let dir = std::env::temp_dir();
+        assert_eq!(recover_stale_mount(&dir), Ok(false));
+    }
+
+    #[test]
+    fn overall_status_shows_failures_first() {
+        let error = MountStatus::Error("FUSE mount failed".to_string());
+        assert_eq!(overall_status([]), MountStatus::Unmounted);
+        assert_eq!(
+            overall_status(&[MountStatus::Mounted, MountStatus::Unmounted]),
+            MountStatus::Mounted
+        );
+        assert_eq!(
+            overall_status(&[MountStatus::Mounted, MountStatus::Mounting]),
+            MountStatus::Mounting
+        );
+        assert_eq!(
+            overall_status(&[MountStatus::Mounted, error.clone(), MountStatus::Mounting]),
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn mounts_share_one_coordinator_until_logout() {
+        let mounts = Mounts::default();
+        let first = mounts.publish_coordinator(false);
+        let second = mounts.publish_coordinator(true);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(mounts.replayer().is_none());
+
+        mounts.clear().await;
+        assert!(!Arc::ptr_eq(&first, &mounts.publish_coordinator(false)));
+    }
+
+    #[test]
+    fn retry_delay_backs_off_to_a_cap() {
+        assert_eq!(retry_delay(1), Duration::from_secs(5));
+        assert_eq!(retry_delay(2), Duration::from_secs(10));
+        assert_eq!(retry_delay(4), Duration::from_secs(40));
+        assert_eq!(retry_delay(7), RETRY_MAX);
+        assert_eq!(retry_delay(u32::MAX), RETRY_MAX);
+    }
+}
@@ -0,0 +1,368 @@
+//! Encrypt-while-uploading of saved file content.
+//!
+//! `release()` hands the closed temp file to a background upload instead of
+//! reading it into memory. Content is read from the temp file, encrypted and
+//! sent piece by piece, so peak memory stays at a few chunks regardless of
+//! file size: CTR content goes out as one streaming request body, chunked
+//! content as one request per FastCDC chunk.
+
+use std::fs::File;
+use std::io::{self, Chain, Cursor, Read, Take};
+use std::sync::Arc;
+
+use tokio::sync::oneshot;
+use zeroize::Zeroizing;
+
+use crate::crypto::aes_ctr::{CtrChunkEncryptor, CTR_CHUNK_SIZE};
+use crate::crypto::aes_gcm_seg::{GCM_SEG_NONCE_PREFIX_SIZE, GCM_SEG_SEGMENT_SIZE};
+use crate::crypto::chunked::EncryptedChunk;
+use crate::crypto::fastcdc::StreamChunker;
+use crate::crypto::keys::FileKey;
+use crate::crypto::padding::PaddingScheme;
+use crate::fuse::file_handle::OpenFileHandle;
+
+/// Temp file content followed by its padding tail.
+pub type PaddedReader = Chain<Take<File>, Cursor<Vec<u8>>>;
+
+/// Open `source`'s temp file for upload. Returns the reader and the padded length.
+pub fn padded_reader(
+    source: &OpenFileHandle,
+    padding: PaddingScheme,
+) -> Result<(PaddedReader, u64), String> {
+    let file = source.open_reader()?;
+    let len = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat temp file: {}", e))?
+        .len();
+    let tail = match padding {
+        PaddingScheme::None => Vec::new(),
+        scheme => crate::crypto::padding::content_padding_tail(len as usize, scheme),
+    };
+    let padded_len = len + tail.len() as u64;
+    Ok((file.take(len).chain(Cursor::new(tail)), padded_len))
+}
+
+/// Streaming request body with the AES-256-CTR encryption of `source`.
+///
+/// Returns the body, its exact length, and a receiver for the chunk hashes
+/// that resolves once the whole body has been produced. The body holds a
+/// reference to `source`, so the temp file outlives the upload.
+pub fn ctr_upload_body(
+    source: Arc<OpenFileHandle>,
+    file_key: &FileKey,
+    iv: &[u8; 16],
+    padding: PaddingScheme,
+) -> Result<(reqwest::Body, u64, oneshot::Receiver<Vec<String>>), String> {
+    let (state, len, hashes_rx) = CtrStream::new(source, file_key, iv, padding)?;
+
+    // File reads and AES run on the blocking pool, one chunk at a time, so
+    // the body never gets more than a chunk ahead of the network.
+    let stream = futures_util::stream::try_unfold(state, |mut state| async move {
+        tokio::task::spawn_blocking(move || -> io::Result<Option<(Vec<u8>, CtrStream)>> {
+            Ok(state.next_chunk()?.map(|chunk| (chunk, state)))
+        })
+        .await
+        .map_err(io::Error::other)?
+    });
+    Ok((reqwest::Body::wrap_stream(stream), len, hashes_rx))
+}
+
+/// State of a CTR upload body.
+struct CtrStream {
+    reader: PaddedReader,
+    encryptor: Option<CtrChunkEncryptor>,
+    hashes_tx: Option<oneshot::Sender<Vec<String>>>,
+    _source: Arc<OpenFileHandle>,
+}
+
+impl CtrStream {
+    fn new(
+        source: Arc<OpenFileHandle>,
+        file_key: &FileKey,
+        iv: &[u8; 16],
+        padding: PaddingScheme,
+    ) -> Result<(Self, u64, oneshot::Receiver<Vec<String>>), String> {
+        let (reader, len) = padded_reader(&source, padding)?;
+        let (hashes_tx, hashes_rx) = oneshot::channel();
+        let state = Self {
+            reader,
+            encryptor: Some(CtrChunkEncryptor::new(file_key.as_bytes(), iv)),
+            hashes_tx: Some(hashes_tx),
+            _source: source,
+        };
+        Ok((state, len, hashes_rx))
+    }
+
+    /// The next `CTR_CHUNK_SIZE` chunk of ciphertext, or `None` at the end.
+    fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(encryptor) = self.encryptor.as_mut() else {
+            return Ok(None);
+        };
+
+        let mut chunk = Zeroizing::new(vec![0u8; CTR_CHUNK_SIZE]);
+        let n = read_full(&mut self.reader, &mut chunk)?;
+        chunk.truncate(n);
+        if n > 0 {
+            encryptor
+                .encrypt_chunk(&mut chunk)
+                .map_err(io::Error::other)?;
+        }
+
+        if n < CTR_CHUNK_SIZE {
+            if let (Some(encryptor), Some(tx)) = (self.encryptor.take(), self.hashes_tx.take()) {
+                let _ = tx.send(encryptor.finish());
+            }
+        }
+        // `chunk` now holds ciphertext only
+        Ok((n > 0).then(|| std::mem::take(&mut *chunk)))
+    }
+}
+
+/// Streaming request body with the GCM-SEG encryption of `source`.
+///
+/// Returns the body and its exact length. Like `ctr_upload_body`, the body
+/// holds a reference to `source` and is produced a segment at a time.
+pub fn gcm_seg_upload_body(
+    source: Arc<OpenFileHandle>,
+    file_key: &FileKey,
+    nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+    padding: PaddingScheme,
+) -> Result<(reqwest::Body, u64), String> {
+    let (state, len) = GcmSegStream::new(source, file_key, nonce_prefix, padding)?;
+
+    let stream = futures_util::stream::try_unfold(state, |mut state| async move {
+        tokio::task::spawn_blocking(move || -> io::Result<Option<(Vec<u8>, GcmSegStream)>> {
+            Ok(state.next_segment()?.map(|segment| (segment, state)))
+        })
+        .await
+        .map_err(io::Error::other)?
+    });
+    Ok((reqwest::Body::wrap_stream(stream), len))
+}
+
+/// State of a GCM-SEG upload body.
+struct GcmSegStream {
+    reader: PaddedReader,
+    file_key: FileKey,
+    nonce_prefix: [u8; GCM_SEG_NONCE_PREFIX_SIZE],
+    index: u64,
+    /// Plaintext of the next segment, read ahead to tell whether the current
+    /// one is the last.
+    next: Option<Zeroizing<Vec<u8>>>,
+    done: bool,
+    _source: Arc<OpenFileHandle>,
+}
+
+impl GcmSegStream {
+    fn new(
+        source: Arc<OpenFileHandle>,
+        file_key: &FileKey,
+        nonce_prefix: &[u8; GCM_SEG_NONCE_PREFIX_SIZE],
+        padding: PaddingScheme,
+    ) -> Result<(Self, u64), String> {
+        let (reader, len) = padded_reader(&source, padding)?;
+        let state = Self {
+            reader,
+            file_key: file_key.clone(),
+            nonce_prefix: *nonce_prefix,
+            index: 0,
+            next: None,
+            done: false,
+            _source: source,
+        };
+        Ok((state, crate::crypto::aes_gcm_seg::encrypted_size(len)))
+    }
+
+    fn read_segment(&mut self) -> io::Result<Zeroizing<Vec<u8>>> {
+        let mut segment = Zeroizing::new(vec![0u8; GCM_SEG_SEGMENT_SIZE]);
+        let n = read_full(&mut self.reader, &mut segment)?;
+        segment.truncate(n);
+        Ok(segment)
+    }
+
+    /// The next sealed segment, or `None` after the last one.
+    fn next_segment(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+        let current = match self.next.take() {
+            Some(segment) => segment,
+            None => self.read_segment()?,
+        };
+        // A short segment is always the last one; a full one is the last
+        // only if nothing follows it.
+        let is_last = if current.len() == GCM_SEG_SEGMENT_SIZE {
+            let next = self.read_segment()?;
+            let is_last = next.is_empty();
+            self.next = (!is_last).then_some(next);
+            is_last
+        } else {
+            true
+        };
+
+        let sealed = crate::crypto::aes_gcm_seg::encrypt_segment(
+            &current,
+            self.file_key.as_bytes(),
+            &self.nonce_prefix,
+            self.index,
+            is_last,
+        )
+        .map_err(io::Error::other)?;
+        self.index += 1;
+        self.done = is_last;
+        Ok(Some(sealed))
+    }
+}
+
+/// FastCDC chunks of `source`, sealed as they are read.
+pub struct ChunkSource {
+    /// Taken while a chunk is being produced on the blocking pool.
+    chunker: Option<StreamChunker<PaddedReader>>,
+    file_key: FileKey,
+    _source: Arc<OpenFileHandle>,
+}
+
+impl ChunkSource {
+    pub fn new(
+        source: Arc<OpenFileHandle>,
+        file_key: FileKey,
+        padding: PaddingScheme,
+    ) -> Result<Self, String> {
+        let (reader, _) = padded_reader(&source, padding)?;
+        Ok(Self {
+            chunker: Some(StreamChunker::new(reader)),
+            file_key,
+            _source: source,
+        })
+    }
+
+    /// The next sealed chunk, or `None` at the end of the file.
+    pub async fn next_chunk(&mut self) -> Result<Option<EncryptedChunk>, String> {
+        let mut chunker = self
+            .chunker
+            .take()
+            .ok_or_else(|| "Chunk source already failed".to_string())?;
+        let file_key = self.file_key.clone();
+        let (next, chunker) = tokio::task::spawn_blocking(move || {
+            let next = match chunker.next_chunk() {
+                Ok(Some(chunk)) => crate::crypto::chunked::encrypt_chunk(&chunk, &file_key)
+                    .map(Some)
+                    .map_err(|e| format!("File encryption failed: {}", e)),
+                Ok(None) => Ok(None),
+                Err(e) => Err(format!("Failed to read temp file: {}", e)),
+            };
+            (next, chunker)
+        })
+        .await
+        .map_err(|e| format!("Chunk encryption task failed: {}", e))?;
+        self.chunker = Some(chunker);
+        next
+    }
+}
+
+/// Read until `buf` is full or the reader is exhausted. Returns the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_source(name: &str, content: &[u8]) -> Arc<OpenFileHandle> {
+        let temp_dir = std::env::temp_dir().join(name);
+        Arc::new(OpenFileHandle::new_write(1, libc::O_RDWR, &temp_dir, Some(content)).unwrap())
+    }
+
+    #[test]
+    fn ctr_stream_matches_one_shot_encryption() {
+        let content: Vec<u8> = (0..2 * CTR_CHUNK_SIZE + 1000).map(|i| (i % 251) as u8).collect();
+        let source = temp_source("cipherbox-test-upload-ctr", &content);
+        let file_key = FileKey::generate();
+        let iv = crate::crypto::utils::generate_ctr_iv();
+
+        for padding in [PaddingScheme::None, PaddingScheme::Padme] {
+            let (mut stream, len, hashes_rx) =
+                CtrStream::new(source.clone(), &file_key, &iv, padding).unwrap();
+            let mut streamed = Vec::new();
+            while let Some(chunk) = stream.next_chunk().unwrap() {
+                assert!(chunk.len() <= CTR_CHUNK_SIZE);
+                streamed.extend_from_slice(&chunk);
+            }
+
+            let padded = match padding {
+                PaddingScheme::None => Zeroizing::new(content.clone()),
+                scheme => crate::crypto::padding::pad_content(&content, scheme),
+            };
+            let expected =
+                crate::crypto::aes_ctr::encrypt_aes_ctr(&padded, file_key.as_bytes(), &iv).unwrap();
+            assert_eq!(len, expected.len() as u64);
+            assert_eq!(streamed, expected);
+            assert_eq!(
+                hashes_rx.blocking_recv().unwrap(),
+                crate::crypto::aes_ctr::ctr_chunk_hashes(&expected)
+            );
+        }
+    }
+
+    #[test]
+    fn gcm_seg_stream_matches_stream_encryption() {
+        let content: Vec<u8> = (0..3 * GCM_SEG_SEGMENT_SIZE).map(|i| (i % 251) as u8).collect();
+        let source = temp_source("cipherbox-test-upload-gcm-seg", &content);
+        let file_key = FileKey::generate();
+        let nonce_prefix = crate::crypto::aes_gcm_seg::generate_nonce_prefix();
+
+        for padding in [PaddingScheme::None, PaddingScheme::Padme] {
+            let (mut stream, len) =
+                GcmSegStream::new(source.clone(), &file_key, &nonce_prefix, padding).unwrap();
+            let mut streamed = Vec::new();
+            while let Some(segment) = stream.next_segment().unwrap() {
+                streamed.extend_from_slice(&segment);
+            }
+
+            let padded = match padding {
+                PaddingScheme::None => Zeroizing::new(content.clone()),
+                scheme => crate::crypto::padding::pad_content(&content, scheme),
+            };
+            let mut expected = Vec::new();
+            crate::crypto::aes_gcm_seg::encrypt_aes_gcm_seg_stream(
+                &padded[..],
+                &mut expected,
+                file_key.as_bytes(),
+                &nonce_prefix,
+            )
+            .unwrap();
+            assert_eq!(len, expected.len() as u64);
+            assert_eq!(streamed, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn chunk_source_matches_in_memory_chunking() {
+        let content = crate::crypto::utils::generate_random_bytes(5 * 1024 * 1024 + 7);
+        let source = temp_source("cipherbox-test-upload-chunks", &content);
+        let file_key = FileKey::generate();
+
+        let mut chunks = ChunkSource::new(source, file_key.clone(), PaddingScheme::None).unwrap();
+        let mut streamed = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().await.unwrap() {
+            streamed.push(chunk);
+        }
+
+        let expected = crate::crypto::chunked::encrypt_chunks(&content, &file_key).unwrap();
+        assert_eq!(streamed.len(), expected.len());
+        for (a, b) in streamed.iter().zip(&expected) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.size, b.size);
+        }
+    }
+}
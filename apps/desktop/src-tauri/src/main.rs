@@ -11,7 +11,7 @@ mod sync;
 mod tray;
 
 use tauri::{Manager, WindowEvent};
-use state::AppState;
+use state::{AppState, Settings};
 
 /// CLI arguments for debug builds only.
 /// Allows bypassing Web3Auth login with a hex-encoded secp256k1 private key.
@@ -25,6 +25,10 @@ mod cli {
         /// Hex-encoded secp256k1 private key for headless auth (debug only)
         #[arg(long)]
         pub dev_key: Option<String>,
+
+        /// Mount the vault read-only (also CIPHERBOX_READ_ONLY=1)
+        #[arg(long)]
+        pub read_only: bool,
     }
 }
 
@@ -35,26 +39,176 @@ fn main() {
 
     env_logger::init();
     log::info!("CipherBox Desktop starting...");
+    crypto::secure_mem::log_memlock_limit(fuse::cache::MAX_CACHE_SIZE);
 
-    // Parse CLI args (debug builds only: --dev-key <hex>)
+    // Parse CLI args (debug builds only: --dev-key <hex>). Release builds get
+    // deep-link URLs as arguments, so only look for --read-only there.
     #[cfg(debug_assertions)]
-    let dev_key: Option<String> = {
+    let (dev_key, read_only_flag): (Option<String>, bool) = {
         use clap::Parser;
         let args = cli::Args::parse();
         if args.dev_key.is_some() {
             log::info!("--dev-key provided: headless auth mode enabled");
         }
-        args.dev_key
+        (args.dev_key, args.read_only)
     };
     #[cfg(not(debug_assertions))]
-    let dev_key: Option<String> = None;
+    let (dev_key, read_only_flag): (Option<String>, bool) =
+        (None, std::env::args().skip(1).any(|arg| arg == "--read-only"));
 
     // API base URL: CIPHERBOX_API_URL > VITE_API_URL > localhost default
     let api_base_url = std::env::var("CIPHERBOX_API_URL")
         .or_else(|_| std::env::var("VITE_API_URL"))
         .unwrap_or_else(|_| "http://localhost:3000".to_string());
 
-    let app_state = AppState::new(&api_base_url, dev_key);
+    // Delegated IPNS routing (e.g. https://delegated-ipfs.dev): when set, IPNS
+    // records are fetched and signature-verified locally instead of trusting
+    // the backend's resolve endpoint.
+    let ipns_routing_url = std::env::var("CIPHERBOX_IPNS_ROUTING_URL").ok();
+    if let Some(ref url) = ipns_routing_url {
+        log::info!("Verified IPNS resolution via delegated routing: {}", url);
+    }
+
+    // Length padding (opt-in): CIPHERBOX_PADDING=padme|pow2 pads file content
+    // and folder and file metadata (either envelope) so ciphertext sizes don't
+    // reveal exact plaintext sizes.
+    let padding = match std::env::var("CIPHERBOX_PADDING") {
+        Ok(name) => crypto::padding::PaddingScheme::from_name(&name).unwrap_or_else(|e| {
+            log::warn!("{}; uploads will not be padded", e);
+            crypto::padding::PaddingScheme::None
+        }),
+        Err(_) => crypto::padding::PaddingScheme::None,
+    };
+    if padding != crypto::padding::PaddingScheme::None {
+        log::info!("Length padding enabled: {}", padding.as_str());
+    }
+
+    // Metadata envelope: CIPHERBOX_METADATA_ENVELOPE=v2 binds folder and file
+    // metadata to their IPNS names. The web app reads only v1 so far, so v1
+    // stays the default. Until then the metadata swap attack stays open by
+    // default: v2 is never written unless this is set, and a v1 envelope is
+    // still accepted for any name not yet read in v2.
+    // TODO: read v2 envelopes in packages/crypto (and the web app), then make
+    // v2 the default here.
+    let metadata_format = match std::env::var("CIPHERBOX_METADATA_ENVELOPE") {
+        Ok(version) => crypto::folder::MetadataFormat::from_name(&version, padding).unwrap_or_else(|e| {
+            log::warn!("{}; writing v1 metadata envelopes", e);
+            crypto::folder::MetadataFormat::Legacy { padding }
+        }),
+        Err(_) => crypto::folder::MetadataFormat::Legacy { padding },
+    };
+    if matches!(metadata_format, crypto::folder::MetadataFormat::V2 { .. }) {
+        log::info!("Writing v2 metadata envelopes");
+    }
+
+    // Chunked uploads (opt-in): CIPHERBOX_CHUNKED=1 stores large files as
+    // FastCDC chunks, so later versions only upload the chunks that changed.
+    // The web app cannot read chunked files yet.
+    let chunked_uploads = matches!(
+        std::env::var("CIPHERBOX_CHUNKED").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    if chunked_uploads {
+        log::info!("Chunked uploads enabled for large files");
+    }
+
+    // Segmented AES-GCM (opt-in): CIPHERBOX_GCM_SEG=1 stores large files that
+    // are not chunked as GCM-SEG, encrypted while they upload instead of in
+    // memory. The web app cannot read GCM-SEG files yet.
+    let gcm_seg_uploads = matches!(
+        std::env::var("CIPHERBOX_GCM_SEG").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    if gcm_seg_uploads {
+        log::info!("GCM-SEG uploads enabled for large files");
+    }
+
+    // Hybrid key wrapping (opt-in): CIPHERBOX_HYBRID_WRAP=1 gives the vault an
+    // ML-KEM-768 key and wraps new folder keys with it too, once the vault
+    // secret sealing it has been exported and confirmed. The web app and
+    // recovery.html cannot unwrap hybrid keys yet; make this the default only
+    // once they can.
+    let hybrid_wrap = matches!(
+        std::env::var("CIPHERBOX_HYBRID_WRAP").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    if hybrid_wrap {
+        log::info!("Hybrid ECIES + ML-KEM-768 key wrapping enabled");
+    }
+
+    // On-disk cache of encrypted content: CIPHERBOX_DISK_CACHE_MB sets the size
+    // budget in MiB (0 disables it).
+    let disk_cache_budget = match std::env::var("CIPHERBOX_DISK_CACHE_MB") {
+        Ok(mb) => match mb.trim().parse::<u64>() {
+            Ok(mb) => mb * 1024 * 1024,
+            Err(_) => {
+                log::warn!("Invalid CIPHERBOX_DISK_CACHE_MB {:?}; using the default", mb);
+                fuse::cache::DEFAULT_DISK_CACHE_SIZE
+            }
+        },
+        Err(_) => fuse::cache::DEFAULT_DISK_CACHE_SIZE,
+    };
+
+    // Mount: CIPHERBOX_MOUNT_POINT (absolute, or starting with ~/) replaces
+    // ~/CipherBox; CIPHERBOX_ALLOW_OTHER=1 lets other users access the mount
+    // (Linux, needs user_allow_other in /etc/fuse.conf);
+    // CIPHERBOX_FOLDER_MOUNTS="Projects/ClientX=/srv/clientx;..." also mounts
+    // single vault folders on their own.
+    let mut mount = fuse::profile::MountConfig::default();
+    if let Ok(path) = std::env::var("CIPHERBOX_MOUNT_POINT") {
+        match fuse::profile::parse_mount_point(&path, dirs::home_dir().as_deref()) {
+            Ok(path) => mount.mount_point = path,
+            Err(e) => log::warn!("{}; mounting at {}", e, mount.mount_point.display()),
+        }
+    }
+    mount.allow_other = matches!(
+        std::env::var("CIPHERBOX_ALLOW_OTHER").as_deref(),
+        Ok("1") | Ok("true")
+    );
+    // Read-only: --read-only or CIPHERBOX_READ_ONLY=1 mounts everything `ro`
+    // and never publishes to the vault
+    mount.read_only = read_only_flag
+        || matches!(
+            std::env::var("CIPHERBOX_READ_ONLY").as_deref(),
+            Ok("1") | Ok("true")
+        );
+    if mount.read_only {
+        log::info!("Read-only mode: the vault will not be modified");
+    }
+    if let Ok(folders) = std::env::var("CIPHERBOX_FOLDER_MOUNTS") {
+        match fuse::profile::parse_folder_mounts(
+            &folders,
+            dirs::home_dir().as_deref(),
+            &mount.mount_point,
+        ) {
+            Ok(folders) => mount.folders = folders,
+            Err(e) => log::warn!("{}; mounting no folders on their own", e),
+        }
+    }
+    for spec in mount.mounts() {
+        log::info!("Mount point of the {}: {}", spec.describe(), spec.mount_point.display());
+    }
+
+    // Detach dead mounts left behind by a crash, so the mount points aren't
+    // failing with ENOTCONN until the next sign-in
+    #[cfg(feature = "fuse")]
+    for spec in mount.mounts() {
+        if let Err(e) = fuse::supervisor::recover_stale_mount(&spec.mount_point) {
+            log::warn!("{}", e);
+        }
+    }
+
+    let settings = Settings {
+        ipns_routing_url,
+        padding,
+        metadata_format,
+        chunked_uploads,
+        gcm_seg_uploads,
+        hybrid_wrap,
+        disk_cache_budget,
+        mount,
+    };
+    let app_state = AppState::new(&api_base_url, settings, dev_key);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_deep_link::init())
@@ -128,6 +282,9 @@ fn main() {
                     commands::try_silent_refresh,
                     commands::logout,
                     commands::start_sync_daemon,
+                    commands::export_vault_secret,
+                    commands::confirm_vault_secret,
+                    commands::import_vault_secret,
                     commands::get_dev_key,
                     commands::handle_test_login_complete,
                 ]
@@ -140,6 +297,9 @@ fn main() {
                     commands::try_silent_refresh,
                     commands::logout,
                     commands::start_sync_daemon,
+                    commands::export_vault_secret,
+                    commands::confirm_vault_secret,
+                    commands::import_vault_secret,
                 ]
             }
         })
@@ -17,6 +17,8 @@ use base64::Engine;
 use crate::api::client::ApiClient;
 use crate::api::ipns::IpnsPublishRequest;
 use crate::crypto;
+use crate::crypto::keys::Secp256k1SecretKey;
+use crate::crypto::padding::PaddingScheme;
 use types::{DeviceAuthStatus, DeviceEntry, DevicePlatform, DeviceRegistry};
 
 /// Register this desktop device in the encrypted device registry.
@@ -26,15 +28,16 @@ use types::{DeviceAuthStatus, DeviceEntry, DevicePlatform, DeviceRegistry};
 /// 2. Try to resolve existing registry from IPNS
 /// 3. Build device entry for this desktop
 /// 4. Update or create registry with the device entry
-/// 5. Encrypt registry with user's public key (ECIES)
+/// 5. Pad the registry JSON (if enabled) and encrypt with user's public key (ECIES)
 /// 6. Upload to IPFS and publish IPNS record
 ///
 /// This function should be called via `tokio::spawn` so failures never block login.
 pub async fn register_device(
     api: &Arc<ApiClient>,
-    private_key: &[u8; 32],
+    private_key: &Secp256k1SecretKey,
     public_key: &[u8],
     _user_id: &str,
+    padding: PaddingScheme,
 ) -> Result<(), String> {
     // 1. Derive registry IPNS keypair via HKDF
     let (reg_ipns_priv, _reg_ipns_pub, reg_ipns_name) =
@@ -89,9 +92,11 @@ pub async fn register_device(
     }
     registry.sequence_number += 1;
 
-    // 5. Encrypt registry with user's public key (ECIES)
+    // 5. Encrypt registry with user's public key (ECIES). Padding is trailing
+    //    whitespace, so readers parse the JSON unchanged.
     let registry_json = serde_json::to_vec(&registry)
         .map_err(|e| format!("Registry serialization failed: {}", e))?;
+    let registry_json = crypto::padding::pad_json(&registry_json, padding);
     let encrypted = crypto::ecies::wrap_key(&registry_json, public_key)
         .map_err(|e| format!("Registry encryption failed: {}", e))?;
 
@@ -99,12 +104,9 @@ pub async fn register_device(
     let cid = crate::api::ipfs::upload_content(api, &encrypted).await?;
 
     // 7. Create and publish IPNS record
-    let reg_ipns_priv_arr: [u8; 32] = reg_ipns_priv.as_slice()
-        .try_into()
-        .map_err(|_| "Invalid registry IPNS key length".to_string())?;
     let value = format!("/ipfs/{}", cid);
     let record =
-        crypto::ipns::create_ipns_record(&reg_ipns_priv_arr, &value, registry.sequence_number, 86_400_000)
+        crypto::ipns::create_ipns_record(&reg_ipns_priv, &value, registry.sequence_number, 86_400_000)
             .map_err(|e| format!("IPNS record creation failed: {}", e))?;
     let marshaled = crypto::ipns::marshal_ipns_record(&record)
         .map_err(|e| format!("IPNS record marshaling failed: {}", e))?;
@@ -130,7 +132,7 @@ pub async fn register_device(
 async fn fetch_and_decrypt_registry(
     api: &ApiClient,
     ipns_name: &str,
-    private_key: &[u8; 32],
+    private_key: &Secp256k1SecretKey,
 ) -> Result<DeviceRegistry, String> {
     let resolve = crate::api::ipns::resolve_ipns(api, ipns_name).await?;
     let encrypted = crate::api::ipfs::fetch_content(api, &resolve.cid).await?;
@@ -140,7 +142,7 @@ async fn fetch_and_decrypt_registry(
 }
 
 /// Generate a random UUID v4 string.
-fn generate_uuid_v4() -> String {
+pub(crate) fn generate_uuid_v4() -> String {
     let bytes = crypto::utils::generate_random_bytes(16);
     format!(
         "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-4{:01x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
@@ -196,7 +198,7 @@ fn get_or_create_device_id() -> String {
 }
 
 /// Get the device name from the system hostname.
-fn get_device_name() -> String {
+pub fn get_device_name() -> String {
     hostname::get()
         .map(|h| h.to_string_lossy().to_string())
         .unwrap_or_else(|_| "CipherBox Desktop".to_string())
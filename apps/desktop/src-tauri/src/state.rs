@@ -9,6 +9,10 @@ use zeroize::Zeroize;
 
 use crate::api::client::ApiClient;
 use crate::api::types::TeeKeysResponse;
+use crate::crypto::keys::{FolderKey, IpnsSigningKey, MlKemSecretKey, Secp256k1SecretKey};
+use crate::crypto::folder::MetadataFormat;
+use crate::crypto::padding::PaddingScheme;
+use crate::fuse::profile::MountConfig;
 
 /// Channel sender type for triggering manual sync from the tray menu.
 pub type SyncTrigger = tokio::sync::mpsc::Sender<()>;
@@ -22,6 +26,26 @@ pub enum MountStatus {
     Error(String),
 }
 
+/// Settings read once at startup (see `main`), stored on `AppState`.
+pub struct Settings {
+    /// Delegated IPNS routing URL (`CIPHERBOX_IPNS_ROUTING_URL`).
+    pub ipns_routing_url: Option<String>,
+    /// Length padding of uploaded content (`CIPHERBOX_PADDING`).
+    pub padding: PaddingScheme,
+    /// Envelope of published metadata (`CIPHERBOX_METADATA_ENVELOPE`).
+    pub metadata_format: MetadataFormat,
+    /// Whether large files are uploaded chunked (`CIPHERBOX_CHUNKED`).
+    pub chunked_uploads: bool,
+    /// Whether large files are uploaded as GCM-SEG (`CIPHERBOX_GCM_SEG`).
+    pub gcm_seg_uploads: bool,
+    /// Whether new folder keys may be hybrid-wrapped (`CIPHERBOX_HYBRID_WRAP`).
+    pub hybrid_wrap: bool,
+    /// On-disk content cache budget in bytes (`CIPHERBOX_DISK_CACHE_MB`).
+    pub disk_cache_budget: u64,
+    /// Mount points, folder mounts and read-only mode.
+    pub mount: MountConfig,
+}
+
 /// Thread-safe application state shared across Tauri commands.
 ///
 /// All sensitive key material is stored in memory only and zeroed on logout.
@@ -31,20 +55,24 @@ pub struct AppState {
     pub api: Arc<ApiClient>,
 
     /// 32-byte secp256k1 private key (memory only, never persisted).
-    pub private_key: RwLock<Option<Vec<u8>>>,
+    pub private_key: RwLock<Option<Secp256k1SecretKey>>,
 
     /// 65-byte uncompressed secp256k1 public key (0x04 prefix).
     pub public_key: RwLock<Option<Vec<u8>>>,
 
+    /// ML-KEM-768 private key for unwrapping hybrid-wrapped keys, unwrapped
+    /// from the vault (memory only, never persisted).
+    pub mlkem_private_key: RwLock<Option<MlKemSecretKey>>,
+
     /// 32-byte AES-256 root folder encryption key.
-    pub root_folder_key: RwLock<Option<Vec<u8>>>,
+    pub root_folder_key: RwLock<Option<FolderKey>>,
 
     /// Root folder IPNS name (base36 CIDv1 string, e.g., k51...).
     pub root_ipns_name: RwLock<Option<String>>,
 
     /// Decrypted 32-byte Ed25519 IPNS private key for signing root folder metadata updates.
     /// Memory only, never persisted to disk.
-    pub root_ipns_private_key: RwLock<Option<Vec<u8>>>,
+    pub root_ipns_private_key: RwLock<Option<IpnsSigningKey>>,
 
     /// Authenticated user ID (JWT `sub` claim).
     pub user_id: RwLock<Option<String>>,
@@ -55,7 +83,8 @@ pub struct AppState {
     /// Whether the user is fully authenticated with vault keys decrypted.
     pub is_authenticated: RwLock<bool>,
 
-    /// Current FUSE mount status.
+    /// Current FUSE mount status, of all mounts taken together
+    /// (`fuse::supervisor::overall_status`).
     pub mount_status: RwLock<MountStatus>,
 
     /// Channel sender to trigger an immediate sync cycle from the tray "Sync Now" button.
@@ -63,18 +92,74 @@ pub struct AppState {
     /// menu event handler is synchronous.
     pub sync_trigger: std::sync::RwLock<Option<SyncTrigger>>,
 
+    /// Length padding applied to uploaded file content.
+    /// Set once at startup from `CIPHERBOX_PADDING`.
+    pub padding: PaddingScheme,
+
+    /// Envelope of published folder and file metadata, padded like content.
+    /// Set once at startup from `CIPHERBOX_METADATA_ENVELOPE` and `CIPHERBOX_PADDING`.
+    pub metadata_format: MetadataFormat,
+
+    /// Whether large files are uploaded chunked ("CHUNKED") instead of as
+    /// single-shot AES-GCM. Set once at startup from `CIPHERBOX_CHUNKED`.
+    pub chunked_uploads: bool,
+
+    /// Whether large files that are not chunked are uploaded as segmented
+    /// AES-GCM ("GCM-SEG") instead of single-shot AES-GCM. Set once at
+    /// startup from `CIPHERBOX_GCM_SEG`.
+    pub gcm_seg_uploads: bool,
+
+    /// Whether the vault may get an ML-KEM key and new folder keys be
+    /// hybrid-wrapped with it, once the vault secret export is confirmed.
+    /// Set once at startup from `CIPHERBOX_HYBRID_WRAP`.
+    pub hybrid_wrap: bool,
+
+    /// Whether the user exported the vault secret on this device and confirmed
+    /// it (`commands::confirm_vault_secret`). Read from the Keychain at login.
+    pub vault_secret_confirmed: RwLock<bool>,
+
+    /// Size budget in bytes of the on-disk encrypted content cache (0 disables it).
+    /// Set once at startup from `CIPHERBOX_DISK_CACHE_MB`.
+    pub disk_cache_budget: u64,
+
+    /// Where and how the vault (and any folders mounted on their own) is mounted,
+    /// and whether read-only. Set once at startup from `CIPHERBOX_MOUNT_POINT`,
+    /// `CIPHERBOX_ALLOW_OTHER`, `CIPHERBOX_FOLDER_MOUNTS` and `CIPHERBOX_READ_ONLY`
+    /// (or `--read-only`).
+    pub mount: MountConfig,
+
+    /// The mounts: their status and supervisors (`fuse::supervisor`), the
+    /// watch list of each and the vault's journal for the sync daemon.
+    #[cfg(feature = "fuse")]
+    pub mounts: crate::fuse::supervisor::Mounts,
+
+    /// Saves that failed in any mount, shown in the tray by the sync daemon.
+    pub save_failures: Arc<crate::sync::SaveFailures>,
+
     /// Hex-encoded secp256k1 private key for headless auth (debug builds only).
     /// Set via `--dev-key <hex>` CLI argument. Compiled out in release builds.
     pub dev_key: RwLock<Option<String>>,
 }
 
 impl AppState {
-    /// Create a new AppState with the given API base URL and optional dev key.
-    pub fn new(api_base_url: &str, dev_key: Option<String>) -> Self {
+    /// Create a new AppState with the given API base URL, startup settings
+    /// and optional dev key.
+    pub fn new(api_base_url: &str, settings: Settings, dev_key: Option<String>) -> Self {
+        let Settings {
+            ipns_routing_url,
+            padding,
+            metadata_format,
+            chunked_uploads,
+            gcm_seg_uploads,
+            hybrid_wrap,
+            disk_cache_budget,
+            mount,
+        } = settings;
         Self {
-            api: Arc::new(ApiClient::new(api_base_url)),
+            api: Arc::new(ApiClient::new(api_base_url).with_ipns_routing_url(ipns_routing_url)),
             private_key: RwLock::new(None),
             public_key: RwLock::new(None),
+            mlkem_private_key: RwLock::new(None),
             root_folder_key: RwLock::new(None),
             root_ipns_name: RwLock::new(None),
             root_ipns_private_key: RwLock::new(None),
@@ -83,36 +168,47 @@ impl AppState {
             is_authenticated: RwLock::new(false),
             mount_status: RwLock::new(MountStatus::Unmounted),
             sync_trigger: std::sync::RwLock::new(None),
+            padding,
+            metadata_format,
+            chunked_uploads,
+            gcm_seg_uploads,
+            hybrid_wrap,
+            vault_secret_confirmed: RwLock::new(false),
+            disk_cache_budget,
+            mount,
+            #[cfg(feature = "fuse")]
+            mounts: Default::default(),
+            save_failures: Default::default(),
             dev_key: RwLock::new(dev_key),
         }
     }
 
+    /// Whether new keys may be hybrid-wrapped: hybrid wrapping is enabled
+    /// (`CIPHERBOX_HYBRID_WRAP`) and the vault secret was exported and confirmed.
+    pub async fn hybrid_wrap_ready(&self) -> bool {
+        self.hybrid_wrap && *self.vault_secret_confirmed.read().await
+    }
+
     /// Zero all sensitive key material and reset authentication state.
     ///
     /// Uses `zeroize` to securely wipe sensitive bytes from memory.
     /// Called on logout and before app exit.
     pub async fn clear_keys(&self) {
-        // Each field uses a single lock acquisition to zeroize and clear.
-        {
-            let mut key = self.private_key.write().await;
-            if let Some(ref mut k) = *key { k.zeroize(); }
-            *key = None;
-        }
+        // Typed secret keys zeroize themselves when dropped.
+        *self.private_key.write().await = None;
+        *self.mlkem_private_key.write().await = None;
+        *self.root_folder_key.write().await = None;
+        *self.root_ipns_private_key.write().await = None;
+        // The mount journal holds the logged-out user's wrapped keys
+        #[cfg(feature = "fuse")]
+        self.mounts.clear().await;
+
+        // Each remaining field uses a single lock acquisition to zeroize and clear.
         {
             let mut key = self.public_key.write().await;
             if let Some(ref mut k) = *key { k.zeroize(); }
             *key = None;
         }
-        {
-            let mut key = self.root_folder_key.write().await;
-            if let Some(ref mut k) = *key { k.zeroize(); }
-            *key = None;
-        }
-        {
-            let mut key = self.root_ipns_private_key.write().await;
-            if let Some(ref mut k) = *key { k.zeroize(); }
-            *key = None;
-        }
 
         // Clear dev key (sensitive: contains private key hex)
         {
@@ -126,8 +222,12 @@ impl AppState {
         *self.user_id.write().await = None;
         *self.tee_keys.write().await = None;
         *self.is_authenticated.write().await = false;
+        *self.vault_secret_confirmed.write().await = false;
 
-        // Clear access token from API client
+        // Clear access token, verified IPNS sequences and v2 envelope names
+        // from API client
         self.api.clear_access_token().await;
+        self.api.ipns_sequences().clear();
+        self.api.envelope_history().clear();
     }
 }
@@ -0,0 +1,642 @@
+//! Durable journal of local vault mutations.
+//!
+//! Every create, write, mkdir, rename and unlink done through the mount is
+//! recorded here before its network side effects run, together with the
+//! IPNS names it still has to be published to. Successful publishes
+//! acknowledge their names (`JournalAck`); whatever is left -- because the
+//! network was down, or the app quit first -- is replayed in order by the sync
+//! daemon once connectivity returns.
+//!
+//! A save is journaled before its content is uploaded, with a sealed copy of
+//! the content kept in the journal's `ContentSpool` until the upload is done,
+//! so content saved offline (or while the app quits) is uploaded on replay.
+//!
+//! Ops are replayed against the *current* remote metadata rather than the
+//! local tree, so they are written to be idempotent: applying an op whose
+//! effect is already visible changes nothing.
+//!
+//! The journal is sealed with AES-256-GCM under the device-local state key and
+//! kept under the user data dir (not the cache dir -- losing it loses changes).
+//! Keys inside ops stay ECIES-wrapped; root keys are never recorded.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::crypto::folder::{FileMetadata, FilePointer, FolderChild, FolderEntry, FolderMetadata};
+use crate::crypto::keys::LocalStateKey;
+use crate::fuse::file_handle::FileBase;
+use crate::sync::spool::ContentSpool;
+
+/// Journal format version.
+pub const JOURNAL_VERSION: u32 = 1;
+
+/// Replay attempts (not counting network failures) before an op is dropped.
+pub const MAX_REPLAY_ATTEMPTS: u32 = 5;
+
+/// A folder an op changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderRef {
+    pub ipns_name: String,
+    /// Hex ECIES-wrapped folder key. `None` for the root, whose keys are
+    /// supplied by the replayer.
+    pub folder_key_encrypted: Option<String>,
+    /// Hex ECIES-wrapped IPNS signing key. `None` for the root.
+    pub ipns_private_key_encrypted: Option<String>,
+}
+
+/// A recorded mutation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum JournalOp {
+    /// New file pointer in `parent`.
+    Create { parent: FolderRef, pointer: FilePointer },
+    /// New content of a file, published to the file's own IPNS record.
+    /// `metadata` is encrypted with `parent`'s folder key.
+    Write {
+        parent: FolderRef,
+        file_ipns_name: String,
+        ipns_private_key_encrypted: String,
+        metadata: FileMetadata,
+        /// Spool id of the content while it is not uploaded yet; `metadata.cid`
+        /// is empty until then.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+        /// Remote revision the save was based on, checked before replay
+        /// publishes it. `None` for new files.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base: Option<FileBase>,
+    },
+    /// New subfolder of `parent`; its own IPNS record starts out empty.
+    Mkdir { parent: FolderRef, entry: FolderEntry },
+    /// Move of `child` (as it was before the move) from `from`/`from_name`
+    /// to `to`/`to_name`, replacing whatever was at the destination.
+    Rename {
+        from: FolderRef,
+        from_name: String,
+        to: FolderRef,
+        to_name: String,
+        child: FolderChild,
+    },
+    /// Removal of the file or folder `name` (with IPNS name `child_ipns_name`).
+    Unlink {
+        parent: FolderRef,
+        name: String,
+        child_ipns_name: String,
+    },
+}
+
+/// IPNS name a folder child is published under.
+pub fn child_ipns_name(child: &FolderChild) -> &str {
+    match child {
+        FolderChild::Folder(entry) => &entry.ipns_name,
+        FolderChild::File(pointer) => &pointer.file_meta_ipns_name,
+    }
+}
+
+/// Name of a folder child.
+pub fn child_name(child: &FolderChild) -> &str {
+    match child {
+        FolderChild::Folder(entry) => &entry.name,
+        FolderChild::File(pointer) => &pointer.name,
+    }
+}
+
+/// Rename a folder child.
+pub fn set_child_name(child: &mut FolderChild, name: &str) {
+    match child {
+        FolderChild::Folder(entry) => entry.name = name.to_string(),
+        FolderChild::File(pointer) => pointer.name = name.to_string(),
+    }
+}
+
+/// Insert `child`, replacing any child with the same IPNS name or name.
+/// Returns whether `metadata` changed.
+fn upsert_child(metadata: &mut FolderMetadata, child: FolderChild) -> bool {
+    let present = metadata.children.iter().any(|c| {
+        child_ipns_name(c) == child_ipns_name(&child) && child_name(c) == child_name(&child)
+    });
+    if present {
+        return false;
+    }
+    metadata.children.retain(|c| {
+        child_ipns_name(c) != child_ipns_name(&child) && child_name(c) != child_name(&child)
+    });
+    metadata.children.push(child);
+    true
+}
+
+impl JournalOp {
+    /// IPNS names this op has to be published to, in publish order.
+    pub fn targets(&self) -> Vec<String> {
+        match self {
+            JournalOp::Create { parent, .. } | JournalOp::Unlink { parent, .. } => {
+                vec![parent.ipns_name.clone()]
+            }
+            JournalOp::Write { file_ipns_name, .. } => vec![file_ipns_name.clone()],
+            JournalOp::Mkdir { parent, entry } => {
+                vec![entry.ipns_name.clone(), parent.ipns_name.clone()]
+            }
+            JournalOp::Rename { from, to, .. } if from.ipns_name == to.ipns_name => {
+                vec![from.ipns_name.clone()]
+            }
+            JournalOp::Rename { from, to, .. } => vec![from.ipns_name.clone(), to.ipns_name.clone()],
+        }
+    }
+
+    /// The folder published under `ipns_name` whose metadata this op edits, if any.
+    pub fn folder(&self, ipns_name: &str) -> Option<&FolderRef> {
+        match self {
+            JournalOp::Create { parent, .. }
+            | JournalOp::Mkdir { parent, .. }
+            | JournalOp::Unlink { parent, .. } => Some(parent),
+            JournalOp::Rename { from, to, .. } => {
+                [from, to].into_iter().find(|f| f.ipns_name == ipns_name)
+            }
+            JournalOp::Write { .. } => None,
+        }
+        .filter(|folder| folder.ipns_name == ipns_name)
+    }
+
+    /// Apply this op to the metadata of the folder published under `ipns_name`.
+    /// Returns whether `metadata` changed.
+    pub fn apply_to_folder(&self, ipns_name: &str, metadata: &mut FolderMetadata) -> bool {
+        if self.folder(ipns_name).is_none() {
+            return false;
+        }
+        match self {
+            JournalOp::Create { pointer, .. } => {
+                upsert_child(metadata, FolderChild::File(pointer.clone()))
+            }
+            JournalOp::Mkdir { entry, .. } => upsert_child(metadata, FolderChild::Folder(entry.clone())),
+            JournalOp::Unlink { name, child_ipns_name: ipns, .. } => {
+                let before = metadata.children.len();
+                metadata
+                    .children
+                    .retain(|c| !(child_name(c) == name && child_ipns_name(c) == ipns));
+                metadata.children.len() != before
+            }
+            JournalOp::Rename { from, to, to_name, child, .. } => {
+                let ipns = child_ipns_name(child);
+                if ipns_name == to.ipns_name {
+                    let mut moved = match metadata.children.iter().find(|c| child_ipns_name(c) == ipns) {
+                        Some(existing) => existing.clone(),
+                        None => child.clone(),
+                    };
+                    set_child_name(&mut moved, to_name);
+                    upsert_child(metadata, moved)
+                } else if ipns_name == from.ipns_name {
+                    let before = metadata.children.len();
+                    metadata.children.retain(|c| child_ipns_name(c) != ipns);
+                    metadata.children.len() != before
+                } else {
+                    false
+                }
+            }
+            JournalOp::Write { .. } => false,
+        }
+    }
+
+    /// Short human-readable description, for logs and the tray.
+    pub fn describe(&self) -> String {
+        match self {
+            JournalOp::Create { pointer, .. } => format!("create '{}'", pointer.name),
+            JournalOp::Write { metadata, .. } => format!("save of a {} byte file", metadata.size),
+            JournalOp::Mkdir { entry, .. } => format!("new folder '{}'", entry.name),
+            JournalOp::Rename { from_name, to_name, .. } => {
+                format!("rename of '{}' to '{}'", from_name, to_name)
+            }
+            JournalOp::Unlink { name, .. } => format!("delete of '{}'", name),
+        }
+    }
+}
+
+/// A recorded op and the IPNS names it still has to be published to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub op: JournalOp,
+    pub pending: Vec<String>,
+    /// Failed replay attempts, not counting network failures.
+    pub attempts: u32,
+}
+
+/// On-disk form of the journal.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JournalFile {
+    version: u32,
+    root_ipns_name: String,
+    next_seq: u64,
+    entries: VecDeque<JournalEntry>,
+}
+
+/// Publishes one op to one of its target IPNS names.
+///
+/// In production this resolves, edits and republishes remote metadata
+/// (`sync::replay::JournalReplayer`); tests use mocks.
+#[allow(async_fn_in_trait)]
+pub trait MutationHandler {
+    /// Make `op`'s effect visible under `ipns_name`.
+    async fn apply(&self, op: &JournalOp, ipns_name: &str) -> Result<(), String>;
+}
+
+/// Outcome of a replay pass.
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// Ops fully published during this pass.
+    pub applied: usize,
+    /// Ops still in the journal afterwards.
+    pub remaining: usize,
+    /// The op the pass stopped at, if any.
+    pub failure: Option<ReplayFailure>,
+}
+
+/// An op that could not be replayed.
+#[derive(Debug)]
+pub struct ReplayFailure {
+    pub description: String,
+    pub error: String,
+    /// Whether the op was dropped after `MAX_REPLAY_ATTEMPTS`.
+    pub dropped: bool,
+}
+
+/// Path of the journal of the vault rooted at `root_ipns_name`:
+/// `<user data dir>/cipherbox/journal/<root IPNS name>`.
+pub fn journal_path(root_ipns_name: &str) -> Option<PathBuf> {
+    if root_ipns_name.is_empty() || !root_ipns_name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    dirs::data_local_dir().map(|dir| dir.join("cipherbox").join("journal").join(root_ipns_name))
+}
+
+/// Spool ids of content referenced by `entries`.
+fn spooled<'a>(entries: impl IntoIterator<Item = &'a JournalEntry>) -> HashSet<String> {
+    entries
+        .into_iter()
+        .filter_map(|e| match &e.op {
+            JournalOp::Write { content, .. } => content.clone(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Associated data binding a sealed journal to its vault.
+fn journal_aad(root_ipns_name: &str) -> Vec<u8> {
+    format!("cipherbox-journal-v{}:{}", JOURNAL_VERSION, root_ipns_name).into_bytes()
+}
+
+/// The mutation journal of one vault, shared between the FUSE thread,
+/// background publishes and the sync daemon.
+pub struct Journal {
+    /// Where the journal is persisted; `None` keeps it in memory only.
+    path: Option<PathBuf>,
+    key: LocalStateKey,
+    state: Mutex<JournalFile>,
+    /// Content of journaled saves not uploaded yet (`<path>.spool/`).
+    spool: ContentSpool,
+    /// Ops being uploaded by the save that recorded them; replay waits for them.
+    claimed: Mutex<HashSet<u64>>,
+}
+
+impl Journal {
+    /// Open the journal at `path`, or start an empty one.
+    ///
+    /// A journal that cannot be decrypted or parsed is moved aside (to
+    /// `<path>.unreadable`) rather than overwritten. Spooled content no
+    /// entry refers to is deleted.
+    pub fn open(path: Option<PathBuf>, key: LocalStateKey, root_ipns_name: &str) -> Self {
+        let state = path
+            .as_deref()
+            .and_then(|path| Self::load(path, &key, root_ipns_name))
+            .unwrap_or_else(|| JournalFile {
+                version: JOURNAL_VERSION,
+                root_ipns_name: root_ipns_name.to_string(),
+                next_seq: 1,
+                entries: VecDeque::new(),
+            });
+        if !state.entries.is_empty() {
+            log::info!("Journal has {} unpublished change(s)", state.entries.len());
+        }
+        let spool = match &path {
+            Some(path) => ContentSpool::new(path.with_extension("spool"), key.clone()),
+            None => ContentSpool::ephemeral(key.clone()),
+        };
+        spool.retain(&spooled(&state.entries));
+        Self {
+            path,
+            key,
+            state: Mutex::new(state),
+            spool,
+            claimed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn load(path: &Path, key: &LocalStateKey, root_ipns_name: &str) -> Option<JournalFile> {
+        let sealed = std::fs::read(path).ok()?;
+        let parsed = crate::crypto::aes::unseal_aes_gcm_with_aad(
+            &sealed,
+            key.as_bytes(),
+            &journal_aad(root_ipns_name),
+        )
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            serde_json::from_slice::<JournalFile>(&Zeroizing::new(json)).map_err(|e| e.to_string())
+        });
+        match parsed {
+            Ok(file) if file.version == JOURNAL_VERSION && file.root_ipns_name == root_ipns_name => {
+                Some(file)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::error!("Unreadable journal {}: {}", path.display(), e);
+                let _ = std::fs::rename(path, path.with_extension("unreadable"));
+                None
+            }
+        }
+    }
+
+    /// Seal and write `state` (atomically, via a temp file).
+    fn save(&self, state: &JournalFile) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+        let result = (|| -> Result<(), String> {
+            let json = Zeroizing::new(
+                serde_json::to_vec(state).map_err(|e| format!("Journal serialization failed: {}", e))?,
+            );
+            let sealed = crate::crypto::aes::seal_aes_gcm_with_aad(
+                &json,
+                self.key.as_bytes(),
+                &journal_aad(&state.root_ipns_name),
+            )
+            .map_err(|e| format!("Journal encryption failed: {}", e))?;
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+            }
+            let temp = path.with_extension("tmp");
+            std::fs::write(&temp, &sealed).map_err(|e| format!("Failed to write journal: {}", e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o600));
+            }
+            std::fs::rename(&temp, path).map_err(|e| format!("Failed to write journal: {}", e))
+        })();
+        if let Err(e) = result {
+            log::error!("{}", e);
+        }
+    }
+
+    /// Run `f` on the journal state and persist the result if it returns true.
+    /// Content spooled for entries that `f` removes is deleted.
+    fn update<T>(&self, f: impl FnOnce(&mut JournalFile) -> (T, bool)) -> T {
+        let mut state = self.state.lock().unwrap();
+        let before = spooled(&state.entries);
+        let (value, changed) = f(&mut state);
+        if changed {
+            self.save(&state);
+            let after = spooled(&state.entries);
+            for id in before.difference(&after) {
+                self.spool.remove(id);
+            }
+        }
+        value
+    }
+
+    /// Where journaled saves keep their content until it is uploaded.
+    pub fn spool(&self) -> &ContentSpool {
+        &self.spool
+    }
+
+    /// A new id for spooled content.
+    pub fn new_spool_id() -> String {
+        hex::encode(crate::crypto::utils::generate_random_bytes(16))
+    }
+
+    /// Record that spooled content `content_id` was uploaded as `cid`, so a
+    /// replay of its save only has to publish it. Returns false if the save
+    /// is no longer journaled (superseded by a newer one, or dropped).
+    pub fn uploaded(&self, content_id: &str, cid: &str, chunk_hashes: Option<Vec<String>>) -> bool {
+        self.update(|state| {
+            let op = state.entries.iter_mut().map(|e| &mut e.op).find(|op| {
+                matches!(op, JournalOp::Write { content: Some(id), .. } if id == content_id)
+            });
+            let Some(JournalOp::Write { metadata, content, .. }) = op else {
+                return (false, false);
+            };
+            metadata.cid = cid.to_string();
+            metadata.chunk_hashes = chunk_hashes;
+            *content = None;
+            (true, true)
+        })
+    }
+
+    /// Keep replay away from op `seq` until the returned claim is dropped,
+    /// while the save that recorded it is still uploading.
+    pub fn claim(self: &Arc<Self>, seq: u64) -> JournalClaim {
+        self.claimed.lock().unwrap().insert(seq);
+        JournalClaim {
+            journal: self.clone(),
+            seq,
+        }
+    }
+
+    fn is_claimed(&self, seq: u64) -> bool {
+        self.claimed.lock().unwrap().contains(&seq)
+    }
+
+    /// Record `op` as pending on all its targets. Returns its sequence number.
+    ///
+    /// A write supersedes earlier writes of the same file that are still
+    /// pending; an unlink of the file drops them.
+    pub fn record(&self, op: JournalOp) -> u64 {
+        self.update(|state| {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let superseded = match &op {
+                JournalOp::Write { file_ipns_name, .. } => Some(file_ipns_name),
+                JournalOp::Unlink { child_ipns_name, .. } => Some(child_ipns_name),
+                _ => None,
+            };
+            if let Some(file_ipns_name) = superseded {
+                state.entries.retain(|e| {
+                    !matches!(&e.op, JournalOp::Write { file_ipns_name: name, .. } if name == file_ipns_name)
+                });
+            }
+            state.entries.push_back(JournalEntry {
+                seq,
+                pending: op.targets(),
+                op,
+                attempts: 0,
+            });
+            (seq, true)
+        })
+    }
+
+    /// Sequence number of the newest recorded op (0 if none was ever recorded).
+    pub fn last_seq(&self) -> u64 {
+        self.state.lock().unwrap().next_seq - 1
+    }
+
+    /// Acknowledge a publish of `ipns_name` that includes every op up to `upto`.
+    pub fn ack(&self, ipns_name: &str, upto: u64) {
+        self.update(|state| {
+            let mut changed = false;
+            for entry in state.entries.iter_mut().filter(|e| e.seq <= upto) {
+                let before = entry.pending.len();
+                entry.pending.retain(|name| name != ipns_name);
+                changed |= entry.pending.len() != before;
+            }
+            state.entries.retain(|e| !e.pending.is_empty());
+            ((), changed)
+        })
+    }
+
+    /// A handle acknowledging publishes of the state as of now.
+    pub fn acker(self: &Arc<Self>) -> JournalAck {
+        JournalAck {
+            journal: self.clone(),
+            upto: self.last_seq(),
+        }
+    }
+
+    /// Copies of all entries, oldest first.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.state.lock().unwrap().entries.iter().cloned().collect()
+    }
+
+    /// Number of ops not yet fully published.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_pending(&self, seq: u64, ipns_name: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .any(|e| e.seq == seq && e.pending.iter().any(|n| n == ipns_name))
+    }
+
+    /// Mark op `seq` as published to `ipns_name`.
+    fn complete(&self, seq: u64, ipns_name: &str) {
+        self.update(|state| {
+            if let Some(entry) = state.entries.iter_mut().find(|e| e.seq == seq) {
+                entry.pending.retain(|name| name != ipns_name);
+            }
+            state.entries.retain(|e| !e.pending.is_empty());
+            ((), true)
+        })
+    }
+
+    /// Count a failed replay of op `seq`; drops it after `MAX_REPLAY_ATTEMPTS`.
+    /// Returns whether it was dropped.
+    fn fail(&self, seq: u64) -> bool {
+        self.update(|state| {
+            let Some(index) = state.entries.iter().position(|e| e.seq == seq) else {
+                return (false, false);
+            };
+            state.entries[index].attempts += 1;
+            let dropped = state.entries[index].attempts >= MAX_REPLAY_ATTEMPTS;
+            if dropped {
+                state.entries.remove(index);
+            }
+            (dropped, true)
+        })
+    }
+
+    /// Publish every pending op, oldest first, through `handler`.
+    ///
+    /// Stops at the first failure, or at an op still being uploaded by its
+    /// save (see `claim`), so later ops never overtake earlier ones.
+    /// Network failures leave the op untouched; other failures count towards
+    /// `MAX_REPLAY_ATTEMPTS`, after which the op is dropped so it cannot block
+    /// the journal forever. `progress` is called with (done, total) before
+    /// each op and once at the end.
+    pub async fn replay<H: MutationHandler>(
+        &self,
+        handler: &H,
+        mut progress: impl FnMut(usize, usize),
+    ) -> ReplayReport {
+        let entries = self.entries();
+        let total = entries.len();
+        let mut report = ReplayReport::default();
+
+        for (done, entry) in entries.iter().enumerate() {
+            if self.is_claimed(entry.seq) {
+                report.remaining = self.len();
+                return report;
+            }
+            progress(done, total);
+            for target in &entry.pending {
+                // Skip names a regular publish acknowledged in the meantime
+                if !self.is_pending(entry.seq, target) {
+                    continue;
+                }
+                match handler.apply(&entry.op, target).await {
+                    Ok(()) => self.complete(entry.seq, target),
+                    Err(error) => {
+                        let dropped = !super::is_network_error(&error) && self.fail(entry.seq);
+                        let description = entry.op.describe();
+                        if dropped {
+                            log::error!("Dropping journaled {}: {}", description, error);
+                        } else {
+                            log::warn!("Replay of journaled {} failed: {}", description, error);
+                        }
+                        report.failure = Some(ReplayFailure {
+                            description,
+                            error,
+                            dropped,
+                        });
+                        report.remaining = self.len();
+                        return report;
+                    }
+                }
+            }
+            report.applied += 1;
+        }
+
+        progress(total, total);
+        report.remaining = self.len();
+        report
+    }
+}
+
+/// Acknowledges publishes built from the local state at a point in time:
+/// every op recorded up to then is done for the published name.
+#[derive(Clone)]
+pub struct JournalAck {
+    journal: Arc<Journal>,
+    upto: u64,
+}
+
+impl JournalAck {
+    pub fn ack(&self, ipns_name: &str) {
+        self.journal.ack(ipns_name, self.upto);
+    }
+}
+
+/// Keeps replay away from a journaled op while it is being uploaded.
+pub struct JournalClaim {
+    journal: Arc<Journal>,
+    seq: u64,
+}
+
+impl Drop for JournalClaim {
+    fn drop(&mut self) {
+        self.journal.claimed.lock().unwrap().remove(&self.seq);
+    }
+}
@@ -0,0 +1,123 @@
+//! Three-way merge of folder metadata edited on several devices.
+//!
+//! A device publishing a folder compares the record it last built its local
+//! state from (the *base*) with the current remote record. If another device
+//! published in between, the local and remote children are merged against the
+//! base instead of the local state overwriting the remote one:
+//!
+//! - entries added on either side are kept,
+//! - entries deleted on one side (and present in the base) are dropped,
+//! - a rename on one side wins over no change on the other; if both sides
+//!   renamed an entry, the local name wins.
+//!
+//! Entries are matched by the IPNS name of the child (file metadata record or
+//! subfolder), which identifies the same file or folder on every device. When
+//! a locally added or renamed entry ends up with the name of a remote entry,
+//! the local one becomes a "conflicted copy", so neither is lost.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::crypto::folder::{FolderChild, FolderMetadata};
+use crate::sync::journal::{child_ipns_name, child_name, set_child_name};
+
+/// Result of merging a folder's local and remote children.
+#[derive(Debug)]
+pub struct FolderMerge {
+    pub metadata: FolderMetadata,
+    /// New names of local entries renamed to conflicted copies.
+    pub conflicts: Vec<String>,
+}
+
+/// `name` with `label` inserted before the extension:
+/// `conflict_copy_name("report.txt", "conflicted copy")` is
+/// `"report (conflicted copy).txt"`. Dotfiles keep their leading dot.
+pub fn conflict_copy_name(name: &str, label: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{} ({}).{}", stem, label, ext),
+        _ => format!("{} ({})", name, label),
+    }
+}
+
+/// `conflict_copy_name` of `name` that is not in `taken`, numbering the label
+/// ("label 2", "label 3", ...) as needed.
+pub fn free_conflict_copy_name(name: &str, label: &str, taken: &HashSet<String>) -> String {
+    let mut copy = conflict_copy_name(name, label);
+    let mut n = 2;
+    while taken.contains(&copy) {
+        copy = conflict_copy_name(name, &format!("{} {}", label, n));
+        n += 1;
+    }
+    copy
+}
+
+/// Three-way merge of `local` and `remote` against their common `base`.
+///
+/// Remote entries keep their order; entries only present locally follow in
+/// local order. `device` names this device in conflicted copy names.
+pub fn merge_folder_metadata(
+    base: &FolderMetadata,
+    local: &FolderMetadata,
+    remote: &FolderMetadata,
+    device: &str,
+) -> FolderMerge {
+    let base_names: HashMap<&str, &str> = base
+        .children
+        .iter()
+        .map(|c| (child_ipns_name(c), child_name(c)))
+        .collect();
+    let local_by_key: HashMap<&str, &FolderChild> =
+        local.children.iter().map(|c| (child_ipns_name(c), c)).collect();
+    let remote_keys: HashSet<&str> = remote.children.iter().map(child_ipns_name).collect();
+
+    // (entry, whether it comes from the local side)
+    let mut merged: Vec<(FolderChild, bool)> = Vec::new();
+    for remote_child in &remote.children {
+        let key = child_ipns_name(remote_child);
+        match (base_names.get(key), local_by_key.get(key)) {
+            // Unchanged locally: take the remote version (renamed or not)
+            (Some(base_name), Some(local_child)) if child_name(local_child) == *base_name => {
+                merged.push((remote_child.clone(), false));
+            }
+            // Renamed locally, or added on both sides: local wins
+            (_, Some(local_child)) => merged.push(((*local_child).clone(), true)),
+            // Deleted locally
+            (Some(_), None) => {}
+            // Added remotely
+            (None, None) => merged.push((remote_child.clone(), false)),
+        }
+    }
+    for local_child in &local.children {
+        let key = child_ipns_name(local_child);
+        // Entries in the base but not the remote were deleted remotely
+        if !remote_keys.contains(key) && !base_names.contains_key(key) {
+            merged.push((local_child.clone(), true));
+        }
+    }
+
+    // Local entries must not take a name a remote entry has
+    let remote_names: HashSet<String> = merged
+        .iter()
+        .filter(|(_, is_local)| !is_local)
+        .map(|(c, _)| child_name(c).to_string())
+        .collect();
+    let mut taken: HashSet<String> = merged.iter().map(|(c, _)| child_name(c).to_string()).collect();
+    let mut conflicts = Vec::new();
+    for (child, is_local) in merged.iter_mut() {
+        if !*is_local || !remote_names.contains(child_name(child)) {
+            continue;
+        }
+        let label = format!("conflicted copy from {}", device);
+        let name = free_conflict_copy_name(child_name(child), &label, &taken);
+        taken.insert(name.clone());
+        set_child_name(child, &name);
+        conflicts.push(name);
+    }
+
+    FolderMerge {
+        metadata: FolderMetadata {
+            version: local.version.clone(),
+            children: merged.into_iter().map(|(c, _)| c).collect(),
+        },
+        conflicts,
+    }
+}
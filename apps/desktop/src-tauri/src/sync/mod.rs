@@ -1,17 +1,26 @@
 //! Background sync daemon for CipherBox Desktop.
 //!
-//! Polls IPNS every 30 seconds for metadata changes, refreshes the inode table
-//! when changes are detected, and processes queued offline writes.
+//! Polls IPNS every 30 seconds for metadata changes of the folders and files
+//! each mount knows (see `watch`) and reports them to that mount, which
+//! re-resolves them, updates the inode table and invalidates the kernel's
+//! caches (see `fuse::kernel`). Also replays mutations left in the mounts'
+//! journals (see `journal`) once the network is reachable.
 //!
 //! Uses sequence number comparison (not CID) per project decision from Phase 7.
 
+pub mod journal;
+pub mod merge;
 pub mod queue;
+#[cfg(feature = "fuse")]
+pub mod replay;
+pub mod spool;
 #[cfg(test)]
 mod tests;
+pub mod watch;
 
-pub use queue::{QueuedWrite, UploadHandler, WriteQueue};
+pub use queue::{QueuedWrite, SaveFailures, UploadHandler};
+pub use watch::{NameKind, RemoteChange, WatchList};
 
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -34,12 +43,11 @@ pub struct SyncDaemon {
     is_authenticated: Arc<RwLock<bool>>,
     /// Poll interval (default 30s).
     poll_interval: Duration,
-    /// Cached IPNS sequence numbers: ipns_name -> last known sequence_number.
-    cached_sequence_numbers: HashMap<String, u64>,
+    /// Names polled while nothing is mounted: just the root. The mounts
+    /// have watch lists of their own.
+    watch_list: Arc<tokio::sync::Mutex<WatchList>>,
     /// Channel receiver for manual sync triggers (from tray "Sync Now" button).
     sync_now_rx: mpsc::Receiver<()>,
-    /// Offline write queue for deferred uploads.
-    write_queue: WriteQueue,
     /// AppHandle for updating tray status.
     app_handle: tauri::AppHandle,
     /// Whether the last poll attempt detected offline state.
@@ -64,9 +72,8 @@ impl SyncDaemon {
             root_ipns_name,
             is_authenticated,
             poll_interval: SYNC_INTERVAL,
-            cached_sequence_numbers: HashMap::new(),
+            watch_list: Default::default(),
             sync_now_rx,
-            write_queue: WriteQueue::default(),
             app_handle,
             was_offline: false,
         }
@@ -74,9 +81,11 @@ impl SyncDaemon {
 
     /// Main run loop. Call from a spawned tokio task.
     ///
-    /// Uses `tokio::select!` to wait on either the periodic tick or a manual trigger.
-    /// On each tick: poll IPNS for changes, process write queue.
+    /// Uses `tokio::select!` to wait on the periodic tick, a manual trigger or
+    /// a failed save. On each tick: poll IPNS for changes, replay the journals.
     pub async fn run(&mut self) {
+        use tauri::Manager;
+        let failures = self.app_handle.state::<crate::state::AppState>().save_failures.clone();
         let mut ticker = tokio::time::interval(self.poll_interval);
         // The first tick fires immediately; skip it to let the app finish mounting.
         ticker.tick().await;
@@ -95,17 +104,36 @@ impl SyncDaemon {
                     log::info!("Manual sync triggered");
                     self.sync_cycle().await;
                 }
+                _ = failures.reported() => {
+                    self.sync_cycle().await;
+                }
             }
         }
     }
 
-    /// Execute one full sync cycle: poll + process write queue.
+    /// Execute one full sync cycle: poll, replay the journals, report failed saves.
     async fn sync_cycle(&mut self) {
         // Check if authenticated
         if !*self.is_authenticated.read().await {
             return;
         }
 
+        // While a mount is down, the tray shows that (see fuse::supervisor)
+        #[cfg(feature = "fuse")]
+        let mount_error = {
+            use tauri::Manager;
+
+            let state = self.app_handle.state::<crate::state::AppState>();
+            let mount_error = match &*state.mount_status.read().await {
+                crate::state::MountStatus::Error(message) => Some(message.clone()),
+                _ => None,
+            };
+            if mount_error.is_some() && !state.mounts.any_mounted() {
+                return;
+            }
+            mount_error
+        };
+
         // Update tray to Syncing
         let _ = crate::tray::update_tray_status(
             &self.app_handle,
@@ -120,26 +148,21 @@ impl SyncDaemon {
                     self.was_offline = false;
                 }
 
-                // Process queued writes (best-effort)
-                if !self.write_queue.is_empty() {
-                    log::info!(
-                        "Processing {} queued writes",
-                        self.write_queue.len()
-                    );
-                    // Write queue processing requires an UploadHandler implementation
-                    // which would use self.api. For v1, log pending items.
-                    // Full write queue processing with FUSE integration is deferred
-                    // to after the UploadHandler trait is wired to the ApiClient+FUSE layer.
-                    log::debug!(
-                        "Write queue has {} pending items",
-                        self.write_queue.len()
-                    );
+                let mut status = self.replay_journal().await;
+                let failures = {
+                    use tauri::Manager;
+                    self.app_handle.state::<crate::state::AppState>().save_failures.clone()
+                };
+                if let Some(message) = failures.take() {
+                    if status == crate::tray::TrayStatus::Synced {
+                        status = crate::tray::TrayStatus::Error(sanitize_error(&message));
+                    }
                 }
-
-                let _ = crate::tray::update_tray_status(
-                    &self.app_handle,
-                    &crate::tray::TrayStatus::Synced,
-                );
+                #[cfg(feature = "fuse")]
+                if let (crate::tray::TrayStatus::Synced, Some(message)) = (&status, mount_error) {
+                    status = crate::tray::TrayStatus::MountError(message);
+                }
+                let _ = crate::tray::update_tray_status(&self.app_handle, &status);
             }
             Err(e) => {
                 log::warn!("Sync poll failed: {}", e);
@@ -164,13 +187,15 @@ impl SyncDaemon {
         }
     }
 
-    /// Poll IPNS for all known folders and detect changes via sequence number comparison.
+    /// Poll IPNS for known folders and files and detect changes via sequence
+    /// number comparison, for each mount (or just the root while nothing is
+    /// mounted).
     ///
-    /// For each folder:
-    /// 1. Resolve IPNS name to get current sequence number
-    /// 2. Compare with cached sequence number
-    /// 3. If changed: log the change (metadata cache TTL handles refresh on next FUSE access)
-    /// 4. Update cached sequence numbers
+    /// 1. Take the next batch of names from the watch list (root first)
+    /// 2. Resolve each to get its current sequence number
+    /// 3. If it moved forward: report the change to the mount
+    ///
+    /// Fails only if the vault root cannot be resolved (e.g. offline).
     async fn poll(&mut self) -> Result<(), String> {
         // Get root IPNS name
         let root_ipns_name = self
@@ -180,47 +205,159 @@ impl SyncDaemon {
             .clone()
             .ok_or_else(|| "Root IPNS name not available".to_string())?;
 
-        // Resolve root folder IPNS
-        let resolve_result =
-            crate::api::ipns::resolve_ipns(&self.api, &root_ipns_name).await?;
-
-        let new_seq = resolve_result
-            .sequence_number
-            .parse::<u64>()
-            .unwrap_or(0);
-
-        let cached_seq = self
-            .cached_sequence_numbers
-            .get(&root_ipns_name)
-            .copied()
-            .unwrap_or(0);
-
-        if new_seq != cached_seq {
-            log::info!(
-                "IPNS change detected for root folder: seq {} -> {}",
-                cached_seq,
-                new_seq
-            );
-            self.cached_sequence_numbers
-                .insert(root_ipns_name.clone(), new_seq);
-
-            // The metadata cache has a 30s TTL, so the next FUSE readdir/lookup
-            // will fetch and decrypt fresh metadata automatically.
-            log::info!(
-                "Root folder metadata changed (CID: {}). Cache will refresh on next access.",
-                resolve_result.cid
-            );
+        for target in self.poll_targets(&root_ipns_name).await {
+            match self.poll_watch_list(&target).await {
+                Ok(()) => {}
+                Err(e) if target.root_ipns_name == root_ipns_name || is_network_error(&e) => {
+                    return Err(e)
+                }
+                // A folder mount whose folder is gone: the vault itself is fine
+                Err(e) => log::warn!(
+                    "Sync poll: resolve of mounted folder {} failed: {}",
+                    target.root_ipns_name,
+                    e
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// The watch lists to poll: each mount's, or the daemon's own for the
+    /// vault root while nothing is mounted.
+    async fn poll_targets(&self, root_ipns_name: &str) -> Vec<PollTarget> {
+        #[cfg(feature = "fuse")]
+        let targets: Vec<PollTarget> = self
+            .mounts()
+            .await
+            .into_iter()
+            .map(|mount| PollTarget {
+                root_ipns_name: mount.root_ipns_name.clone(),
+                watch_list: mount.watch_list.clone(),
+                changes: Some(mount.remote_changes.clone()),
+            })
+            .collect();
+        #[cfg(not(feature = "fuse"))]
+        let targets: Vec<PollTarget> = vec![];
+
+        if !targets.is_empty() {
+            return targets;
+        }
+        vec![PollTarget {
+            root_ipns_name: root_ipns_name.to_string(),
+            watch_list: self.watch_list.clone(),
+            changes: None,
+        }]
+    }
+
+    /// Poll the next batch of `target`'s watch list. Fails only if its root
+    /// cannot be resolved.
+    async fn poll_watch_list(&self, target: &PollTarget) -> Result<(), String> {
+        let batch = {
+            let mut watch_list = target.watch_list.lock().await;
+            watch_list.set_root(&target.root_ipns_name);
+            watch_list.poll_batch(watch::MAX_NAMES_PER_POLL, std::time::Instant::now())
+        };
+
+        for ipns_name in batch {
+            let resolved = match crate::api::ipns::resolve_ipns(&self.api, &ipns_name).await {
+                Ok(resolved) => resolved,
+                Err(e) if ipns_name == target.root_ipns_name => return Err(e.into()),
+                Err(e) => {
+                    log::debug!("Sync poll: resolve of {} failed: {}", ipns_name, e);
+                    continue;
+                }
+            };
+            let sequence = resolved.sequence_number.parse::<u64>().unwrap_or(0);
+
+            let change = target
+                .watch_list
+                .lock()
+                .await
+                .record(&ipns_name, sequence, &resolved.cid);
+            if let Some(change) = change {
+                log::info!(
+                    "IPNS change detected for {:?} {}: seq {} -> {} (CID: {})",
+                    change.kind,
+                    change.ipns_name,
+                    change.previous_sequence,
+                    change.sequence,
+                    change.cid
+                );
+                // Have the mount re-resolve the changed name and apply the change
+                if let Some(changes) = &target.changes {
+                    let _ = changes.send(change);
+                }
+            }
         }
 
         Ok(())
     }
 
-    /// Access the write queue for enqueuing offline writes.
-    pub fn write_queue_mut(&mut self) -> &mut WriteQueue {
-        &mut self.write_queue
+    /// The mounts, to sync.
+    #[cfg(feature = "fuse")]
+    async fn mounts(&self) -> Vec<Arc<crate::fuse::VaultMount>> {
+        use tauri::Manager;
+
+        let state = self.app_handle.state::<crate::state::AppState>();
+        let mounts = state.mounts.entries.read().await.values().cloned().collect();
+        mounts
+    }
+
+    /// Replay the vault's journaled mutations, in order, showing progress
+    /// in the tray. Returns the tray status to show afterwards.
+    async fn replay_journal(&mut self) -> crate::tray::TrayStatus {
+        #[cfg(feature = "fuse")]
+        let replayer = {
+            use tauri::Manager;
+            self.app_handle.state::<crate::state::AppState>().mounts.replayer()
+        };
+        #[cfg(feature = "fuse")]
+        if let Some(replayer) = replayer {
+            if !replayer.journal.is_empty() {
+                log::info!("Replaying {} journaled change(s)", replayer.journal.len());
+                let app_handle = self.app_handle.clone();
+                let report = replayer
+                    .journal
+                    .replay(&*replayer, |done, total| {
+                        let _ = crate::tray::update_tray_status(
+                            &app_handle,
+                            &crate::tray::TrayStatus::Replaying { done, total },
+                        );
+                    })
+                    .await;
+                log::info!(
+                    "Journal replay: {} applied, {} remaining",
+                    report.applied,
+                    report.remaining
+                );
+
+                if let Some(failure) = report.failure {
+                    if is_network_error(&failure.error) {
+                        self.was_offline = true;
+                        return crate::tray::TrayStatus::Offline;
+                    }
+                    let message = if failure.dropped {
+                        format!("Gave up on {}: {}", failure.description, failure.error)
+                    } else {
+                        format!("Could not upload {}: {}", failure.description, failure.error)
+                    };
+                    return crate::tray::TrayStatus::Error(sanitize_error(&message));
+                }
+            }
+        }
+        crate::tray::TrayStatus::Synced
     }
 }
 
+/// A watch list the daemon polls.
+struct PollTarget {
+    /// Root of the watch list: the mounted folder, or the vault root.
+    root_ipns_name: String,
+    watch_list: Arc<tokio::sync::Mutex<WatchList>>,
+    /// The mount that applies the changes found; `None` while nothing is mounted.
+    changes: Option<mpsc::UnboundedSender<RemoteChange>>,
+}
+
 /// Sanitize error messages before displaying in tray status or notifications.
 ///
 /// Removes sensitive information that could leak implementation details:
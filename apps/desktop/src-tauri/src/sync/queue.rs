@@ -1,42 +1,74 @@
-//! Offline write queue for deferred file uploads.
+//! Saved files whose content upload has not gone through yet.
 //!
-//! When the user writes a file while offline (or when the network drops),
-//! the encrypted content is queued in memory and retried when connectivity returns.
+//! `release()` journals every save (see `journal`) together with a sealed
+//! copy of its content in the journal's spool, and only then uploads it. Until
+//! that upload succeeds -- offline, after a failed upload, or after the app
+//! quit mid-upload -- the save is a `QueuedWrite`: the journal keeps it on
+//! disk, and its replay by the sync daemon hands it to the `UploadHandler`,
+//! which streams the content back out of the spool. There is no size limit.
 //!
-//! Memory-only queue per CONTEXT.md -- queued items are lost on app quit.
-//! Acceptable for v1 given small file sizes and tech demo scope.
+//! Saves that cannot be kept at all are reported to the tray through
+//! `SaveFailures`.
 
-use std::collections::VecDeque;
-use std::time::Instant;
+use std::sync::Mutex;
 
-/// A single queued write operation (already encrypted at queue time).
+use tokio::sync::Notify;
+
+use crate::crypto::folder::FileMetadata;
+use crate::fuse::file_handle::FileBase;
+use crate::sync::journal::{FolderRef, JournalOp};
+
+/// A journaled save whose content is not uploaded yet.
 #[derive(Debug, Clone)]
 pub struct QueuedWrite {
-    /// Unique identifier for this queued item.
-    pub id: String,
-    /// Parent folder inode number (for folder metadata rebuild).
-    pub parent_ino: u64,
-    /// Already-encrypted file content (AES-256-GCM sealed bytes).
-    pub encrypted_content: Vec<u8>,
-    /// ECIES-wrapped file key (hex-encoded).
-    pub encrypted_file_key: Vec<u8>,
-    /// AES-GCM initialization vector.
-    pub iv: Vec<u8>,
-    /// Original filename.
-    pub filename: String,
-    /// When this write was queued.
-    pub created_at: Instant,
-    /// Number of upload attempts that failed.
-    pub retries: u32,
+    /// Spool id of the sealed content.
+    pub content_id: String,
+    /// Parent folder, whose key encrypts the file's metadata.
+    pub parent: FolderRef,
+    /// IPNS name of the file's metadata record.
+    pub file_ipns_name: String,
+    /// ECIES-wrapped IPNS private key of the file's metadata record (hex-encoded).
+    pub ipns_private_key_encrypted: String,
+    /// Metadata published to the file's IPNS record. `cid` is filled in
+    /// once the content is uploaded.
+    pub metadata: FileMetadata,
+    /// Remote revision the save was based on; `None` for a new file.
+    pub base: Option<FileBase>,
+}
+
+impl QueuedWrite {
+    /// The queued write recorded by `op`, if it is a save whose content is
+    /// not uploaded yet.
+    pub fn from_op(op: &JournalOp) -> Option<Self> {
+        match op {
+            JournalOp::Write {
+                parent,
+                file_ipns_name,
+                ipns_private_key_encrypted,
+                metadata,
+                content: Some(content_id),
+                base,
+            } => Some(Self {
+                content_id: content_id.clone(),
+                parent: parent.clone(),
+                file_ipns_name: file_ipns_name.clone(),
+                ipns_private_key_encrypted: ipns_private_key_encrypted.clone(),
+                metadata: metadata.clone(),
+                base: base.clone(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Trait abstracting the upload operation for testability.
 ///
-/// In production, `ApiClient` implements this via IPFS upload + folder metadata update.
-/// In tests, a mock implementation controls success/failure behavior.
+/// In production, `JournalReplayer` implements this via IPFS upload, a
+/// revision check and per-file IPNS publish. In tests, a mock implementation
+/// controls success/failure behavior.
 #[allow(async_fn_in_trait)]
 pub trait UploadHandler {
-    /// Attempt to upload encrypted content and update the parent folder metadata.
+    /// Attempt to upload the content of `write` and publish the file's metadata.
     ///
     /// Returns `Ok(())` on success, `Err(message)` on failure.
     async fn upload_and_register(
@@ -45,96 +77,34 @@ pub trait UploadHandler {
     ) -> Result<(), String>;
 }
 
-/// FIFO queue of offline writes awaiting upload.
-///
-/// Items are processed front-to-back. On failure, the item is moved to the
-/// back with `retries` incremented. Items exceeding `max_retries` are dropped.
-pub struct WriteQueue {
-    queue: VecDeque<QueuedWrite>,
-    max_retries: u32,
+/// Saves that failed, reported by the mounts' upload threads and shown in
+/// the tray by the sync daemon, which each report also wakes.
+#[derive(Default)]
+pub struct SaveFailures {
+    last: Mutex<Option<String>>,
+    notify: Notify,
 }
 
-impl WriteQueue {
-    /// Create a new empty write queue with the given max retry count.
-    pub fn new(max_retries: u32) -> Self {
-        Self {
-            queue: VecDeque::new(),
-            max_retries,
-        }
+impl SaveFailures {
+    /// Report a save that was lost, with a message for the tray.
+    pub fn report(&self, message: String) {
+        *self.last.lock().unwrap() = Some(message);
+        self.notify.notify_one();
     }
 
-    /// Add a write operation to the back of the queue.
-    pub fn enqueue(&mut self, write: QueuedWrite) {
-        self.queue.push_back(write);
+    /// Report a save whose upload failed but which is journaled, so the sync
+    /// daemon retries it (and shows why, if that fails too) right away.
+    pub fn retry(&self) {
+        self.notify.notify_one();
     }
 
-    /// Process all queued writes using the given upload handler.
-    ///
-    /// Returns the number of successfully processed items.
-    /// Items that fail are moved to the back of the queue with `retries` incremented.
-    /// Items exceeding `max_retries` are dropped with a log message.
-    pub async fn process<H: UploadHandler>(&mut self, handler: &H) -> Result<usize, String> {
-        let count = self.queue.len();
-        if count == 0 {
-            return Ok(0);
-        }
-
-        let mut processed = 0;
-        let mut remaining = VecDeque::new();
-
-        // Process each item exactly once per call
-        while let Some(mut item) = self.queue.pop_front() {
-            match handler.upload_and_register(&item).await {
-                Ok(()) => {
-                    log::info!(
-                        "Queued write processed: {} ({})",
-                        item.filename,
-                        item.id
-                    );
-                    processed += 1;
-                }
-                Err(e) => {
-                    item.retries += 1;
-                    if item.retries > self.max_retries {
-                        log::error!(
-                            "Queued write dropped after {} retries: {} ({}) - {}",
-                            self.max_retries,
-                            item.filename,
-                            item.id,
-                            e
-                        );
-                    } else {
-                        log::warn!(
-                            "Queued write retry {}/{}: {} ({}) - {}",
-                            item.retries,
-                            self.max_retries,
-                            item.filename,
-                            item.id,
-                            e
-                        );
-                        remaining.push_back(item);
-                    }
-                }
-            }
-        }
-
-        self.queue = remaining;
-        Ok(processed)
+    /// The last lost save reported since the previous call.
+    pub fn take(&self) -> Option<String> {
+        self.last.lock().unwrap().take()
     }
 
-    /// Number of items currently in the queue.
-    pub fn len(&self) -> usize {
-        self.queue.len()
-    }
-
-    /// Whether the queue is empty.
-    pub fn is_empty(&self) -> bool {
-        self.queue.is_empty()
-    }
-}
-
-impl Default for WriteQueue {
-    fn default() -> Self {
-        Self::new(5)
+    /// Wait for the next report. Reports made while nobody waits are not lost.
+    pub async fn reported(&self) {
+        self.notify.notified().await;
     }
 }
@@ -0,0 +1,412 @@
+//! Replay of journaled mutations and saves against the remote vault.
+//!
+//! `JournalReplayer` is the production `MutationHandler`: folder edits are
+//! applied to freshly resolved metadata and republished, new folders get their
+//! initial (empty) IPNS record if they have none yet, and file saves upload
+//! their spooled content if it is not uploaded yet and republish the file's
+//! own metadata. The vault has one journal and one replayer, shared by the
+//! writable mounts. Publishes go through their shared `PublishCoordinator`,
+//! so they serialize with the FUSE threads' own.
+//!
+//! It is also the production `UploadHandler` for journaled saves whose content
+//! is not uploaded yet: the content is uploaded from the spool, checked
+//! against the revision the save was based on (a save that lost to another
+//! device becomes a conflicted copy), and published to the file's IPNS record.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::api::client::ApiClient;
+use crate::api::ipns::ResolveError;
+use crate::crypto::folder::{FileMetadata, FilePointer, FolderChild, FolderMetadata, MetadataFormat};
+use crate::crypto::keys::{
+    FolderKey, IpnsSigningKey, MlKemSecretKey, Secp256k1SecretKey, SecretKey,
+};
+use crate::fuse::file_handle::{FileBase, OpenFileHandle};
+use crate::fuse::{ConflictCopy, PublishCoordinator, UploadComplete};
+use crate::sync::journal::{child_ipns_name, child_name, FolderRef, Journal, JournalOp, MutationHandler};
+use crate::sync::queue::{QueuedWrite, UploadHandler};
+
+/// Publishes journaled ops of the mounted vault. Created by the first
+/// writable mount (`supervisor::Mounts::journal_replayer`); the root is the
+/// vault root, whatever that mount shows.
+pub struct JournalReplayer {
+    pub api: Arc<ApiClient>,
+    pub journal: Arc<Journal>,
+    pub coordinator: Arc<PublishCoordinator>,
+    pub private_key: Secp256k1SecretKey,
+    /// The user's ML-KEM-768 private key, for hybrid-wrapped folder keys.
+    pub mlkem_private_key: Option<MlKemSecretKey>,
+    pub root_ipns_name: String,
+    pub root_folder_key: FolderKey,
+    pub root_ipns_private_key: Option<IpnsSigningKey>,
+    /// TEE public key and epoch, for enrolling new folders in republishing.
+    pub tee_public_key: Option<Vec<u8>>,
+    pub tee_key_epoch: Option<u32>,
+    /// Envelope (and padding) of published folder and file metadata.
+    pub metadata_format: MetadataFormat,
+    /// Report uploaded journaled saves to the mounts, which record their CIDs.
+    /// Senders of mounts that are gone are dropped on the next report.
+    pub upload_txs: std::sync::Mutex<Vec<std::sync::mpsc::Sender<UploadComplete>>>,
+    /// Where spooled content is decrypted for upload (`fuse::replay_temp_dir`).
+    pub temp_dir: PathBuf,
+    /// The user's public key, wrapping keys of conflicted copies.
+    pub public_key: Vec<u8>,
+}
+
+impl JournalReplayer {
+    /// Report uploaded journaled saves to the mount listening on `upload_tx` too.
+    pub fn add_mount(&self, upload_tx: std::sync::mpsc::Sender<UploadComplete>) {
+        self.upload_txs.lock().unwrap().push(upload_tx);
+    }
+
+    /// Tell the mounts that the save of `file_ipns_name` was uploaded as `cid`.
+    fn report_uploaded(&self, file_ipns_name: &str, cid: &str, chunk_hashes: &Option<Vec<String>>) {
+        self.upload_txs.lock().unwrap().retain(|upload_tx| {
+            upload_tx
+                .send(UploadComplete {
+                    ino: 0,
+                    new_cid: cid.to_string(),
+                    parent_ino: 0,
+                    old_file_cid: None,
+                    pruned_cids: vec![],
+                    chunk_hashes: chunk_hashes.clone(),
+                    conflict: None,
+                    file_meta_ipns_name: Some(file_ipns_name.to_string()),
+                })
+                .is_ok()
+        });
+    }
+
+    fn unwrap_hex<K: SecretKey>(&self, hex_key: &str) -> Result<K, String> {
+        let wrapped = hex::decode(hex_key).map_err(|e| format!("Invalid wrapped key hex: {}", e))?;
+        crate::crypto::ecies::unwrap_secret_key_hybrid(
+            &wrapped,
+            &self.private_key,
+            self.mlkem_private_key.as_ref(),
+        )
+        .map_err(|e| format!("Key unwrapping failed: {}", e))
+    }
+
+    /// Folder key of `folder`.
+    fn folder_key(&self, folder: &FolderRef) -> Result<FolderKey, String> {
+        match &folder.folder_key_encrypted {
+            Some(hex_key) => self.unwrap_hex(hex_key),
+            None if folder.ipns_name == self.root_ipns_name => Ok(self.root_folder_key.clone()),
+            None => Err(format!("No folder key recorded for {}", folder.ipns_name)),
+        }
+    }
+
+    /// IPNS signing key of `folder`.
+    fn ipns_key(&self, folder: &FolderRef) -> Result<IpnsSigningKey, String> {
+        match &folder.ipns_private_key_encrypted {
+            Some(hex_key) => self.unwrap_hex(hex_key),
+            None if folder.ipns_name == self.root_ipns_name => self
+                .root_ipns_private_key
+                .clone()
+                .ok_or_else(|| "Root folder IPNS private key not available".to_string()),
+            None => Err(format!("No IPNS key recorded for {}", folder.ipns_name)),
+        }
+    }
+
+    /// Apply `edit` to the current metadata of `folder` and republish it if
+    /// that changed anything. `what` names the edit in logs.
+    async fn edit_folder(
+        &self,
+        folder: &FolderRef,
+        what: &str,
+        edit: impl FnOnce(&mut FolderMetadata) -> bool,
+    ) -> Result<(), String> {
+        let folder_key = self.folder_key(folder)?;
+        let ipns_key = self.ipns_key(folder)?;
+
+        let lock = self.coordinator.get_lock(&folder.ipns_name);
+        let _guard = lock.lock().await;
+
+        let resolved = crate::api::ipns::resolve_ipns(&self.api, &folder.ipns_name).await?;
+        let bytes = crate::api::ipfs::fetch_content(&self.api, &resolved.cid).await?;
+        let mut metadata = crate::fuse::operations::decrypt_metadata_from_ipfs_public(
+            &bytes,
+            &folder_key,
+            &folder.ipns_name,
+            self.api.envelope_history(),
+        )?;
+        if !edit(&mut metadata) {
+            log::debug!("{} already visible in {}", what, folder.ipns_name);
+            return Ok(());
+        }
+
+        crate::fuse::publish_folder_metadata(
+            &self.api,
+            &self.coordinator,
+            &metadata,
+            &folder_key,
+            &ipns_key,
+            &folder.ipns_name,
+            self.metadata_format,
+        )
+        .await?;
+        let _ = crate::api::ipfs::unpin_content(&self.api, &resolved.cid).await;
+        Ok(())
+    }
+
+    /// Upload the spooled content `content_id` of a journaled save, as
+    /// described by its `metadata`. Returns the content CID and, for CTR
+    /// content, the chunk hashes.
+    async fn upload_spooled(
+        &self,
+        content_id: &str,
+        metadata: &FileMetadata,
+    ) -> Result<(String, Option<Vec<String>>), String> {
+        let journal = self.journal.clone();
+        let temp_dir = self.temp_dir.clone();
+        let content_id = content_id.to_string();
+        let source = tokio::task::spawn_blocking(move || -> Result<OpenFileHandle, String> {
+            let source = OpenFileHandle::new_write(0, libc::O_RDWR, &temp_dir, None)?;
+            let writer = std::io::BufWriter::new(source.open_writer()?);
+            journal.spool().restore(&content_id, writer)?;
+            Ok(source)
+        })
+        .await
+        .map_err(|e| format!("Spool restore task failed: {}", e))??;
+        crate::fuse::operations::upload_saved_content_public(
+            &self.api,
+            &self.private_key,
+            metadata,
+            Arc::new(source),
+        )
+        .await
+    }
+
+    /// Publish a journaled save that lost to a newer remote version of its
+    /// file as a new file next to it, "name (conflict from <device>).ext".
+    /// The mount picks the copy up from the parent's remote metadata.
+    async fn publish_conflict_copy(
+        &self,
+        parent: &FolderRef,
+        file_ipns_name: &str,
+        metadata: FileMetadata,
+        folder_key: &FolderKey,
+    ) -> Result<(), String> {
+        let copy = ConflictCopy::new(metadata, &self.public_key, &self.coordinator)?;
+        copy.publish(&self.api, &self.coordinator, folder_key, self.metadata_format).await?;
+
+        let label = format!("conflict from {}", crate::registry::get_device_name());
+        let what = format!("Conflicted copy of {}", file_ipns_name);
+        self.edit_folder(parent, &what, |folder| {
+            if folder.children.iter().any(|c| child_ipns_name(c) == copy.ipns_name) {
+                return false;
+            }
+            let Some(original) = folder
+                .children
+                .iter()
+                .find(|c| child_ipns_name(c) == file_ipns_name)
+                .map(|c| child_name(c).to_string())
+            else {
+                // The file is gone remotely; nothing to keep the copy next to
+                return false;
+            };
+            let taken: HashSet<String> = folder.children.iter().map(|c| child_name(c).to_string()).collect();
+            let name = crate::sync::merge::free_conflict_copy_name(&original, &label, &taken);
+            log::warn!(
+                "'{}' was saved on another device since this save; keeping it as '{}'",
+                original,
+                name
+            );
+            folder.children.push(FolderChild::File(FilePointer {
+                id: crate::registry::generate_uuid_v4(),
+                name,
+                file_meta_ipns_name: copy.ipns_name.clone(),
+                ipns_private_key_encrypted: Some(copy.ipns_key_encrypted_hex.clone()),
+                created_at: copy.metadata.created_at,
+                modified_at: copy.metadata.modified_at,
+            }));
+            true
+        })
+        .await
+    }
+
+    /// Publish the uploaded save of `file_ipns_name` described by `metadata`.
+    ///
+    /// Compare-and-swap, as in release(): if another device saved the file
+    /// since `base`, this save becomes a conflicted copy instead. If the
+    /// remote version cannot be checked, nothing is published and the error
+    /// keeps the save journaled for the next replay. Returns whether the save
+    /// was published to the file itself.
+    async fn publish_save(
+        &self,
+        parent: &FolderRef,
+        file_ipns_name: &str,
+        ipns_private_key_encrypted: &str,
+        metadata: FileMetadata,
+        base: Option<&FileBase>,
+    ) -> Result<bool, String> {
+        let folder_key = self.folder_key(parent)?;
+        let ipns_key: IpnsSigningKey = self.unwrap_hex(ipns_private_key_encrypted)?;
+
+        let lock = self.coordinator.get_lock(file_ipns_name);
+        let _guard = lock.lock().await;
+        if let Some(base) = base {
+            let remote = crate::fuse::operations::remote_file_if_advanced_public(
+                &self.api,
+                &self.coordinator,
+                &folder_key,
+                file_ipns_name,
+                base,
+            )
+            .await
+            .map_err(|e| format!("Could not compare {} with its remote version: {}", file_ipns_name, e))?;
+            if remote.is_some() {
+                self.publish_conflict_copy(parent, file_ipns_name, metadata, &folder_key).await?;
+                return Ok(false);
+            }
+        }
+
+        crate::fuse::operations::publish_file_metadata_public(
+            &self.api,
+            &metadata,
+            &folder_key,
+            &ipns_key,
+            file_ipns_name,
+            &self.coordinator,
+            self.metadata_format,
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Publish the initial empty metadata of a new folder, unless it already has a record.
+    async fn publish_new_folder(
+        &self,
+        folder_key_encrypted: &str,
+        ipns_private_key_encrypted: &str,
+        ipns_name: &str,
+    ) -> Result<(), String> {
+        match crate::api::ipns::resolve_ipns(&self.api, ipns_name).await {
+            Ok(_) => return Ok(()),
+            Err(ResolveError::NotFound) if !self.coordinator.has_record(ipns_name) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let folder_key: FolderKey = self.unwrap_hex(folder_key_encrypted)?;
+        let ipns_key: IpnsSigningKey = self.unwrap_hex(ipns_private_key_encrypted)?;
+        let metadata = FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![],
+        };
+        let json_bytes = crate::crypto::folder::encrypt_folder_metadata_envelope(
+            &metadata,
+            &folder_key,
+            ipns_name,
+            self.metadata_format,
+        )
+        .map_err(|e| format!("Metadata encryption failed: {}", e))?;
+        let cid = crate::api::ipfs::upload_content(&self.api, &json_bytes).await?;
+
+        let record = crate::crypto::ipns::create_ipns_record(
+            &ipns_key,
+            &format!("/ipfs/{}", cid),
+            0,
+            86_400_000,
+        )
+        .map_err(|e| format!("IPNS record creation failed: {}", e))?;
+        let marshaled = crate::crypto::ipns::marshal_ipns_record(&record)
+            .map_err(|e| format!("IPNS marshal failed: {}", e))?;
+
+        let encrypted_ipns_for_tee = match &self.tee_public_key {
+            Some(tee_key) => Some(hex::encode(
+                crate::crypto::ecies::wrap_key(ipns_key.as_bytes(), tee_key)
+                    .map_err(|e| format!("TEE key wrapping failed: {}", e))?,
+            )),
+            None => None,
+        };
+
+        use base64::Engine;
+        let req = crate::api::ipns::IpnsPublishRequest {
+            ipns_name: ipns_name.to_string(),
+            record: base64::engine::general_purpose::STANDARD.encode(&marshaled),
+            metadata_cid: cid,
+            encrypted_ipns_private_key: encrypted_ipns_for_tee,
+            key_epoch: self.tee_key_epoch,
+        };
+        crate::api::ipns::publish_ipns(&self.api, &req).await?;
+        self.coordinator.record_publish(ipns_name, 0);
+        Ok(())
+    }
+}
+
+impl MutationHandler for JournalReplayer {
+    async fn apply(&self, op: &JournalOp, ipns_name: &str) -> Result<(), String> {
+        if let Some(folder) = op.folder(ipns_name) {
+            let what = format!("Journaled {}", op.describe());
+            return self
+                .edit_folder(folder, &what, |metadata| op.apply_to_folder(&folder.ipns_name, metadata))
+                .await;
+        }
+        match op {
+            JournalOp::Mkdir { entry, .. } if entry.ipns_name == ipns_name => {
+                self.publish_new_folder(
+                    &entry.folder_key_encrypted,
+                    &entry.ipns_private_key_encrypted,
+                    ipns_name,
+                )
+                .await
+            }
+            JournalOp::Write {
+                parent,
+                file_ipns_name,
+                ipns_private_key_encrypted,
+                metadata,
+                base,
+                ..
+            } if file_ipns_name == ipns_name => {
+                if let Some(write) = QueuedWrite::from_op(op) {
+                    return self.upload_and_register(&write).await;
+                }
+                let published = self
+                    .publish_save(
+                        parent,
+                        file_ipns_name,
+                        ipns_private_key_encrypted,
+                        metadata.clone(),
+                        base.as_ref(),
+                    )
+                    .await?;
+                if published {
+                    self.report_uploaded(file_ipns_name, &metadata.cid, &metadata.chunk_hashes);
+                }
+                Ok(())
+            }
+            _ => Err(format!("{} has nothing to publish to {}", op.describe(), ipns_name)),
+        }
+    }
+}
+
+impl UploadHandler for JournalReplayer {
+    async fn upload_and_register(&self, write: &QueuedWrite) -> Result<(), String> {
+        let (cid, chunk_hashes) = self.upload_spooled(&write.content_id, &write.metadata).await?;
+        log::info!("Journaled save of {} uploaded -> CID {}", write.file_ipns_name, cid);
+        if !self.journal.uploaded(&write.content_id, &cid, chunk_hashes.clone()) {
+            // Saved again (or deleted) meanwhile; that op publishes instead
+            return Ok(());
+        }
+        let mut metadata = write.metadata.clone();
+        metadata.cid = cid.clone();
+        metadata.chunk_hashes = chunk_hashes.clone();
+
+        let published = self
+            .publish_save(
+                &write.parent,
+                &write.file_ipns_name,
+                &write.ipns_private_key_encrypted,
+                metadata,
+                write.base.as_ref(),
+            )
+            .await?;
+        if published {
+            self.report_uploaded(&write.file_ipns_name, &cid, &chunk_hashes);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Sealed copies of saved files whose content is not uploaded yet.
+//!
+//! A save is journaled (see `journal`) before its content goes out, so the
+//! content has to survive the app quitting mid-upload as well. It is copied
+//! here, sealed with GCM-SEG under the device-local state key, and streamed
+//! back out when the journal replays the save. Copies are deleted once the
+//! content is uploaded or the save is superseded.
+//!
+//! File layout: `nonce prefix (7 bytes) || GCM-SEG ciphertext`. Memory use is
+//! a couple of 64 KiB segments regardless of file size.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use crate::crypto::aes_gcm_seg::{self, GCM_SEG_NONCE_PREFIX_SIZE};
+use crate::crypto::keys::LocalStateKey;
+
+/// Directory of sealed content copies.
+pub struct ContentSpool {
+    dir: PathBuf,
+    key: LocalStateKey,
+    /// Whether the directory only lives as long as this spool (for journals
+    /// kept in memory).
+    ephemeral: bool,
+}
+
+impl ContentSpool {
+    /// Spool in `dir`, created on first use.
+    pub fn new(dir: PathBuf, key: LocalStateKey) -> Self {
+        Self {
+            dir,
+            key,
+            ephemeral: false,
+        }
+    }
+
+    /// Spool in a fresh temp directory, removed when the spool is dropped.
+    pub fn ephemeral(key: LocalStateKey) -> Self {
+        let name = hex::encode(crate::crypto::utils::generate_random_bytes(8));
+        Self {
+            dir: std::env::temp_dir().join(format!("cipherbox-spool-{}", name)),
+            key,
+            ephemeral: true,
+        }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid spool id '{}'", id));
+        }
+        Ok(self.dir.join(id))
+    }
+
+    /// Seal everything `reader` yields as spooled content `id` (atomically,
+    /// via a temp file). Returns the plaintext length.
+    pub fn store(&self, id: &str, reader: impl Read) -> Result<u64, String> {
+        let path = self.path(id)?;
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create spool directory: {}", e))?;
+        let temp = path.with_extension("tmp");
+        let result = (|| -> Result<u64, String> {
+            let file = File::create(&temp).map_err(|e| format!("Failed to write spooled content: {}", e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = file.set_permissions(std::fs::Permissions::from_mode(0o600));
+            }
+            let mut writer = BufWriter::new(file);
+            let nonce_prefix = aes_gcm_seg::generate_nonce_prefix();
+            writer
+                .write_all(&nonce_prefix)
+                .map_err(|e| format!("Failed to write spooled content: {}", e))?;
+            let len = aes_gcm_seg::encrypt_aes_gcm_seg_stream(
+                BufReader::new(reader),
+                &mut writer,
+                self.key.as_bytes(),
+                &nonce_prefix,
+            )
+            .map_err(|e| format!("Failed to spool content: {}", e))?;
+            writer
+                .into_inner()
+                .map_err(|e| format!("Failed to write spooled content: {}", e.error()))?
+                .sync_all()
+                .map_err(|e| format!("Failed to write spooled content: {}", e))?;
+            std::fs::rename(&temp, &path).map_err(|e| format!("Failed to write spooled content: {}", e))?;
+            Ok(len)
+        })();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+
+    /// Decrypt spooled content `id` into `writer`. Returns the plaintext length.
+    ///
+    /// On error, whatever was already written must be discarded.
+    pub fn restore(&self, id: &str, writer: impl Write) -> Result<u64, String> {
+        let path = self.path(id)?;
+        let file = File::open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "Saved content was lost before it was uploaded".to_string(),
+            _ => format!("Failed to read spooled content: {}", e),
+        })?;
+        let mut reader = BufReader::new(file);
+        let mut nonce_prefix = [0u8; GCM_SEG_NONCE_PREFIX_SIZE];
+        reader
+            .read_exact(&mut nonce_prefix)
+            .map_err(|e| format!("Failed to read spooled content: {}", e))?;
+        aes_gcm_seg::decrypt_aes_gcm_seg_stream(reader, writer, self.key.as_bytes(), &nonce_prefix)
+            .map_err(|e| format!("Spooled content is unreadable: {}", e))
+    }
+
+    /// Delete spooled content `id`, if present.
+    pub fn remove(&self, id: &str) {
+        if let Ok(path) = self.path(id) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Failed to delete spooled content {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Delete everything in the spool except `live` (content of saves still
+    /// journaled), e.g. partial copies left by a crash.
+    pub fn retain(&self, live: &HashSet<String>) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if !name.to_str().is_some_and(|name| live.contains(name)) {
+                log::info!("Deleting orphaned spooled content {}", entry.path().display());
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+impl Drop for ContentSpool {
+    fn drop(&mut self) {
+        if self.ephemeral {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
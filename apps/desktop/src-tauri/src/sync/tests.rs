@@ -1,216 +1,997 @@
-//! Unit tests for the offline write queue.
-//!
-//! Uses a mock UploadHandler that can be configured to succeed or fail.
+//! Unit tests for the sync module: saves awaiting upload, the mutation
+//! journal, merging, the watch list, and replay of journaled saves against
+//! an in-memory backend.
 
 #[cfg(test)]
-mod write_queue_tests {
-    use std::sync::atomic::{AtomicU32, Ordering};
-    use std::sync::Arc;
-    use std::time::Instant;
+mod queue_tests {
+    use std::time::Duration;
+
+    use crate::sync::journal::{FolderRef, JournalOp};
+    use crate::sync::queue::{QueuedWrite, SaveFailures};
+
+    use super::journal_tests::write;
+
+    #[test]
+    fn test_spooled_save_is_a_queued_write() {
+        let queued = QueuedWrite::from_op(&write("kfile", 10, Some("abc"))).expect("queued write");
+        assert_eq!(queued.content_id, "abc");
+        assert_eq!(queued.file_ipns_name, "kfile");
+        assert_eq!(queued.parent.ipns_name, "kparent");
+        assert_eq!(queued.metadata.size, 10);
+        assert!(queued.base.is_none());
+    }
 
-    use crate::sync::queue::{QueuedWrite, UploadHandler, WriteQueue};
+    #[test]
+    fn test_uploaded_save_and_other_ops_are_not_queued() {
+        assert!(QueuedWrite::from_op(&write("kfile", 10, None)).is_none());
+        let unlink = JournalOp::Unlink {
+            parent: FolderRef {
+                ipns_name: "kparent".to_string(),
+                folder_key_encrypted: None,
+                ipns_private_key_encrypted: None,
+            },
+            name: "a.txt".to_string(),
+            child_ipns_name: "kfile".to_string(),
+        };
+        assert!(QueuedWrite::from_op(&unlink).is_none());
+    }
 
-    // ── Mock Upload Handler ──────────────────────────────────────────────
+    #[tokio::test]
+    async fn test_save_failure_reported_before_waiting_is_kept() {
+        let failures = SaveFailures::default();
+        failures.report("Could not save 'a.txt': disk full".to_string());
+
+        tokio::time::timeout(Duration::from_secs(1), failures.reported())
+            .await
+            .expect("report wakes the waiter");
+        assert_eq!(failures.take().as_deref(), Some("Could not save 'a.txt': disk full"));
+        assert_eq!(failures.take(), None);
+    }
 
-    /// Mock handler that always succeeds.
-    struct SuccessHandler {
-        call_count: AtomicU32,
+    #[tokio::test]
+    async fn test_retry_wakes_without_a_failure() {
+        let failures = SaveFailures::default();
+        failures.retry();
+
+        tokio::time::timeout(Duration::from_secs(1), failures.reported())
+            .await
+            .expect("retry wakes the waiter");
+        assert_eq!(failures.take(), None);
     }
+}
 
-    impl SuccessHandler {
-        fn new() -> Self {
-            Self {
-                call_count: AtomicU32::new(0),
-            }
+#[cfg(test)]
+mod journal_tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::crypto::folder::{FilePointer, FolderChild, FolderMetadata};
+    use crate::crypto::keys::LocalStateKey;
+    use crate::sync::journal::{
+        FolderRef, Journal, JournalOp, MutationHandler, MAX_REPLAY_ATTEMPTS,
+    };
+
+    // ── Helpers ──────────────────────────────────────────────────────────
+
+    fn folder(ipns_name: &str) -> FolderRef {
+        FolderRef {
+            ipns_name: ipns_name.to_string(),
+            folder_key_encrypted: None,
+            ipns_private_key_encrypted: None,
         }
+    }
 
-        fn calls(&self) -> u32 {
-            self.call_count.load(Ordering::SeqCst)
+    fn pointer(name: &str, ipns_name: &str) -> FilePointer {
+        FilePointer {
+            id: format!("id-{}", ipns_name),
+            name: name.to_string(),
+            file_meta_ipns_name: ipns_name.to_string(),
+            ipns_private_key_encrypted: None,
+            created_at: 1,
+            modified_at: 1,
         }
     }
 
-    impl UploadHandler for SuccessHandler {
-        async fn upload_and_register(
-            &self,
-            _write: &QueuedWrite,
-        ) -> Result<(), String> {
-            self.call_count.fetch_add(1, Ordering::SeqCst);
-            Ok(())
+    fn create(parent: &str, name: &str, ipns_name: &str) -> JournalOp {
+        JournalOp::Create {
+            parent: folder(parent),
+            pointer: pointer(name, ipns_name),
         }
     }
 
-    /// Mock handler that always fails with a configurable message.
-    struct FailHandler;
+    pub(super) fn write(file_ipns_name: &str, size: u64, content: Option<&str>) -> JournalOp {
+        JournalOp::Write {
+            parent: folder("kparent"),
+            file_ipns_name: file_ipns_name.to_string(),
+            ipns_private_key_encrypted: "00".to_string(),
+            metadata: crate::crypto::folder::FileMetadata {
+                version: "v1".to_string(),
+                cid: if content.is_some() { String::new() } else { format!("cid{}", size) },
+                file_key_encrypted: String::new(),
+                file_iv: String::new(),
+                size,
+                mime_type: "text/plain".to_string(),
+                encryption_mode: "GCM".to_string(),
+                padding: None,
+                chunk_hashes: None,
+                created_at: 1,
+                modified_at: 1,
+                versions: None,
+            },
+            content: content.map(str::to_string),
+            base: None,
+        }
+    }
 
-    impl UploadHandler for FailHandler {
-        async fn upload_and_register(
-            &self,
-            _write: &QueuedWrite,
-        ) -> Result<(), String> {
-            Err("network unreachable".to_string())
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("cipherbox-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn names(metadata: &FolderMetadata) -> Vec<String> {
+        metadata
+            .children
+            .iter()
+            .map(|c| crate::sync::journal::child_name(c).to_string())
+            .collect()
+    }
+
+    fn empty() -> FolderMetadata {
+        FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![],
         }
     }
 
-    /// Mock handler that tracks the order of processed filenames.
-    struct OrderTracker {
-        order: std::sync::Mutex<Vec<String>>,
+    fn memory_journal() -> Arc<Journal> {
+        Arc::new(Journal::open(None, LocalStateKey::from_bytes([7u8; 32]), "kroot"))
+    }
+
+    /// Records (op description, target) of every apply; fails with `error`
+    /// for targets listed in `failing`.
+    struct RecordingHandler {
+        applied: Mutex<Vec<(String, String)>>,
+        failing: Vec<String>,
+        error: String,
     }
 
-    impl OrderTracker {
-        fn new() -> Self {
+    impl RecordingHandler {
+        fn new(failing: &[&str], error: &str) -> Self {
             Self {
-                order: std::sync::Mutex::new(Vec::new()),
+                applied: Mutex::new(Vec::new()),
+                failing: failing.iter().map(|s| s.to_string()).collect(),
+                error: error.to_string(),
             }
         }
 
-        fn processed_order(&self) -> Vec<String> {
-            self.order.lock().unwrap().clone()
+        fn applied(&self) -> Vec<(String, String)> {
+            self.applied.lock().unwrap().clone()
         }
     }
 
-    impl UploadHandler for OrderTracker {
-        async fn upload_and_register(
-            &self,
-            write: &QueuedWrite,
-        ) -> Result<(), String> {
-            self.order.lock().unwrap().push(write.filename.clone());
+    impl MutationHandler for RecordingHandler {
+        async fn apply(&self, op: &JournalOp, ipns_name: &str) -> Result<(), String> {
+            if self.failing.iter().any(|f| f == ipns_name) {
+                return Err(self.error.clone());
+            }
+            self.applied
+                .lock()
+                .unwrap()
+                .push((op.describe(), ipns_name.to_string()));
             Ok(())
         }
     }
 
+    // ── Tests ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_ack_covers_only_ops_recorded_before() {
+        let journal = memory_journal();
+        journal.record(create("kparent", "a.txt", "kfa"));
+        let ack = journal.acker();
+        journal.record(create("kparent", "b.txt", "kfb"));
+
+        ack.ack("kparent");
+        let entries = journal.entries();
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(&entries[0].op, JournalOp::Create { pointer, .. } if pointer.name == "b.txt"));
+    }
+
+    #[test]
+    fn test_entry_stays_until_all_targets_acked() {
+        let journal = memory_journal();
+        journal.record(JournalOp::Rename {
+            from: folder("kfrom"),
+            from_name: "a.txt".to_string(),
+            to: folder("kto"),
+            to_name: "b.txt".to_string(),
+            child: FolderChild::File(pointer("a.txt", "kfa")),
+        });
+
+        journal.acker().ack("kfrom");
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal.entries()[0].pending, vec!["kto".to_string()]);
+        journal.acker().ack("kto");
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn test_write_supersedes_pending_write_of_same_file() {
+        let journal = memory_journal();
+        journal.record(write("kfile", 1, None));
+        journal.record(create("kparent", "c.txt", "kfc"));
+        journal.record(write("kfile", 2, None));
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[1].op, JournalOp::Write { metadata, .. } if metadata.size == 2));
+    }
+
+    #[test]
+    fn test_spooled_content_survives_reopen_until_uploaded() {
+        let dir = test_dir("journal-spool");
+        let path = dir.join("kroot");
+        let key = LocalStateKey::from_bytes([3u8; 32]);
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let journal = Journal::open(Some(path.clone()), key.clone(), "kroot");
+        let id = Journal::new_spool_id();
+        journal.record(write("kfile", content.len() as u64, Some(&id)));
+        journal.spool().store(&id, &content[..]).unwrap();
+        // A copy no entry refers to, as left by a crash
+        journal.spool().store("0rphan", &b"gone"[..]).unwrap();
+        drop(journal);
+
+        let spool_dir = path.with_extension("spool");
+        let sealed = std::fs::read(spool_dir.join(&id)).unwrap();
+        assert!(!sealed.windows(64).any(|w| w == &content[1000..1064]));
+
+        let journal = Journal::open(Some(path.clone()), key, "kroot");
+        assert!(!spool_dir.join("0rphan").exists());
+        let mut restored = Vec::new();
+        journal.spool().restore(&id, &mut restored).unwrap();
+        assert_eq!(restored, content);
+
+        assert!(journal.uploaded(&id, "bafyuploaded", None));
+        assert!(!spool_dir.join(&id).exists());
+        let entries = journal.entries();
+        assert!(matches!(
+            &entries[0].op,
+            JournalOp::Write { metadata, content: None, .. } if metadata.cid == "bafyuploaded"
+        ));
+        assert!(!journal.uploaded(&id, "bafyagain", None));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_superseded_and_unlinked_saves_drop_spooled_content() {
+        let journal = memory_journal();
+        let first = Journal::new_spool_id();
+        journal.record(write("kfile", 1, Some(&first)));
+        journal.spool().store(&first, &b"one"[..]).unwrap();
+        let second = Journal::new_spool_id();
+        journal.record(write("kfile", 2, Some(&second)));
+        journal.spool().store(&second, &b"two"[..]).unwrap();
+
+        assert!(journal.spool().restore(&first, &mut Vec::new()).is_err());
+        assert!(!journal.uploaded(&first, "bafyold", None));
+
+        journal.record(JournalOp::Unlink {
+            parent: folder("kparent"),
+            name: "file.txt".to_string(),
+            child_ipns_name: "kfile".to_string(),
+        });
+        assert_eq!(journal.len(), 1);
+        assert!(journal.spool().restore(&second, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_journal_persists_sealed() {
+        let dir = test_dir("journal");
+        let path = dir.join("kroot");
+        let key = LocalStateKey::from_bytes([1u8; 32]);
+
+        let journal = Journal::open(Some(path.clone()), key.clone(), "kroot");
+        journal.record(create("kroot", "secret-name.txt", "kfa"));
+        drop(journal);
+
+        let on_disk = std::fs::read(&path).unwrap();
+        assert!(!on_disk.windows(11).any(|w| w == b"secret-name"));
+
+        let reopened = Journal::open(Some(path.clone()), key, "kroot");
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened.last_seq(), 1);
+
+        // Another key cannot read it; the file is kept aside, not overwritten
+        let other = Journal::open(Some(path.clone()), LocalStateKey::from_bytes([2u8; 32]), "kroot");
+        assert!(other.is_empty());
+        assert!(path.with_extension("unreadable").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_apply_create_is_idempotent() {
+        let op = create("kparent", "a.txt", "kfa");
+        let mut metadata = empty();
+        assert!(op.apply_to_folder("kparent", &mut metadata));
+        assert!(!op.apply_to_folder("kparent", &mut metadata));
+        assert!(!op.apply_to_folder("kother", &mut metadata));
+        assert_eq!(names(&metadata), vec!["a.txt"]);
+    }
+
+    #[test]
+    fn test_apply_rename_across_folders() {
+        let op = JournalOp::Rename {
+            from: folder("kfrom"),
+            from_name: "a.txt".to_string(),
+            to: folder("kto"),
+            to_name: "b.txt".to_string(),
+            child: FolderChild::File(pointer("a.txt", "kfa")),
+        };
+        let mut from = empty();
+        from.children.push(FolderChild::File(pointer("a.txt", "kfa")));
+        from.children.push(FolderChild::File(pointer("keep.txt", "kfk")));
+        let mut to = empty();
+        to.children.push(FolderChild::File(pointer("b.txt", "kfold")));
+
+        assert!(op.apply_to_folder("kfrom", &mut from));
+        assert!(op.apply_to_folder("kto", &mut to));
+        assert_eq!(names(&from), vec!["keep.txt"]);
+        assert_eq!(names(&to), vec!["b.txt"]);
+        assert!(matches!(&to.children[0], FolderChild::File(p) if p.file_meta_ipns_name == "kfa"));
+        assert!(!op.apply_to_folder("kto", &mut to));
+    }
+
+    #[test]
+    fn test_apply_unlink_only_removes_same_child() {
+        let op = JournalOp::Unlink {
+            parent: folder("kparent"),
+            name: "a.txt".to_string(),
+            child_ipns_name: "kfa".to_string(),
+        };
+        let mut metadata = empty();
+        // Same name, but a different file created remotely since
+        metadata.children.push(FolderChild::File(pointer("a.txt", "kfnew")));
+        assert!(!op.apply_to_folder("kparent", &mut metadata));
+
+        metadata.children = vec![FolderChild::File(pointer("a.txt", "kfa"))];
+        assert!(op.apply_to_folder("kparent", &mut metadata));
+        assert!(metadata.children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_in_order() {
+        let journal = memory_journal();
+        journal.record(create("kparent", "a.txt", "kfa"));
+        journal.record(create("kparent", "b.txt", "kfb"));
+
+        let handler = RecordingHandler::new(&[], "");
+        let mut progress = Vec::new();
+        let report = journal.replay(&handler, |done, total| progress.push((done, total))).await;
+
+        assert_eq!(report.applied, 2);
+        assert_eq!(report.remaining, 0);
+        assert!(report.failure.is_none());
+        assert!(journal.is_empty());
+        let order: Vec<String> = handler.applied().into_iter().map(|(d, _)| d).collect();
+        assert_eq!(order, vec!["create 'a.txt'", "create 'b.txt'"]);
+        assert_eq!(progress, vec![(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_waits_for_claimed_op() {
+        let journal = memory_journal();
+        let seq = journal.record(write("kfile", 1, None));
+        journal.record(create("kparent", "b.txt", "kfb"));
+
+        let handler = RecordingHandler::new(&[], "");
+        let claim = journal.claim(seq);
+        let report = journal.replay(&handler, |_, _| {}).await;
+        assert!(report.failure.is_none());
+        assert_eq!(report.remaining, 2);
+        assert!(handler.applied().is_empty());
+
+        drop(claim);
+        let report = journal.replay(&handler, |_, _| {}).await;
+        assert_eq!(report.applied, 2);
+        assert!(journal.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_stops_at_network_failure() {
+        let journal = memory_journal();
+        journal.record(create("kdown", "a.txt", "kfa"));
+        journal.record(create("kup", "b.txt", "kfb"));
+
+        let handler = RecordingHandler::new(&["kdown"], "connect error: network unreachable");
+        for _ in 0..MAX_REPLAY_ATTEMPTS + 1 {
+            let report = journal.replay(&handler, |_, _| {}).await;
+            let failure = report.failure.unwrap();
+            assert!(!failure.dropped, "network failures never drop an op");
+            assert_eq!(report.remaining, 2);
+        }
+        // The later op never overtook the failing one
+        assert!(handler.applied().is_empty());
+        assert_eq!(journal.entries()[0].attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_drops_op_after_max_attempts() {
+        let journal = memory_journal();
+        journal.record(create("kbroken", "a.txt", "kfa"));
+        journal.record(create("kok", "b.txt", "kfb"));
+
+        let handler = RecordingHandler::new(&["kbroken"], "Metadata decryption failed");
+        for attempt in 1..MAX_REPLAY_ATTEMPTS {
+            let report = journal.replay(&handler, |_, _| {}).await;
+            assert!(!report.failure.unwrap().dropped);
+            assert_eq!(journal.entries()[0].attempts, attempt);
+        }
+        let report = journal.replay(&handler, |_, _| {}).await;
+        assert!(report.failure.unwrap().dropped);
+        assert_eq!(journal.len(), 1);
+
+        let report = journal.replay(&handler, |_, _| {}).await;
+        assert_eq!(report.applied, 1);
+        assert!(journal.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::crypto::folder::{FilePointer, FolderChild, FolderMetadata};
+    use crate::sync::journal::child_name;
+    use crate::sync::merge::{conflict_copy_name, free_conflict_copy_name, merge_folder_metadata};
+
     // ── Helpers ──────────────────────────────────────────────────────────
 
-    fn make_write(id: &str, filename: &str) -> QueuedWrite {
-        QueuedWrite {
-            id: id.to_string(),
-            parent_ino: 1,
-            encrypted_content: vec![0xDE, 0xAD],
-            encrypted_file_key: vec![0xBE, 0xEF],
-            iv: vec![0x00; 12],
-            filename: filename.to_string(),
-            created_at: Instant::now(),
-            retries: 0,
+    fn file(name: &str, ipns_name: &str) -> FolderChild {
+        FolderChild::File(FilePointer {
+            id: format!("id-{}", ipns_name),
+            name: name.to_string(),
+            file_meta_ipns_name: ipns_name.to_string(),
+            ipns_private_key_encrypted: None,
+            created_at: 1,
+            modified_at: 1,
+        })
+    }
+
+    fn folder(children: Vec<FolderChild>) -> FolderMetadata {
+        FolderMetadata {
+            version: "v2".to_string(),
+            children,
         }
     }
 
+    fn names(metadata: &FolderMetadata) -> Vec<&str> {
+        metadata.children.iter().map(child_name).collect()
+    }
+
     // ── Tests ────────────────────────────────────────────────────────────
 
-    #[tokio::test]
-    async fn test_write_queue_enqueue_and_len() {
-        let mut queue = WriteQueue::new(5);
-        assert_eq!(queue.len(), 0);
-        assert!(queue.is_empty());
+    #[test]
+    fn test_merge_keeps_additions_from_both_sides() {
+        let base = folder(vec![file("a.txt", "ka")]);
+        let local = folder(vec![file("a.txt", "ka"), file("mine.txt", "kmine")]);
+        let remote = folder(vec![file("a.txt", "ka"), file("theirs.txt", "ktheirs")]);
 
-        queue.enqueue(make_write("1", "a.txt"));
-        assert_eq!(queue.len(), 1);
-        assert!(!queue.is_empty());
+        let merge = merge_folder_metadata(&base, &local, &remote, "laptop");
+        assert_eq!(names(&merge.metadata), vec!["a.txt", "theirs.txt", "mine.txt"]);
+        assert!(merge.conflicts.is_empty());
+    }
 
-        queue.enqueue(make_write("2", "b.txt"));
-        assert_eq!(queue.len(), 2);
+    #[test]
+    fn test_merge_applies_deletions_from_both_sides() {
+        let base = folder(vec![file("a.txt", "ka"), file("b.txt", "kb"), file("c.txt", "kc")]);
+        let local = folder(vec![file("b.txt", "kb"), file("c.txt", "kc")]);
+        let remote = folder(vec![file("a.txt", "ka"), file("c.txt", "kc")]);
 
-        queue.enqueue(make_write("3", "c.txt"));
-        assert_eq!(queue.len(), 3);
+        let merge = merge_folder_metadata(&base, &local, &remote, "laptop");
+        assert_eq!(names(&merge.metadata), vec!["c.txt"]);
     }
 
-    #[tokio::test]
-    async fn test_write_queue_process_success() {
-        let mut queue = WriteQueue::new(5);
-        queue.enqueue(make_write("1", "a.txt"));
-        queue.enqueue(make_write("2", "b.txt"));
+    #[test]
+    fn test_merge_takes_renames() {
+        let base = folder(vec![file("a.txt", "ka"), file("b.txt", "kb"), file("c.txt", "kc")]);
+        let local = folder(vec![file("a.txt", "ka"), file("b-local.txt", "kb"), file("c-local.txt", "kc")]);
+        let remote = folder(vec![file("a-remote.txt", "ka"), file("b.txt", "kb"), file("c-remote.txt", "kc")]);
+
+        let merge = merge_folder_metadata(&base, &local, &remote, "laptop");
+        // One-sided renames win; when both renamed, the local name does
+        assert_eq!(names(&merge.metadata), vec!["a-remote.txt", "b-local.txt", "c-local.txt"]);
+    }
 
-        let handler = SuccessHandler::new();
-        let processed = queue.process(&handler).await.unwrap();
+    #[test]
+    fn test_merge_turns_name_clash_into_conflicted_copy() {
+        let base = folder(vec![]);
+        let local = folder(vec![file("notes.txt", "kmine")]);
+        let remote = folder(vec![file("notes.txt", "ktheirs")]);
 
-        assert_eq!(processed, 2);
-        assert_eq!(handler.calls(), 2);
-        assert!(queue.is_empty());
+        let merge = merge_folder_metadata(&base, &local, &remote, "laptop");
+        assert_eq!(
+            names(&merge.metadata),
+            vec!["notes.txt", "notes (conflicted copy from laptop).txt"]
+        );
+        assert_eq!(merge.conflicts, vec!["notes (conflicted copy from laptop).txt"]);
+        // The remote file keeps the name; the local one is the copy
+        match &merge.metadata.children[1] {
+            FolderChild::File(p) => assert_eq!(p.file_meta_ipns_name, "kmine"),
+            _ => panic!("Expected a file"),
+        }
     }
 
-    #[tokio::test]
-    async fn test_write_queue_process_failure_retries() {
-        let mut queue = WriteQueue::new(3); // max 3 retries
-        queue.enqueue(make_write("1", "failing.txt"));
+    #[test]
+    fn test_merge_conflicted_copy_names_stay_unique() {
+        let base = folder(vec![]);
+        let local = folder(vec![file("notes.txt", "kmine")]);
+        let remote = folder(vec![
+            file("notes.txt", "ktheirs"),
+            file("notes (conflicted copy from laptop).txt", "kolder"),
+        ]);
+
+        let merge = merge_folder_metadata(&base, &local, &remote, "laptop");
+        assert_eq!(merge.conflicts, vec!["notes (conflicted copy from laptop 2).txt"]);
+    }
 
-        let handler = FailHandler;
+    #[test]
+    fn test_merge_without_base_keeps_remote_entries() {
+        let local = folder(vec![file("mine.txt", "kmine")]);
+        let remote = folder(vec![file("theirs.txt", "ktheirs")]);
 
-        // First process: item fails, retries=1, moved to back
-        let processed = queue.process(&handler).await.unwrap();
-        assert_eq!(processed, 0);
-        assert_eq!(queue.len(), 1); // Still in queue
+        let merge = merge_folder_metadata(&folder(vec![]), &local, &remote, "laptop");
+        assert_eq!(names(&merge.metadata), vec!["theirs.txt", "mine.txt"]);
+    }
 
-        // Second process: retries=2
-        let _ = queue.process(&handler).await;
-        assert_eq!(queue.len(), 1);
+    #[test]
+    fn test_conflict_copy_name() {
+        assert_eq!(conflict_copy_name("report.final.pdf", "x"), "report.final (x).pdf");
+        assert_eq!(conflict_copy_name("Photos", "x"), "Photos (x)");
+        assert_eq!(conflict_copy_name(".bashrc", "x"), ".bashrc (x)");
+    }
 
-        // Third process: retries=3
-        let _ = queue.process(&handler).await;
-        assert_eq!(queue.len(), 1);
+    #[test]
+    fn test_free_conflict_copy_name_numbers_taken_names() {
+        let mut taken = std::collections::HashSet::new();
+        assert_eq!(free_conflict_copy_name("a.txt", "x", &taken), "a (x).txt");
 
-        // Fourth process: retries=4 > max_retries=3, item dropped
-        let _ = queue.process(&handler).await;
-        assert_eq!(queue.len(), 0);
-        assert!(queue.is_empty());
+        taken.insert("a (x).txt".to_string());
+        taken.insert("a (x 2).txt".to_string());
+        assert_eq!(free_conflict_copy_name("a.txt", "x", &taken), "a (x 3).txt");
     }
+}
 
-    #[tokio::test]
-    async fn test_write_queue_fifo_order() {
-        let mut queue = WriteQueue::new(5);
-        queue.enqueue(make_write("1", "first.txt"));
-        queue.enqueue(make_write("2", "second.txt"));
-        queue.enqueue(make_write("3", "third.txt"));
+#[cfg(test)]
+mod watch_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::sync::watch::{NameKind, RemoteChange, WatchList};
 
-        let tracker = OrderTracker::new();
-        let processed = queue.process(&tracker).await.unwrap();
+    // ── Helpers ──────────────────────────────────────────────────────────
+
+    /// root: folders "docs" and "pics"; "docs" holds file "a", root holds "b".
+    fn tree() -> WatchList {
+        let mut list = WatchList::default();
+        list.set_root("root");
+        list.replace(vec![
+            ("docs".to_string(), NameKind::Folder, Some("root".to_string())),
+            ("pics".to_string(), NameKind::Folder, Some("root".to_string())),
+            ("a".to_string(), NameKind::File, Some("docs".to_string())),
+            ("b".to_string(), NameKind::File, Some("root".to_string())),
+        ]);
+        list
+    }
+
+    // ── Tests ────────────────────────────────────────────────────────────
 
-        assert_eq!(processed, 3);
+    #[test]
+    fn test_record_reports_forward_moves_after_baseline() {
+        let mut list = tree();
+
+        assert_eq!(list.record("docs", 3, "bafy3"), None, "first sequence is the baseline");
+        assert_eq!(list.record("docs", 3, "bafy3"), None);
         assert_eq!(
-            tracker.processed_order(),
-            vec!["first.txt", "second.txt", "third.txt"]
+            list.record("docs", 5, "bafy5"),
+            Some(RemoteChange {
+                ipns_name: "docs".to_string(),
+                kind: NameKind::Folder,
+                previous_sequence: 3,
+                sequence: 5,
+                cid: "bafy5".to_string(),
+            })
         );
+        assert_eq!(list.record("docs", 4, "bafy4"), None, "lagging resolver");
+        assert_eq!(list.record("docs", 6, "bafy6").map(|c| c.previous_sequence), Some(5));
+        assert_eq!(list.record("unknown", 1, "bafy"), None);
     }
 
-    #[tokio::test]
-    async fn test_write_queue_is_empty() {
-        let mut queue = WriteQueue::new(5);
+    #[test]
+    fn test_replace_keeps_known_sequences_and_root() {
+        let mut list = tree();
+        list.record("root", 1, "bafyr");
+        list.record("a", 2, "bafya");
+
+        list.replace(vec![("a".to_string(), NameKind::File, Some("pics".to_string()))]);
+
+        assert_eq!(list.len(), 2);
+        assert!(list.record("root", 2, "bafyr2").is_some());
+        assert!(list.record("a", 3, "bafya3").is_some());
+        assert_eq!(list.record("docs", 1, "bafyd"), None, "no longer tracked");
+    }
 
-        // Empty on init
-        assert!(queue.is_empty());
+    #[test]
+    fn test_poll_batch_prefers_root_and_recent_accesses() {
+        let mut list = tree();
+        list.touch("docs");
 
-        // Non-empty after enqueue
-        queue.enqueue(make_write("1", "test.txt"));
-        assert!(!queue.is_empty());
+        let batch = list.poll_batch(8, Instant::now());
+        assert_eq!(batch[..3], ["root", "docs", "a"], "root, accessed folder, then its file");
+        assert_eq!(batch.len(), 5);
 
-        // Empty after successful process
-        let handler = SuccessHandler::new();
-        let _ = queue.process(&handler).await;
-        assert!(queue.is_empty());
+        // Accesses older than RECENT_ACCESS no longer count
+        let later = Instant::now() + Duration::from_secs(10 * 60);
+        let batch = list.poll_batch(8, later);
+        assert_eq!(batch[0], "root");
+        assert_eq!(batch.len(), 5);
     }
 
+    #[test]
+    fn test_poll_batch_round_robins_the_rest() {
+        let mut list = tree();
+        let now = Instant::now();
+
+        let first = list.poll_batch(3, now);
+        let second = list.poll_batch(3, now + Duration::from_secs(30));
+        assert_eq!(first[0], "root");
+        assert_eq!(second[0], "root");
+
+        // Four non-root names, two per batch: the second batch polls the other two
+        let mut polled: Vec<_> = first[1..].iter().chain(&second[1..]).cloned().collect();
+        polled.sort();
+        assert_eq!(polled, ["a", "b", "docs", "pics"]);
+    }
+
+    #[test]
+    fn test_recent_accesses_leave_room_for_the_rest() {
+        let mut list = WatchList::default();
+        list.set_root("root");
+        list.replace((0..8).map(|i| (format!("f{}", i), NameKind::Folder, Some("root".to_string()))));
+        for i in 0..6 {
+            list.touch(&format!("f{}", i));
+        }
+
+        let batch = list.poll_batch(4, Instant::now());
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch[0], "root");
+        let untouched = batch.iter().filter(|name| *name == "f6" || *name == "f7").count();
+        assert_eq!(untouched, 1, "a quarter of the batch goes to names not accessed recently");
+    }
+}
+
+#[cfg(all(test, feature = "fuse"))]
+mod replay_tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use base64::Engine;
+
+    use crate::api::client::ApiClient;
+    use crate::api::test_server::{self, Request};
+    use crate::crypto::folder::{FileMetadata, FilePointer, FolderChild, FolderMetadata, MetadataFormat};
+    use crate::crypto::keys::{FileKey, FolderKey, IpnsSigningKey, LocalStateKey, Secp256k1SecretKey};
+    use crate::fuse::file_handle::FileBase;
+    use crate::fuse::{PublishCoordinator, UploadComplete};
+    use crate::sync::journal::{child_ipns_name, child_name, FolderRef, Journal, JournalOp};
+    use crate::sync::replay::JournalReplayer;
+
+    // ── Helpers ──────────────────────────────────────────────────────────
+
+    /// In-memory stand-in for the backend's IPFS and IPNS endpoints.
+    #[derive(Default)]
+    struct Backend {
+        /// Content by CID.
+        content: HashMap<String, Vec<u8>>,
+        /// Current record by IPNS name: (CID, sequence).
+        records: HashMap<String, (String, u64)>,
+    }
+
+    impl Backend {
+        fn add(&mut self, bytes: Vec<u8>) -> String {
+            let cid = format!("bafytest{}", self.content.len());
+            self.content.insert(cid.clone(), bytes);
+            cid
+        }
+
+        /// Answer one request with a status and a body.
+        fn handle(&mut self, request: &Request) -> (u16, Vec<u8>) {
+            let (method, path, body) = (request.method(), request.path(), &request.body);
+            let json = |value: serde_json::Value| value.to_string().into_bytes();
+            match method {
+                "POST" if path == "/ipfs/upload" => {
+                    let cid = self.add(request.multipart_file());
+                    (200, json(serde_json::json!({ "cid": cid })))
+                }
+                "POST" if path == "/ipfs/unpin" => (200, json(serde_json::json!({ "success": true }))),
+                "POST" if path == "/ipns/publish" => {
+                    let request: serde_json::Value = serde_json::from_slice(body).unwrap();
+                    let name = request["ipnsName"].as_str().unwrap().to_string();
+                    let marshaled = base64::engine::general_purpose::STANDARD
+                        .decode(request["record"].as_str().unwrap())
+                        .unwrap();
+                    let record = crate::crypto::ipns::validate_ipns_record(&marshaled, &name).unwrap();
+                    if self.records.get(&name).is_some_and(|(_, seq)| *seq >= record.sequence) {
+                        return (409, json(serde_json::json!({ "error": "stale sequence" })));
+                    }
+                    let cid = record.value.trim_start_matches("/ipfs/").to_string();
+                    self.records.insert(name, (cid, record.sequence));
+                    (200, json(serde_json::json!({ "success": true })))
+                }
+                "GET" if path.starts_with("/ipns/resolve?ipnsName=") => {
+                    match self.records.get(&path["/ipns/resolve?ipnsName=".len()..]) {
+                        Some((cid, seq)) => (
+                            200,
+                            json(serde_json::json!({
+                                "success": true,
+                                "cid": cid,
+                                "sequenceNumber": seq.to_string(),
+                            })),
+                        ),
+                        None => (404, json(serde_json::json!({ "error": "not found" }))),
+                    }
+                }
+                "GET" if path.starts_with("/ipfs/") => match self.content.get(&path["/ipfs/".len()..]) {
+                    Some(bytes) => (200, bytes.clone()),
+                    None => (404, Vec::new()),
+                },
+                _ => (404, Vec::new()),
+            }
+        }
+    }
+
+    /// Serve `backend` from a local listener. Returns the base URL.
+    async fn serve(backend: Arc<Mutex<Backend>>) -> String {
+        test_server::serve(move |request| backend.lock().unwrap().handle(&request)).await
+    }
+
+    fn wrap(key: &[u8], public_key: &[u8]) -> String {
+        hex::encode(crate::crypto::ecies::wrap_key(key, public_key).unwrap())
+    }
+
+    fn ipns_name(public_key: Vec<u8>) -> String {
+        crate::crypto::ipns::derive_ipns_name(&public_key.try_into().unwrap()).unwrap()
+    }
+
+    fn decrypt(ciphertext: &[u8], file_key: &FileKey, iv: &[u8; 12]) -> Vec<u8> {
+        crate::crypto::aes::decrypt_aes_gcm(ciphertext, file_key.as_bytes(), iv).unwrap()
+    }
+
+    fn file_metadata(cid: &str, file_key_encrypted: String, iv: &[u8; 12], size: u64) -> FileMetadata {
+        FileMetadata {
+            version: "v1".to_string(),
+            cid: cid.to_string(),
+            file_key_encrypted,
+            file_iv: hex::encode(iv),
+            size,
+            mime_type: "text/plain".to_string(),
+            encryption_mode: "GCM".to_string(),
+            padding: None,
+            chunk_hashes: None,
+            created_at: 1,
+            modified_at: 2,
+            versions: None,
+        }
+    }
+
+    /// A vault whose root folder holds "notes.txt", kept by a `Backend`, and
+    /// the replayer of its journal.
+    struct TestVault {
+        backend: Arc<Mutex<Backend>>,
+        replayer: JournalReplayer,
+        uploads: std::sync::mpsc::Receiver<UploadComplete>,
+        file_ipns_name: String,
+        file_ipns_key_encrypted: String,
+    }
+
+    async fn vault(name: &str) -> TestVault {
+        let secret = [9u8; 32];
+        let public_key = ::ecies::PublicKey::from_secret_key(
+            &::ecies::SecretKey::parse_slice(&secret).unwrap(),
+        )
+        .serialize()
+        .to_vec();
+        let (root_ipns_key, root_ipns_public) = IpnsSigningKey::generate();
+        let root_ipns_name = ipns_name(root_ipns_public);
+        let (file_ipns_key, file_ipns_public) = IpnsSigningKey::generate();
+        let file_ipns_name = ipns_name(file_ipns_public);
+        let file_ipns_key_encrypted = wrap(file_ipns_key.as_bytes(), &public_key);
+        let folder_key = FolderKey::generate();
+
+        let root = FolderMetadata {
+            version: "v2".to_string(),
+            children: vec![FolderChild::File(FilePointer {
+                id: "file-1".to_string(),
+                name: "notes.txt".to_string(),
+                file_meta_ipns_name: file_ipns_name.clone(),
+                ipns_private_key_encrypted: Some(file_ipns_key_encrypted.clone()),
+                created_at: 1,
+                modified_at: 1,
+            })],
+        };
+        let envelope = crate::crypto::folder::encrypt_folder_metadata_envelope(
+            &root,
+            &folder_key,
+            &root_ipns_name,
+            MetadataFormat::default(),
+        )
+        .unwrap();
+        let backend = Arc::new(Mutex::new(Backend::default()));
+        {
+            let mut backend = backend.lock().unwrap();
+            let cid = backend.add(envelope);
+            backend.records.insert(root_ipns_name.clone(), (cid, 1));
+        }
+
+        let (upload_tx, uploads) = std::sync::mpsc::channel();
+        let replayer = JournalReplayer {
+            api: Arc::new(ApiClient::new(&serve(backend.clone()).await)),
+            journal: Arc::new(Journal::open(None, LocalStateKey::from_bytes([7u8; 32]), &root_ipns_name)),
+            coordinator: Arc::new(PublishCoordinator::new()),
+            private_key: Secp256k1SecretKey::from_bytes(secret),
+            mlkem_private_key: None,
+            root_ipns_name,
+            root_folder_key: folder_key,
+            root_ipns_private_key: Some(root_ipns_key),
+            tee_public_key: None,
+            tee_key_epoch: None,
+            metadata_format: MetadataFormat::default(),
+            upload_txs: Mutex::new(vec![upload_tx]),
+            temp_dir: std::env::temp_dir().join(format!("cipherbox-test-{}-{}", name, std::process::id())),
+            public_key,
+        };
+        TestVault {
+            backend,
+            replayer,
+            uploads,
+            file_ipns_name,
+            file_ipns_key_encrypted,
+        }
+    }
+
+    impl TestVault {
+        /// Publish a save of notes.txt with content `content_cid` as record
+        /// `sequence`, as another device would.
+        fn remote_save(&self, content_cid: &str, sequence: u64) {
+            let metadata = file_metadata(content_cid, String::new(), &[0u8; 12], 0);
+            let envelope = crate::crypto::folder::encrypt_file_metadata_envelope(
+                &metadata,
+                &self.replayer.root_folder_key,
+                &self.file_ipns_name,
+                MetadataFormat::default(),
+            )
+            .unwrap();
+            let mut backend = self.backend.lock().unwrap();
+            let cid = backend.add(envelope);
+            backend.records.insert(self.file_ipns_name.clone(), (cid, sequence));
+        }
+
+        /// Journal a save of notes.txt based on `base`, its content in the
+        /// spool, as release() does before uploading. Returns the file key
+        /// and IV the content is to be encrypted with.
+        fn journal_save(&self, content: &[u8], base: FileBase) -> (FileKey, [u8; 12]) {
+            let file_key = FileKey::generate();
+            let iv = [5u8; 12];
+            let content_id = Journal::new_spool_id();
+            let file_key_encrypted = wrap(file_key.as_bytes(), &self.replayer.public_key);
+            self.replayer.journal.record(JournalOp::Write {
+                parent: FolderRef {
+                    ipns_name: self.replayer.root_ipns_name.clone(),
+                    folder_key_encrypted: None,
+                    ipns_private_key_encrypted: None,
+                },
+                file_ipns_name: self.file_ipns_name.clone(),
+                ipns_private_key_encrypted: self.file_ipns_key_encrypted.clone(),
+                metadata: file_metadata("", file_key_encrypted, &iv, content.len() as u64),
+                content: Some(content_id.clone()),
+                base: Some(base),
+            });
+            self.replayer.journal.spool().store(&content_id, content).unwrap();
+            (file_key, iv)
+        }
+
+        fn content(&self, cid: &str) -> Vec<u8> {
+            self.backend.lock().unwrap().content[cid].clone()
+        }
+
+        /// Published metadata of the file `ipns_name` and its record's sequence.
+        fn file(&self, ipns_name: &str) -> (FileMetadata, u64) {
+            let (cid, sequence) = self.backend.lock().unwrap().records[ipns_name].clone();
+            let metadata = crate::crypto::folder::decrypt_file_metadata_envelope(
+                &self.content(&cid),
+                &self.replayer.root_folder_key,
+                ipns_name,
+            )
+            .unwrap();
+            (metadata, sequence)
+        }
+
+        /// Published metadata of the root folder.
+        fn root(&self) -> FolderMetadata {
+            let name = &self.replayer.root_ipns_name;
+            let (cid, _) = self.backend.lock().unwrap().records[name].clone();
+            crate::crypto::folder::decrypt_folder_metadata_envelope(
+                &self.content(&cid),
+                &self.replayer.root_folder_key,
+                name,
+            )
+            .unwrap()
+        }
+    }
+
+    // ── Tests ────────────────────────────────────────────────────────────
+
     #[tokio::test]
-    async fn test_write_queue_process_empty_returns_zero() {
-        let mut queue = WriteQueue::new(5);
-        let handler = SuccessHandler::new();
-        let processed = queue.process(&handler).await.unwrap();
-        assert_eq!(processed, 0);
-        assert_eq!(handler.calls(), 0);
+    async fn test_replayed_save_is_uploaded_published_and_acked() {
+        let vault = vault("replay-save").await;
+        vault.remote_save("bafybase", 3);
+        let content = b"saved while offline".to_vec();
+        let (file_key, iv) = vault.journal_save(
+            &content,
+            FileBase {
+                sequence: Some(3),
+                cid: "bafybase".to_string(),
+            },
+        );
+
+        let journal = vault.replayer.journal.clone();
+        let report = journal.replay(&vault.replayer, |_, _| {}).await;
+        assert!(report.failure.is_none(), "{:?}", report.failure.as_ref().map(|f| &f.error));
+        assert_eq!(report.applied, 1);
+        assert!(journal.is_empty());
+
+        let (metadata, sequence) = vault.file(&vault.file_ipns_name);
+        assert_eq!(sequence, 4);
+        assert_eq!(decrypt(&vault.content(&metadata.cid), &file_key, &iv), content);
+        let uploaded = vault.uploads.try_recv().expect("mounts are told about the upload");
+        assert_eq!(uploaded.new_cid, metadata.cid);
+        assert_eq!(uploaded.file_meta_ipns_name.as_deref(), Some(vault.file_ipns_name.as_str()));
+        assert_eq!(vault.root().children.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&vault.replayer.temp_dir);
     }
 
     #[tokio::test]
-    async fn test_write_queue_default_max_retries() {
-        let queue = WriteQueue::default();
-        assert!(queue.is_empty());
-        // Default max_retries is 5 -- we verify by creating with default
-        // and checking an item gets 5 retries before drop
-        let mut queue = WriteQueue::default();
-        queue.enqueue(make_write("1", "test.txt"));
-
-        let handler = FailHandler;
-        for _ in 0..5 {
-            let _ = queue.process(&handler).await;
-            assert_eq!(queue.len(), 1, "Item should remain in queue within max_retries");
-        }
-        // 6th failure: retries=6 > max_retries=5, dropped
-        let _ = queue.process(&handler).await;
-        assert!(queue.is_empty(), "Item should be dropped after exceeding max_retries");
+    async fn test_replayed_save_behind_remote_becomes_conflicted_copy() {
+        let vault = vault("replay-conflict").await;
+        // Another device saved twice since the save was based on sequence 3
+        vault.remote_save("bafyother", 5);
+        let content = b"saved while offline".to_vec();
+        let (file_key, iv) = vault.journal_save(
+            &content,
+            FileBase {
+                sequence: Some(3),
+                cid: "bafybase".to_string(),
+            },
+        );
+
+        let journal = vault.replayer.journal.clone();
+        let report = journal.replay(&vault.replayer, |_, _| {}).await;
+        assert!(report.failure.is_none(), "{:?}", report.failure.as_ref().map(|f| &f.error));
+        assert!(journal.is_empty());
+
+        // The other device's save stays
+        let (metadata, sequence) = vault.file(&vault.file_ipns_name);
+        assert_eq!(metadata.cid, "bafyother");
+        assert_eq!(sequence, 5);
+        assert!(vault.uploads.try_recv().is_err(), "the file itself was not saved over");
+
+        let root = vault.root();
+        assert_eq!(root.children.len(), 2);
+        let copy = &root.children[1];
+        assert!(child_name(copy).starts_with("notes (conflict from "), "{}", child_name(copy));
+        let (copy_metadata, _) = vault.file(child_ipns_name(copy));
+        assert_eq!(decrypt(&vault.content(&copy_metadata.cid), &file_key, &iv), content);
+
+        let _ = std::fs::remove_dir_all(&vault.replayer.temp_dir);
     }
 }
@@ -0,0 +1,194 @@
+//! The set of IPNS names the sync daemon polls.
+//!
+//! The mounted vault fills the list from its inode tree (every folder and file
+//! metadata record it knows) and records which folders and files are being
+//! accessed. Each poll resolves a bounded batch of names, most relevant first:
+//!
+//! 1. the root folder,
+//! 2. folders accessed within `RECENT_ACCESS`, most recent first,
+//! 3. files accessed (or in folders accessed) within `RECENT_ACCESS`,
+//! 4. everything else, least recently polled first.
+//!
+//! The recent tiers get at most three quarters of a batch, so the rest of the
+//! tree keeps being polled round-robin. A name whose sequence number moved
+//! forward produces a `RemoteChange`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Accesses this recent put a name ahead of the rest of the tree.
+pub const RECENT_ACCESS: Duration = Duration::from_secs(5 * 60);
+
+/// Names resolved per poll.
+pub const MAX_NAMES_PER_POLL: usize = 48;
+
+/// What an IPNS name publishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    Folder,
+    File,
+}
+
+/// A name whose IPNS record moved since it was last polled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteChange {
+    pub ipns_name: String,
+    pub kind: NameKind,
+    pub previous_sequence: u64,
+    pub sequence: u64,
+    /// CID the record now points at.
+    pub cid: String,
+}
+
+#[derive(Debug, Clone)]
+struct Watched {
+    kind: NameKind,
+    /// IPNS name of the folder holding it.
+    parent: Option<String>,
+    last_access: Option<Instant>,
+    last_polled: Option<Instant>,
+    /// Sequence number seen by the last poll.
+    sequence: Option<u64>,
+}
+
+/// Shared between the mounted vault and the sync daemon.
+#[derive(Debug, Default)]
+pub struct WatchList {
+    root: Option<String>,
+    names: HashMap<String, Watched>,
+}
+
+impl WatchList {
+    /// Set the root folder, polled first on every cycle.
+    pub fn set_root(&mut self, ipns_name: &str) {
+        if self.root.as_deref() != Some(ipns_name) {
+            self.root = Some(ipns_name.to_string());
+            self.names.entry(ipns_name.to_string()).or_insert_with(|| Watched {
+                kind: NameKind::Folder,
+                parent: None,
+                last_access: None,
+                last_polled: None,
+                sequence: None,
+            });
+        }
+    }
+
+    /// Replace the tracked names with `names` (name, kind, parent folder name),
+    /// keeping what is known about names tracked before. The root stays.
+    pub fn replace<I>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = (String, NameKind, Option<String>)>,
+    {
+        let mut previous = std::mem::take(&mut self.names);
+        if let Some(root) = &self.root {
+            if let Some(watched) = previous.remove(root) {
+                self.names.insert(root.clone(), watched);
+            }
+        }
+        for (name, kind, parent) in names {
+            if self.root.as_ref() == Some(&name) {
+                continue;
+            }
+            let watched = match previous.remove(&name) {
+                Some(watched) if watched.kind == kind => Watched { parent, ..watched },
+                _ => Watched {
+                    kind,
+                    parent,
+                    last_access: None,
+                    last_polled: None,
+                    sequence: None,
+                },
+            };
+            self.names.insert(name, watched);
+        }
+    }
+
+    /// Record that the folder or file `ipns_name` was just accessed.
+    pub fn touch(&mut self, ipns_name: &str) {
+        if let Some(watched) = self.names.get_mut(ipns_name) {
+            watched.last_access = Some(Instant::now());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Up to `max` names to resolve now, in priority order. They count as
+    /// polled from `now` on.
+    pub fn poll_batch(&mut self, max: usize, now: Instant) -> Vec<String> {
+        let recent = |access: Option<Instant>| {
+            access.filter(|at| now.saturating_duration_since(*at) < RECENT_ACCESS)
+        };
+        let mut root = Vec::new();
+        let mut recent_folders = Vec::new();
+        let mut recent_files = Vec::new();
+        let mut rest = Vec::new();
+        for (name, watched) in &self.names {
+            if self.root.as_ref() == Some(name) {
+                root.push(name.clone());
+                continue;
+            }
+            let parent_access = watched
+                .parent
+                .as_ref()
+                .and_then(|parent| self.names.get(parent))
+                .and_then(|parent| parent.last_access);
+            match (watched.kind, recent(watched.last_access), recent(parent_access)) {
+                (NameKind::Folder, Some(at), _) => recent_folders.push((at, name.clone())),
+                (NameKind::File, own, parent) => match own.max(parent) {
+                    Some(at) => recent_files.push((at, name.clone())),
+                    None => rest.push((watched.last_polled, name.clone())),
+                },
+                _ => rest.push((watched.last_polled, name.clone())),
+            }
+        }
+        recent_folders.sort_by(|a, b| b.cmp(a));
+        recent_files.sort_by(|a, b| b.cmp(a));
+        rest.sort();
+
+        let recent_budget = max - max / 4;
+        let mut batch: Vec<String> = root;
+        batch.extend(
+            recent_folders
+                .into_iter()
+                .chain(recent_files)
+                .map(|(_, name)| name)
+                .take(recent_budget.saturating_sub(batch.len())),
+        );
+        let remaining = max.saturating_sub(batch.len());
+        batch.extend(rest.into_iter().map(|(_, name)| name).take(remaining));
+        batch.truncate(max);
+
+        for name in &batch {
+            if let Some(watched) = self.names.get_mut(name) {
+                watched.last_polled = Some(now);
+            }
+        }
+        batch
+    }
+
+    /// Record the sequence number `ipns_name` resolved to. Returns the change
+    /// if it moved forward; the first sequence seen for a name is its baseline,
+    /// and an older one (from a lagging resolver) is ignored.
+    pub fn record(&mut self, ipns_name: &str, sequence: u64, cid: &str) -> Option<RemoteChange> {
+        let watched = self.names.get_mut(ipns_name)?;
+        let previous = watched.sequence;
+        if previous.is_some_and(|previous| sequence < previous) {
+            return None;
+        }
+        watched.sequence = Some(sequence);
+        let previous = previous?;
+        (sequence > previous).then(|| RemoteChange {
+            ipns_name: ipns_name.to_string(),
+            kind: watched.kind,
+            previous_sequence: previous,
+            sequence,
+            cid: cid.to_string(),
+        })
+    }
+}
@@ -1,7 +1,7 @@
 //! System tray (menu bar) icon and menu for CipherBox Desktop.
 //!
 //! Creates a macOS menu bar icon with status display and actions:
-//! Open CipherBox, Sync Now, Login/Logout, Quit.
+//! Open CipherBox, Sync Now, Retry Mount, Login/Logout, Quit.
 //!
 //! The app runs as a pure background utility (no Dock icon).
 
@@ -26,6 +26,7 @@ const TRAY_ID: &str = "cipherbox-tray";
 /// - `status`: Disabled informational line showing current status
 /// - `open`: Open ~/CipherBox in Finder (enabled when mounted)
 /// - `sync`: Trigger immediate sync (enabled when connected)
+/// - `retry_mount`: Retry mounting now (enabled when the mount failed)
 /// - separator
 /// - `login`: Show Web3Auth webview (when not connected)
 /// - `logout`: Unmount + clear keys (when connected)
@@ -59,8 +60,12 @@ fn build_menu(
     status: &TrayStatus,
 ) -> Result<tauri::menu::Menu<tauri::Wry>, String> {
     let status_text = format!("Status: {}", status.label());
-    let is_mounted = matches!(status, TrayStatus::Syncing | TrayStatus::Synced);
+    let is_mounted = matches!(
+        status,
+        TrayStatus::Syncing | TrayStatus::Replaying { .. } | TrayStatus::Synced
+    );
     let is_syncable = matches!(status, TrayStatus::Synced | TrayStatus::Error(_));
+    let is_mount_failed = matches!(status, TrayStatus::MountError(_));
     let is_disconnected = matches!(status, TrayStatus::NotConnected);
     let is_connected = status.is_connected();
 
@@ -79,6 +84,11 @@ fn build_menu(
         .build(app)
         .map_err(|e| format!("Failed to build sync item: {}", e))?;
 
+    let retry_mount_item = MenuItemBuilder::with_id("retry_mount", "Retry Mount")
+        .enabled(is_mount_failed)
+        .build(app)
+        .map_err(|e| format!("Failed to build retry mount item: {}", e))?;
+
     let login_item = MenuItemBuilder::with_id("login", "Login...")
         .enabled(is_disconnected)
         .build(app)
@@ -102,6 +112,7 @@ fn build_menu(
         .item(&status_item)
         .item(&open_item)
         .item(&sync_item)
+        .item(&retry_mount_item)
         .item(&sep1)
         .item(&login_item)
         .item(&logout_item)
@@ -116,10 +127,9 @@ fn handle_menu_event(app: &AppHandle, id: &str) {
     use tauri_plugin_notification::NotificationExt;
     match id {
         "open" => {
-            // Open ~/CipherBox in Finder
-            let mount_point = dirs::home_dir()
-                .map(|h| h.join("CipherBox"))
-                .unwrap_or_default();
+            // Open the mount point in Finder (or the Linux file manager)
+            let state = app.state::<crate::state::AppState>();
+            let mount_point = state.mount.mount_point.clone();
             if !mount_point.exists() {
                 log::warn!("Mount point {} does not exist — FUSE may not be mounted", mount_point.display());
                 // Show notification instead of a confusing Finder error
@@ -133,11 +143,12 @@ fn handle_menu_event(app: &AppHandle, id: &str) {
                 }
                 return;
             }
-            if let Err(e) = std::process::Command::new("open")
-                .arg(mount_point.to_str().unwrap_or("~/CipherBox"))
+            let open = crate::fuse::profile::Platform::current().open_command();
+            if let Err(e) = std::process::Command::new(open)
+                .arg(&mount_point)
                 .spawn()
             {
-                log::error!("Failed to open CipherBox in Finder: {}", e);
+                log::error!("Failed to open CipherBox in the file manager: {}", e);
             }
         }
         "sync" => {
@@ -150,6 +161,16 @@ fn handle_menu_event(app: &AppHandle, id: &str) {
                 log::warn!("Sync trigger channel not available");
             }
         }
+        "retry_mount" => {
+            // Wake the mount supervisors (see fuse::supervisor); those of
+            // mounts that are up aren't waiting
+            #[cfg(feature = "fuse")]
+            {
+                let state = app.state::<crate::state::AppState>();
+                state.mounts.retry.notify_waiters();
+                log::info!("Mount retry triggered");
+            }
+        }
         "login" => {
             // Show existing window or create a new one with on_new_window
             // handler for OAuth popups.
@@ -215,7 +236,7 @@ fn handle_menu_event(app: &AppHandle, id: &str) {
                 // Unmount FUSE filesystem
                 #[cfg(feature = "fuse")]
                 {
-                    if let Err(e) = crate::fuse::unmount_filesystem() {
+                    if let Err(e) = crate::fuse::supervisor::unmount_all(&state) {
                         log::warn!("FUSE unmount during logout failed: {}", e);
                     }
                     *state.mount_status.write().await = crate::state::MountStatus::Unmounted;
@@ -244,7 +265,8 @@ fn handle_menu_event(app: &AppHandle, id: &str) {
             // Unmount FUSE if mounted, then exit
             #[cfg(feature = "fuse")]
             {
-                let _ = crate::fuse::unmount_filesystem();
+                let state = app.state::<crate::state::AppState>();
+                let _ = crate::fuse::supervisor::unmount_all(&state);
             }
             app.exit(0);
         }
@@ -269,7 +291,7 @@ pub fn update_tray_status(app: &AppHandle, status: &TrayStatus) -> Result<(), St
         .map_err(|e| format!("Failed to set tray menu: {}", e))?;
 
     // Send notification on Error status
-    if let TrayStatus::Error(ref msg) = status {
+    if let TrayStatus::Error(ref msg) | TrayStatus::MountError(ref msg) = status {
         if let Err(e) = send_error_notification(app, msg) {
             log::warn!("Failed to send error notification: {}", e);
         }
@@ -15,33 +15,45 @@ pub enum TrayStatus {
     Mounting,
     /// Background sync is actively polling/refreshing metadata.
     Syncing,
+    /// Publishing changes journaled while offline (`done` of `total`).
+    Replaying { done: usize, total: usize },
     /// Up to date -- last sync completed successfully.
     Synced,
     /// Network unavailable -- waiting for connectivity to resume.
     Offline,
     /// Something went wrong (with human-readable description).
     Error(String),
+    /// The vault is not mounted (with human-readable description); the mount
+    /// supervisor retries, and the menu offers to retry now.
+    MountError(String),
 }
 
 impl TrayStatus {
     /// Human-readable status text for the tray menu item.
-    pub fn label(&self) -> &str {
+    pub fn label(&self) -> String {
         match self {
-            TrayStatus::NotConnected => "Not Connected",
-            TrayStatus::Mounting => "Mounting...",
-            TrayStatus::Syncing => "Syncing...",
-            TrayStatus::Synced => "Synced",
-            TrayStatus::Offline => "Offline",
-            TrayStatus::Error(_) => "Error",
+            TrayStatus::NotConnected => "Not Connected".to_string(),
+            TrayStatus::Mounting => "Mounting...".to_string(),
+            TrayStatus::Syncing => "Syncing...".to_string(),
+            TrayStatus::Replaying { done, total } => {
+                format!("Uploading offline changes ({}/{})...", done, total)
+            }
+            TrayStatus::Synced => "Synced".to_string(),
+            TrayStatus::Offline => "Offline".to_string(),
+            TrayStatus::Error(_) => "Error".to_string(),
+            TrayStatus::MountError(_) => "Not Mounted".to_string(),
         }
     }
 
     /// Returns `true` when the app is authenticated and has (or had) a mounted filesystem.
     ///
-    /// True for Syncing, Synced, Offline (connected but temporarily unreachable).
-    /// False for NotConnected, Mounting, Error.
+    /// True for Syncing, Replaying, Synced, Offline (connected but temporarily unreachable).
+    /// False for NotConnected, Mounting, Error, MountError.
     pub fn is_connected(&self) -> bool {
-        matches!(self, TrayStatus::Syncing | TrayStatus::Synced | TrayStatus::Offline)
+        matches!(
+            self,
+            TrayStatus::Syncing | TrayStatus::Replaying { .. } | TrayStatus::Synced | TrayStatus::Offline
+        )
     }
 }
 
@@ -54,9 +66,14 @@ mod tests {
         assert_eq!(TrayStatus::NotConnected.label(), "Not Connected");
         assert_eq!(TrayStatus::Mounting.label(), "Mounting...");
         assert_eq!(TrayStatus::Syncing.label(), "Syncing...");
+        assert_eq!(
+            TrayStatus::Replaying { done: 2, total: 5 }.label(),
+            "Uploading offline changes (2/5)..."
+        );
         assert_eq!(TrayStatus::Synced.label(), "Synced");
         assert_eq!(TrayStatus::Offline.label(), "Offline");
         assert_eq!(TrayStatus::Error("disk full".into()).label(), "Error");
+        assert_eq!(TrayStatus::MountError("ENOTCONN".into()).label(), "Not Mounted");
     }
 
     #[test]
@@ -64,8 +81,10 @@ mod tests {
         assert!(!TrayStatus::NotConnected.is_connected());
         assert!(!TrayStatus::Mounting.is_connected());
         assert!(TrayStatus::Syncing.is_connected());
+        assert!(TrayStatus::Replaying { done: 0, total: 1 }.is_connected());
         assert!(TrayStatus::Synced.is_connected());
         assert!(TrayStatus::Offline.is_connected());
         assert!(!TrayStatus::Error("oops".into()).is_connected());
+        assert!(!TrayStatus::MountError("oops".into()).is_connected());
     }
 }
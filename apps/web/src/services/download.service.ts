@@ -1,4 +1,5 @@
 import { decryptAesGcm, decryptAesCtr, unwrapKey, hexToBytes, clearBytes } from '@cipherbox/crypto';
+import type { FileMetadata as FileMetadataRecord } from '@cipherbox/crypto';
 import { fetchFromIpfs, DownloadProgressCallback } from '../lib/api/ipfs';
 import { UploadedFile } from './upload.service';
 import { resolveFileMetadata } from './file-metadata.service';
@@ -8,8 +9,11 @@ import { resolveFileMetadata } from './file-metadata.service';
  * This matches the UploadedFile type but only needs the fields for download.
  */
 export type FileMetadata = Pick<UploadedFile, 'cid' | 'iv' | 'wrappedKey' | 'originalName'> & {
-  /** Encryption mode: 'GCM' (default) or 'CTR' (streaming media) */
-  encryptionMode?: 'GCM' | 'CTR';
+  /**
+   * Encryption mode: 'GCM' (default) or 'CTR' (streaming media). Modes only
+   * the desktop app writes so far cannot be decrypted here.
+   */
+  encryptionMode?: FileMetadataRecord['encryptionMode'];
 };
 
 /**
@@ -25,6 +29,10 @@ export async function downloadFile(
   privateKey: Uint8Array,
   onProgress?: DownloadProgressCallback
 ): Promise<Uint8Array> {
+  if (metadata.encryptionMode && metadata.encryptionMode !== 'GCM' && metadata.encryptionMode !== 'CTR') {
+    throw new Error(`${metadata.encryptionMode} files can only be opened in the desktop app`);
+  }
+
   // 1. Fetch encrypted file from IPFS
   const ciphertext = await fetchFromIpfs(metadata.cid, onProgress);
 
@@ -19,7 +19,7 @@ export type VersionEntry = {
   /** When this version was created (Unix ms) -- i.e., when it became a "past" version */
   timestamp: number;
   /** Encryption mode used for this version */
-  encryptionMode: 'GCM' | 'CTR';
+  encryptionMode: 'GCM' | 'CTR' | 'GCM-SEG' | 'CHUNKED';
 };
 
 /**
@@ -30,18 +30,32 @@ export type VersionEntry = {
 export type FileMetadata = {
   /** Schema version */
   version: 'v1';
-  /** IPFS CID of the encrypted file content */
+  /** IPFS CID of the encrypted file content (of the encrypted chunk manifest for 'CHUNKED') */
   cid: string;
   /** Hex-encoded ECIES-wrapped AES-256 key for decrypting file */
   fileKeyEncrypted: string;
-  /** Hex-encoded IV used for file encryption */
+  /** Hex-encoded IV used for file encryption (the 7-byte nonce prefix for 'GCM-SEG', empty for 'CHUNKED') */
   fileIv: string;
   /** Original file size in bytes (before encryption) */
   size: number;
   /** MIME type of the original file */
   mimeType: string;
-  /** Encryption mode (optional for backward compat; defaults to 'GCM') */
-  encryptionMode?: 'GCM' | 'CTR';
+  /**
+   * Encryption mode (optional for backward compat; defaults to 'GCM').
+   * 'GCM-SEG' (segmented AES-GCM) and 'CHUNKED' (FastCDC chunks plus an
+   * encrypted manifest) are written only by the desktop app, opt-in; this
+   * package cannot decrypt them yet and its validator rejects them.
+   */
+  encryptionMode?: 'GCM' | 'CTR' | 'GCM-SEG' | 'CHUNKED';
+  /**
+   * Length padding of the content before encryption ('padme' or 'pow2'), set
+   * by the desktop app. The decrypted content is then `content || 0x80 || 0x00...`:
+   * strip trailing 0x00 bytes, then the 0x80 marker (anything else is corrupt).
+   * Omitted if the content is unpadded. This package does not unpad yet.
+   */
+  padding?: 'padme' | 'pow2';
+  /** CTR only: hex SHA-256 of each 1MB ciphertext chunk, for verifying range reads */
+  chunkHashes?: string[];
   /** Creation timestamp (Unix ms) */
   createdAt: number;
   /** Last modification timestamp (Unix ms) */